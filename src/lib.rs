@@ -0,0 +1,15054 @@
+//! crank drives long-running agent runs: it loads a TOML [`Config`], persists a
+//! [`RunState`] under the run's state directory, and loops backend turns until
+//! every task is terminal.
+//!
+//! Embedders call [`load_config`] and [`run_governor`]; [`read_state`] inspects a
+//! run from outside. A custom [`Backend`] runs the turns when passed as
+//! [`GovernorOptions::backend_factory`]. The `crank` binary is a thin wrapper
+//! around [`run_cli`].
+//!
+//! ```no_run
+//! let cfg = crank::load_config(std::path::Path::new("crank.toml"))?;
+//! let options = crank::GovernorOptions {
+//!     preflight: true,
+//!     ..Default::default()
+//! };
+//! crank::run_governor(cfg, options)?;
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use anyhow::{Context, Result, anyhow};
+use chrono::Utc;
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+const HELP_LONG_ABOUT: &str = include_str!("../prompts/help_long_about.md");
+const HELP_AFTER_LONG: &str = include_str!("../prompts/help_after_long.md");
+const TURN_PROMPT_TEMPLATE: &str = include_str!("../prompts/turn_prompt.md");
+const DEFAULT_TEAMS_DIR: &str = "teams";
+const REQUIRED_CODEX_ARG: &str = "--yolo";
+const REQUIRED_CLAUDE_ARG: &str = "--dangerously-skip-permissions";
+
+#[derive(Debug, Parser)]
+#[command(name = "crank")]
+#[command(about = "Agent-first governor for plan-driven tasks")]
+#[command(long_about = HELP_LONG_ABOUT, after_long_help = HELP_AFTER_LONG)]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Debug, Subcommand)]
+enum Commands {
+    #[command(about = "Run the unattended governor from a TOML config")]
+    Run(RunArgs),
+    #[command(about = "Replay a config against a scripted mock backend on a simulated clock")]
+    Simulate(SimulateArgs),
+    #[command(about = "Write a starter TOML config template")]
+    Init(InitArgs),
+    #[command(about = "Inspect or control a running governor state dir")]
+    Ctl(CtlArgs),
+    #[command(about = "Manage reusable role/model team definitions")]
+    Teams(TeamsArgs),
+    #[command(about = "Inspect and validate governor TOML configs")]
+    Config(ConfigArgs),
+    #[command(about = "Manage finished run state dirs")]
+    Runs(RunsArgs),
+    #[command(about = "Clean up the task worktrees and branches crank leaves in a repo")]
+    Worktree(WorktreeArgs),
+    #[command(about = "Print the JSON Schema of the config, state.json, or run-summary.json")]
+    Schema(SchemaArgs),
+    #[command(about = "Print a shell completion script")]
+    Completions(CompletionsArgs),
+    #[command(about = "Print the man page, or write one per subcommand into a directory")]
+    Man(ManArgs),
+}
+
+#[derive(Debug, Args)]
+struct RunArgs {
+    #[arg(long, help = "Path to crank TOML config")]
+    config: PathBuf,
+    #[arg(long, help = "Apply team by name (e.g. xhigh) to role settings")]
+    team: Option<String>,
+    #[arg(long, help = "Apply team from explicit TOML file path")]
+    team_file: Option<PathBuf>,
+    #[arg(long, default_value = DEFAULT_TEAMS_DIR, help = "Teams directory")]
+    teams_dir: PathBuf,
+    #[arg(long, help = "Serve Prometheus metrics on this port at /metrics")]
+    metrics_port: Option<u16>,
+    #[arg(
+        long,
+        default_value = "127.0.0.1",
+        help = "Address the metrics server binds (0.0.0.0 exposes it to the network)"
+    )]
+    metrics_addr: IpAddr,
+    #[arg(long, help = "Skip the backend and harness preflight checks")]
+    skip_preflight: bool,
+    #[arg(
+        long,
+        help = "Re-read the config between cycles and apply safe changes (timeouts, limits, new tasks, ...)"
+    )]
+    watch_config: bool,
+    #[arg(
+        long,
+        value_enum,
+        help = "Title the multiplexer pane crank runs in after the active task"
+    )]
+    mux: Option<Mux>,
+}
+
+#[derive(Debug, Args)]
+struct SimulateArgs {
+    #[arg(long, help = "Path to crank TOML config")]
+    config: PathBuf,
+    #[arg(long, help = "Mock scenario TOML scripting each task's turns")]
+    scenario: PathBuf,
+    #[arg(
+        long,
+        help = "State dir for the simulated run (default: <state_dir>-simulation next to the config's)"
+    )]
+    state_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+struct InitArgs {
+    #[arg(long, help = "Output path for starter TOML config")]
+    output: PathBuf,
+    #[arg(long, help = "Seed config with team by name (e.g. xhigh)")]
+    team: Option<String>,
+    #[arg(long, help = "Seed config with team from explicit TOML file path")]
+    team_file: Option<PathBuf>,
+    #[arg(long, default_value = DEFAULT_TEAMS_DIR, help = "Teams directory")]
+    teams_dir: PathBuf,
+    #[arg(
+        long,
+        help = "Prompt for workspace, state dir, backend, team, and tasks instead of writing the example"
+    )]
+    interactive: bool,
+}
+
+#[derive(Debug, Args)]
+struct CtlArgs {
+    #[command(subcommand)]
+    command: CtlCommand,
+}
+
+#[derive(Debug, Args)]
+struct TeamsArgs {
+    #[command(subcommand)]
+    command: TeamsCommand,
+}
+
+#[derive(Debug, Args)]
+struct ConfigArgs {
+    #[command(subcommand)]
+    command: ConfigCommand,
+}
+
+#[derive(Debug, Args)]
+struct SchemaArgs {
+    #[arg(value_enum, help = "Which file to describe")]
+    target: SchemaTarget,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum SchemaTarget {
+    /// The run TOML config.
+    Config,
+    /// `<state_dir>/state.json`.
+    State,
+    /// `<state_dir>/run-summary.json`.
+    Summary,
+}
+
+#[derive(Debug, Args)]
+struct CompletionsArgs {
+    #[arg(value_enum, help = "Shell to generate completions for")]
+    shell: clap_complete::Shell,
+}
+
+#[derive(Debug, Args)]
+struct ManArgs {
+    #[arg(
+        long,
+        help = "Write crank.1 plus a page per subcommand (crank-ctl-note.1, ...) here instead of printing crank.1"
+    )]
+    out_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+struct RunsArgs {
+    #[command(subcommand)]
+    command: RunsCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum CtlCommand {
+    #[command(about = "Print current run state JSON")]
+    Snapshot {
+        #[arg(long, help = "Governor state directory path")]
+        state_dir: PathBuf,
+    },
+    #[command(about = "Exit 0 if run is safe to stop; 1 otherwise")]
+    CanExit {
+        #[arg(long, help = "Governor state directory path")]
+        state_dir: PathBuf,
+    },
+    #[command(about = "Append an operator note to the run journal")]
+    Note {
+        #[arg(long, help = "Governor state directory path")]
+        state_dir: PathBuf,
+        #[arg(long, help = "Note text to append to journal")]
+        message: String,
+    },
+    #[command(about = "Wake the governor so it re-evaluates now instead of finishing its sleep")]
+    Kick {
+        #[arg(long, help = "Governor state directory path")]
+        state_dir: PathBuf,
+    },
+    #[command(about = "Queue a one-shot instruction for a task's next turn prompt")]
+    Instruct {
+        #[arg(long, help = "Governor state directory path")]
+        state_dir: PathBuf,
+        #[arg(long, help = "Task id the instruction is for")]
+        task_id: String,
+        #[arg(
+            long,
+            help = "Instruction text injected into the task's next turn prompt"
+        )]
+        message: String,
+    },
+    #[command(about = "Answer a task's pending escalation question")]
+    Answer {
+        #[arg(long, help = "Governor state directory path")]
+        state_dir: PathBuf,
+        #[arg(long, help = "Task id whose escalation is being answered")]
+        task_id: String,
+        #[arg(long, help = "Answer text injected into the task's next turn prompt")]
+        message: String,
+        #[arg(
+            long,
+            help = "Escalation number (defaults to the latest unanswered); answered ones are refused"
+        )]
+        question: Option<u32>,
+    },
+    #[command(about = "Safely change one task's status, dependencies, or completion file")]
+    EditTask {
+        #[arg(long, help = "Governor state directory path")]
+        state_dir: PathBuf,
+        #[arg(long, help = "Task id to edit")]
+        task_id: String,
+        #[arg(
+            long,
+            help = "New status: pending, running, completed, blocked_best_effort, skipped, cancelled"
+        )]
+        status: Option<TaskStatus>,
+        #[arg(
+            long,
+            value_delimiter = ',',
+            num_args = 0..,
+            help = "Replace depends_on (comma-separated task ids; empty clears)"
+        )]
+        depends_on: Option<Vec<String>>,
+        #[arg(long, help = "Set completion_file (empty string clears it)")]
+        completion_file: Option<String>,
+    },
+    #[command(about = "Append a new pending task to a run")]
+    AddTask {
+        #[arg(long, help = "Governor state directory path")]
+        state_dir: PathBuf,
+        #[arg(long, help = "Id of the new task")]
+        id: String,
+        #[arg(long, help = "Todo file the task works through")]
+        todo_file: PathBuf,
+        #[arg(
+            long,
+            value_delimiter = ',',
+            help = "Task ids the new task waits for (comma-separated)"
+        )]
+        depends_on: Vec<String>,
+        #[arg(long, help = "Completion artifact that marks the task done")]
+        completion_file: Option<String>,
+        #[arg(
+            long,
+            default_value_t = 0,
+            help = "Scheduling priority (higher runs first)"
+        )]
+        priority: i32,
+    },
+    #[command(about = "Cancel a task so the run stops waiting on it")]
+    Cancel {
+        #[arg(long, help = "Governor state directory path")]
+        state_dir: PathBuf,
+        #[arg(long, help = "Task id to cancel")]
+        task_id: String,
+        #[arg(
+            long,
+            help = "Why the task is cancelled (recorded in state and summaries)"
+        )]
+        reason: Option<String>,
+    },
+    #[command(about = "Render a Markdown report of a run")]
+    Report {
+        #[arg(long, help = "Governor state directory path")]
+        state_dir: PathBuf,
+        #[arg(long, help = "Write the report to this file instead of stdout")]
+        out: Option<PathBuf>,
+    },
+    #[command(about = "Export the run summary with per-task timings for CI tooling")]
+    Summary {
+        #[arg(long, help = "Governor state directory path")]
+        state_dir: PathBuf,
+        #[arg(long, value_enum, default_value = "json", help = "Output format")]
+        format: SummaryFormat,
+        #[arg(long, help = "Write the export to this file instead of stdout")]
+        out: Option<PathBuf>,
+    },
+    #[command(about = "Search archived turn prompts/responses and the turns log")]
+    Grep {
+        #[arg(long, help = "Governor state directory path")]
+        state_dir: PathBuf,
+        #[arg(long, help = "Regex to search for")]
+        pattern: String,
+        #[arg(long, help = "Only search this task's turns")]
+        task: Option<String>,
+        #[arg(
+            long,
+            value_parser = parse_cycle_range,
+            help = "Only search these cycles, e.g. 10..20, 10.., or ..20 (inclusive)"
+        )]
+        cycle_range: Option<CycleRange>,
+        #[arg(long, help = "Print one JSON object per match")]
+        json: bool,
+    },
+    #[command(about = "One-line status for a run, or for every run under a directory")]
+    Status {
+        #[arg(
+            long,
+            required_unless_present = "all",
+            conflicts_with = "all",
+            help = "Governor state directory path"
+        )]
+        state_dir: Option<PathBuf>,
+        #[arg(long, help = "Scan every state dir under --runs-dir")]
+        all: bool,
+        #[arg(
+            long,
+            default_value = "runs",
+            requires = "all",
+            help = "Directory to scan for state dirs with --all"
+        )]
+        runs_dir: PathBuf,
+        #[arg(long, help = "Print a JSON array instead of one line per run")]
+        json: bool,
+    },
+    #[command(about = "List indexed coord_dir/artifacts deliverables")]
+    Artifacts {
+        #[arg(long, help = "Governor state directory path")]
+        state_dir: PathBuf,
+        #[arg(long, help = "Only list this task's artifacts")]
+        task_id: Option<String>,
+    },
+    #[command(about = "Run a command when the governor stops making progress")]
+    Watchdog {
+        #[arg(long, help = "Governor state directory path")]
+        state_dir: PathBuf,
+        #[arg(
+            long,
+            help = "Seconds without state updates or heartbeat before firing"
+        )]
+        stall_secs: u64,
+        #[arg(long, help = "Shell command to execute when the run is stale")]
+        exec: String,
+        #[arg(long, default_value_t = 30, help = "Seconds between staleness checks")]
+        poll_secs: u64,
+        #[arg(long, help = "Exit after the command fires once")]
+        once: bool,
+    },
+    #[command(about = "Open a multiplexer split tailing the run's journal and events")]
+    Attach {
+        #[arg(long, help = "Governor state directory path")]
+        state_dir: PathBuf,
+        #[arg(
+            long,
+            value_enum,
+            help = "Multiplexer to split (default: the one this shell runs in)"
+        )]
+        mux: Option<Mux>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum SummaryFormat {
+    Json,
+    /// One testcase per task; blocked tasks are failures, unfinished ones errors.
+    Junit,
+    Html,
+    Csv,
+}
+
+#[derive(Debug, Subcommand)]
+enum TeamsCommand {
+    #[command(about = "List available teams")]
+    List {
+        #[arg(long, default_value = DEFAULT_TEAMS_DIR, help = "Teams directory")]
+        dir: PathBuf,
+    },
+    #[command(about = "Validate team file(s) and required harness launch args")]
+    Validate(TeamsValidateArgs),
+    #[command(about = "Print a team's resolved roles as TOML")]
+    Show {
+        #[arg(help = "Team name (file stem or builtin)")]
+        name: String,
+        #[arg(long, default_value = DEFAULT_TEAMS_DIR, help = "Teams directory")]
+        dir: PathBuf,
+    },
+    #[command(about = "Scaffold a new team file from an existing team")]
+    New {
+        #[arg(help = "Name of the new team (file stem)")]
+        name: String,
+        #[arg(long, default_value = "xhigh", help = "Team to copy roles from")]
+        from: String,
+        #[arg(long, default_value = DEFAULT_TEAMS_DIR, help = "Teams directory")]
+        dir: PathBuf,
+        #[arg(long, help = "Overwrite an existing team file")]
+        force: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ConfigCommand {
+    #[command(about = "Validate a run config and report every problem found")]
+    Validate(ConfigValidateArgs),
+}
+
+#[derive(Debug, Subcommand)]
+enum RunsCommand {
+    #[command(
+        about = "Pack a finished run into a .tar.zst after checking state.json and run-summary.json agree"
+    )]
+    Archive(RunsArchiveArgs),
+}
+
+#[derive(Debug, Args)]
+struct WorktreeArgs {
+    #[command(subcommand)]
+    command: WorktreeCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum WorktreeCommand {
+    #[command(
+        about = "Remove stale crank/<run_id>/<task> branches and their worktrees, skipping live runs"
+    )]
+    Gc(WorktreeGcArgs),
+}
+
+#[derive(Debug, Args)]
+struct WorktreeGcArgs {
+    #[arg(long, default_value = ".", help = "Repository to clean up")]
+    workspace: PathBuf,
+    #[arg(
+        long,
+        default_value = "HEAD",
+        help = "Branches not merged into this revision are kept unless --force"
+    )]
+    base: String,
+    #[arg(
+        long,
+        help = "Also delete branches not merged into --base, losing their commits"
+    )]
+    force: bool,
+    #[arg(
+        long,
+        value_parser = parse_age_secs,
+        help = "Only remove branches whose last commit is older than this, e.g. 14d, 12h, 2w"
+    )]
+    older_than: Option<u64>,
+    #[arg(long, help = "List what would be removed without removing it")]
+    dry_run: bool,
+}
+
+#[derive(Debug, Args)]
+struct RunsArchiveArgs {
+    #[arg(long, help = "Governor state directory path")]
+    state_dir: PathBuf,
+    #[arg(
+        long,
+        help = "Archive path (default: <state_dir>.tar.zst next to the state dir)"
+    )]
+    output: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "Delete the state dir once the archive is written and verified"
+    )]
+    delete_original: bool,
+}
+
+#[derive(Debug, Args)]
+struct ConfigValidateArgs {
+    #[arg(long, help = "Path to crank TOML config")]
+    config: PathBuf,
+    #[arg(long, help = "Apply team by name (e.g. xhigh) to role settings")]
+    team: Option<String>,
+    #[arg(long, help = "Apply team from explicit TOML file path")]
+    team_file: Option<PathBuf>,
+    #[arg(long, default_value = DEFAULT_TEAMS_DIR, help = "Teams directory")]
+    teams_dir: PathBuf,
+}
+
+#[derive(Debug, Args)]
+struct TeamsValidateArgs {
+    #[arg(long, help = "Validate a specific team by name (file stem)")]
+    team: Option<String>,
+    #[arg(long, help = "Validate an explicit team file path")]
+    file: Option<PathBuf>,
+    #[arg(long, default_value = DEFAULT_TEAMS_DIR, help = "Teams directory")]
+    dir: PathBuf,
+    #[arg(long, help = "Validate all *.toml files in teams directory")]
+    all: bool,
+}
+
+/// A parsed run config. Build one with [`load_config`].
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    run_id: Option<String>,
+    workspace: PathBuf,
+    state_dir: PathBuf,
+    #[serde(default = "default_unattended")]
+    unattended: bool,
+    #[serde(default = "default_poll_interval")]
+    poll_interval_secs: u64,
+    #[serde(default)]
+    timeouts: TimeoutsConfig,
+    #[serde(default)]
+    recovery: RecoveryConfig,
+    #[serde(default)]
+    policy: PolicyConfig,
+    #[serde(default)]
+    schedule: ScheduleConfig,
+    #[serde(default)]
+    limits: LimitsConfig,
+    #[serde(default)]
+    digest: DigestConfig,
+    #[serde(default)]
+    git: GitConfig,
+    #[serde(default)]
+    hooks: HooksConfig,
+    #[serde(default)]
+    logging: LoggingConfig,
+    #[serde(default)]
+    preflight: PreflightConfig,
+    #[serde(default)]
+    security: SecurityConfig,
+    #[serde(default)]
+    sandbox: SandboxConfig,
+    #[serde(default)]
+    sync: SyncConfig,
+    #[serde(default)]
+    cluster: Option<ClusterConfig>,
+    /// Markdown turn prompt replacing the embedded template.
+    #[serde(default)]
+    prompt_template: Option<PathBuf>,
+    backend: BackendSection,
+    roles: RolesConfig,
+    tasks: Vec<TaskConfig>,
+    /// The config file and its includes, recorded by [`load_config`].
+    #[serde(skip)]
+    config_files: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct GitConfig {
+    /// Record HEAD, working-tree status, and diffstat after every successful turn.
+    #[serde(default)]
+    snapshot_per_turn: bool,
+    /// Refuse to start a fresh run over uncommitted changes in the workspace.
+    #[serde(default)]
+    require_clean_start: bool,
+    /// Stash uncommitted changes (including untracked files) before a fresh run.
+    #[serde(default)]
+    stash_on_start: bool,
+    /// Remove a done task's worktree and branch once the branch is merged.
+    #[serde(default)]
+    remove_merged_worktrees: bool,
+    /// Where `branch = true` tasks get their worktrees, as `<run_id>/<task>`.
+    /// Defaults to `crank-worktrees` in the task repo's git dir; relative
+    /// paths resolve against the run workspace.
+    #[serde(default)]
+    worktree_dir: Option<PathBuf>,
+}
+
+/// Off-machine mirrors of `state.json`, `JOURNAL.md`, and `run-summary.json`,
+/// refreshed in the background after state saves.
+#[derive(Debug, Clone, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct SyncConfig {
+    /// `s3://bucket/prefix`; files are copied with `aws s3 cp`.
+    #[serde(default)]
+    s3_uri: Option<String>,
+    /// rsync destination directory, local or `host:path`.
+    #[serde(default)]
+    rsync_target: Option<String>,
+}
+
+/// Task leases shared by governors on several machines, each with its own
+/// `state_dir`, so they can split one plan between them.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct ClusterConfig {
+    /// Directory every governor can reach, e.g. on NFS.
+    lease_dir: PathBuf,
+    /// This governor's name in leases; defaults to the hostname.
+    #[serde(default)]
+    node_id: Option<String>,
+    /// How long a lease outlives its last renewal; must exceed `timeouts.stall_secs`.
+    #[serde(default = "default_lease_secs")]
+    lease_secs: u64,
+}
+
+/// Shell commands run with `sh -c` at run and task lifecycle points.
+#[derive(Debug, Clone, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct HooksConfig {
+    /// Run before the first turn; a failure aborts the run.
+    #[serde(default)]
+    pre_run: Vec<String>,
+    #[serde(default)]
+    post_run: Vec<String>,
+    /// Run when a task starts; a failure blocks the task.
+    #[serde(default)]
+    pre_task: Vec<String>,
+    #[serde(default)]
+    post_task: Vec<String>,
+    /// Seconds each hook command may run before it is killed; default 600.
+    #[serde(default)]
+    timeout_secs: Option<u64>,
+}
+
+const DEFAULT_HOOK_TIMEOUT_SECS: u64 = 600;
+
+impl HooksConfig {
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs.unwrap_or(DEFAULT_HOOK_TIMEOUT_SECS))
+    }
+}
+
+/// Limits on what spawned backends inherit from the governor.
+#[derive(Debug, Clone, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct SecurityConfig {
+    /// Environment variables passed to backend processes; entries ending in `*`
+    /// match by prefix. Unset inherits the full environment.
+    #[serde(default)]
+    env_allowlist: Option<Vec<String>>,
+}
+
+/// One access level mapped onto each harness's own sandbox switches.
+#[derive(Debug, Clone, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct SandboxConfig {
+    /// Overrides codex `sandbox_mode`, claude's permission flags, and droid `auto`.
+    /// Unset keeps each backend's own settings.
+    #[serde(default)]
+    profile: Option<SandboxProfile>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+enum SandboxProfile {
+    Readonly,
+    WorkspaceWrite,
+    Full,
+}
+
+impl SandboxProfile {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Readonly => "readonly",
+            Self::WorkspaceWrite => "workspace-write",
+            Self::Full => "full",
+        }
+    }
+
+    /// `codex exec --sandbox` mode.
+    fn codex_sandbox_mode(self) -> &'static str {
+        match self {
+            Self::Readonly => "read-only",
+            Self::WorkspaceWrite => "workspace-write",
+            Self::Full => "danger-full-access",
+        }
+    }
+
+    /// Claude permission flags.
+    fn claude_args(self) -> &'static [&'static str] {
+        match self {
+            Self::Readonly => &["--permission-mode", "plan"],
+            Self::WorkspaceWrite => &["--permission-mode", "acceptEdits"],
+            Self::Full => &[
+                "--dangerously-skip-permissions",
+                "--permission-mode",
+                "bypassPermissions",
+            ],
+        }
+    }
+
+    /// `droid exec --auto` level; without one droid only reads.
+    fn droid_auto(self) -> Option<&'static str> {
+        match self {
+            Self::Readonly => None,
+            Self::WorkspaceWrite => Some("low"),
+            Self::Full => Some("high"),
+        }
+    }
+}
+
+/// Backend settings that contradict `[sandbox] profile`.
+fn sandbox_problems(cfg: &Config) -> Vec<String> {
+    let Some(profile) = cfg.sandbox.profile else {
+        return Vec::new();
+    };
+    let mut problems = Vec::new();
+    for backend in cfg.backend.chain() {
+        let (extra_args, conflicting): (&[String], &[&str]) = match backend {
+            BackendConfig::Codex(c) => (
+                &c.extra_args,
+                &[
+                    "--sandbox",
+                    "-s",
+                    "--dangerously-bypass-approvals-and-sandbox",
+                ],
+            ),
+            BackendConfig::Claude(c) => (
+                &c.extra_args,
+                &["--dangerously-skip-permissions", "--permission-mode"],
+            ),
+            BackendConfig::Droid(c) => (&c.extra_args, &["--auto", "--skip-permissions-unsafe"]),
+            BackendConfig::Pi(_) => {
+                if profile != SandboxProfile::Full {
+                    problems.push(format!(
+                        "sandbox.profile '{}' is not supported by the pi backend, which has no sandbox; use 'full'",
+                        profile.as_str()
+                    ));
+                }
+                continue;
+            }
+            BackendConfig::Mock(_) | BackendConfig::Replay(_) => continue,
+        };
+        for arg in extra_args {
+            let flag = arg.split('=').next().unwrap_or(arg);
+            if conflicting.contains(&flag) {
+                problems.push(format!(
+                    "{} backend extra_args '{arg}' conflicts with sandbox.profile '{}'",
+                    backend.kind(),
+                    profile.as_str()
+                ));
+            }
+        }
+    }
+    problems
+}
+
+/// Checks run before the first turn; `crank run --skip-preflight` disables them.
+#[derive(Debug, Clone, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct PreflightConfig {
+    /// Also send each backend a tiny probe turn, which catches missing auth.
+    #[serde(default)]
+    probe_turn: bool,
+}
+
+/// Size caps for the append-only logs under `state_dir/logs`. Unset caps let them grow.
+#[derive(Debug, Clone, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct LoggingConfig {
+    #[serde(default)]
+    max_events_mb: Option<u64>,
+    #[serde(default)]
+    max_turns_mb: Option<u64>,
+    /// Rotated generations to keep (`.1` is newest, default 3). `0` truncates the
+    /// log from the head instead of rotating.
+    #[serde(default)]
+    keep_rotated: Option<u32>,
+    /// Regexes masked in backend events before they are written.
+    #[serde(default)]
+    redact: Vec<String>,
+    /// Environment variables whose values are masked in backend events.
+    #[serde(default)]
+    redact_env: Vec<String>,
+}
+
+/// Caps that stop runaway loops. Unset limits are unbounded.
+#[derive(Debug, Clone, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct LimitsConfig {
+    #[serde(default)]
+    max_turns_per_task: Option<u32>,
+    #[serde(default)]
+    max_turns_per_run: Option<u64>,
+    /// Only enforced against backends that report spend (currently claude).
+    #[serde(default)]
+    max_cost_usd: Option<f64>,
+    /// Approximate budget (chars / 4) for a rendered turn prompt.
+    #[serde(default)]
+    max_prompt_tokens: Option<u64>,
+    /// Trimming rules applied in order while a prompt exceeds `max_prompt_tokens`.
+    /// Defaults to `["task_board", "digest"]`.
+    #[serde(default)]
+    prompt_trim: Option<Vec<PromptTrim>>,
+}
+
+/// A way to shrink an over-budget turn prompt.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum PromptTrim {
+    /// Collapse finished tasks on the task board, oldest first.
+    TaskBoard,
+    /// Replace the run digest with a pointer to DIGEST.md.
+    Digest,
+}
+
+const DEFAULT_PROMPT_TRIM: [PromptTrim; 2] = [PromptTrim::TaskBoard, PromptTrim::Digest];
+
+/// Rough token count for budgeting; about four characters per token.
+fn approx_tokens(text: &str) -> u64 {
+    (text.chars().count() as u64).div_ceil(4)
+}
+
+/// Periodic compression of JOURNAL.md into `state_dir/DIGEST.md`.
+#[derive(Debug, Clone, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct DigestConfig {
+    /// Ask the active backend to fold new journal entries into the digest every
+    /// this many cycles. Unset disables digests.
+    #[serde(default)]
+    every_cycles: Option<u64>,
+}
+
+/// Windows in which the governor may launch turns. Times are local.
+#[derive(Debug, Clone, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct ScheduleConfig {
+    /// RFC 3339 timestamp before which no turn is launched.
+    #[serde(default)]
+    start_after: Option<String>,
+    /// Daily window such as "22:00-07:00"; may wrap past midnight.
+    #[serde(default)]
+    active_hours: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct TimeoutsConfig {
+    #[serde(default = "default_stall_secs")]
+    stall_secs: u64,
+    /// Per-role heartbeat stall thresholds; roles not listed use `stall_secs`.
+    #[serde(default)]
+    role_stall_secs: BTreeMap<String, u64>,
+    /// Seconds each task `verify` command may run before it is killed and the
+    /// completion rejected; default 1800.
+    #[serde(default)]
+    verify_secs: Option<u64>,
+}
+
+const DEFAULT_VERIFY_TIMEOUT_SECS: u64 = 1800;
+
+impl TimeoutsConfig {
+    fn verify_timeout(&self) -> Duration {
+        Duration::from_secs(self.verify_secs.unwrap_or(DEFAULT_VERIFY_TIMEOUT_SECS))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct RecoveryConfig {
+    #[serde(default = "default_max_recovery_attempts_per_task")]
+    max_recovery_attempts_per_task: u32,
+    #[serde(default = "default_max_failures_before_block")]
+    max_failures_before_block: u32,
+    #[serde(default = "default_backoff_initial_secs")]
+    backoff_initial_secs: u64,
+    #[serde(default = "default_backoff_max_secs")]
+    backoff_max_secs: u64,
+    /// Turns in a row without a usable control block before the next
+    /// `control_strategies` entry is applied to the task.
+    #[serde(default = "default_control_miss_threshold")]
+    control_miss_threshold: u32,
+    /// Applied in order, one per `control_miss_threshold` misses.
+    #[serde(default)]
+    control_strategies: Vec<ControlStrategy>,
+    /// Regexes marking a response as a no-op ("I cannot continue", apology
+    /// loops); a matching turn counts as failed.
+    #[serde(default)]
+    degenerate_patterns: Vec<String>,
+    /// `degenerate_patterns`, compiled once by `load_config`.
+    #[serde(skip)]
+    degenerate_regexes: Vec<regex::Regex>,
+    /// Responses shorter than this (in characters, control block included)
+    /// count as failed turns.
+    #[serde(default)]
+    min_response_chars: Option<usize>,
+}
+
+/// Escalating responses to a task whose turns keep ending without a usable
+/// control block.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum ControlStrategy {
+    /// End every later prompt with a "finish with only CONTROL_JSON" instruction.
+    StrictSuffix,
+    /// Run later turns one `thinking` level lower.
+    DowngradeThinking,
+    /// Move the run to the next backend in the failover chain.
+    FallbackBackend,
+}
+
+impl ControlStrategy {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::StrictSuffix => "strict_suffix",
+            Self::DowngradeThinking => "downgrade_thinking",
+            Self::FallbackBackend => "fallback_backend",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct PolicyConfig {
+    #[serde(default)]
+    unattended_escalate: UnattendedEscalatePolicy,
+    /// Re-prompt once with a "fix your control block" nudge on schema violations.
+    #[serde(default)]
+    reprompt_invalid_control: bool,
+    #[serde(default)]
+    scheduling: SchedulingPolicy,
+    /// Only accept `state.md == done` once the reviewer quorum approved the
+    /// final request in `coord_dir/decisions/`.
+    #[serde(default)]
+    require_decision_quorum: bool,
+}
+
+/// Order in which ready pending tasks are started.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum SchedulingPolicy {
+    /// Declaration order.
+    #[default]
+    Fifo,
+    /// Highest `priority` first, then declaration order.
+    Priority,
+    /// Shallowest dependency chain first, then priority, then declaration order.
+    ShortestDepsFirst,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum UnattendedEscalatePolicy {
+    Strict,
+    BestEffortOnce,
+}
+
+impl Default for UnattendedEscalatePolicy {
+    fn default() -> Self {
+        default_unattended_escalate_policy()
+    }
+}
+
+impl UnattendedEscalatePolicy {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Strict => "strict",
+            Self::BestEffortOnce => "best_effort_once",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum BackendConfig {
+    Codex(CodexBackendConfig),
+    Claude(ClaudeBackendConfig),
+    Droid(DroidBackendConfig),
+    Pi(PiBackendConfig),
+    Mock(MockBackendConfig),
+    Replay(ReplayBackendConfig),
+}
+
+/// `[backend]`: the primary backend plus optional `fallbacks` (same shape, each
+/// with its own `kind`) tried in order once `failover_after` turns fail in a row.
+/// With `record_dir`, every live turn is also recorded there for `kind = "replay"`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "toml::Table")]
+struct BackendSection {
+    primary: BackendConfig,
+    fallbacks: Vec<BackendConfig>,
+    failover_after: u32,
+    record_dir: Option<PathBuf>,
+}
+
+const DEFAULT_FAILOVER_AFTER: u32 = 2;
+
+impl TryFrom<toml::Table> for BackendSection {
+    type Error = String;
+
+    fn try_from(mut table: toml::Table) -> Result<Self, Self::Error> {
+        let fallbacks = match table.remove("fallbacks") {
+            Some(value) => value
+                .try_into::<Vec<BackendConfig>>()
+                .map_err(|err| format!("invalid backend.fallbacks: {err}"))?,
+            None => Vec::new(),
+        };
+        let failover_after = match table.remove("failover_after") {
+            Some(value) => value
+                .try_into::<u32>()
+                .map_err(|err| format!("invalid backend.failover_after: {err}"))?,
+            None => DEFAULT_FAILOVER_AFTER,
+        };
+        let record_dir = match table.remove("record_dir") {
+            Some(value) => Some(
+                value
+                    .try_into::<PathBuf>()
+                    .map_err(|err| format!("invalid backend.record_dir: {err}"))?,
+            ),
+            None => None,
+        };
+        let primary = toml::Value::Table(table)
+            .try_into::<BackendConfig>()
+            .map_err(|err| err.to_string())?;
+        Ok(Self {
+            primary,
+            fallbacks,
+            failover_after,
+            record_dir,
+        })
+    }
+}
+
+/// `[backend]` is one backend's settings with the failover keys alongside, so
+/// each backend variant also accepts them.
+impl JsonSchema for BackendSection {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "BackendSection".into()
+    }
+
+    fn json_schema(generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        let mut schema = BackendConfig::json_schema(generator);
+        let extra = schemars::json_schema!({
+            "fallbacks": {
+                "description": "Backends tried in order once `failover_after` turns fail in a row.",
+                "type": "array",
+                "items": generator.subschema_for::<BackendConfig>(),
+            },
+            "failover_after": {
+                "type": "integer",
+                "minimum": 0,
+                "default": DEFAULT_FAILOVER_AFTER,
+            },
+            "record_dir": {
+                "description": "Also record every live turn here for `kind = \"replay\"`.",
+                "type": "string",
+            },
+        });
+        if let Some(Value::Array(variants)) = schema.get_mut("oneOf") {
+            for variant in variants {
+                if let Some(Value::Object(properties)) = variant.get_mut("properties")
+                    && let Some(extra) = extra.as_object()
+                {
+                    properties.extend(extra.clone());
+                }
+            }
+        }
+        schema.insert(
+            "description".to_string(),
+            Value::from(
+                "`[backend]`: the primary backend (by `kind`) plus optional failover settings.",
+            ),
+        );
+        schema
+    }
+}
+
+impl From<BackendConfig> for BackendSection {
+    fn from(primary: BackendConfig) -> Self {
+        Self {
+            primary,
+            fallbacks: Vec::new(),
+            failover_after: DEFAULT_FAILOVER_AFTER,
+            record_dir: None,
+        }
+    }
+}
+
+impl BackendSection {
+    /// Backend at `index` in the failover chain (0 is the primary).
+    fn at(&self, index: usize) -> &BackendConfig {
+        match index {
+            0 => &self.primary,
+            n => self.fallbacks.get(n - 1).unwrap_or(&self.primary),
+        }
+    }
+
+    fn chain(&self) -> impl Iterator<Item = &BackendConfig> {
+        std::iter::once(&self.primary).chain(&self.fallbacks)
+    }
+
+    /// One backend per chain entry, wrapped for recording when `record_dir` is set.
+    fn build(&self) -> Vec<Box<dyn Backend>> {
+        self.chain()
+            .map(|config| match config {
+                BackendConfig::Replay(_) => create_backend(config),
+                _ => self.recorded(create_backend(config)),
+            })
+            .collect()
+    }
+
+    /// Wraps `backend` so its turns are written to `record_dir`, when set.
+    fn recorded(&self, backend: Box<dyn Backend>) -> Box<dyn Backend> {
+        match &self.record_dir {
+            Some(dir) => Box::new(RecordingBackend {
+                inner: backend,
+                dir: dir.clone(),
+            }),
+            None => backend,
+        }
+    }
+}
+
+impl BackendConfig {
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Codex(_) => "codex",
+            Self::Claude(_) => "claude",
+            Self::Droid(_) => "droid",
+            Self::Pi(_) => "pi",
+            Self::Mock(_) => "mock",
+            Self::Replay(_) => "replay",
+        }
+    }
+
+    fn thinking(&self) -> Option<&str> {
+        match self {
+            Self::Codex(codex) => Some(&codex.thinking),
+            Self::Claude(claude) => Some(&claude.thinking),
+            Self::Droid(droid) => Some(&droid.thinking),
+            Self::Pi(pi) => Some(&pi.thinking),
+            Self::Mock(_) | Self::Replay(_) => None,
+        }
+    }
+
+    fn binary(&self) -> Option<&str> {
+        match self {
+            Self::Codex(codex) => Some(&codex.binary),
+            Self::Claude(claude) => Some(&claude.binary),
+            Self::Droid(droid) => Some(&droid.binary),
+            Self::Pi(pi) => Some(&pi.binary),
+            Self::Mock(_) | Self::Replay(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct CodexBackendConfig {
+    #[serde(default = "default_codex_binary")]
+    binary: String,
+    model: String,
+    thinking: String,
+    #[serde(default = "default_approval_policy")]
+    approval_policy: String,
+    #[serde(default = "default_sandbox_mode")]
+    sandbox_mode: String,
+    /// Named profile from the codex config, passed as `--profile`.
+    #[serde(default)]
+    profile: Option<String>,
+    /// Codex config TOML whose settings are passed as `--config` overrides.
+    #[serde(default)]
+    config_file: Option<PathBuf>,
+    /// `--config key=value` overrides, applied after `config_file`.
+    #[serde(default)]
+    #[schemars(with = "BTreeMap<String, Value>")]
+    config_overrides: BTreeMap<String, toml::Value>,
+    #[serde(default)]
+    extra_args: Vec<String>,
+}
+
+/// Flattens a codex config table into `dotted.key=value` overrides, one per
+/// leaf, so each setting merges into the codex config instead of replacing a
+/// whole table.
+fn flatten_codex_config(prefix: &str, table: &toml::Table, out: &mut Vec<String>) {
+    for (key, value) in table {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{prefix}.{key}")
+        };
+        match value {
+            toml::Value::Table(inner) => flatten_codex_config(&path, inner, out),
+            other => out.push(format!("{path}={other}")),
+        }
+    }
+}
+
+/// `--config` values from `config_file` and then `config_overrides`. Codex
+/// applies them in order, so inline overrides win over the file.
+fn codex_config_overrides(backend: &CodexBackendConfig) -> Result<Vec<String>> {
+    let mut out = Vec::new();
+    if let Some(path) = &backend.config_file {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read codex config_file {}", path.display()))?;
+        let table: toml::Table = toml::from_str(&text)
+            .with_context(|| format!("failed to parse codex config_file {}", path.display()))?;
+        flatten_codex_config("", &table, &mut out);
+    }
+    for (key, value) in &backend.config_overrides {
+        out.push(format!("{key}={value}"));
+    }
+    Ok(out)
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct ClaudeBackendConfig {
+    #[serde(default = "default_claude_binary")]
+    binary: String,
+    model: String,
+    thinking: String,
+    /// Extended-thinking budget passed as `--thinking-budget-tokens`; overrides
+    /// the budget `thinking` maps to in `CLAUDE_THINKING_BUDGETS`.
+    #[serde(default)]
+    thinking_budget_tokens: Option<u32>,
+    #[serde(default)]
+    extra_args: Vec<String>,
+}
+
+/// Extended-thinking token budget for each claude `thinking` level. `xhigh`
+/// gets its own budget rather than running as `high`, so teams that say
+/// `xhigh` behave the same way on every backend that distinguishes it.
+const CLAUDE_THINKING_BUDGETS: &[(&str, u32)] = &[
+    ("low", 4_000),
+    ("medium", 10_000),
+    ("high", 32_000),
+    ("xhigh", 64_000),
+];
+
+impl ClaudeBackendConfig {
+    fn thinking_budget(&self) -> Option<u32> {
+        self.thinking_budget_tokens
+            .or_else(|| claude_level_budget(&self.thinking))
+    }
+}
+
+fn claude_level_budget(level: &str) -> Option<u32> {
+    CLAUDE_THINKING_BUDGETS
+        .iter()
+        .find(|(name, _)| *name == level)
+        .map(|(_, budget)| *budget)
+}
+
+/// `thinking` levels every backend accepts, lowest first.
+const THINKING_LEVELS: &[&str] = &["low", "medium", "high", "xhigh"];
+
+/// One level below `current`, or `None` at the bottom or for custom levels.
+fn lower_thinking(current: &str) -> Option<&'static str> {
+    let idx = THINKING_LEVELS.iter().position(|level| *level == current)?;
+    idx.checked_sub(1).map(|lower| THINKING_LEVELS[lower])
+}
+
+/// Claude backends whose thinking budget cannot be resolved.
+fn claude_thinking_problems(cfg: &Config) -> Vec<String> {
+    let levels: Vec<&str> = CLAUDE_THINKING_BUDGETS.iter().map(|(l, _)| *l).collect();
+    cfg.backend
+        .chain()
+        .filter_map(|backend| match backend {
+            BackendConfig::Claude(c) if c.thinking_budget_tokens == Some(0) => {
+                Some("claude backend thinking_budget_tokens must be positive".to_string())
+            }
+            BackendConfig::Claude(c) if c.thinking_budget().is_none() => Some(format!(
+                "claude backend thinking '{}' has no token budget; use one of {} or set thinking_budget_tokens",
+                c.thinking,
+                levels.join(", ")
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct DroidBackendConfig {
+    #[serde(default = "default_droid_binary")]
+    binary: String,
+    model: String,
+    thinking: String,
+    #[serde(default = "default_droid_autonomy")]
+    auto: String,
+    #[serde(default)]
+    extra_args: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct PiBackendConfig {
+    #[serde(default = "default_pi_binary")]
+    binary: String,
+    model: String,
+    thinking: String,
+    #[serde(default)]
+    provider: Option<String>,
+    #[serde(default)]
+    extra_args: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct MockBackendConfig {
+    #[serde(default = "default_mock_steps_per_task")]
+    steps_per_task: u32,
+    /// TOML file of scripted turns per task; tasks without a script, or whose
+    /// script ran out, fall back to `steps_per_task`.
+    #[serde(default)]
+    scenario: Option<PathBuf>,
+}
+
+/// One scripted mock turn. Scenario files map task ids to arrays of these:
+/// `[[call-audio]]` tables with an `action` and optional fields.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MockStep {
+    action: MockAction,
+    /// Control block summary, or the error text of a `fail` turn.
+    #[serde(default)]
+    message: Option<String>,
+    /// Seconds a `stall` turn waits without signalling activity.
+    #[serde(default)]
+    sleep_secs: u64,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum MockAction {
+    /// Heartbeat, `state.md = active`, `in_progress` control block.
+    Progress,
+    /// `state.md = done`, `completed` control block.
+    Complete,
+    /// `blocked` control block.
+    Blocked,
+    /// `in_progress` with `next_action = "ESCALATE"` and a question request.
+    Escalate,
+    /// The turn itself fails.
+    Fail,
+    /// No coord writes and no activity, then an `in_progress` control block.
+    Stall,
+    /// A response without a control block.
+    NoControl,
+    /// A control block that violates the schema.
+    InvalidControl,
+}
+
+fn load_mock_scenario(path: &Path) -> Result<BTreeMap<String, Vec<MockStep>>> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("failed to read mock scenario {}", path.display()))?;
+    toml::from_str(&text)
+        .with_context(|| format!("failed to parse mock scenario {}", path.display()))
+}
+
+/// Plays back turns recorded under `[backend] record_dir`, in order per task.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct ReplayBackendConfig {
+    dir: PathBuf,
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct RolesConfig {
+    implementer: RoleConfig,
+    reviewer_1: RoleConfig,
+    reviewer_2: RoleConfig,
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct RoleConfig {
+    harness: String,
+    model: String,
+    thinking: String,
+    #[serde(default)]
+    launch_args: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct TeamFile {
+    name: Option<String>,
+    description: Option<String>,
+    roles: RolesConfig,
+}
+
+#[derive(Debug, Clone, Deserialize, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct TaskConfig {
+    id: String,
+    todo_file: PathBuf,
+    #[serde(default)]
+    depends_on: Vec<String>,
+    coord_dir: Option<PathBuf>,
+    completion_file: Option<PathBuf>,
+    /// Hex sha256 `completion_file` must have before it counts.
+    #[serde(default)]
+    completion_file_sha256: Option<String>,
+    /// Further artifacts completion waits on, besides the completion artifact.
+    #[serde(default)]
+    completion_requires: Vec<CompletionRequirement>,
+    #[serde(default)]
+    prompt_template: Option<PathBuf>,
+    /// Checkout this task works in instead of the run `workspace`; relative
+    /// paths resolve against the run workspace.
+    #[serde(default)]
+    workspace: Option<PathBuf>,
+    /// Run this task's turns in a dedicated git worktree and branch.
+    #[serde(default)]
+    branch: bool,
+    /// Shell commands that must succeed before a completion artifact is accepted.
+    #[serde(default)]
+    verify: Vec<String>,
+    /// Higher runs first under `scheduling = "priority"`.
+    #[serde(default)]
+    priority: i32,
+    /// Start the task only when this holds once its dependencies are done.
+    #[serde(default)]
+    run_if: Option<TaskCondition>,
+    /// Skip the task when this holds once its dependencies are done.
+    #[serde(default)]
+    skip_if: Option<TaskCondition>,
+    /// Extra environment for this task's backend turns.
+    #[serde(default)]
+    env: BTreeMap<String, String>,
+}
+
+/// An artifact a task's completion requires, as a bare path or as
+/// `{ path = "...", matches = "<regex>" }` to also require matching content.
+/// Relative paths resolve against the task's coord dir.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(untagged)]
+enum CompletionRequirement {
+    Exists(PathBuf),
+    Matches { path: PathBuf, matches: String },
+}
+
+impl CompletionRequirement {
+    fn path(&self, coord_dir: &Path) -> PathBuf {
+        let path = match self {
+            Self::Exists(path) | Self::Matches { path, .. } => path,
+        };
+        coord_dir.join(path)
+    }
+
+    /// Why the requirement does not hold yet, if it does not.
+    fn unmet(&self, coord_dir: &Path) -> Option<String> {
+        let path = self.path(coord_dir);
+        if !path.is_file() {
+            return Some(format!("{} does not exist", path.display()));
+        }
+        let Self::Matches { matches, .. } = self else {
+            return None;
+        };
+        let regex = match regex::Regex::new(matches) {
+            Ok(regex) => regex,
+            Err(err) => return Some(format!("invalid pattern '{matches}': {err}")),
+        };
+        match fs::read_to_string(&path) {
+            Ok(text) if regex.is_match(&text) => None,
+            Ok(_) => Some(format!("{} does not match /{matches}/", path.display())),
+            Err(err) => Some(format!("{} could not be read: {err}", path.display())),
+        }
+    }
+}
+
+/// Condition checked when a pending task becomes ready. Relative paths resolve
+/// against the task's workspace.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct TaskCondition {
+    #[serde(default)]
+    file_exists: Option<PathBuf>,
+}
+
+impl TaskCondition {
+    fn holds(&self, workspace: &Path) -> bool {
+        match &self.file_exists {
+            Some(path) => workspace.join(path).exists(),
+            None => true,
+        }
+    }
+
+    fn describe(&self) -> String {
+        match &self.file_exists {
+            Some(path) => format!("file_exists = {}", path.display()),
+            None => "empty condition".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RunStatus {
+    Running,
+    Completed,
+    FailedTerminal,
+    LimitReached,
+}
+
+impl RunStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Running => "running",
+            Self::Completed => "completed",
+            Self::FailedTerminal => "failed_terminal",
+            Self::LimitReached => "limit_reached",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskStatus {
+    Pending,
+    Running,
+    Completed,
+    BlockedBestEffort,
+    /// A `run_if`/`skip_if` condition ruled the task out.
+    Skipped,
+    /// The operator called the task off (`ctl cancel`).
+    Cancelled,
+}
+
+impl std::str::FromStr for TaskStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_json::from_value(Value::from(s)).map_err(|_| format!("unknown task status '{s}'"))
+    }
+}
+
+impl TaskStatus {
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            Self::Completed | Self::BlockedBestEffort | Self::Skipped | Self::Cancelled
+        )
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Running => "running",
+            Self::Completed => "completed",
+            Self::BlockedBestEffort => "blocked_best_effort",
+            Self::Skipped => "skipped",
+            Self::Cancelled => "cancelled",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+struct TaskRuntime {
+    id: String,
+    todo_file: String,
+    depends_on: Vec<String>,
+    status: TaskStatus,
+    coord_dir: String,
+    completion_file: Option<String>,
+    started_at: Option<String>,
+    completed_at: Option<String>,
+    #[serde(default)]
+    blocked_reason: Option<String>,
+    last_progress_epoch: Option<i64>,
+    recovery_attempts: u32,
+    #[serde(default)]
+    unattended_escalate_retries: u32,
+    #[serde(default)]
+    thread_id: Option<String>,
+    #[serde(default)]
+    turns: u32,
+    #[serde(default)]
+    last_git_snapshot: Option<GitSnapshot>,
+    /// Checkout the task's turns run in when it differs from the run workspace.
+    #[serde(default)]
+    workspace: Option<String>,
+    #[serde(default)]
+    branch: Option<String>,
+    #[serde(default)]
+    verify: Vec<String>,
+    /// Artifact mtime at the last failed verification; a newer artifact is re-verified.
+    #[serde(default)]
+    verify_rejected_epoch: Option<i64>,
+    /// Recovery note delivered with the task's next turn.
+    #[serde(default)]
+    pending_note: Option<String>,
+    /// State written before this flag existed only held tasks whose hooks had
+    /// run, so a missing flag means "ran"; non-terminal tasks reset it.
+    #[serde(default = "default_post_task_hooks_ran")]
+    post_task_hooks_ran: bool,
+    #[serde(default)]
+    priority: i32,
+    #[serde(default)]
+    run_if: Option<TaskCondition>,
+    #[serde(default)]
+    skip_if: Option<TaskCondition>,
+    /// Latest `heartbeats/<role>.json` of each orchestrator role, refreshed while running.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    heartbeats: Vec<RoleHeartbeat>,
+    /// Checkbox tally of `todo_file`, refreshed every cycle.
+    #[serde(default)]
+    todo_progress: Option<TodoProgress>,
+    /// Files under `coord_dir/artifacts/`, indexed while the task runs.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    artifacts: Vec<ArtifactRecord>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    completion_file_sha256: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    completion_requires: Vec<CompletionRequirement>,
+    /// Turns in a row that ended without a usable control block.
+    #[serde(default)]
+    control_misses: u32,
+    /// `[recovery] control_strategies` applied to this task so far.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    control_strategies: Vec<ControlStrategy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    thinking_override: Option<String>,
+}
+
+/// A deliverable the orchestrator wrote under `coord_dir/artifacts/`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+struct ArtifactRecord {
+    /// Path relative to the artifacts directory, `/`-separated.
+    name: String,
+    size: u64,
+    sha256: String,
+    /// Cycle in which this content was first seen.
+    cycle: u64,
+    #[serde(default)]
+    modified_epoch: Option<i64>,
+}
+
+/// Checked vs total `- [ ]` / `- [x]` items in a task's plan file.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+struct TodoProgress {
+    checked: u32,
+    total: u32,
+}
+
+impl TodoProgress {
+    fn ratio(self) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            f64::from(self.checked) / f64::from(self.total)
+        }
+    }
+}
+
+/// `<coord_dir>/heartbeats/<role>.json`, written by an orchestrator role.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+struct RoleHeartbeat {
+    /// File stem; not part of the file itself.
+    #[serde(default)]
+    role: String,
+    epoch: i64,
+    #[serde(default)]
+    phase: String,
+    #[serde(default)]
+    message: String,
+    /// Set once this heartbeat has triggered a stall recovery, so it fires once.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    stall_reported: bool,
+}
+
+impl RoleHeartbeat {
+    /// Roles in these phases are expected to be quiet.
+    fn is_idle(&self) -> bool {
+        matches!(self.phase.as_str(), "done" | "idle")
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+struct GitSnapshot {
+    cycle: u64,
+    taken_at: String,
+    head: String,
+    /// Count of `git status --porcelain` entries per status code, e.g. "M:2 ??:1".
+    status_summary: String,
+    changed_files: usize,
+    diffstat: String,
+}
+
+/// Persisted progress of a run (`state.json`).
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RunState {
+    run_id: String,
+    workspace: String,
+    state_dir: String,
+    unattended: bool,
+    status: RunStatus,
+    started_at: String,
+    updated_at: String,
+    journal_path: String,
+    /// Legacy run-wide backend thread; migrated onto a task on load.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    thread_id: Option<String>,
+    cycle: u64,
+    last_turn_at: Option<String>,
+    #[serde(default)]
+    cost_usd: f64,
+    #[serde(default)]
+    stop_reason: Option<String>,
+    /// Position in the `[backend]` failover chain; 0 is the primary.
+    #[serde(default)]
+    backend_index: usize,
+    /// Cycle of the last journal digest attempt.
+    #[serde(default)]
+    digest_cycle: u64,
+    /// Bytes of JOURNAL.md already folded into DIGEST.md.
+    #[serde(default)]
+    digest_journal_offset: u64,
+    /// The turn in flight, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    live_turn: Option<LiveTurn>,
+    tasks: Vec<TaskRuntime>,
+}
+
+/// Progress of the turn in flight, counted from the backend's event stream and
+/// saved with the state while the turn runs, so a snapshot during a long turn
+/// shows what it is doing. Cleared when the turn returns.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, JsonSchema)]
+struct LiveTurn {
+    task_id: String,
+    started_at: String,
+    events: u64,
+    bytes: u64,
+    #[serde(default)]
+    last_event_type: Option<String>,
+    #[serde(default)]
+    last_event_at: Option<String>,
+}
+
+impl LiveTurn {
+    fn observe(&mut self, raw_line: &str) {
+        self.events = self.events.saturating_add(1);
+        self.bytes = self.bytes.saturating_add(raw_line.len() as u64);
+        self.last_event_type = serde_json::from_str::<Value>(raw_line)
+            .ok()
+            .and_then(|v| v.get("type")?.as_str().map(str::to_string))
+            .or_else(|| Some("text".to_string()));
+        self.last_event_at = Some(now_iso());
+    }
+}
+
+/// Outcome of one backend turn.
+#[derive(Debug, Clone)]
+pub struct TurnResult {
+    /// Backend thread/session to resume on the task's next turn.
+    pub thread_id: Option<String>,
+    pub final_response: String,
+    /// Spend reported by the backend for this turn, when it reports one.
+    pub cost_usd: Option<f64>,
+}
+
+/// Everything a backend needs for one turn of one task.
+pub struct TurnContext<'a> {
+    pub task_id: &'a str,
+    pub prompt: &'a str,
+    /// Thread/session from the task's previous turn, if any.
+    pub thread_id: Option<&'a str>,
+    /// Checkout the turn runs in (the task worktree for `branch = true` tasks).
+    pub workspace: PathBuf,
+    pub coord_dir: &'a Path,
+    pub state_dir: &'a Path,
+    events_log: PathBuf,
+    redactor: &'a Redactor,
+    /// Also collects the (redacted) event lines while a turn is being recorded.
+    event_sink: Option<&'a RefCell<Vec<String>>>,
+    /// Tallies event lines into the governor's `live_turn`.
+    progress: Option<&'a RefCell<LiveTurn>>,
+    /// `[security] env_allowlist`; `None` inherits the governor's environment.
+    env_allowlist: Option<&'a [String]>,
+    /// The task's `env` table, set on top of the (possibly scrubbed) environment.
+    task_env: Option<&'a BTreeMap<String, String>>,
+    /// `[sandbox] profile`, overriding the backend's own permission settings.
+    sandbox: Option<SandboxProfile>,
+    /// Lowered `thinking` level from a control-block recovery strategy.
+    thinking: Option<&'a str>,
+}
+
+/// Redactor for contexts built outside the governor, which has no `[logging]`.
+static NO_REDACTION: Redactor = Redactor {
+    patterns: Vec::new(),
+};
+
+impl<'a> TurnContext<'a> {
+    /// A fresh-thread turn with no redaction, environment policy, or sandbox,
+    /// logging events to the run's usual events log under `state_dir`. For
+    /// driving a [`Backend`] outside [`run_governor`]; set `thread_id` to resume.
+    pub fn new(
+        task_id: &'a str,
+        prompt: &'a str,
+        workspace: PathBuf,
+        coord_dir: &'a Path,
+        state_dir: &'a Path,
+    ) -> Self {
+        Self {
+            task_id,
+            prompt,
+            thread_id: None,
+            workspace,
+            coord_dir,
+            state_dir,
+            events_log: events_log_path(state_dir),
+            redactor: &NO_REDACTION,
+            event_sink: None,
+            progress: None,
+            env_allowlist: None,
+            task_env: None,
+            sandbox: None,
+            thinking: None,
+        }
+    }
+
+    /// Applies the run's environment policy to a backend child process.
+    pub fn apply_env(&self, cmd: &mut Command) {
+        if let Some(allowlist) = self.env_allowlist {
+            cmd.env_clear();
+            for (key, value) in std::env::vars_os() {
+                if let Some(name) = key.to_str()
+                    && env_allowed(allowlist, name)
+                {
+                    cmd.env(&key, value);
+                }
+            }
+        }
+        for (key, value) in self.task_env.into_iter().flatten() {
+            cmd.env(key, value);
+        }
+    }
+
+    /// Appends a raw backend event line to the run's events log, redacted.
+    pub fn append_event(&self, raw_line: &str) -> Result<()> {
+        if let Some(sink) = self.event_sink {
+            sink.borrow_mut().push(self.redactor.apply(raw_line));
+        }
+        if let Some(progress) = self.progress {
+            progress.borrow_mut().observe(raw_line);
+        }
+        append_event_line(&self.events_log, raw_line, self.redactor)
+    }
+}
+
+/// Allowlist entries match a variable name exactly, or by prefix when they end in `*`.
+fn env_allowed(allowlist: &[String], name: &str) -> bool {
+    allowlist.iter().any(|entry| match entry.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => entry == name,
+    })
+}
+
+/// A harness that can execute a turn. Built from `[backend]` config by
+/// `create_backend`, or supplied by an embedder through
+/// [`GovernorOptions::backend_factory`].
+pub trait Backend {
+    fn kind(&self) -> &'static str;
+
+    /// Runs one turn, calling `on_activity` whenever the harness shows signs of life.
+    fn run_turn(
+        &self,
+        ctx: TurnContext<'_>,
+        on_activity: &mut dyn FnMut() -> Result<()>,
+    ) -> Result<TurnResult>;
+}
+
+const CONTROL_SCHEMA_VERSION: u32 = 2;
+
+/// Machine-readable block the orchestrator ends each turn with. Version 1
+/// blocks (no `version`, no lists) still parse; unknown keys are rejected.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ControlBlock {
+    #[serde(default = "default_control_version")]
+    version: u32,
+    task_id: String,
+    status: ControlStatus,
+    #[serde(default)]
+    needs_user_input: bool,
+    #[serde(default)]
+    summary: String,
+    #[serde(default)]
+    next_action: String,
+    #[serde(default)]
+    artifacts: Vec<String>,
+    #[serde(default)]
+    blockers: Vec<String>,
+    #[serde(default)]
+    requests: Vec<ControlRequest>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum ControlStatus {
+    InProgress,
+    Completed,
+    Blocked,
+}
+
+/// Case-insensitive, so `"BLOCKED"` or `"In-Progress"` from a model still
+/// counts; `blocked_best_effort` is an alias for `blocked`.
+impl<'de> Deserialize<'de> for ControlStatus {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = String::deserialize(deserializer)?;
+        match raw
+            .trim()
+            .to_ascii_lowercase()
+            .replace(['-', ' '], "_")
+            .as_str()
+        {
+            "in_progress" => Ok(Self::InProgress),
+            "completed" => Ok(Self::Completed),
+            "blocked" | "blocked_best_effort" => Ok(Self::Blocked),
+            _ => Err(serde::de::Error::unknown_variant(
+                &raw,
+                &["in_progress", "completed", "blocked"],
+            )),
+        }
+    }
+}
+
+impl ControlStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::InProgress => "in_progress",
+            Self::Completed => "completed",
+            Self::Blocked => "blocked",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ControlRequest {
+    kind: String,
+    #[serde(default)]
+    detail: String,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+enum ControlBlockError {
+    Missing,
+    Invalid(String),
+}
+
+impl std::fmt::Display for ControlBlockError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Missing => write!(f, "no CONTROL_JSON block found"),
+            Self::Invalid(reason) => write!(f, "invalid CONTROL_JSON block: {reason}"),
+        }
+    }
+}
+
+struct LockGuard {
+    lock_path: PathBuf,
+}
+
+impl LockGuard {
+    fn acquire(state_dir: &Path) -> Result<Self> {
+        ensure_dir(state_dir)?;
+        let lock_path = state_dir.join("run.lock");
+        let mut file = match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&lock_path)
+        {
+            Ok(file) => file,
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => {
+                if try_break_stale_lock(&lock_path)? {
+                    OpenOptions::new()
+                        .write(true)
+                        .create_new(true)
+                        .open(&lock_path)
+                        .with_context(|| {
+                            format!(
+                                "could not acquire lock {} after removing stale lock",
+                                lock_path.display()
+                            )
+                        })?
+                } else {
+                    return Err(anyhow!(
+                        "could not acquire lock {} (another crank run may be active)",
+                        lock_path.display()
+                    ));
+                }
+            }
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("could not acquire lock {}", lock_path.display()));
+            }
+        };
+        writeln!(file, "pid={}", std::process::id())?;
+        Ok(Self { lock_path })
+    }
+}
+
+impl Drop for LockGuard {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.lock_path);
+    }
+}
+
+fn lock_pid(lock_path: &Path) -> Option<u32> {
+    let text = fs::read_to_string(lock_path).ok()?;
+    for line in text.lines() {
+        if let Some(raw) = line.strip_prefix("pid=")
+            && let Ok(pid) = raw.trim().parse::<u32>()
+        {
+            return Some(pid);
+        }
+    }
+    None
+}
+
+fn process_is_alive(pid: u32) -> bool {
+    Command::new("kill")
+        .arg("-0")
+        .arg(pid.to_string())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+fn try_break_stale_lock(lock_path: &Path) -> Result<bool> {
+    let Some(pid) = lock_pid(lock_path) else {
+        return Ok(false);
+    };
+    if process_is_alive(pid) {
+        return Ok(false);
+    }
+    fs::remove_file(lock_path)
+        .with_context(|| format!("failed to remove stale lock {}", lock_path.display()))?;
+    Ok(true)
+}
+
+/// Delay before retrying a lease renewal that failed to read or write.
+const LEASE_RETRY_SECS: i64 = 30;
+
+/// `<lease_dir>/<task_id>.json`: which node works on a task, or how it ended.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+struct TaskLease {
+    task_id: String,
+    node: String,
+    status: TaskStatus,
+    updated_at: String,
+    /// Past this a running lease may be taken over; ignored once terminal.
+    expires_epoch: i64,
+}
+
+/// One governor's view of the shared `[cluster]` lease directory.
+struct LeaseBoard {
+    dir: PathBuf,
+    node: String,
+    lease_secs: u64,
+}
+
+impl LeaseBoard {
+    fn new(cluster: &ClusterConfig) -> Result<Self> {
+        ensure_dir(&cluster.lease_dir)?;
+        let node = cluster.node_id.clone().unwrap_or_else(local_hostname);
+        Ok(Self {
+            dir: cluster.lease_dir.clone(),
+            node,
+            lease_secs: cluster.lease_secs,
+        })
+    }
+
+    fn path(&self, task_id: &str) -> PathBuf {
+        self.dir.join(format!("{task_id}.json"))
+    }
+
+    fn read(&self, task_id: &str) -> Result<Option<TaskLease>> {
+        let path = self.path(task_id);
+        match fs::read_to_string(&path) {
+            Ok(text) => serde_json::from_str(&text)
+                .map(Some)
+                .with_context(|| format!("failed to parse lease {}", path.display())),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err).with_context(|| format!("failed to read {}", path.display())),
+        }
+    }
+
+    fn lease(&self, task_id: &str, status: TaskStatus) -> TaskLease {
+        TaskLease {
+            task_id: task_id.to_string(),
+            node: self.node.clone(),
+            status,
+            updated_at: now_iso(),
+            expires_epoch: now_epoch().saturating_add(self.lease_secs as i64),
+        }
+    }
+
+    /// Writes a node-private temp file, then links it into place (`create_new`)
+    /// or renames it over the old lease (`replace`); both are atomic on NFS.
+    fn write(&self, lease: &TaskLease, replace: bool) -> Result<bool> {
+        let path = self.path(&lease.task_id);
+        let tmp = self
+            .dir
+            .join(format!(".{}.{}.tmp", lease.task_id, self.node));
+        fs::write(&tmp, serde_json::to_vec_pretty(lease)?)
+            .with_context(|| format!("failed to write {}", tmp.display()))?;
+        let placed = if replace {
+            fs::rename(&tmp, &path).map(|()| true)
+        } else {
+            let linked = fs::hard_link(&tmp, &path);
+            let _ = fs::remove_file(&tmp);
+            match linked {
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => Ok(false),
+                other => other.map(|()| true),
+            }
+        };
+        placed.with_context(|| format!("failed to write lease {}", path.display()))
+    }
+
+    /// Takes (or renews) the task's lease. Returns the lease standing in the
+    /// way when another node holds it or it has already ended.
+    fn claim(&self, task_id: &str) -> Result<Option<TaskLease>> {
+        let fresh = self.lease(task_id, TaskStatus::Running);
+        match self.read(task_id)? {
+            None => {
+                if self.write(&fresh, false)? {
+                    return Ok(None);
+                }
+            }
+            Some(current) if current.status.is_terminal() => return Ok(Some(current)),
+            Some(current) if current.node != self.node && current.expires_epoch > now_epoch() => {
+                return Ok(Some(current));
+            }
+            Some(_) => {
+                self.write(&fresh, true)?;
+            }
+        }
+        // Another node may have raced us; whoever's lease is on disk wins.
+        match self.read(task_id)? {
+            Some(current) if current.node == self.node && !current.status.is_terminal() => Ok(None),
+            Some(current) => Ok(Some(current)),
+            None => Err(anyhow!(
+                "lease for task {task_id} vanished while claiming it"
+            )),
+        }
+    }
+
+    /// Renews the lease of a task mid-turn. Returns the node that took the task
+    /// over, if any, and when to renew next. Lease dir errors are journaled
+    /// and retried after `LEASE_RETRY_SECS` rather than failing the turn.
+    fn renew(&self, task_id: &str, journal: &Path) -> Result<(Option<TaskLease>, i64)> {
+        let now = now_epoch();
+        match self.claim(task_id) {
+            Ok(holder) => Ok((holder, now + self.lease_secs as i64 / 2)),
+            Err(err) => {
+                append_journal(
+                    journal,
+                    "lease renewal failed",
+                    &format!("Task {task_id}: {err:#}. Retrying in {LEASE_RETRY_SECS}s."),
+                )?;
+                Ok((None, now + LEASE_RETRY_SECS))
+            }
+        }
+    }
+
+    /// Reconciles local task state with the shared leases: publishes tasks that
+    /// ended here, adopts outcomes from other nodes, renews leases of running
+    /// tasks, and gives up tasks another node took over. Returns the ids of
+    /// tasks another node is working on.
+    fn sync(
+        &self,
+        state: &mut RunState,
+        journal: &Path,
+    ) -> Result<std::collections::BTreeSet<String>> {
+        let mut elsewhere = std::collections::BTreeSet::new();
+        for task in &mut state.tasks {
+            let lease = self.read(&task.id)?;
+            if task.status.is_terminal() {
+                if lease
+                    .as_ref()
+                    .is_none_or(|l| l.node == self.node && !l.status.is_terminal())
+                {
+                    self.write(&self.lease(&task.id, task.status.clone()), true)?;
+                }
+                continue;
+            }
+            let lease = match lease {
+                // Reopened here (e.g. an answered escalation): the old outcome is void.
+                Some(l) if l.node == self.node && l.status.is_terminal() => {
+                    let _ = fs::remove_file(self.path(&task.id));
+                    None
+                }
+                other => other,
+            };
+            if let Some(l) = lease.as_ref().filter(|l| l.status.is_terminal()) {
+                task.status = l.status.clone();
+                if task.completed_at.is_none() {
+                    task.completed_at = Some(now_iso());
+                }
+                if l.status == TaskStatus::BlockedBestEffort {
+                    task.blocked_reason = Some(format!("blocked on node {}", l.node));
+                }
+                task.last_progress_epoch = Some(now_epoch());
+                // The node that finished the task ran its post_task hooks.
+                task.post_task_hooks_ran = true;
+                append_journal(
+                    journal,
+                    "task finished on another node",
+                    &format!(
+                        "Task {} ended as {} on node {}.",
+                        task.id,
+                        l.status.as_str(),
+                        l.node
+                    ),
+                )?;
+                continue;
+            }
+            if task.status == TaskStatus::Running {
+                if let Some(holder) = self.claim(&task.id)? {
+                    task.status = TaskStatus::Pending;
+                    append_journal(
+                        journal,
+                        "task lease lost",
+                        &format!(
+                            "Task {} is now leased by node {}; stopped working on it here.",
+                            task.id, holder.node
+                        ),
+                    )?;
+                    elsewhere.insert(task.id.clone());
+                }
+                continue;
+            }
+            if lease.is_some_and(|l| l.node != self.node && l.expires_epoch > now_epoch()) {
+                elsewhere.insert(task.id.clone());
+            }
+        }
+        Ok(elsewhere)
+    }
+
+    /// Publishes final outcomes and frees leases of tasks left running, so
+    /// other nodes can pick them up without waiting for the lease to expire.
+    fn settle(&self, state: &RunState) -> Result<()> {
+        for task in &state.tasks {
+            let lease = self.read(&task.id)?;
+            let ours = lease
+                .as_ref()
+                .is_some_and(|l| l.node == self.node && !l.status.is_terminal());
+            if task.status.is_terminal() && (ours || lease.is_none()) {
+                self.write(&self.lease(&task.id, task.status.clone()), true)?;
+            } else if task.status == TaskStatus::Running && ours {
+                let _ = fs::remove_file(self.path(&task.id));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn local_hostname() -> String {
+    Command::new("hostname")
+        .output()
+        .ok()
+        .filter(|out| out.status.success())
+        .map(|out| String::from_utf8_lossy(&out.stdout).trim().to_string())
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "localhost".to_string())
+}
+
+fn default_lease_secs() -> u64 {
+    1800
+}
+
+fn default_unattended() -> bool {
+    true
+}
+
+fn default_post_task_hooks_ran() -> bool {
+    true
+}
+
+fn default_poll_interval() -> u64 {
+    30
+}
+
+fn default_stall_secs() -> u64 {
+    900
+}
+
+fn default_max_recovery_attempts_per_task() -> u32 {
+    4
+}
+
+fn default_max_failures_before_block() -> u32 {
+    6
+}
+
+/// Why a turn's response looks like a polite no-op rather than work, per
+/// `[recovery] min_response_chars` and `degenerate_patterns`.
+fn degenerate_response_reason(recovery: &RecoveryConfig, response: &str) -> Option<String> {
+    let chars = response.trim().chars().count();
+    if let Some(min) = recovery.min_response_chars
+        && chars < min
+    {
+        return Some(format!(
+            "response is {chars} chars, below min_response_chars ({min})"
+        ));
+    }
+    recovery
+        .degenerate_regexes
+        .iter()
+        .find(|re| re.is_match(response))
+        .map(|re| format!("response matches degenerate pattern '{}'", re.as_str()))
+}
+
+/// Compiles `[recovery] degenerate_patterns`, failing on the first invalid one.
+fn compile_degenerate_patterns(recovery: &mut RecoveryConfig) -> Result<()> {
+    recovery.degenerate_regexes = recovery
+        .degenerate_patterns
+        .iter()
+        .map(|pattern| {
+            regex::Regex::new(pattern).with_context(|| {
+                format!("recovery.degenerate_patterns entry '{pattern}' is invalid")
+            })
+        })
+        .collect::<Result<_>>()?;
+    Ok(())
+}
+
+fn default_control_miss_threshold() -> u32 {
+    2
+}
+
+fn default_backoff_initial_secs() -> u64 {
+    5
+}
+
+fn default_backoff_max_secs() -> u64 {
+    120
+}
+
+fn default_unattended_escalate_policy() -> UnattendedEscalatePolicy {
+    UnattendedEscalatePolicy::BestEffortOnce
+}
+
+fn default_codex_binary() -> String {
+    "codex".to_string()
+}
+
+fn default_approval_policy() -> String {
+    "never".to_string()
+}
+
+fn default_sandbox_mode() -> String {
+    "danger-full-access".to_string()
+}
+
+fn default_claude_binary() -> String {
+    "claude".to_string()
+}
+
+fn default_droid_binary() -> String {
+    "droid".to_string()
+}
+
+fn default_droid_autonomy() -> String {
+    "high".to_string()
+}
+
+fn default_pi_binary() -> String {
+    "pi".to_string()
+}
+
+fn default_control_version() -> u32 {
+    1
+}
+
+fn default_mock_steps_per_task() -> u32 {
+    2
+}
+
+fn default_roles() -> RolesConfig {
+    RolesConfig {
+        implementer: RoleConfig {
+            harness: "codex".to_string(),
+            model: "gpt-5.3-codex".to_string(),
+            thinking: "xhigh".to_string(),
+            launch_args: vec![REQUIRED_CODEX_ARG.to_string()],
+        },
+        reviewer_1: RoleConfig {
+            harness: "codex".to_string(),
+            model: "gpt-5.3-codex".to_string(),
+            thinking: "xhigh".to_string(),
+            launch_args: vec![REQUIRED_CODEX_ARG.to_string()],
+        },
+        reviewer_2: RoleConfig {
+            harness: "claude".to_string(),
+            model: "claude-opus-4-6".to_string(),
+            thinking: "xhigh".to_string(),
+            launch_args: vec![REQUIRED_CLAUDE_ARG.to_string()],
+        },
+    }
+}
+
+fn builtin_team(name: &str) -> Option<TeamFile> {
+    match name {
+        "xhigh" => Some(TeamFile {
+            name: Some("xhigh".to_string()),
+            description: Some(
+                "Codex implementer + codex reviewer-1 + Claude reviewer-2, all xhigh".to_string(),
+            ),
+            roles: default_roles(),
+        }),
+        _ => None,
+    }
+}
+
+fn builtin_team_names() -> &'static [&'static str] {
+    &["xhigh"]
+}
+
+/// Time source for the governor: every timestamp, stall age, backoff, and poll
+/// sleep goes through it. [`SystemClock`] is the default; `crank simulate` and
+/// tests run on a [`SimulatedClock`] whose sleeps only advance its own time.
+pub trait Clock: Send + Sync + std::fmt::Debug {
+    fn now(&self) -> chrono::DateTime<Utc>;
+    fn sleep(&self, duration: Duration);
+}
+
+/// Wall-clock time and real sleeps.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> chrono::DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+/// Starts at a fixed instant and moves forward only when slept on.
+#[derive(Debug)]
+pub struct SimulatedClock {
+    now: Mutex<chrono::DateTime<Utc>>,
+}
+
+impl SimulatedClock {
+    pub fn starting_at(start: chrono::DateTime<Utc>) -> Self {
+        Self {
+            now: Mutex::new(start),
+        }
+    }
+}
+
+impl Clock for SimulatedClock {
+    fn now(&self) -> chrono::DateTime<Utc> {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn sleep(&self, duration: Duration) {
+        let mut now = self.now.lock().unwrap_or_else(|e| e.into_inner());
+        *now += chrono::Duration::from_std(duration).unwrap_or(chrono::Duration::MAX);
+    }
+}
+
+thread_local! {
+    static CLOCK: RefCell<Option<Arc<dyn Clock>>> = const { RefCell::new(None) };
+}
+
+/// Restores the previously installed clock when dropped.
+struct ClockGuard(Option<Arc<dyn Clock>>);
+
+/// Makes `clock` this thread's time source until the guard drops.
+fn install_clock(clock: Arc<dyn Clock>) -> ClockGuard {
+    ClockGuard(CLOCK.with(|c| c.replace(Some(clock))))
+}
+
+impl Drop for ClockGuard {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        CLOCK.with(|c| *c.borrow_mut() = previous);
+    }
+}
+
+/// The clock installed on this thread, or [`SystemClock`].
+fn current_clock() -> Arc<dyn Clock> {
+    CLOCK
+        .with(|c| c.borrow().clone())
+        .unwrap_or_else(|| Arc::new(SystemClock))
+}
+
+fn clock_now() -> chrono::DateTime<Utc> {
+    current_clock().now()
+}
+
+fn clock_sleep(duration: Duration) {
+    current_clock().sleep(duration);
+}
+
+/// Time elapsed on the current clock since `since`.
+fn clock_elapsed(since: chrono::DateTime<Utc>) -> Duration {
+    (clock_now() - since).to_std().unwrap_or_default()
+}
+
+fn elapsed_ms(since: chrono::DateTime<Utc>) -> u64 {
+    u64::try_from(clock_elapsed(since).as_millis()).unwrap_or(u64::MAX)
+}
+
+fn now_iso() -> String {
+    clock_now().to_rfc3339()
+}
+
+fn now_epoch() -> i64 {
+    clock_now().timestamp()
+}
+
+fn ensure_dir(path: &Path) -> Result<()> {
+    fs::create_dir_all(path).with_context(|| format!("failed to create {}", path.display()))
+}
+
+fn state_path(state_dir: &Path) -> PathBuf {
+    state_dir.join("state.json")
+}
+
+/// Last state that parsed before the current one was written.
+fn state_backup_path(state_dir: &Path) -> PathBuf {
+    state_dir.join("state.json.bak")
+}
+
+fn journal_path(state_dir: &Path) -> PathBuf {
+    state_dir.join("JOURNAL.md")
+}
+
+fn digest_path(state_dir: &Path) -> PathBuf {
+    state_dir.join("DIGEST.md")
+}
+
+fn events_log_path(state_dir: &Path) -> PathBuf {
+    state_dir.join("logs").join("orchestrator.events.jsonl")
+}
+
+fn governor_heartbeat_path(state_dir: &Path) -> PathBuf {
+    state_dir.join("governor.heartbeat")
+}
+
+/// Touched by `ctl kick`; consumed by the governor's next sleep.
+fn kick_path(state_dir: &Path) -> PathBuf {
+    state_dir.join("kick")
+}
+
+const KICK_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Sleeps up to `duration`, returning early (and consuming the kick file) when
+/// `ctl kick` asks for an immediate cycle.
+fn governor_sleep(state_dir: &Path, duration: Duration) {
+    let deadline =
+        clock_now() + chrono::Duration::from_std(duration).unwrap_or(chrono::Duration::MAX);
+    let kick = kick_path(state_dir);
+    loop {
+        if fs::remove_file(&kick).is_ok() {
+            return;
+        }
+        let Ok(remaining) = (deadline - clock_now()).to_std() else {
+            return;
+        };
+        if remaining.is_zero() {
+            return;
+        }
+        clock_sleep(KICK_POLL_INTERVAL.min(remaining));
+    }
+}
+
+fn turns_log_path(state_dir: &Path) -> PathBuf {
+    state_dir.join("logs").join("orchestrator.turns.log")
+}
+
+const DEFAULT_KEEP_ROTATED_LOGS: u32 = 3;
+const BYTES_PER_MB: u64 = 1024 * 1024;
+
+fn rotated_log_path(path: &Path, generation: u32) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(format!(".{generation}"));
+    PathBuf::from(name)
+}
+
+/// Rotates or head-truncates `path` once it exceeds `max_bytes`. Returns whether
+/// the file was touched.
+fn enforce_log_cap(path: &Path, max_bytes: u64, keep_rotated: u32) -> Result<bool> {
+    let len = match fs::metadata(path) {
+        Ok(meta) => meta.len(),
+        Err(_) => return Ok(false),
+    };
+    if len <= max_bytes {
+        return Ok(false);
+    }
+
+    if keep_rotated == 0 {
+        // Keep the newest half of the cap, starting at a line boundary.
+        let mut file =
+            File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+        file.seek(SeekFrom::Start(len - max_bytes / 2))?;
+        let mut tail = Vec::new();
+        file.read_to_end(&mut tail)?;
+        let start = tail
+            .iter()
+            .position(|b| *b == b'\n')
+            .map_or(tail.len(), |i| i + 1);
+        let tmp = path.with_extension("truncate.tmp");
+        fs::write(&tmp, &tail[start..])
+            .with_context(|| format!("failed to write {}", tmp.display()))?;
+        fs::rename(&tmp, path).with_context(|| format!("failed to replace {}", path.display()))?;
+        return Ok(true);
+    }
+
+    for generation in (1..keep_rotated).rev() {
+        let from = rotated_log_path(path, generation);
+        if from.exists() {
+            fs::rename(&from, rotated_log_path(path, generation + 1))
+                .with_context(|| format!("failed to rotate {}", from.display()))?;
+        }
+    }
+    fs::rename(path, rotated_log_path(path, 1))
+        .with_context(|| format!("failed to rotate {}", path.display()))?;
+    File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+    Ok(true)
+}
+
+/// Applies `[logging]` caps to the events and turns logs. Returns the capped paths.
+fn enforce_log_caps(state_dir: &Path, logging: &LoggingConfig) -> Result<Vec<PathBuf>> {
+    let keep = logging.keep_rotated.unwrap_or(DEFAULT_KEEP_ROTATED_LOGS);
+    let mut capped = Vec::new();
+    for (path, cap) in [
+        (events_log_path(state_dir), logging.max_events_mb),
+        (turns_log_path(state_dir), logging.max_turns_mb),
+    ] {
+        if let Some(mb) = cap
+            && enforce_log_cap(&path, mb.saturating_mul(BYTES_PER_MB), keep)?
+        {
+            capped.push(path);
+        }
+    }
+    Ok(capped)
+}
+
+fn turns_archive_dir(state_dir: &Path) -> PathBuf {
+    state_dir.join("turns")
+}
+
+fn ensure_log_files(state_dir: &Path) -> Result<()> {
+    for path in [events_log_path(state_dir), turns_log_path(state_dir)] {
+        if !path.exists() {
+            File::create(&path).with_context(|| format!("failed to create {}", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes through a synced temp file and rename, then syncs the directory so
+/// the rename itself survives a crash.
+fn write_json_atomic<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+    let bytes = serde_json::to_vec_pretty(value)?;
+    let mut file =
+        File::create(&tmp).with_context(|| format!("failed to write {}", tmp.display()))?;
+    file.write_all(&bytes)
+        .and_then(|()| file.sync_all())
+        .with_context(|| format!("failed to write {}", tmp.display()))?;
+    fs::rename(&tmp, path)
+        .with_context(|| format!("failed to move {} to {}", tmp.display(), path.display()))?;
+    sync_parent_dir(path)
+}
+
+fn sync_parent_dir(path: &Path) -> Result<()> {
+    let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) else {
+        return Ok(());
+    };
+    File::open(dir)
+        .and_then(|d| d.sync_all())
+        .with_context(|| format!("failed to sync {}", dir.display()))
+}
+
+fn write_governor_heartbeat(state_dir: &Path) -> Result<()> {
+    let path = governor_heartbeat_path(state_dir);
+    fs::write(&path, format!("{}\n", now_epoch()))
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+fn append_journal(journal: &Path, title: &str, body: &str) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(journal)
+        .with_context(|| format!("failed to open {}", journal.display()))?;
+    writeln!(file, "\n## {}", now_iso())?;
+    writeln!(file, "**{}**", title)?;
+    writeln!(file, "{}", body)?;
+    Ok(())
+}
+
+fn append_text(path: &Path, text: &str) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("failed to open {}", path.display()))?;
+    file.write_all(text.as_bytes())?;
+    Ok(())
+}
+
+const MAX_EVENT_OUTPUT_CHARS: usize = 1200;
+
+fn truncate_event_field(map: &mut serde_json::Map<String, Value>, key: &str, max_chars: usize) {
+    let Some(Value::String(s)) = map.get_mut(key) else {
+        return;
+    };
+    if s.chars().count() <= max_chars {
+        return;
+    }
+    let original_chars = s.chars().count();
+    let truncated: String = s.chars().take(max_chars).collect();
+    *s = format!(
+        "{truncated}\n...[truncated {} chars]",
+        original_chars.saturating_sub(max_chars)
+    );
+}
+
+fn sanitize_event_value(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for key in ["aggregated_output", "stdout", "stderr"] {
+                truncate_event_field(map, key, MAX_EVENT_OUTPUT_CHARS);
+            }
+            for nested in map.values_mut() {
+                sanitize_event_value(nested);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                sanitize_event_value(item);
+            }
+        }
+        _ => {}
+    }
+}
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Masks `[logging] redact` patterns and `redact_env` values in event lines
+/// and turn transcripts.
+#[derive(Default)]
+struct Redactor {
+    patterns: Vec<regex::Regex>,
+}
+
+impl Redactor {
+    fn new(logging: &LoggingConfig) -> Result<Self> {
+        let mut patterns = Vec::new();
+        for pattern in &logging.redact {
+            patterns.push(
+                regex::Regex::new(pattern)
+                    .with_context(|| format!("invalid logging.redact pattern '{pattern}'"))?,
+            );
+        }
+        for name in &logging.redact_env {
+            if let Ok(value) = std::env::var(name)
+                && !value.is_empty()
+            {
+                patterns.push(regex::Regex::new(&regex::escape(&value))?);
+            }
+        }
+        Ok(Self { patterns })
+    }
+
+    fn apply(&self, text: &str) -> String {
+        let mut out = text.to_string();
+        for pattern in &self.patterns {
+            out = pattern.replace_all(&out, REDACTED).into_owned();
+        }
+        out
+    }
+}
+
+fn append_event_line(path: &Path, raw_line: &str, redactor: &Redactor) -> Result<()> {
+    let rendered = match serde_json::from_str::<Value>(raw_line) {
+        Ok(mut value) => {
+            sanitize_event_value(&mut value);
+            serde_json::to_string(&value).unwrap_or_else(|_| raw_line.to_string())
+        }
+        Err(_) => raw_line.to_string(),
+    };
+    append_text(path, &format!("{}\n", redactor.apply(&rendered)))
+}
+
+fn mtime_epoch(path: &Path) -> Option<i64> {
+    let md = fs::metadata(path).ok()?;
+    let modified = md.modified().ok()?;
+    let dur = modified.duration_since(UNIX_EPOCH).ok()?;
+    Some(dur.as_secs() as i64)
+}
+
+/// Parses `heartbeats/*.json` in a coord dir, sorted by role. Malformed files
+/// are ignored; their mtime still counts as progress.
+fn read_role_heartbeats(coord_dir: &Path) -> Vec<RoleHeartbeat> {
+    let Ok(entries) = fs::read_dir(coord_dir.join("heartbeats")) else {
+        return Vec::new();
+    };
+    let mut heartbeats: Vec<RoleHeartbeat> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                return None;
+            }
+            let role = path.file_stem()?.to_str()?.to_string();
+            let text = fs::read_to_string(&path).ok()?;
+            let mut heartbeat: RoleHeartbeat = serde_json::from_str(&text).ok()?;
+            heartbeat.role = role;
+            heartbeat.stall_reported = false;
+            Some(heartbeat)
+        })
+        .collect();
+    heartbeats.sort_by(|a, b| a.role.cmp(&b.role));
+    heartbeats
+}
+
+/// Replaces a task's heartbeats with the on-disk ones, keeping `stall_reported`
+/// for heartbeats that have not been rewritten since.
+fn refresh_role_heartbeats(task: &mut TaskRuntime) {
+    let mut fresh = read_role_heartbeats(Path::new(&task.coord_dir));
+    for heartbeat in &mut fresh {
+        heartbeat.stall_reported = task.heartbeats.iter().any(|old| {
+            old.role == heartbeat.role && old.epoch == heartbeat.epoch && old.stall_reported
+        });
+    }
+    task.heartbeats = fresh;
+}
+
+/// Active roles whose heartbeat is older than their threshold and has not
+/// already triggered a recovery.
+fn stalled_role_heartbeats<'a>(
+    task: &'a TaskRuntime,
+    timeouts: &TimeoutsConfig,
+    now: i64,
+) -> Vec<&'a RoleHeartbeat> {
+    task.heartbeats
+        .iter()
+        .filter(|hb| {
+            let threshold = timeouts
+                .role_stall_secs
+                .get(&hb.role)
+                .copied()
+                .unwrap_or(timeouts.stall_secs);
+            !hb.is_idle() && !hb.stall_reported && now.saturating_sub(hb.epoch) > threshold as i64
+        })
+        .collect()
+}
+
+/// Tallies markdown task-list items; `None` when the plan has no checkboxes.
+fn parse_todo_progress(text: &str) -> Option<TodoProgress> {
+    let mut progress = TodoProgress {
+        checked: 0,
+        total: 0,
+    };
+    for line in text.lines() {
+        let Some(item) = ["- [", "* [", "+ ["]
+            .iter()
+            .find_map(|bullet| line.trim_start().strip_prefix(bullet))
+        else {
+            continue;
+        };
+        match item.get(..2) {
+            Some(" ]") => progress.total += 1,
+            Some("x]") | Some("X]") => {
+                progress.checked += 1;
+                progress.total += 1;
+            }
+            _ => {}
+        }
+    }
+    (progress.total > 0).then_some(progress)
+}
+
+/// Re-reads the task's plan file. Returns true when the tally changed.
+fn refresh_todo_progress(task: &mut TaskRuntime) -> bool {
+    let Ok(text) = fs::read_to_string(&task.todo_file) else {
+        return false;
+    };
+    let fresh = parse_todo_progress(&text);
+    let changed = task.todo_progress.is_some() && fresh != task.todo_progress;
+    task.todo_progress = fresh;
+    changed
+}
+
+fn sha256_file(path: &Path) -> Result<String> {
+    use sha2::{Digest, Sha256};
+    let mut file =
+        File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect())
+}
+
+/// Re-indexes `coord_dir/artifacts/`. Files whose size and mtime are unchanged
+/// keep their record; new or rewritten content is stamped with `cycle`.
+/// Returns the names of new or changed artifacts.
+fn index_task_artifacts(task: &mut TaskRuntime, cycle: u64) -> Vec<String> {
+    let root = Path::new(&task.coord_dir).join("artifacts");
+    let mut files = Vec::new();
+    let mut pending = vec![root.clone()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            match entry.file_type() {
+                Ok(kind) if kind.is_dir() => pending.push(path),
+                Ok(kind) if kind.is_file() => files.push(path),
+                _ => {}
+            }
+        }
+    }
+    files.sort();
+
+    let mut changed = Vec::new();
+    let mut indexed = Vec::with_capacity(files.len());
+    for path in files {
+        let Ok(rel) = path.strip_prefix(&root) else {
+            continue;
+        };
+        let name = rel
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let Ok(meta) = fs::metadata(&path) else {
+            continue;
+        };
+        let modified_epoch = mtime_epoch(&path);
+        let previous = task.artifacts.iter().find(|a| a.name == name);
+        if let Some(prev) = previous
+            && prev.size == meta.len()
+            && prev.modified_epoch == modified_epoch
+        {
+            indexed.push(prev.clone());
+            continue;
+        }
+        let Ok(sha256) = sha256_file(&path) else {
+            continue;
+        };
+        let cycle = match previous {
+            Some(prev) if prev.sha256 == sha256 => prev.cycle,
+            _ => {
+                changed.push(name.clone());
+                cycle
+            }
+        };
+        indexed.push(ArtifactRecord {
+            name,
+            size: meta.len(),
+            sha256,
+            cycle,
+            modified_epoch,
+        });
+    }
+    task.artifacts = indexed;
+    changed
+}
+
+fn latest_progress_epoch(coord_dir: &Path) -> Option<i64> {
+    let mut latest = mtime_epoch(&coord_dir.join("state.md"));
+    for sub in ["requests", "reviews", "decisions", "heartbeats"] {
+        let dir = coord_dir.join(sub);
+        let entries = match fs::read_dir(&dir) {
+            Ok(it) => it,
+            Err(_) => continue,
+        };
+        for entry in entries.flatten() {
+            if let Some(ts) = mtime_epoch(&entry.path()) {
+                latest = Some(latest.map_or(ts, |cur| cur.max(ts)));
+            }
+        }
+    }
+    latest
+}
+
+fn check_coord_done(coord_dir: &Path) -> bool {
+    let path = coord_dir.join("state.md");
+    let text = match fs::read_to_string(path) {
+        Ok(v) => v,
+        Err(_) => return false,
+    };
+    text.trim() == "done"
+}
+
+fn required_launch_arg_for_harness(harness: &str) -> Option<&'static str> {
+    match harness {
+        "codex" => Some(REQUIRED_CODEX_ARG),
+        "claude" => Some(REQUIRED_CLAUDE_ARG),
+        _ => None,
+    }
+}
+
+fn role_launch_args_display(role: &RoleConfig) -> String {
+    if role.launch_args.is_empty() {
+        "(none)".to_string()
+    } else {
+        role.launch_args.join(" ")
+    }
+}
+
+fn validate_role(role_name: &str, role: &RoleConfig) -> Result<()> {
+    if role.harness.trim().is_empty() {
+        return Err(anyhow!("role '{role_name}' must set harness"));
+    }
+    if role.model.trim().is_empty() {
+        return Err(anyhow!("role '{role_name}' must set model"));
+    }
+    if role.thinking.trim().is_empty() {
+        return Err(anyhow!("role '{role_name}' must set thinking"));
+    }
+
+    if let Some(required) = required_launch_arg_for_harness(role.harness.as_str()) {
+        let has_required = role.launch_args.iter().any(|arg| arg == required);
+        if !has_required {
+            return Err(anyhow!(
+                "role '{role_name}' (harness={}) must include launch arg '{}'",
+                role.harness,
+                required
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_roles(roles: &RolesConfig) -> Result<()> {
+    validate_role("implementer", &roles.implementer)?;
+    validate_role("reviewer_1", &roles.reviewer_1)?;
+    validate_role("reviewer_2", &roles.reviewer_2)?;
+    Ok(())
+}
+
+fn parse_team_file(path: &Path) -> Result<TeamFile> {
+    let text =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    parse_team_text(&text, path)
+}
+
+/// Parses and validates team TOML; `path` only labels errors.
+fn parse_team_text(text: &str, path: &Path) -> Result<TeamFile> {
+    let team: TeamFile =
+        toml::from_str(text).with_context(|| format!("failed to parse {}", path.display()))?;
+    validate_roles(&team.roles).with_context(|| format!("invalid team {}", path.display()))?;
+    Ok(team)
+}
+
+fn list_team_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut files = Vec::new();
+    let entries =
+        fs::read_dir(dir).with_context(|| format!("failed to read teams dir {}", dir.display()))?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) == Some("toml") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn resolve_team_path(dir: &Path, team: &str) -> PathBuf {
+    let mut file = team.to_string();
+    if !file.ends_with(".toml") {
+        file.push_str(".toml");
+    }
+    dir.join(file)
+}
+
+fn load_team(dir: &Path, team: &str) -> Result<TeamFile> {
+    let path = resolve_team_path(dir, team);
+    if path.exists() {
+        return parse_team_file(&path);
+    }
+    if let Some(builtin) = builtin_team(team) {
+        return Ok(builtin);
+    }
+    Err(anyhow!(
+        "team '{}' not found in {} and not a builtin team",
+        team,
+        dir.display()
+    ))
+}
+
+fn load_team_from_file(path: &Path) -> Result<TeamFile> {
+    parse_team_file(path)
+}
+
+fn cmd_teams_list(dir: &Path) -> Result<()> {
+    let files = list_team_files(dir)?;
+    let mut file_team_names = std::collections::BTreeSet::new();
+    for path in &files {
+        if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
+            file_team_names.insert(stem.to_string());
+        }
+    }
+
+    for name in builtin_team_names() {
+        if file_team_names.contains(*name) {
+            continue;
+        }
+        if let Some(team) = builtin_team(name) {
+            let desc = team.description.unwrap_or_default();
+            if desc.is_empty() {
+                println!("{name}");
+            } else {
+                println!("{name}\t{desc}");
+            }
+        }
+    }
+
+    if files.is_empty() && builtin_team_names().is_empty() {
+        println!("(no teams found in {})", dir.display());
+        return Ok(());
+    }
+
+    let mut file_count = 0usize;
+    for path in files {
+        let fallback_name = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("(unknown)")
+            .to_string();
+        match parse_team_file(&path) {
+            Ok(team) => {
+                let name = team.name.unwrap_or(fallback_name);
+                let desc = team.description.unwrap_or_default();
+                if desc.is_empty() {
+                    println!("{name}");
+                } else {
+                    println!("{name}\t{desc}");
+                }
+            }
+            Err(err) => {
+                println!("{fallback_name}\tINVALID ({err})");
+            }
+        }
+        file_count += 1;
+    }
+
+    if file_count == 0 {
+        println!("(no file-based teams in {})", dir.display());
+    }
+    Ok(())
+}
+
+fn cmd_teams_validate(args: &TeamsValidateArgs) -> Result<()> {
+    let requested = args.file.is_some() || args.team.is_some() || args.all;
+    if !requested {
+        return Err(anyhow!(
+            "provide one of --all, --team <name>, or --file <path>"
+        ));
+    }
+    if args.all && (args.file.is_some() || args.team.is_some()) {
+        return Err(anyhow!("--all cannot be combined with --team/--file"));
+    }
+    if args.file.is_some() && args.team.is_some() {
+        return Err(anyhow!("use either --team or --file, not both"));
+    }
+
+    let mut failures = Vec::new();
+    if args.all {
+        let files = list_team_files(&args.dir)?;
+        let mut file_team_names = std::collections::BTreeSet::new();
+        for file in &files {
+            if let Some(stem) = file.file_stem().and_then(|s| s.to_str()) {
+                file_team_names.insert(stem.to_string());
+            }
+        }
+        for name in builtin_team_names() {
+            if file_team_names.contains(*name) {
+                continue;
+            }
+            match load_team(&args.dir, name) {
+                Ok(_) => println!("ok\tbuiltin:{name}"),
+                Err(err) => {
+                    println!("err\tbuiltin:{name}\t{err}");
+                    failures.push(format!("builtin:{name}: {err}"));
+                }
+            }
+        }
+        for file in &files {
+            match parse_team_file(file) {
+                Ok(_) => println!("ok\t{}", file.display()),
+                Err(err) => {
+                    println!("err\t{}\t{}", file.display(), err);
+                    failures.push(format!("{}: {err}", file.display()));
+                }
+            }
+        }
+        if files.is_empty() && builtin_team_names().is_empty() {
+            failures.push("no teams available to validate".to_string());
+        }
+    } else if let Some(path) = &args.file {
+        match load_team_from_file(path) {
+            Ok(_) => println!("ok\t{}", path.display()),
+            Err(err) => {
+                println!("err\t{}\t{}", path.display(), err);
+                failures.push(format!("{}: {err}", path.display()));
+            }
+        }
+    } else {
+        let team_name = args.team.as_deref().expect("checked above");
+        match load_team(&args.dir, team_name) {
+            Ok(_) => println!("ok\t{}", team_name),
+            Err(err) => {
+                println!("err\t{}\t{}", team_name, err);
+                failures.push(format!("{team_name}: {err}"));
+            }
+        }
+    }
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!("team validation failed:\n{}", failures.join("\n")))
+    }
+}
+
+fn render_team_file(name: &str, description: Option<&str>, roles: &RolesConfig) -> String {
+    let mut out = format!("name = {}\n", toml_string(name));
+    if let Some(description) = description {
+        out.push_str(&format!("description = {}\n", toml_string(description)));
+    }
+    for (role_name, role) in [
+        ("implementer", &roles.implementer),
+        ("reviewer_1", &roles.reviewer_1),
+        ("reviewer_2", &roles.reviewer_2),
+    ] {
+        out.push('\n');
+        out.push_str(&render_role_block(role_name, role));
+    }
+    out
+}
+
+fn cmd_teams_show(dir: &Path, name: &str) -> Result<()> {
+    let team = load_team(dir, name)?;
+    let team_name = team.name.as_deref().unwrap_or(name);
+    print!(
+        "{}",
+        render_team_file(team_name, team.description.as_deref(), &team.roles)
+    );
+    Ok(())
+}
+
+fn ensure_required_launch_arg(role: &mut RoleConfig) {
+    if let Some(required) = required_launch_arg_for_harness(role.harness.as_str())
+        && !role.launch_args.iter().any(|arg| arg == required)
+    {
+        role.launch_args.insert(0, required.to_string());
+    }
+}
+
+fn cmd_teams_new(dir: &Path, name: &str, from: &str, force: bool) -> Result<()> {
+    let path = resolve_team_path(dir, name);
+    if path.exists() && !force {
+        return Err(anyhow!(
+            "{} already exists (pass --force to overwrite)",
+            path.display()
+        ));
+    }
+
+    let source = load_team(dir, from)?;
+    let mut roles = source.roles;
+    ensure_required_launch_arg(&mut roles.implementer);
+    ensure_required_launch_arg(&mut roles.reviewer_1);
+    ensure_required_launch_arg(&mut roles.reviewer_2);
+
+    let stem = name.strip_suffix(".toml").unwrap_or(name);
+    let description = format!("Scaffolded from team '{from}'");
+    let content = render_team_file(stem, Some(&description), &roles);
+    // Validate before writing so a bad scaffold never lands where `--team` finds it.
+    parse_team_text(&content, &path)?;
+    ensure_dir(dir)?;
+    let tmp = path.with_extension("toml.tmp");
+    fs::write(&tmp, content).with_context(|| format!("failed to write {}", tmp.display()))?;
+    if let Err(err) = fs::rename(&tmp, &path) {
+        let _ = fs::remove_file(&tmp);
+        return Err(err).with_context(|| format!("failed to write {}", path.display()));
+    }
+    println!("wrote {}", path.display());
+    Ok(())
+}
+
+fn parse_clock_minutes(raw: &str) -> Result<u32> {
+    let (h, m) = raw
+        .trim()
+        .split_once(':')
+        .ok_or_else(|| anyhow!("expected HH:MM, got '{raw}'"))?;
+    let h: u32 = h
+        .parse()
+        .with_context(|| format!("invalid hour in '{raw}'"))?;
+    let m: u32 = m
+        .parse()
+        .with_context(|| format!("invalid minute in '{raw}'"))?;
+    if h > 23 || m > 59 {
+        return Err(anyhow!("time '{raw}' out of range"));
+    }
+    Ok(h * 60 + m)
+}
+
+/// Parses "HH:MM-HH:MM" into start/end minutes of the day.
+fn parse_active_hours(raw: &str) -> Result<(u32, u32)> {
+    let (start, end) = raw
+        .split_once('-')
+        .ok_or_else(|| anyhow!("active_hours must look like \"22:00-07:00\", got '{raw}'"))?;
+    let window = (parse_clock_minutes(start)?, parse_clock_minutes(end)?);
+    if window.0 == window.1 {
+        return Err(anyhow!("active_hours '{raw}' is an empty window"));
+    }
+    Ok(window)
+}
+
+fn parse_start_after(raw: &str) -> Result<chrono::DateTime<chrono::FixedOffset>> {
+    chrono::DateTime::parse_from_rfc3339(raw.trim())
+        .with_context(|| format!("start_after must be an RFC 3339 timestamp, got '{raw}'"))
+}
+
+fn schedule_problems(schedule: &ScheduleConfig) -> Vec<String> {
+    let mut problems = Vec::new();
+    if let Some(raw) = &schedule.start_after
+        && let Err(err) = parse_start_after(raw)
+    {
+        problems.push(format!("schedule: {err:#}"));
+    }
+    if let Some(raw) = &schedule.active_hours
+        && let Err(err) = parse_active_hours(raw)
+    {
+        problems.push(format!("schedule: {err:#}"));
+    }
+    problems
+}
+
+/// Seconds until `secs_of_day` falls inside the window; 0 when already inside.
+fn active_hours_wait_secs(window: (u32, u32), secs_of_day: u32) -> u64 {
+    let (start, end) = (window.0 * 60, window.1 * 60);
+    let inside = if start < end {
+        (start..end).contains(&secs_of_day)
+    } else {
+        secs_of_day >= start || secs_of_day < end
+    };
+    if inside {
+        0
+    } else if secs_of_day < start {
+        u64::from(start - secs_of_day)
+    } else {
+        u64::from(24 * 3600 - secs_of_day + start)
+    }
+}
+
+/// Seconds the governor must wait before launching a turn, if any.
+fn schedule_wait_secs(schedule: &ScheduleConfig, now: chrono::DateTime<chrono::Local>) -> u64 {
+    use chrono::Timelike;
+
+    let mut wait = 0u64;
+    if let Some(start) = schedule
+        .start_after
+        .as_deref()
+        .and_then(|raw| parse_start_after(raw).ok())
+    {
+        wait = wait.max(start.timestamp().saturating_sub(now.timestamp()).max(0) as u64);
+    }
+    if let Some(window) = schedule
+        .active_hours
+        .as_deref()
+        .and_then(|raw| parse_active_hours(raw).ok())
+    {
+        let at = now + chrono::Duration::seconds(wait as i64);
+        wait += active_hours_wait_secs(window, at.num_seconds_from_midnight());
+    }
+    wait
+}
+
+impl Config {
+    pub fn run_id(&self) -> Option<&str> {
+        self.run_id.as_deref()
+    }
+
+    pub fn workspace(&self) -> &Path {
+        &self.workspace
+    }
+
+    pub fn state_dir(&self) -> &Path {
+        &self.state_dir
+    }
+}
+
+impl RunState {
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    pub fn status(&self) -> &RunStatus {
+        &self.status
+    }
+
+    pub fn cycle(&self) -> u64 {
+        self.cycle
+    }
+
+    pub fn cost_usd(&self) -> f64 {
+        self.cost_usd
+    }
+
+    /// `(task id, status)` in declaration order.
+    pub fn task_statuses(&self) -> impl Iterator<Item = (&str, &TaskStatus)> {
+        self.tasks.iter().map(|t| (t.id.as_str(), &t.status))
+    }
+}
+
+/// A run config table with its `include`s merged in, and every file read.
+struct ConfigSource {
+    table: toml::Table,
+    files: Vec<PathBuf>,
+}
+
+/// Reads a run config and merges its `include = [...]` fragments. Includes
+/// resolve against the including file's directory and apply in order, each
+/// overriding the ones before it; the including file overrides them all.
+/// Tables merge key by key, while arrays (`[[tasks]]` included) and scalars are
+/// replaced whole. Includes may nest; a cycle is an error.
+fn read_config_source(path: &Path) -> Result<ConfigSource> {
+    let mut files = Vec::new();
+    let table = read_config_table(path, &mut Vec::new(), &mut files)?;
+    Ok(ConfigSource { table, files })
+}
+
+fn read_config_table(
+    path: &Path,
+    stack: &mut Vec<PathBuf>,
+    files: &mut Vec<PathBuf>,
+) -> Result<toml::Table> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("failed to read config {}", path.display()))?;
+    let mut table: toml::Table =
+        toml::from_str(&text).with_context(|| format!("failed to parse {}", path.display()))?;
+    let key = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    if stack.contains(&key) {
+        return Err(anyhow!("config include cycle at {}", path.display()));
+    }
+    files.push(path.to_path_buf());
+
+    let includes = match table.remove("include") {
+        None => return Ok(table),
+        Some(toml::Value::Array(items)) => items
+            .into_iter()
+            .map(|item| match item {
+                toml::Value::String(include) => Ok(include),
+                other => Err(anyhow!(
+                    "{}: include entries must be paths, got {other}",
+                    path.display()
+                )),
+            })
+            .collect::<Result<Vec<_>>>()?,
+        Some(other) => {
+            return Err(anyhow!(
+                "{}: include must be an array of paths, got {other}",
+                path.display()
+            ));
+        }
+    };
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+    stack.push(key);
+    let mut merged = toml::Table::new();
+    for include in includes {
+        let fragment = read_config_table(&base_dir.join(&include), stack, files)
+            .with_context(|| format!("included from {}", path.display()))?;
+        merge_toml_tables(&mut merged, fragment);
+    }
+    stack.pop();
+    merge_toml_tables(&mut merged, table);
+    Ok(merged)
+}
+
+/// Overlays `overlay` onto `base`: nested tables merge, anything else replaces.
+fn merge_toml_tables(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base_inner)), toml::Value::Table(inner)) => {
+                merge_toml_tables(base_inner, inner);
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Newest mtime across a config and its includes, so `--watch-config` also
+/// notices edits to a shared fragment.
+fn config_sources_mtime(path: &Path) -> Option<i64> {
+    read_config_source(path)
+        .map(|source| source.files)
+        .unwrap_or_else(|_| vec![path.to_path_buf()])
+        .iter()
+        .filter_map(|file| mtime_epoch(file))
+        .max()
+}
+
+/// Reads a run config and checks its structure: task graph, schedule, and
+/// backend settings. Missing files and binaries are left to `config validate`
+/// and preflight, and roles are checked once any `--team` override is applied.
+pub fn load_config(path: &Path) -> Result<Config> {
+    let source = read_config_source(path)?;
+    let mut cfg: Config = toml::Value::Table(source.table)
+        .try_into()
+        .with_context(|| format!("failed to parse {}", path.display()))?;
+    cfg.config_files = source.files;
+    compile_degenerate_patterns(&mut cfg.recovery)
+        .with_context(|| format!("invalid [recovery] in {}", path.display()))?;
+
+    let problems = config_problems(&cfg);
+    if !problems.is_empty() {
+        return Err(anyhow!(
+            "invalid config {}:\n{}",
+            path.display(),
+            problems.join("\n")
+        ));
+    }
+
+    Ok(cfg)
+}
+
+fn config_graph_problems(tasks: &[TaskConfig]) -> Vec<String> {
+    let mut problems = Vec::new();
+    let ids: std::collections::BTreeSet<&str> = tasks.iter().map(|t| t.id.as_str()).collect();
+    for task in tasks {
+        for dep in &task.depends_on {
+            if dep == &task.id {
+                problems.push(format!("task '{}' depends on itself", task.id));
+            } else if !ids.contains(dep.as_str()) {
+                problems.push(format!(
+                    "task '{}' depends on unknown task id '{}'",
+                    task.id, dep
+                ));
+            }
+        }
+    }
+    if let Some(cycle) = find_dependency_cycle(tasks) {
+        problems.push(format!("dependency cycle: {}", cycle.join(" -> ")));
+    }
+    problems
+}
+
+fn find_dependency_cycle(tasks: &[TaskConfig]) -> Option<Vec<String>> {
+    fn visit<'a>(
+        id: &'a str,
+        tasks: &'a [TaskConfig],
+        done: &mut std::collections::BTreeSet<&'a str>,
+        path: &mut Vec<&'a str>,
+    ) -> Option<Vec<String>> {
+        if let Some(pos) = path.iter().position(|p| *p == id) {
+            let mut cycle: Vec<String> = path[pos..].iter().map(|p| p.to_string()).collect();
+            cycle.push(id.to_string());
+            return Some(cycle);
+        }
+        if done.contains(id) {
+            return None;
+        }
+        let task = tasks.iter().find(|t| t.id == id)?;
+        path.push(id);
+        for dep in &task.depends_on {
+            // Self-references are reported separately as a clearer problem.
+            if dep == id {
+                continue;
+            }
+            if let Some(cycle) = visit(dep, tasks, done, path) {
+                return Some(cycle);
+            }
+        }
+        path.pop();
+        done.insert(id);
+        None
+    }
+
+    let mut done = std::collections::BTreeSet::new();
+    for task in tasks {
+        let mut path = Vec::new();
+        if let Some(cycle) = visit(&task.id, tasks, &mut done, &mut path) {
+            return Some(cycle);
+        }
+    }
+    None
+}
+
+fn find_on_path(binary: &str) -> Option<PathBuf> {
+    let candidate = Path::new(binary);
+    if candidate.components().count() > 1 {
+        return candidate.is_file().then(|| candidate.to_path_buf());
+    }
+    let path_var = std::env::var_os("PATH")?;
+    std::env::split_paths(&path_var)
+        .map(|dir| dir.join(binary))
+        .find(|path| path.is_file())
+}
+
+fn config_problems(cfg: &Config) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    if cfg.tasks.is_empty() {
+        problems.push("config.tasks must not be empty".to_string());
+    }
+    let mut seen = std::collections::BTreeSet::new();
+    for task in &cfg.tasks {
+        if task.id.trim().is_empty() {
+            problems.push("task id must not be empty".to_string());
+        } else if !seen.insert(task.id.clone()) {
+            problems.push(format!("duplicate task id '{}'", task.id));
+        }
+        if let Some(sha256) = &task.completion_file_sha256 {
+            if task.completion_file.is_none() {
+                problems.push(format!(
+                    "task '{}' sets completion_file_sha256 without completion_file",
+                    task.id
+                ));
+            }
+            if sha256.trim().len() != 64 || !sha256.trim().chars().all(|c| c.is_ascii_hexdigit()) {
+                problems.push(format!(
+                    "task '{}' completion_file_sha256 must be 64 hex digits",
+                    task.id
+                ));
+            }
+        }
+        for req in &task.completion_requires {
+            if let CompletionRequirement::Matches { matches, .. } = req
+                && let Err(err) = regex::Regex::new(matches)
+            {
+                problems.push(format!(
+                    "task '{}' completion_requires pattern '{matches}' is invalid: {err}",
+                    task.id
+                ));
+            }
+        }
+    }
+    problems.extend(config_graph_problems(&cfg.tasks));
+    problems.extend(schedule_problems(&cfg.schedule));
+    problems.extend(claude_thinking_problems(cfg));
+    for backend in cfg.backend.chain() {
+        if let BackendConfig::Codex(codex) = backend {
+            if codex.profile.as_ref().is_some_and(|p| p.trim().is_empty()) {
+                problems.push("codex backend profile must not be empty".to_string());
+            }
+            if let Err(err) = codex_config_overrides(codex) {
+                problems.push(format!("{err:#}"));
+            }
+        }
+    }
+    problems.extend(sandbox_problems(cfg));
+    for pattern in &cfg.recovery.degenerate_patterns {
+        if let Err(err) = regex::Regex::new(pattern) {
+            problems.push(format!(
+                "recovery.degenerate_patterns entry '{pattern}' is invalid: {err}"
+            ));
+        }
+    }
+    if let Some(cluster) = &cfg.cluster {
+        if cluster
+            .node_id
+            .as_ref()
+            .is_some_and(|n| n.trim().is_empty())
+        {
+            problems.push("cluster.node_id must not be empty".to_string());
+        }
+        if cluster.lease_secs <= cfg.timeouts.stall_secs {
+            problems.push(format!(
+                "cluster.lease_secs ({}) must exceed timeouts.stall_secs ({}) so a quiet turn keeps its lease",
+                cluster.lease_secs, cfg.timeouts.stall_secs
+            ));
+        }
+    }
+    if let Some(uri) = &cfg.sync.s3_uri
+        && !uri.starts_with("s3://")
+    {
+        problems.push(format!("sync.s3_uri '{uri}' must start with s3://"));
+    }
+    if cfg
+        .sync
+        .rsync_target
+        .as_ref()
+        .is_some_and(|t| t.trim().is_empty())
+    {
+        problems.push("sync.rsync_target must not be empty".to_string());
+    }
+    for (key, cap) in [
+        ("max_events_mb", cfg.logging.max_events_mb),
+        ("max_turns_mb", cfg.logging.max_turns_mb),
+    ] {
+        if cap == Some(0) {
+            problems.push(format!("logging.{key} must be greater than 0"));
+        }
+    }
+    for pattern in &cfg.logging.redact {
+        if let Err(err) = regex::Regex::new(pattern) {
+            problems.push(format!(
+                "logging.redact pattern '{pattern}' is invalid: {err}"
+            ));
+        }
+    }
+
+    if !cfg.backend.fallbacks.is_empty() && cfg.backend.failover_after == 0 {
+        problems.push("backend.failover_after must be greater than 0".to_string());
+    }
+
+    problems
+}
+
+/// Files and directories the run reads: prompt templates, todo files,
+/// workspaces, and mock scenarios. Checked by `config validate` and preflight,
+/// not at load, so `simulate` and `--skip-preflight` work on partial checkouts.
+fn config_file_problems(cfg: &Config) -> Vec<String> {
+    let mut problems = Vec::new();
+    for task in &cfg.tasks {
+        if let Some(template) = &task.prompt_template
+            && !template.is_file()
+        {
+            problems.push(format!(
+                "task '{}' prompt_template {} does not exist",
+                task.id,
+                template.display()
+            ));
+        }
+        if !task.todo_file.exists() {
+            problems.push(format!(
+                "task '{}' todo_file {} does not exist",
+                task.id,
+                task.todo_file.display()
+            ));
+        }
+    }
+    for backend in cfg.backend.chain() {
+        if let BackendConfig::Mock(MockBackendConfig {
+            scenario: Some(path),
+            ..
+        }) = backend
+            && let Err(err) = load_mock_scenario(path)
+        {
+            problems.push(format!("{err:#}"));
+        }
+    }
+
+    if let Some(template) = &cfg.prompt_template
+        && !template.is_file()
+    {
+        problems.push(format!(
+            "prompt_template {} does not exist",
+            template.display()
+        ));
+    }
+
+    if !cfg.workspace.is_dir() {
+        problems.push(format!(
+            "workspace {} does not exist or is not a directory",
+            cfg.workspace.display()
+        ));
+    }
+    for task in &cfg.tasks {
+        if task.workspace.is_some() {
+            let workspace = configured_task_workspace(cfg, &task.id);
+            if !workspace.is_dir() {
+                problems.push(format!(
+                    "task '{}' workspace {} does not exist or is not a directory",
+                    task.id,
+                    workspace.display()
+                ));
+            }
+        }
+    }
+    problems
+}
+
+/// Backend binaries missing from `PATH`. Only `config validate` reports these;
+/// `crank run` probes the binaries in preflight instead.
+fn config_binary_problems(cfg: &Config) -> Vec<String> {
+    cfg.backend
+        .chain()
+        .filter_map(|backend| {
+            let binary = backend.binary()?;
+            find_on_path(binary).is_none().then(|| {
+                format!(
+                    "{} backend binary '{}' not found on PATH",
+                    backend.kind(),
+                    binary
+                )
+            })
+        })
+        .collect()
+}
+
+fn cmd_config_validate(args: &ConfigValidateArgs) -> Result<()> {
+    let source = match read_config_source(&args.config) {
+        Ok(source) => source,
+        Err(err) => {
+            println!("err\t{}\t{:#}", args.config.display(), err);
+            return Err(err.context("config validation failed"));
+        }
+    };
+    let mut cfg: Config = match toml::Value::Table(source.table).try_into() {
+        Ok(cfg) => cfg,
+        Err(err) => {
+            println!("err\t{}\t{}", args.config.display(), err.message());
+            return Err(anyhow!(
+                "config validation failed:\n{}: {err}",
+                args.config.display()
+            ));
+        }
+    };
+
+    let mut problems = Vec::new();
+    match resolve_team_roles(
+        args.team.as_deref(),
+        args.team_file.as_deref(),
+        &args.teams_dir,
+    ) {
+        Ok(Some(team_roles)) => cfg.roles = team_roles,
+        Ok(None) => {}
+        Err(err) => problems.push(format!("team: {err:#}")),
+    }
+    problems.extend(config_problems(&cfg));
+    problems.extend(config_file_problems(&cfg));
+    problems.extend(config_binary_problems(&cfg));
+    if let Err(err) = validate_roles(&cfg.roles) {
+        problems.push(format!("invalid roles: {err}"));
+    }
+
+    if problems.is_empty() {
+        println!("ok\t{}", args.config.display());
+        return Ok(());
+    }
+    for problem in &problems {
+        println!("err\t{}\t{}", args.config.display(), problem);
+    }
+    Err(anyhow!(
+        "config validation failed with {} problem(s):\n{}",
+        problems.len(),
+        problems.join("\n")
+    ))
+}
+
+/// zstd level for `runs archive`: most of the size win on JSONL logs without
+/// the throughput cliff of the 19+ levels on multi-GB state dirs.
+const ARCHIVE_ZSTD_LEVEL: i32 = 9;
+
+/// Paths, relative to the state dir, that only matter while a governor is
+/// live: the lock, liveness files, task worktrees of runs from before they
+/// moved out of the state dir (their work is on the task branches), and each
+/// in-state-dir coord dir's role heartbeats. Half-written
+/// `*.tmp` files are skipped separately.
+fn archive_scratch_paths(state: &RunState) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = ["run.lock", "governor.heartbeat", "kick", "worktrees"]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+    paths.extend(state.tasks.iter().filter_map(|t| {
+        Path::new(&t.coord_dir)
+            .strip_prefix(&state.state_dir)
+            .ok()
+            .map(|rel| rel.join("heartbeats"))
+    }));
+    paths
+}
+
+/// Reasons a state dir is not ready to archive: the run is not finished, a
+/// governor still holds it, or `run-summary.json` disagrees with `state.json`.
+fn archive_problems(state_dir: &Path, state: &RunState) -> Vec<String> {
+    let mut problems = Vec::new();
+    if state.status == RunStatus::Running {
+        problems.push("run is still running; only finished runs can be archived".to_string());
+    }
+    if let Some(pid) = lock_pid(&state_dir.join("run.lock")).filter(|pid| process_is_alive(*pid)) {
+        problems.push(format!("governor pid {pid} still holds run.lock"));
+    }
+    let path = run_summary_path(state_dir);
+    let summary: Value = match fs::read_to_string(&path)
+        .map_err(anyhow::Error::from)
+        .and_then(|text| serde_json::from_str(&text).map_err(anyhow::Error::from))
+    {
+        Ok(summary) => summary,
+        Err(err) => {
+            problems.push(format!("cannot read {}: {err}", path.display()));
+            return problems;
+        }
+    };
+    let count = |status: TaskStatus| state.tasks.iter().filter(|t| t.status == status).count();
+    let expected = [
+        ("run_id", Value::from(state.run_id.as_str())),
+        ("status", Value::from(state.status.as_str())),
+        ("cycle", Value::from(state.cycle)),
+        ("tasks_total", Value::from(state.tasks.len())),
+        ("tasks_completed", Value::from(count(TaskStatus::Completed))),
+        (
+            "tasks_blocked",
+            Value::from(count(TaskStatus::BlockedBestEffort)),
+        ),
+    ];
+    for (field, want) in expected {
+        let got = summary.get(field).unwrap_or(&Value::Null);
+        if *got != want {
+            problems.push(format!(
+                "run-summary.json {field} is {got} but state.json says {want}"
+            ));
+        }
+    }
+    problems
+}
+
+/// Default archive path: a sibling of the state dir, `<name>.tar.zst`.
+fn default_archive_path(state_dir: &Path) -> Result<PathBuf> {
+    let name = state_dir
+        .file_name()
+        .ok_or_else(|| anyhow!("cannot name an archive for {}", state_dir.display()))?;
+    let mut file = name.to_os_string();
+    file.push(".tar.zst");
+    Ok(state_dir.with_file_name(file))
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct ArchiveStats {
+    files: u64,
+    bytes: u64,
+    skipped: u64,
+}
+
+/// Archive layout: `root` stands in for the state dir and `rel` is the
+/// directory being added, relative to both.
+struct ArchiveWalk<'a> {
+    state_dir: &'a Path,
+    root: &'a Path,
+    scratch: &'a [PathBuf],
+}
+
+fn append_archive_dir(
+    builder: &mut tar::Builder<impl Write>,
+    walk: &ArchiveWalk<'_>,
+    rel: &Path,
+    stats: &mut ArchiveStats,
+) -> Result<()> {
+    let dir = walk.state_dir.join(rel);
+    builder.append_dir(walk.root.join(rel), &dir)?;
+    let mut entries = fs::read_dir(&dir)
+        .with_context(|| format!("failed to read {}", dir.display()))?
+        .collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        let entry_rel = rel.join(entry.file_name());
+        if walk.scratch.contains(&entry_rel) || path.extension().is_some_and(|ext| ext == "tmp") {
+            stats.skipped += 1;
+            continue;
+        }
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            append_archive_dir(builder, walk, &entry_rel, stats)?;
+        } else {
+            if file_type.is_file() {
+                stats.files += 1;
+                stats.bytes += entry.metadata()?.len();
+            }
+            builder
+                .append_path_with_name(&path, walk.root.join(&entry_rel))
+                .with_context(|| format!("failed to archive {}", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Counts the regular files and bytes in a written archive, so a truncated or
+/// corrupt archive is caught before the original is deleted.
+fn archive_contents(path: &Path) -> Result<ArchiveStats> {
+    let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let mut archive = tar::Archive::new(zstd::Decoder::new(file)?);
+    let mut stats = ArchiveStats::default();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if entry.header().entry_type().is_file() {
+            stats.files += 1;
+            stats.bytes += std::io::copy(&mut entry, &mut std::io::sink())?;
+        }
+    }
+    Ok(stats)
+}
+
+/// Packs a finished run's state dir into a zstd-compressed tarball rooted at
+/// the state dir's name, leaving out scratch, then verifies what was written.
+fn archive_run(state_dir: &Path, output: &Path) -> Result<ArchiveStats> {
+    let state = read_state(state_dir)?;
+    let problems = archive_problems(state_dir, &state);
+    if !problems.is_empty() {
+        return Err(anyhow!(
+            "refusing to archive {}:\n{}",
+            state_dir.display(),
+            problems.join("\n")
+        ));
+    }
+    if output.exists() {
+        return Err(anyhow!("{} already exists", output.display()));
+    }
+    let root = fs::canonicalize(state_dir)?;
+    if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty())
+        && fs::canonicalize(parent).is_ok_and(|parent| parent.starts_with(&root))
+    {
+        return Err(anyhow!(
+            "archive output {} must be outside the state dir",
+            output.display()
+        ));
+    }
+
+    let name = PathBuf::from(
+        root.file_name()
+            .ok_or_else(|| anyhow!("cannot archive {}", state_dir.display()))?,
+    );
+    let scratch = archive_scratch_paths(&state);
+    let mut tmp = output.as_os_str().to_os_string();
+    tmp.push(".partial");
+    let tmp = PathBuf::from(tmp);
+    let file = File::create(&tmp).with_context(|| format!("failed to write {}", tmp.display()))?;
+    let mut builder = tar::Builder::new(zstd::Encoder::new(file, ARCHIVE_ZSTD_LEVEL)?);
+    builder.follow_symlinks(false);
+    let mut stats = ArchiveStats::default();
+    let walk = ArchiveWalk {
+        state_dir,
+        root: &name,
+        scratch: &scratch,
+    };
+    let written = append_archive_dir(&mut builder, &walk, Path::new(""), &mut stats)
+        .and_then(|()| Ok(builder.into_inner()?.finish()?.sync_all()?));
+    if let Err(err) = written {
+        let _ = fs::remove_file(&tmp);
+        return Err(err);
+    }
+
+    let found = archive_contents(&tmp)?;
+    if (found.files, found.bytes) != (stats.files, stats.bytes) {
+        let _ = fs::remove_file(&tmp);
+        return Err(anyhow!(
+            "archive verification failed: wrote {} files ({} bytes) but read back {} ({} bytes)",
+            stats.files,
+            stats.bytes,
+            found.files,
+            found.bytes
+        ));
+    }
+    fs::rename(&tmp, output)
+        .with_context(|| format!("failed to move {} to {}", tmp.display(), output.display()))?;
+    Ok(stats)
+}
+
+/// Task work that deleting a state dir would strand, since task worktrees are
+/// not archived and go with it: worktrees with uncommitted changes, and task
+/// branches no other branch or remote ref contains.
+fn stranded_task_work(state: &RunState) -> Vec<String> {
+    let mut problems = Vec::new();
+    for task in &state.tasks {
+        let (Some(branch), Some(worktree)) = (&task.branch, &task.workspace) else {
+            continue;
+        };
+        let worktree = Path::new(worktree);
+        if !worktree.exists() {
+            continue;
+        }
+        match git_output(worktree, &["status", "--porcelain"]) {
+            Ok(status) if !status.trim().is_empty() => problems.push(format!(
+                "task {}: {} has uncommitted changes",
+                task.id,
+                worktree.display()
+            )),
+            Ok(_) => {}
+            Err(err) => problems.push(format!("task {}: {err:#}", task.id)),
+        }
+        let own_ref = format!("refs/heads/{branch}");
+        match git_output(
+            worktree,
+            &[
+                "for-each-ref",
+                "--format=%(refname)",
+                "--contains",
+                branch,
+                "refs/heads",
+                "refs/remotes",
+            ],
+        ) {
+            Ok(refs) if refs.lines().all(|r| r.trim() == own_ref) => problems.push(format!(
+                "task {}: branch {branch} is not merged into any other branch",
+                task.id
+            )),
+            Ok(_) => {}
+            Err(err) => problems.push(format!("task {}: {err:#}", task.id)),
+        }
+    }
+    problems
+}
+
+fn cmd_runs_archive(args: &RunsArchiveArgs) -> Result<()> {
+    if args.delete_original {
+        let stranded = stranded_task_work(&read_state(&args.state_dir)?);
+        if !stranded.is_empty() {
+            return Err(anyhow!(
+                "refusing --delete-original: task worktrees are not archived and would lose work \
+                 (commit and merge it, or clean up with `crank worktree gc --force`):\n- {}",
+                stranded.join("\n- ")
+            ));
+        }
+    }
+    let output = match &args.output {
+        Some(path) => path.clone(),
+        None => default_archive_path(&args.state_dir)?,
+    };
+    let stats = archive_run(&args.state_dir, &output)?;
+    let size = fs::metadata(&output)?.len();
+    println!(
+        "archived {} -> {} ({} files, {} bytes -> {} bytes, {} scratch entries skipped)",
+        args.state_dir.display(),
+        output.display(),
+        stats.files,
+        stats.bytes,
+        size,
+        stats.skipped
+    );
+    if args.delete_original {
+        let state = read_state(&args.state_dir).ok();
+        fs::remove_dir_all(&args.state_dir)
+            .with_context(|| format!("failed to delete {}", args.state_dir.display()))?;
+        // Worktrees hold nothing the branches lack (checked above); remove
+        // them with the run, and drop git's records of older in-state-dir ones.
+        if let Some(state) = state {
+            for task in state.tasks.iter().filter(|t| t.branch.is_some()) {
+                let Some(worktree) = task.workspace.as_deref().map(Path::new) else {
+                    continue;
+                };
+                if let Some(repo) = worktree_main_repo(worktree) {
+                    match remove_task_worktree(&repo, worktree) {
+                        Ok(()) => println!("removed worktree {}", worktree.display()),
+                        Err(err) => eprintln!("kept worktree {}: {err:#}", worktree.display()),
+                    }
+                }
+                if let Some(run_dir) = worktree.parent() {
+                    let _ = fs::remove_file(run_dir.join(WORKTREE_RUN_MARKER));
+                    let _ = fs::remove_dir(run_dir);
+                }
+            }
+            let _ = git_output(Path::new(&state.workspace), &["worktree", "prune"]);
+        }
+        println!("deleted {}", args.state_dir.display());
+    }
+    Ok(())
+}
+
+fn init_state(cfg: &Config) -> Result<RunState> {
+    ensure_dir(&cfg.state_dir)?;
+    ensure_dir(&cfg.state_dir.join("logs"))?;
+    ensure_dir(&cfg.state_dir.join("coord"))?;
+
+    let journal = journal_path(&cfg.state_dir);
+    if !journal.exists() {
+        let mut file = File::create(&journal)?;
+        writeln!(file, "# JOURNAL")?;
+        writeln!(file)?;
+        writeln!(
+            file,
+            "Run journal for unattended orchestration. Blockers are recorded here instead of stopping the run."
+        )?;
+    }
+
+    let s_path = state_path(&cfg.state_dir);
+    if s_path.exists() {
+        let (mut existing, from_backup) = load_state_with_backup(&cfg.state_dir)?;
+        if from_backup {
+            append_journal(
+                &journal,
+                "state recovered from backup",
+                &format!(
+                    "{} could not be parsed; resumed from {} (updated {}). Progress after that save is lost.",
+                    s_path.display(),
+                    state_backup_path(&cfg.state_dir).display(),
+                    existing.updated_at
+                ),
+            )?;
+            write_json_atomic(&s_path, &existing)?;
+        }
+        migrate_legacy_thread_id(&mut existing);
+        // Whatever turn was in flight died with the previous governor.
+        existing.live_turn = None;
+        return Ok(existing);
+    }
+
+    let run_id = cfg
+        .run_id
+        .clone()
+        .unwrap_or_else(|| format!("run-{}", now_epoch()));
+
+    let tasks = cfg
+        .tasks
+        .iter()
+        .map(|task| task_runtime_from_config(&cfg.state_dir, task))
+        .collect();
+
+    let now = now_iso();
+    Ok(RunState {
+        run_id,
+        workspace: cfg.workspace.display().to_string(),
+        state_dir: cfg.state_dir.display().to_string(),
+        unattended: cfg.unattended,
+        status: RunStatus::Running,
+        started_at: now.clone(),
+        updated_at: now,
+        journal_path: journal.display().to_string(),
+        thread_id: None,
+        cycle: 0,
+        last_turn_at: None,
+        cost_usd: 0.0,
+        stop_reason: None,
+        backend_index: 0,
+        digest_cycle: 0,
+        digest_journal_offset: 0,
+        live_turn: None,
+        tasks,
+    })
+}
+
+fn task_runtime_from_config(state_dir: &Path, task: &TaskConfig) -> TaskRuntime {
+    let coord = task
+        .coord_dir
+        .clone()
+        .unwrap_or_else(|| state_dir.join("coord").join(&task.id));
+    TaskRuntime {
+        id: task.id.clone(),
+        todo_file: task.todo_file.display().to_string(),
+        depends_on: task.depends_on.clone(),
+        status: TaskStatus::Pending,
+        coord_dir: coord.display().to_string(),
+        completion_file: task
+            .completion_file
+            .as_ref()
+            .map(|p| p.display().to_string()),
+        started_at: None,
+        completed_at: None,
+        blocked_reason: None,
+        last_progress_epoch: None,
+        recovery_attempts: 0,
+        unattended_escalate_retries: 0,
+        thread_id: None,
+        turns: 0,
+        last_git_snapshot: None,
+        workspace: None,
+        branch: None,
+        verify: task.verify.clone(),
+        verify_rejected_epoch: None,
+        pending_note: None,
+        post_task_hooks_ran: false,
+        priority: task.priority,
+        run_if: task.run_if.clone(),
+        skip_if: task.skip_if.clone(),
+        heartbeats: Vec::new(),
+        todo_progress: None,
+        artifacts: Vec::new(),
+        completion_file_sha256: task.completion_file_sha256.clone(),
+        completion_requires: task.completion_requires.clone(),
+        control_misses: 0,
+        control_strategies: Vec::new(),
+        thinking_override: None,
+    }
+}
+
+/// Appends config tasks missing from a resumed state. Existing tasks keep their
+/// runtime status; a finished run is reopened when new work was added.
+fn merge_new_config_tasks(state: &mut RunState, cfg: &Config) -> Vec<String> {
+    let mut added = Vec::new();
+    for task in &cfg.tasks {
+        if state.tasks.iter().any(|t| t.id == task.id) {
+            continue;
+        }
+        state
+            .tasks
+            .push(task_runtime_from_config(&cfg.state_dir, task));
+        added.push(task.id.clone());
+    }
+    if !added.is_empty() {
+        state.status = RunStatus::Running;
+    }
+    added
+}
+
+/// What `reload_config` did with a re-read config.
+#[derive(Debug, Default)]
+struct ConfigReload {
+    applied: Vec<String>,
+    refused: Vec<String>,
+}
+
+/// Applies the hot-swappable parts of `fresh` to the running config: polling,
+/// timeouts, recovery, limits, policy, schedule, hooks, and added tasks. Changes
+/// to anything the governor has already acted on are refused. Roles are never
+/// compared because `--team` overrides them after loading.
+fn reload_config(cfg: &mut Config, fresh: Config) -> ConfigReload {
+    let mut reload = ConfigReload::default();
+    macro_rules! hot {
+        ($($field:ident),*) => {$(
+            if format!("{:?}", cfg.$field) != format!("{:?}", fresh.$field) {
+                reload.applied.push(format!(
+                    "{}: {:?} -> {:?}",
+                    stringify!($field),
+                    cfg.$field,
+                    fresh.$field
+                ));
+                cfg.$field = fresh.$field.clone();
+            }
+        )*};
+    }
+    macro_rules! frozen {
+        ($($field:ident),*) => {$(
+            if format!("{:?}", cfg.$field) != format!("{:?}", fresh.$field) {
+                reload.refused.push(format!("{} changed", stringify!($field)));
+            }
+        )*};
+    }
+    hot!(
+        poll_interval_secs,
+        timeouts,
+        recovery,
+        limits,
+        policy,
+        schedule,
+        hooks,
+        digest
+    );
+    frozen!(
+        run_id,
+        workspace,
+        state_dir,
+        unattended,
+        backend,
+        git,
+        logging,
+        preflight,
+        security,
+        sandbox,
+        sync,
+        cluster,
+        prompt_template
+    );
+
+    for task in &fresh.tasks {
+        match cfg.tasks.iter().find(|t| t.id == task.id) {
+            Some(existing) if format!("{existing:?}") != format!("{task:?}") => reload
+                .refused
+                .push(format!("task '{}' definition changed", task.id)),
+            Some(_) => {}
+            None => {
+                reload.applied.push(format!("added task '{}'", task.id));
+                cfg.tasks.push(task.clone());
+            }
+        }
+    }
+    for task in &cfg.tasks {
+        if !fresh.tasks.iter().any(|t| t.id == task.id) {
+            reload.refused.push(format!("task '{}' removed", task.id));
+        }
+    }
+    reload
+}
+
+/// Older state files kept a single run-wide `thread_id`. Hand it to the task that
+/// most likely owns the session: the running task, else the last one started.
+fn migrate_legacy_thread_id(state: &mut RunState) {
+    let Some(thread_id) = state.thread_id.take() else {
+        return;
+    };
+    let owner = state
+        .tasks
+        .iter()
+        .position(|t| t.status == TaskStatus::Running)
+        .or_else(|| {
+            state
+                .tasks
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, t)| t.started_at.as_ref().map(|at| (at, idx)))
+                .max()
+                .map(|(_, idx)| idx)
+        });
+    if let Some(idx) = owner
+        && state.tasks[idx].thread_id.is_none()
+    {
+        state.tasks[idx].thread_id = Some(thread_id);
+    }
+}
+
+fn save_state(state: &mut RunState, state_dir: &Path) -> Result<()> {
+    state.updated_at = now_iso();
+    backup_state(state_dir)?;
+    write_json_atomic(&state_path(state_dir), state)
+}
+
+/// Copies the current `state.json` to `state.json.bak`, but only when it
+/// parses, so a torn primary never overwrites a good backup.
+fn backup_state(state_dir: &Path) -> Result<()> {
+    let Ok(bytes) = fs::read(state_path(state_dir)) else {
+        return Ok(());
+    };
+    if serde_json::from_slice::<RunState>(&bytes).is_err() {
+        return Ok(());
+    }
+    let backup = state_backup_path(state_dir);
+    let tmp = backup.with_extension("bak.tmp");
+    let mut file =
+        File::create(&tmp).with_context(|| format!("failed to write {}", tmp.display()))?;
+    file.write_all(&bytes)
+        .and_then(|()| file.sync_all())
+        .with_context(|| format!("failed to write {}", tmp.display()))?;
+    fs::rename(&tmp, &backup)
+        .with_context(|| format!("failed to move {} to {}", tmp.display(), backup.display()))
+}
+
+/// Parses `state.json`, falling back to `state.json.bak` when the primary is
+/// unreadable. The flag is true when the backup was used.
+fn load_state_with_backup(state_dir: &Path) -> Result<(RunState, bool)> {
+    let path = state_path(state_dir);
+    let primary = fs::read(&path)
+        .with_context(|| format!("failed to read state under {}", state_dir.display()))
+        .and_then(|bytes| {
+            serde_json::from_slice::<RunState>(&bytes)
+                .with_context(|| format!("failed to parse {}", path.display()))
+        });
+    let err = match primary {
+        Ok(state) => return Ok((state, false)),
+        Err(err) => err,
+    };
+    let backup = state_backup_path(state_dir);
+    match fs::read(&backup)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice::<RunState>(&bytes).ok())
+    {
+        Some(state) => Ok((state, true)),
+        None => Err(err),
+    }
+}
+
+fn deps_satisfied(state: &RunState, idx: usize) -> bool {
+    let Some(task) = state.tasks.get(idx) else {
+        return false;
+    };
+
+    for dep in &task.depends_on {
+        let Some(dep_task) = state.tasks.iter().find(|t| &t.id == dep) else {
+            return false;
+        };
+        if !dep_task.status.is_terminal() {
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Marks ready pending tasks whose `run_if` fails or `skip_if` holds as skipped.
+/// Returns `(task id, reason)` for each task skipped.
+fn skip_conditional_tasks(cfg: &Config, state: &mut RunState) -> Vec<(String, String)> {
+    let mut skipped = Vec::new();
+    for idx in 0..state.tasks.len() {
+        if state.tasks[idx].status != TaskStatus::Pending || !deps_satisfied(state, idx) {
+            continue;
+        }
+        let task = &state.tasks[idx];
+        let workspace = task_workspace(cfg, task);
+        let reason = if let Some(cond) = &task.run_if
+            && !cond.holds(&workspace)
+        {
+            format!("run_if not met ({})", cond.describe())
+        } else if let Some(cond) = &task.skip_if
+            && cond.holds(&workspace)
+        {
+            format!("skip_if met ({})", cond.describe())
+        } else {
+            continue;
+        };
+        let task = &mut state.tasks[idx];
+        task.status = TaskStatus::Skipped;
+        task.completed_at = Some(now_iso());
+        skipped.push((task.id.clone(), reason));
+    }
+    skipped
+}
+
+/// Length of the longest dependency chain below each task (0 for a root
+/// task), indexed like `state.tasks`. Each depth is computed once, so shared
+/// dependencies cost nothing extra; a cycle edge counts as a root.
+fn dependency_depths(state: &RunState) -> Vec<usize> {
+    fn depth(
+        state: &RunState,
+        idx: usize,
+        index: &BTreeMap<&str, usize>,
+        memo: &mut [Option<usize>],
+        visiting: &mut [bool],
+    ) -> usize {
+        if let Some(depth) = memo[idx] {
+            return depth;
+        }
+        if visiting[idx] {
+            return 0;
+        }
+        visiting[idx] = true;
+        let mut longest = 0;
+        for dep in &state.tasks[idx].depends_on {
+            if let Some(&dep) = index.get(dep.as_str()) {
+                longest = longest.max(1 + depth(state, dep, index, memo, visiting));
+            }
+        }
+        visiting[idx] = false;
+        memo[idx] = Some(longest);
+        longest
+    }
+    let index: BTreeMap<&str, usize> = state
+        .tasks
+        .iter()
+        .enumerate()
+        .map(|(idx, t)| (t.id.as_str(), idx))
+        .collect();
+    let mut memo = vec![None; state.tasks.len()];
+    let mut visiting = vec![false; state.tasks.len()];
+    (0..state.tasks.len())
+        .map(|idx| depth(state, idx, &index, &mut memo, &mut visiting))
+        .collect()
+}
+
+/// Picks the next ready task, passing over tasks other `[cluster]` nodes hold.
+fn choose_next_pending_task(
+    state: &RunState,
+    policy: SchedulingPolicy,
+    leased_elsewhere: &std::collections::BTreeSet<String>,
+) -> Option<usize> {
+    let ready = (0..state.tasks.len()).filter(|&idx| {
+        state.tasks[idx].status == TaskStatus::Pending
+            && !leased_elsewhere.contains(&state.tasks[idx].id)
+            && deps_satisfied(state, idx)
+    });
+    // `min_by_key` keeps the first of equal keys, so ties fall back to declaration order.
+    match policy {
+        SchedulingPolicy::Fifo => ready.min(),
+        SchedulingPolicy::Priority => {
+            ready.min_by_key(|&idx| -i64::from(state.tasks[idx].priority))
+        }
+        SchedulingPolicy::ShortestDepsFirst => {
+            let depths = dependency_depths(state);
+            ready.min_by_key(|&idx| (depths[idx], -i64::from(state.tasks[idx].priority)))
+        }
+    }
+}
+
+fn all_terminal(state: &RunState) -> bool {
+    state.tasks.iter().all(|t| t.status.is_terminal())
+}
+
+fn can_exit(state: &RunState) -> bool {
+    all_terminal(state)
+}
+
+fn task_done_by_artifact(task: &TaskRuntime) -> bool {
+    if let Some(completion) = &task.completion_file {
+        return Path::new(completion).exists();
+    }
+    check_coord_done(Path::new(&task.coord_dir))
+}
+
+fn task_artifact_epoch(task: &TaskRuntime) -> Option<i64> {
+    match &task.completion_file {
+        Some(completion) => mtime_epoch(Path::new(completion)),
+        None => mtime_epoch(&Path::new(&task.coord_dir).join("state.md")),
+    }
+}
+
+/// Whether completing `task` waits on the decisions ledger: only coord-done
+/// tasks are gated, and only under `require_decision_quorum`.
+fn gated_on_decisions(task: &TaskRuntime, require_decision_quorum: bool) -> bool {
+    require_decision_quorum && task.completion_file.is_none()
+}
+
+fn has_completion_checks(task: &TaskRuntime) -> bool {
+    task.completion_file_sha256.is_some() || !task.completion_requires.is_empty()
+}
+
+/// Unmet `completion_file_sha256` / `completion_requires` checks of a task whose
+/// completion artifact is present.
+fn completion_integrity_problems(task: &TaskRuntime) -> Vec<String> {
+    let coord_dir = Path::new(&task.coord_dir);
+    let mut problems = Vec::new();
+    if let (Some(expected), Some(file)) = (&task.completion_file_sha256, &task.completion_file) {
+        match sha256_file(Path::new(file)) {
+            Ok(actual) if actual.eq_ignore_ascii_case(expected.trim()) => {}
+            Ok(actual) => problems.push(format!(
+                "{file} has sha256 {actual}, expected {}",
+                expected.trim()
+            )),
+            Err(err) => problems.push(format!("{err:#}")),
+        }
+    }
+    problems.extend(
+        task.completion_requires
+            .iter()
+            .filter_map(|req| req.unmet(coord_dir)),
+    );
+    problems
+}
+
+/// Newest change to anything completion depends on: the artifact, the required
+/// artifacts, and the decisions ledger when gated.
+fn completion_inputs_epoch(task: &TaskRuntime, require_decision_quorum: bool) -> Option<i64> {
+    let coord_dir = Path::new(&task.coord_dir);
+    let artifact = task
+        .completion_requires
+        .iter()
+        .filter_map(|req| mtime_epoch(&req.path(coord_dir)))
+        .fold(task_artifact_epoch(task), |newest, ts| {
+            Some(newest.map_or(ts, |cur| cur.max(ts)))
+        });
+    if !gated_on_decisions(task, require_decision_quorum) {
+        return artifact;
+    }
+    let decisions = read_decisions_ledger(Path::new(&task.coord_dir))
+        .iter()
+        .map(|d| d.epoch)
+        .max();
+    artifact.max(decisions)
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Verdict {
+    Approve,
+    ChangesRequested,
+    Blocked,
+    GiveUp,
+}
+
+impl Verdict {
+    fn parse(raw: &str) -> Option<Self> {
+        match raw
+            .trim()
+            .to_ascii_uppercase()
+            .replace([' ', '-'], "_")
+            .as_str()
+        {
+            "APPROVE" | "APPROVED" => Some(Self::Approve),
+            "CHANGES_REQUESTED" => Some(Self::ChangesRequested),
+            "BLOCKED" => Some(Self::Blocked),
+            "GIVE_UP" => Some(Self::GiveUp),
+            _ => None,
+        }
+    }
+}
+
+/// One reviewer verdict on one request, from `coord_dir/decisions/<file>`.
+#[derive(Debug, Clone)]
+struct Decision {
+    request: String,
+    reviewer: String,
+    verdict: Verdict,
+    epoch: i64,
+}
+
+/// Reads `request`, `reviewer` and `verdict` from a decision file, either a
+/// JSON object or `key: value` lines.
+fn parse_decision(text: &str) -> Option<(String, String, Verdict)> {
+    let mut fields: BTreeMap<String, String> = BTreeMap::new();
+    if let Ok(Value::Object(map)) = serde_json::from_str::<Value>(text) {
+        for (key, value) in map {
+            if let Some(value) = value.as_str() {
+                fields.insert(key.to_ascii_lowercase(), value.to_string());
+            }
+        }
+    } else {
+        for line in text.lines() {
+            let line = line.trim().trim_start_matches(['-', '*', ' ']);
+            if let Some((key, value)) = line.split_once(':') {
+                let key = key.trim().trim_matches('*').to_ascii_lowercase();
+                fields
+                    .entry(key)
+                    .or_insert_with(|| value.trim().trim_matches('*').trim().to_string());
+            }
+        }
+    }
+    let verdict = Verdict::parse(fields.get("verdict")?)?;
+    let request = fields.remove("request").filter(|r| !r.is_empty())?;
+    let reviewer = fields.remove("reviewer").filter(|r| !r.is_empty())?;
+    Some((request, reviewer, verdict))
+}
+
+/// All parseable decisions, oldest first.
+fn read_decisions_ledger(coord_dir: &Path) -> Vec<Decision> {
+    let Ok(entries) = fs::read_dir(coord_dir.join("decisions")) else {
+        return Vec::new();
+    };
+    let mut ledger: Vec<(i64, String, Decision)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let (request, reviewer, verdict) = parse_decision(&fs::read_to_string(&path).ok()?)?;
+            let epoch = mtime_epoch(&path).unwrap_or(0);
+            let name = entry.file_name().to_string_lossy().into_owned();
+            Some((
+                epoch,
+                name,
+                Decision {
+                    request,
+                    reviewer,
+                    verdict,
+                    epoch,
+                },
+            ))
+        })
+        .collect();
+    ledger.sort_by(|a, b| (a.0, &a.1).cmp(&(b.0, &b.1)));
+    ledger.into_iter().map(|(_, _, d)| d).collect()
+}
+
+/// The request completion is judged on: the newest file in `requests/` (by
+/// mtime, then name), else the newest request any decision names.
+fn final_request(coord_dir: &Path, ledger: &[Decision]) -> Option<String> {
+    let from_requests = fs::read_dir(coord_dir.join("requests"))
+        .ok()
+        .into_iter()
+        .flat_map(|entries| entries.flatten())
+        .filter_map(|entry| {
+            let path = entry.path();
+            let stem = path.file_stem()?.to_str()?.to_string();
+            Some((mtime_epoch(&path).unwrap_or(0), stem))
+        })
+        .max()
+        .map(|(_, stem)| stem);
+    from_requests.or_else(|| ledger.last().map(|d| d.request.clone()))
+}
+
+/// Describes what keeps the decisions ledger short of `quorum` approvals on the
+/// final request, or `None` when it is met. A reviewer's latest verdict counts.
+fn missing_decision_quorum(coord_dir: &Path, quorum: u32) -> Option<String> {
+    let ledger = read_decisions_ledger(coord_dir);
+    let Some(request) = final_request(coord_dir, &ledger) else {
+        return Some(format!(
+            "no requests or decisions found; {quorum} reviewer approval(s) required"
+        ));
+    };
+    let mut latest: BTreeMap<&str, Verdict> = BTreeMap::new();
+    for decision in ledger.iter().filter(|d| d.request == request) {
+        latest.insert(&decision.reviewer, decision.verdict);
+    }
+    let approvals: Vec<&str> = latest
+        .iter()
+        .filter(|(_, v)| **v == Verdict::Approve)
+        .map(|(r, _)| *r)
+        .collect();
+    if approvals.len() as u32 >= quorum {
+        return None;
+    }
+    let others: Vec<String> = latest
+        .iter()
+        .filter(|(_, v)| **v != Verdict::Approve)
+        .map(|(r, v)| format!("{r}: {v:?}"))
+        .collect();
+    let mut missing = format!(
+        "request '{request}' has {} of {quorum} required approval(s)",
+        approvals.len()
+    );
+    if !approvals.is_empty() {
+        missing.push_str(&format!(" (approved by {})", approvals.join(", ")));
+    }
+    if !others.is_empty() {
+        missing.push_str(&format!("; other verdicts: {}", others.join(", ")));
+    }
+    Some(missing)
+}
+
+fn mark_task_completed(task: &mut TaskRuntime) {
+    task.status = TaskStatus::Completed;
+    if task.completed_at.is_none() {
+        task.completed_at = Some(now_iso());
+    }
+    task.blocked_reason = None;
+    task.last_progress_epoch = Some(now_epoch());
+}
+
+/// Refreshes progress and completes tasks whose artifact appeared. Returns the
+/// indices of tasks whose artifact still needs checking against `verify`
+/// commands or the decisions ledger.
+fn sync_completion_and_progress(state: &mut RunState, require_decision_quorum: bool) -> Vec<usize> {
+    let mut unverified = Vec::new();
+    let cycle = state.cycle;
+    for (idx, task) in state.tasks.iter_mut().enumerate() {
+        if task.status == TaskStatus::Running {
+            index_task_artifacts(task, cycle);
+            if let Some(ts) = latest_progress_epoch(Path::new(&task.coord_dir)) {
+                task.last_progress_epoch =
+                    Some(task.last_progress_epoch.map_or(ts, |cur| cur.max(ts)));
+            }
+            refresh_role_heartbeats(task);
+        }
+        if refresh_todo_progress(task) && task.status == TaskStatus::Running {
+            task.last_progress_epoch = Some(now_epoch());
+        }
+
+        if task.status.is_terminal() || !task_done_by_artifact(task) {
+            continue;
+        }
+        if task.verify.is_empty()
+            && !gated_on_decisions(task, require_decision_quorum)
+            && !has_completion_checks(task)
+        {
+            mark_task_completed(task);
+        } else if task.verify_rejected_epoch.is_none()
+            || completion_inputs_epoch(task, require_decision_quorum) > task.verify_rejected_epoch
+        {
+            unverified.push(idx);
+        }
+    }
+    unverified
+}
+
+const MAX_VERIFY_OUTPUT_CHARS: usize = 4000;
+
+/// Runs commands in order with `sh -c`, stopping at the first failure.
+/// Returns a report of the failing command and the tail of its output.
+/// Runs `cmd` in its own process group and kills the whole group once
+/// `timeout` passes; `on_wait` runs about once a second meanwhile. Returns the
+/// exit status (`None` after a timeout), stdout, and stderr.
+fn output_with_timeout(
+    cmd: &mut Command,
+    timeout: Duration,
+    on_wait: &mut dyn FnMut(),
+) -> std::io::Result<(Option<std::process::ExitStatus>, Vec<u8>, Vec<u8>)> {
+    use std::os::unix::process::CommandExt;
+
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .process_group(0)
+        .spawn()?;
+    let [stdout, stderr] = [
+        child
+            .stdout
+            .take()
+            .map(|out| Box::new(out) as Box<dyn Read + Send>),
+        child
+            .stderr
+            .take()
+            .map(|err| Box::new(err) as Box<dyn Read + Send>),
+    ]
+    .map(|pipe| {
+        thread::spawn(move || {
+            let mut bytes = Vec::new();
+            if let Some(mut pipe) = pipe {
+                let _ = pipe.read_to_end(&mut bytes);
+            }
+            bytes
+        })
+    });
+    let started = Instant::now();
+    let mut waited_at = started;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break Some(status);
+        }
+        if waited_at.elapsed() >= Duration::from_secs(1) {
+            on_wait();
+            waited_at = Instant::now();
+        }
+        if started.elapsed() >= timeout {
+            let _ = Command::new("kill")
+                .arg("-KILL")
+                .arg("--")
+                .arg(format!("-{}", child.id()))
+                .stderr(Stdio::null())
+                .status();
+            let _ = child.kill();
+            let _ = child.wait();
+            break None;
+        }
+        thread::sleep(Duration::from_millis(50));
+    };
+    Ok((
+        status,
+        stdout.join().unwrap_or_default(),
+        stderr.join().unwrap_or_default(),
+    ))
+}
+
+/// Runs `sh -c <command>` through [`output_with_timeout`], returning the
+/// exit status and the combined stdout and stderr.
+fn run_shell_command(
+    workspace: &Path,
+    command: &str,
+    env: &[(&str, String)],
+    timeout: Duration,
+    on_wait: &mut dyn FnMut(),
+) -> std::io::Result<(Option<std::process::ExitStatus>, String)> {
+    let (status, stdout, stderr) = output_with_timeout(
+        Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .current_dir(workspace)
+            .envs(env.iter().map(|(k, v)| (*k, v.as_str()))),
+        timeout,
+        on_wait,
+    )?;
+    let mut text = String::from_utf8_lossy(&stdout).into_owned();
+    text.push_str(&String::from_utf8_lossy(&stderr));
+    Ok((status, text))
+}
+
+fn command_failure(
+    workspace: &Path,
+    commands: &[String],
+    env: &[(&str, String)],
+    timeout: Duration,
+    on_wait: &mut dyn FnMut(),
+) -> Option<String> {
+    for command in commands {
+        let (status, text) = match run_shell_command(workspace, command, env, timeout, on_wait) {
+            Ok(v) => v,
+            Err(err) => return Some(format!("`{command}` could not be started: {err}")),
+        };
+        if status.is_some_and(|s| s.success()) {
+            continue;
+        }
+        let total = text.chars().count();
+        let tail: String = if total > MAX_VERIFY_OUTPUT_CHARS {
+            let kept: String = text.chars().skip(total - MAX_VERIFY_OUTPUT_CHARS).collect();
+            format!(
+                "...[truncated {} chars]\n{kept}",
+                total - MAX_VERIFY_OUTPUT_CHARS
+            )
+        } else {
+            text
+        };
+        let outcome = match status {
+            Some(status) => format!("failed with {status}"),
+            None => format!("timed out after {}s", timeout.as_secs()),
+        };
+        return Some(format!("`{command}` {outcome}:\n{}", tail.trim_end()));
+    }
+    None
+}
+
+fn hook_env(cfg: &Config, run_id: &str, task: Option<&TaskRuntime>) -> Vec<(&'static str, String)> {
+    let mut env = vec![
+        ("CRANK_RUN_ID", run_id.to_string()),
+        ("CRANK_STATE_DIR", cfg.state_dir.display().to_string()),
+    ];
+    if let Some(task) = task {
+        env.push(("CRANK_TASK_ID", task.id.clone()));
+        env.push(("CRANK_TASK_STATUS", task.status.as_str().to_string()));
+    }
+    env
+}
+
+/// Runs one lifecycle hook stage and journals the outcome. Returns the failure
+/// report when a command fails.
+fn run_hooks(
+    journal: &Path,
+    stage: &str,
+    commands: &[String],
+    timeout: Duration,
+    workspace: &Path,
+    env: &[(&str, String)],
+) -> Result<Option<String>> {
+    if commands.is_empty() {
+        return Ok(None);
+    }
+    let failure = command_failure(workspace, commands, env, timeout, &mut || {});
+    match &failure {
+        None => append_journal(
+            journal,
+            &format!("{stage} hooks"),
+            &format!("Ran {} {stage} hook command(s).", commands.len()),
+        )?,
+        Some(report) => append_journal(journal, &format!("{stage} hook failed"), report)?,
+    }
+    Ok(failure)
+}
+
+/// Runs `post_task` hooks once per task each time it reaches a terminal status.
+fn run_post_task_hooks(cfg: &Config, state: &mut RunState, journal: &Path) -> Result<()> {
+    for idx in 0..state.tasks.len() {
+        if !state.tasks[idx].status.is_terminal() {
+            state.tasks[idx].post_task_hooks_ran = false;
+            continue;
+        }
+        if state.tasks[idx].post_task_hooks_ran {
+            continue;
+        }
+        let env = hook_env(cfg, &state.run_id, Some(&state.tasks[idx]));
+        let workspace = task_workspace(cfg, &state.tasks[idx]);
+        run_hooks(
+            journal,
+            "post_task",
+            &cfg.hooks.post_task,
+            cfg.hooks.timeout(),
+            &workspace,
+            &env,
+        )?;
+        state.tasks[idx].post_task_hooks_ran = true;
+    }
+    Ok(())
+}
+
+fn verify_task_completions(
+    cfg: &Config,
+    state: &mut RunState,
+    journal: &Path,
+    unverified: &[usize],
+) -> Result<()> {
+    let require_decision_quorum = cfg.policy.require_decision_quorum;
+    for &idx in unverified {
+        let workspace = task_workspace(cfg, &state.tasks[idx]);
+        let env = hook_env(cfg, &state.run_id, Some(&state.tasks[idx]));
+        let task = &mut state.tasks[idx];
+        if gated_on_decisions(task, require_decision_quorum)
+            && let Some(missing) = missing_decision_quorum(
+                Path::new(&task.coord_dir),
+                configured_reviewer_quorum(&cfg.roles),
+            )
+        {
+            task.verify_rejected_epoch =
+                completion_inputs_epoch(task, require_decision_quorum).or(Some(now_epoch()));
+            task.last_progress_epoch = Some(now_epoch());
+            task.pending_note = Some(format!(
+                "state.md says done, but the reviewer quorum has not approved the final request, so the task is not complete: {missing}. Get the missing verdicts recorded under decisions/; completion is re-checked when they land."
+            ));
+            append_journal(
+                journal,
+                "task completion awaiting review quorum",
+                &format!("Task {} stays open: {}.", task.id, missing),
+            )?;
+            continue;
+        }
+        let incomplete = completion_integrity_problems(task);
+        if !incomplete.is_empty() {
+            task.verify_rejected_epoch =
+                completion_inputs_epoch(task, require_decision_quorum).or(Some(now_epoch()));
+            task.last_progress_epoch = Some(now_epoch());
+            let list = incomplete
+                .iter()
+                .map(|p| format!("- {p}"))
+                .collect::<Vec<_>>()
+                .join("\n");
+            task.pending_note = Some(format!(
+                "The completion artifact appeared, but the task's other completion requirements are not met, so the task is not complete. Finish the work they stand for; completion is re-checked when any of these files change.\n{list}"
+            ));
+            append_journal(
+                journal,
+                "task completion incomplete",
+                &format!("Task {} stays open:\n{}", task.id, list),
+            )?;
+            continue;
+        }
+        if task.verify.is_empty() {
+            mark_task_completed(task);
+            continue;
+        }
+        // Long test suites must not look like a stalled governor.
+        let mut on_wait = || {
+            let _ = write_governor_heartbeat(&cfg.state_dir);
+        };
+        match command_failure(
+            &workspace,
+            &task.verify,
+            &env,
+            cfg.timeouts.verify_timeout(),
+            &mut on_wait,
+        ) {
+            None => {
+                mark_task_completed(task);
+                append_journal(
+                    journal,
+                    "task verified",
+                    &format!(
+                        "Task {} passed {} verify command(s). Marked completed.",
+                        task.id,
+                        task.verify.len()
+                    ),
+                )?;
+            }
+            Some(report) => {
+                task.verify_rejected_epoch =
+                    completion_inputs_epoch(task, require_decision_quorum).or(Some(now_epoch()));
+                task.last_progress_epoch = Some(now_epoch());
+                task.pending_note = Some(format!(
+                    "Verification failed after the completion artifact appeared, so the task is not complete. Fix the failure, then write the completion artifact again.\n{report}"
+                ));
+                append_journal(
+                    journal,
+                    "task verification failed",
+                    &format!("Task {} stays open.\n\n{}", task.id, report),
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn mark_task_started(task: &mut TaskRuntime) -> Result<()> {
+    task.status = TaskStatus::Running;
+    task.blocked_reason = None;
+    if task.started_at.is_none() {
+        task.started_at = Some(now_iso());
+    }
+    let coord = Path::new(&task.coord_dir);
+    ensure_dir(coord)?;
+    ensure_dir(&coord.join("heartbeats"))?;
+    Ok(())
+}
+
+fn mark_task_blocked(task: &mut TaskRuntime, reason: &str) {
+    task.status = TaskStatus::BlockedBestEffort;
+    task.completed_at = Some(now_iso());
+    task.blocked_reason = Some(reason.to_string());
+    task.last_progress_epoch = Some(now_epoch());
+}
+
+/// The prompt's task board, with the first `omit_finished` terminal tasks
+/// collapsed into one line to fit `max_prompt_tokens`.
+fn task_board(state: &RunState, omit_finished: usize) -> String {
+    let now = now_epoch();
+    let mut lines = Vec::new();
+    if omit_finished > 0 {
+        lines.push(format!("- ({omit_finished} finished tasks omitted)"));
+    }
+    let mut omitted = 0;
+    for task in &state.tasks {
+        if omitted < omit_finished && task.status.is_terminal() {
+            omitted += 1;
+            continue;
+        }
+        lines.push(task_board_entry(task, now));
+    }
+    lines.join("\n")
+}
+
+/// One task's task board line, plus its role heartbeats while it runs.
+fn task_board_entry(task: &TaskRuntime, now: i64) -> String {
+    let mut entry = format!(
+        "- {}: {} (deps: [{}])",
+        task.id,
+        task.status.as_str(),
+        task.depends_on.join(", ")
+    );
+    if let Some(todo) = task.todo_progress {
+        entry.push_str(&format!(
+            " todo {}/{} ({:.0}%)",
+            todo.checked,
+            todo.total,
+            todo.ratio() * 100.0
+        ));
+    }
+    if task.status != TaskStatus::Running {
+        return entry;
+    }
+    for hb in &task.heartbeats {
+        entry.push_str(&format!(
+            "\n  - {} heartbeat {}s ago: {}",
+            hb.role,
+            now.saturating_sub(hb.epoch),
+            if hb.phase.is_empty() { "-" } else { &hb.phase }
+        ));
+        if !hb.message.is_empty() {
+            entry.push_str(&format!(" ({})", hb.message));
+        }
+    }
+    entry
+}
+
+fn configured_reviewer_quorum(roles: &RolesConfig) -> u32 {
+    let mut count = 0u32;
+    if !roles.reviewer_1.harness.trim().is_empty() {
+        count = count.saturating_add(1);
+    }
+    if !roles.reviewer_2.harness.trim().is_empty() {
+        count = count.saturating_add(1);
+    }
+    count.max(1)
+}
+
+fn coord_reviewer_count(coord_dir: &Path) -> Option<u32> {
+    let meta_path = coord_dir.join("meta.env");
+    let text = fs::read_to_string(meta_path).ok()?;
+    for line in text.lines() {
+        if let Some(raw) = line.strip_prefix("REVIEWER_COUNT=") {
+            let cleaned = raw.trim().trim_matches('\'').trim_matches('"');
+            if let Ok(value) = cleaned.parse::<u32>() {
+                return Some(value);
+            }
+            let digits: String = cleaned.chars().filter(|c| c.is_ascii_digit()).collect();
+            if let Ok(value) = digits.parse::<u32>() {
+                return Some(value);
+            }
+        }
+    }
+    None
+}
+
+/// One line per turn cycle, appended after the cycle's closing sleep.
+fn cycle_metrics_path(state_dir: &Path) -> PathBuf {
+    state_dir.join("metrics.jsonl")
+}
+
+/// Where one turn cycle spent its time. `backend_ms` and `parse_ms` include a
+/// control-block re-prompt; `sleep_ms` is the poll wait or failure backoff that
+/// closed the cycle, cut short by `ctl kick`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+struct CycleTiming {
+    cycle: u64,
+    task_id: String,
+    ok: bool,
+    prompt_build_ms: u64,
+    backend_ms: u64,
+    parse_ms: u64,
+    sleep_ms: u64,
+}
+
+fn append_cycle_timing(state_dir: &Path, timing: &CycleTiming) -> Result<()> {
+    append_text(
+        &cycle_metrics_path(state_dir),
+        &format!("{}\n", serde_json::to_string(timing)?),
+    )
+}
+
+/// Distribution of one `CycleTiming` phase across the run, in milliseconds.
+#[derive(Debug, Serialize, PartialEq, JsonSchema)]
+struct TimingStats {
+    count: usize,
+    mean_ms: f64,
+    p50_ms: u64,
+    p90_ms: u64,
+    p99_ms: u64,
+    max_ms: u64,
+}
+
+impl TimingStats {
+    fn from_samples(mut samples: Vec<u64>) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        samples.sort_unstable();
+        let count = samples.len();
+        // Nearest-rank percentile.
+        let rank = |p: f64| samples[((p * count as f64).ceil() as usize).clamp(1, count) - 1];
+        Some(Self {
+            count,
+            mean_ms: samples.iter().sum::<u64>() as f64 / count as f64,
+            p50_ms: rank(0.5),
+            p90_ms: rank(0.9),
+            p99_ms: rank(0.99),
+            max_ms: samples[count - 1],
+        })
+    }
+}
+
+/// Per-phase stats over `metrics.jsonl`. Unparseable lines are skipped.
+fn summarize_cycle_timings(state_dir: &Path) -> BTreeMap<&'static str, TimingStats> {
+    let Ok(text) = fs::read_to_string(cycle_metrics_path(state_dir)) else {
+        return BTreeMap::new();
+    };
+    let timings: Vec<CycleTiming> = text
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    let phase = |pick: fn(&CycleTiming) -> u64| {
+        TimingStats::from_samples(timings.iter().map(pick).collect())
+    };
+    [
+        ("prompt_build", phase(|t| t.prompt_build_ms)),
+        ("backend", phase(|t| t.backend_ms)),
+        ("parse", phase(|t| t.parse_ms)),
+        ("sleep", phase(|t| t.sleep_ms)),
+    ]
+    .into_iter()
+    .filter_map(|(name, stats)| Some((name, stats?)))
+    .collect()
+}
+
+fn run_summary_path(state_dir: &Path) -> PathBuf {
+    state_dir.join("run-summary.json")
+}
+
+#[derive(Serialize, JsonSchema)]
+struct RunSummary {
+    run_id: String,
+    status: RunStatus,
+    stop_reason: Option<String>,
+    cost_usd: f64,
+    cycle: u64,
+    started_at: String,
+    finished_at: String,
+    /// Deprecated, kept for existing consumers: the thread of the most recently
+    /// active task. Use `thread_ids`.
+    thread_id: Option<String>,
+    thread_ids: std::collections::BTreeMap<String, String>,
+    task_branches: std::collections::BTreeMap<String, String>,
+    unattended: bool,
+    unattended_escalate_policy: String,
+    tasks_total: usize,
+    tasks_completed: usize,
+    tasks_blocked: usize,
+    tasks_skipped: usize,
+    tasks_cancelled: usize,
+    blocked_tasks: Vec<BlockedTaskSummary>,
+    cancelled_tasks: Vec<BlockedTaskSummary>,
+    artifacts: BTreeMap<String, Vec<ArtifactRecord>>,
+    /// Per-phase timing over every turn cycle in `metrics.jsonl`.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    cycle_timings: BTreeMap<&'static str, TimingStats>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct BlockedTaskSummary {
+    id: String,
+    reason: Option<String>,
+}
+
+fn write_run_summary(state: &RunState, cfg: &Config) -> Result<()> {
+    let mut tasks_completed = 0usize;
+    let mut tasks_blocked = 0usize;
+    let mut tasks_skipped = 0usize;
+    let mut blocked_tasks = Vec::new();
+    let mut cancelled_tasks = Vec::new();
+
+    for task in &state.tasks {
+        match task.status {
+            TaskStatus::Completed => tasks_completed = tasks_completed.saturating_add(1),
+            TaskStatus::Skipped => tasks_skipped = tasks_skipped.saturating_add(1),
+            TaskStatus::Cancelled => cancelled_tasks.push(BlockedTaskSummary {
+                id: task.id.clone(),
+                reason: task.blocked_reason.clone(),
+            }),
+            TaskStatus::BlockedBestEffort => {
+                tasks_blocked = tasks_blocked.saturating_add(1);
+                blocked_tasks.push(BlockedTaskSummary {
+                    id: task.id.clone(),
+                    reason: task.blocked_reason.clone(),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    let summary = RunSummary {
+        run_id: state.run_id.clone(),
+        status: state.status.clone(),
+        stop_reason: state.stop_reason.clone(),
+        cost_usd: state.cost_usd,
+        cycle: state.cycle,
+        started_at: state.started_at.clone(),
+        finished_at: state.updated_at.clone(),
+        thread_id: state
+            .tasks
+            .iter()
+            .filter(|t| t.thread_id.is_some())
+            .max_by_key(|t| t.last_progress_epoch)
+            .and_then(|t| t.thread_id.clone()),
+        thread_ids: state
+            .tasks
+            .iter()
+            .filter_map(|t| t.thread_id.clone().map(|id| (t.id.clone(), id)))
+            .collect(),
+        task_branches: state
+            .tasks
+            .iter()
+            .filter_map(|t| t.branch.clone().map(|b| (t.id.clone(), b)))
+            .collect(),
+        unattended: state.unattended,
+        unattended_escalate_policy: cfg.policy.unattended_escalate.as_str().to_string(),
+        tasks_total: state.tasks.len(),
+        tasks_completed,
+        tasks_blocked,
+        tasks_skipped,
+        tasks_cancelled: cancelled_tasks.len(),
+        blocked_tasks,
+        cancelled_tasks,
+        artifacts: state
+            .tasks
+            .iter()
+            .filter(|t| !t.artifacts.is_empty())
+            .map(|t| (t.id.clone(), t.artifacts.clone()))
+            .collect(),
+        cycle_timings: summarize_cycle_timings(&cfg.state_dir),
+    };
+
+    write_json_atomic(&run_summary_path(&cfg.state_dir), &summary)
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum EscalateHandling {
+    Ignore,
+    Retry,
+    Block,
+}
+
+fn decide_unattended_escalate(
+    unattended: bool,
+    policy: UnattendedEscalatePolicy,
+    task: &mut TaskRuntime,
+    control_status: Option<&str>,
+    next_action: Option<&str>,
+) -> EscalateHandling {
+    if !unattended {
+        return EscalateHandling::Ignore;
+    }
+    let action_escalate = next_action
+        .map(|v| v.eq_ignore_ascii_case("ESCALATE"))
+        .unwrap_or(false);
+    let status_escalate = control_status
+        .map(|v| {
+            let s = v.trim();
+            s.eq_ignore_ascii_case("blocked") || s.eq_ignore_ascii_case("blocked_best_effort")
+        })
+        .unwrap_or(false);
+    let should_escalate = action_escalate || status_escalate;
+    if !should_escalate {
+        return EscalateHandling::Ignore;
+    }
+
+    match policy {
+        UnattendedEscalatePolicy::Strict => EscalateHandling::Block,
+        UnattendedEscalatePolicy::BestEffortOnce => {
+            if task.unattended_escalate_retries == 0 {
+                task.unattended_escalate_retries = 1;
+                EscalateHandling::Retry
+            } else {
+                EscalateHandling::Block
+            }
+        }
+    }
+}
+
+fn escalations_dir(state_dir: &Path, task_id: &str) -> PathBuf {
+    state_dir.join("escalations").join(task_id)
+}
+
+fn escalation_answer_path(dir: &Path, n: u32) -> PathBuf {
+    dir.join(format!("{n}.answer.md"))
+}
+
+fn escalation_delivered_path(dir: &Path, n: u32) -> PathBuf {
+    dir.join(format!("{n}.answer.delivered.md"))
+}
+
+/// Question numbers present under an escalation dir (`<n>.md`), ascending.
+fn escalation_numbers(dir: &Path) -> Vec<u32> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut numbers: Vec<u32> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            name.strip_suffix(".md")?.parse::<u32>().ok()
+        })
+        .collect();
+    numbers.sort_unstable();
+    numbers
+}
+
+fn write_escalation_question(
+    state_dir: &Path,
+    task: &TaskRuntime,
+    cycle: u64,
+    control: &ControlBlock,
+) -> Result<PathBuf> {
+    let dir = escalations_dir(state_dir, &task.id);
+    ensure_dir(&dir)?;
+    let n = escalation_numbers(&dir).last().copied().unwrap_or(0) + 1;
+    let bullet_list = |items: Vec<String>| {
+        if items.is_empty() {
+            "- (none)".to_string()
+        } else {
+            items
+                .iter()
+                .map(|item| format!("- {item}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+    };
+    let body = format!(
+        "# Escalation {n}: task {task_id}\n\n\
+         - raised_at: {raised_at}\n\
+         - cycle: {cycle}\n\
+         - status: {status}\n\
+         - next_action: {next_action}\n\n\
+         ## Summary\n\n{summary}\n\n\
+         ## Blockers\n\n{blockers}\n\n\
+         ## Requests\n\n{requests}\n\n\
+         ## Answer\n\n\
+         crank ctl answer --state-dir {state_dir} --task-id {task_id} --question {n} --message \"...\"\n",
+        task_id = task.id,
+        raised_at = now_iso(),
+        status = control.status.as_str(),
+        next_action = control.next_action,
+        summary = control.summary,
+        blockers = bullet_list(control.blockers.clone()),
+        requests = bullet_list(
+            control
+                .requests
+                .iter()
+                .map(|r| format!("{}: {}", r.kind, r.detail))
+                .collect()
+        ),
+        state_dir = state_dir.display(),
+    );
+    let path = dir.join(format!("{n}.md"));
+    fs::write(&path, body).with_context(|| format!("failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+struct EscalationAnswer {
+    n: u32,
+    text: String,
+}
+
+/// Answers written by `ctl answer` that have not yet reached a turn prompt.
+fn pending_escalation_answers(state_dir: &Path, task_id: &str) -> Vec<EscalationAnswer> {
+    let dir = escalations_dir(state_dir, task_id);
+    escalation_numbers(&dir)
+        .into_iter()
+        .filter_map(|n| {
+            let text = fs::read_to_string(escalation_answer_path(&dir, n)).ok()?;
+            Some(EscalationAnswer {
+                n,
+                text: text.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+fn mark_escalation_answers_delivered(
+    state_dir: &Path,
+    task_id: &str,
+    answers: &[EscalationAnswer],
+) -> Result<()> {
+    let dir = escalations_dir(state_dir, task_id);
+    for answer in answers {
+        let from = escalation_answer_path(&dir, answer.n);
+        let to = escalation_delivered_path(&dir, answer.n);
+        fs::rename(&from, &to)
+            .with_context(|| format!("failed to move {} to {}", from.display(), to.display()))?;
+    }
+    Ok(())
+}
+
+fn instructions_dir(state_dir: &Path, task_id: &str) -> PathBuf {
+    state_dir.join("instructions").join(task_id)
+}
+
+/// A queued `ctl instruct` message (`instructions/<task>/<stamp>.md`).
+struct OperatorInstruction {
+    path: PathBuf,
+    text: String,
+}
+
+/// Undelivered instructions for a task, oldest first.
+fn pending_operator_instructions(state_dir: &Path, task_id: &str) -> Vec<OperatorInstruction> {
+    let Ok(entries) = fs::read_dir(instructions_dir(state_dir, task_id)) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| {
+            p.extension().and_then(|e| e.to_str()) == Some("md")
+                && !p.to_string_lossy().ends_with(".delivered.md")
+        })
+        .collect();
+    paths.sort();
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let text = fs::read_to_string(&path).ok()?;
+            Some(OperatorInstruction {
+                path,
+                text: text.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+fn mark_operator_instructions_delivered(instructions: &[OperatorInstruction]) -> Result<()> {
+    for instruction in instructions {
+        let to = instruction.path.with_extension("delivered.md");
+        fs::rename(&instruction.path, &to).with_context(|| {
+            format!(
+                "failed to move {} to {}",
+                instruction.path.display(),
+                to.display()
+            )
+        })?;
+    }
+    Ok(())
+}
+
+/// Blocked tasks with an operator answer waiting go back to pending so the
+/// answer can be delivered on their next turn.
+fn reopen_answered_escalations(state: &mut RunState, state_dir: &Path) -> Vec<String> {
+    let mut reopened = Vec::new();
+    for task in &mut state.tasks {
+        if task.status == TaskStatus::BlockedBestEffort
+            && !pending_escalation_answers(state_dir, &task.id).is_empty()
+        {
+            task.status = TaskStatus::Pending;
+            task.blocked_reason = None;
+            task.completed_at = None;
+            task.unattended_escalate_retries = 0;
+            reopened.push(task.id.clone());
+        }
+    }
+    if !reopened.is_empty() {
+        state.status = RunStatus::Running;
+    }
+    reopened
+}
+
+const MAX_TEMPLATE_FILE_BYTES: usize = 32 * 1024;
+
+/// `{{env:VAR}}` and `{{file:path}}` are only resolved when rendering with a
+/// workspace; without one `render_template_in` leaves them in place.
+fn is_plugin_placeholder(key: &str) -> bool {
+    key.starts_with("env:") || key.starts_with("file:")
+}
+
+#[derive(Debug)]
+struct TemplateInclude {
+    path: PathBuf,
+    bytes: usize,
+    truncated: bool,
+}
+
+fn read_template_include(workspace: &Path, raw: &str) -> Result<(String, TemplateInclude)> {
+    let rel = Path::new(raw);
+    if rel.is_absolute()
+        || rel
+            .components()
+            .any(|c| matches!(c, std::path::Component::ParentDir))
+    {
+        return Err(anyhow!(
+            "template file placeholder '{raw}' must be a path relative to the workspace"
+        ));
+    }
+    let path = workspace.join(rel);
+    let text = fs::read_to_string(&path)
+        .with_context(|| format!("failed to read template include {}", path.display()))?;
+    let bytes = text.len();
+    if bytes <= MAX_TEMPLATE_FILE_BYTES {
+        return Ok((
+            text,
+            TemplateInclude {
+                path,
+                bytes,
+                truncated: false,
+            },
+        ));
+    }
+    let mut cut = MAX_TEMPLATE_FILE_BYTES;
+    while !text.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    let truncated = format!(
+        "{}\n...[truncated {} bytes]",
+        &text[..cut],
+        bytes.saturating_sub(cut)
+    );
+    Ok((
+        truncated,
+        TemplateInclude {
+            path,
+            bytes,
+            truncated: true,
+        },
+    ))
+}
+
+/// Fills `{{key}}` from `vars` and, given a workspace, `{{env:VAR}}` and
+/// `{{file:path}}`, in one left-to-right pass over the template. Substituted
+/// values and included files are never rescanned, so agent output, operator
+/// notes, and file contents may hold literal `{{...}}` without expanding it.
+fn render_template_in(
+    template: &str,
+    vars: &[(&str, String)],
+    workspace: Option<&Path>,
+) -> Result<(String, Vec<TemplateInclude>)> {
+    let mut out = String::with_capacity(template.len());
+    let mut includes = Vec::new();
+    let mut pending: Vec<String> = Vec::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        let raw = &rest[start..start + 2 + end + 2];
+        let key = after[..end].trim();
+        if let Some((_, value)) = vars.iter().find(|(name, _)| *name == key) {
+            out.push_str(value);
+        } else if workspace.is_some()
+            && let Some(name) = key.strip_prefix("env:")
+        {
+            let value = std::env::var(name.trim())
+                .map_err(|_| anyhow!("template env placeholder '{}' is not set", name.trim()))?;
+            out.push_str(&value);
+        } else if let Some(workspace) = workspace
+            && let Some(path) = key.strip_prefix("file:")
+        {
+            let (text, include) = read_template_include(workspace, path.trim())?;
+            out.push_str(&text);
+            includes.push(include);
+        } else {
+            if !key.is_empty()
+                && !is_plugin_placeholder(key)
+                && !pending.iter().any(|existing| existing == key)
+            {
+                pending.push(key.to_string());
+            }
+            out.push_str(raw);
+        }
+        rest = &after[end + 2..];
+    }
+    out.push_str(rest);
+
+    if !pending.is_empty() {
+        return Err(anyhow!(
+            "unresolved template placeholders: {}",
+            pending.join(", ")
+        ));
+    }
+    Ok((out, includes))
+}
+
+/// Turn prompt templates loaded once at governor start: the config-level
+/// override (or embedded default) plus any per-task overrides.
+struct PromptTemplates {
+    default: String,
+    by_path: std::collections::BTreeMap<PathBuf, String>,
+}
+
+impl PromptTemplates {
+    fn load(cfg: &Config) -> Result<Self> {
+        let read = |path: &Path| {
+            fs::read_to_string(path)
+                .with_context(|| format!("failed to read prompt template {}", path.display()))
+        };
+        let default = match &cfg.prompt_template {
+            Some(path) => read(path)?,
+            None => TURN_PROMPT_TEMPLATE.to_string(),
+        };
+        let mut by_path = std::collections::BTreeMap::new();
+        for task in &cfg.tasks {
+            if let Some(path) = &task.prompt_template
+                && !by_path.contains_key(path)
+            {
+                by_path.insert(path.clone(), read(path)?);
+            }
+        }
+        Ok(Self { default, by_path })
+    }
+
+    fn for_task(&self, cfg: &Config, task_id: &str) -> &str {
+        cfg.tasks
+            .iter()
+            .find(|t| t.id == task_id)
+            .and_then(|t| t.prompt_template.as_ref())
+            .and_then(|path| self.by_path.get(path))
+            .unwrap_or(&self.default)
+    }
+}
+
+/// A rendered turn prompt plus the `(title, body)` journal entries building
+/// it produced (trimming, budget overruns, file includes).
+#[derive(Debug)]
+struct TurnPrompt {
+    text: String,
+    journal: Vec<(&'static str, String)>,
+}
+
+fn build_prompt(
+    cfg: &Config,
+    templates: &PromptTemplates,
+    state: &RunState,
+    task: &TaskRuntime,
+    recovery_note: Option<&str>,
+    operator_notes: &[String],
+) -> Result<TurnPrompt> {
+    let reviewer_quorum = configured_reviewer_quorum(&cfg.roles);
+    let mut completion_line = if let Some(completion_file) = &task.completion_file {
+        format!("- completion_file: {completion_file}")
+    } else {
+        "- completion rule: coord_dir/state.md must be exactly 'done'".to_string()
+    };
+    if let Some(sha256) = &task.completion_file_sha256 {
+        completion_line.push_str(&format!(" (its sha256 must be {sha256})"));
+    }
+    let coord_dir = Path::new(&task.coord_dir);
+    for req in &task.completion_requires {
+        completion_line.push_str(&format!(
+            "\n- completion also requires: {}",
+            req.path(coord_dir).display()
+        ));
+        if let CompletionRequirement::Matches { matches, .. } = req {
+            completion_line.push_str(&format!(" (content matching /{matches}/)"));
+        }
+    }
+
+    let recovery_block = recovery_note
+        .map(|note| format!("\nRecovery note from governor:\n{note}\n"))
+        .unwrap_or_default();
+    let mut digest_block = fs::read_to_string(digest_path(&cfg.state_dir))
+        .ok()
+        .filter(|d| !d.trim().is_empty())
+        .map(|d| {
+            format!(
+                "\nRun digest (earlier journal entries, summarized):\n{}\n",
+                d.trim()
+            )
+        })
+        .unwrap_or_default();
+    let operator_block = if operator_notes.is_empty() {
+        String::new()
+    } else {
+        format!("\nFrom the operator:\n{}\n", operator_notes.join("\n"))
+    };
+
+    let workspace = task_workspace(cfg, task);
+    let render = |omit_finished: usize, digest_block: &str| {
+        render_template_in(
+            templates.for_task(cfg, &task.id),
+            &[
+                ("run_id", state.run_id.clone()),
+                ("workspace", workspace.display().to_string()),
+                (
+                    "journal",
+                    journal_path(&cfg.state_dir).display().to_string(),
+                ),
+                ("state_dir", cfg.state_dir.display().to_string()),
+                (
+                    "thread_id",
+                    task.thread_id.as_deref().unwrap_or("(new)").to_string(),
+                ),
+                ("task_board", task_board(state, omit_finished)),
+                ("digest_block", digest_block.to_string()),
+                ("task_id", task.id.clone()),
+                ("todo_file", task.todo_file.clone()),
+                ("coord_dir", task.coord_dir.clone()),
+                ("completion_line", completion_line.clone()),
+                ("implementer_harness", cfg.roles.implementer.harness.clone()),
+                ("implementer_model", cfg.roles.implementer.model.clone()),
+                (
+                    "implementer_thinking",
+                    cfg.roles.implementer.thinking.clone(),
+                ),
+                (
+                    "implementer_args",
+                    role_launch_args_display(&cfg.roles.implementer),
+                ),
+                ("reviewer_1_harness", cfg.roles.reviewer_1.harness.clone()),
+                ("reviewer_1_model", cfg.roles.reviewer_1.model.clone()),
+                ("reviewer_1_thinking", cfg.roles.reviewer_1.thinking.clone()),
+                (
+                    "reviewer_1_args",
+                    role_launch_args_display(&cfg.roles.reviewer_1),
+                ),
+                ("reviewer_2_harness", cfg.roles.reviewer_2.harness.clone()),
+                ("reviewer_2_model", cfg.roles.reviewer_2.model.clone()),
+                ("reviewer_2_thinking", cfg.roles.reviewer_2.thinking.clone()),
+                (
+                    "reviewer_2_args",
+                    role_launch_args_display(&cfg.roles.reviewer_2),
+                ),
+                ("reviewer_quorum", reviewer_quorum.to_string()),
+                (
+                    "unattended_escalate_policy",
+                    cfg.policy.unattended_escalate.as_str().to_string(),
+                ),
+                ("recovery_block", recovery_block.clone()),
+                ("operator_block", operator_block.clone()),
+            ],
+            Some(&workspace),
+        )
+    };
+
+    let mut journal = Vec::new();
+    let (mut text, mut includes) = render(0, &digest_block)?;
+    let untrimmed_tokens = approx_tokens(&text);
+    if let Some(max) = cfg
+        .limits
+        .max_prompt_tokens
+        .filter(|max| untrimmed_tokens > *max)
+    {
+        // `approx_tokens` counts four chars per token, so the budget in chars
+        // tells each rule exactly how much to cut.
+        let budget_chars = usize::try_from(max.saturating_mul(4)).unwrap_or(usize::MAX);
+        let mut omit_finished = 0;
+        let mut digest_trimmed = false;
+        let mut applied = Vec::new();
+        for rule in cfg
+            .limits
+            .prompt_trim
+            .as_deref()
+            .unwrap_or(&DEFAULT_PROMPT_TRIM)
+        {
+            let chars = text.chars().count();
+            if chars <= budget_chars {
+                break;
+            }
+            match rule {
+                PromptTrim::TaskBoard if omit_finished == 0 => {
+                    omit_finished = finished_tasks_to_omit(state, chars - budget_chars);
+                    if omit_finished == 0 {
+                        continue;
+                    }
+                    applied.push(format!(
+                        "omitted {omit_finished} finished tasks from the task board"
+                    ));
+                }
+                PromptTrim::Digest if !digest_block.is_empty() && !digest_trimmed => {
+                    digest_trimmed = true;
+                    digest_block = format!(
+                        "\nRun digest: omitted to fit the prompt budget; read {} if needed.\n",
+                        digest_path(&cfg.state_dir).display()
+                    );
+                    applied.push("replaced the run digest with its path".to_string());
+                }
+                _ => continue,
+            }
+            (text, includes) = render(omit_finished, &digest_block)?;
+        }
+        let tokens = approx_tokens(&text);
+        if !applied.is_empty() {
+            journal.push((
+                "prompt trimmed",
+                format!(
+                    "Task {} prompt trimmed from ~{untrimmed_tokens} to ~{tokens} tokens: {}.",
+                    task.id,
+                    applied.join("; ")
+                ),
+            ));
+        }
+        if tokens > max {
+            journal.push((
+                "prompt over budget",
+                format!(
+                    "Task {} prompt is ~{tokens} tokens after trimming, over max_prompt_tokens = {max}. Sending it anyway.",
+                    task.id
+                ),
+            ));
+        }
+    }
+    for include in includes {
+        journal.push((
+            "prompt file included",
+            format!(
+                "Task {} prompt embeds {} ({} bytes{}).",
+                task.id,
+                include.path.display(),
+                include.bytes,
+                if include.truncated {
+                    format!(", truncated to {MAX_TEMPLATE_FILE_BYTES}")
+                } else {
+                    String::new()
+                }
+            ),
+        ));
+    }
+    Ok(TurnPrompt { text, journal })
+}
+
+/// How many of the oldest finished tasks to drop from the task board to cut
+/// at least `excess_chars` from the prompt, counting the line noting the
+/// omission. All of them when that is still not enough.
+fn finished_tasks_to_omit(state: &RunState, excess_chars: usize) -> usize {
+    let now = now_epoch();
+    let mut saved = 0usize;
+    let mut omitted = 0usize;
+    for task in state.tasks.iter().filter(|t| t.status.is_terminal()) {
+        saved += task_board_entry(task, now).chars().count() + 1;
+        omitted += 1;
+        let note = format!("- ({omitted} finished tasks omitted)")
+            .chars()
+            .count()
+            + 1;
+        if saved >= excess_chars + note {
+            break;
+        }
+    }
+    omitted
+}
+
+fn parse_control_block(raw: &str) -> Result<ControlBlock, ControlBlockError> {
+    let control: ControlBlock =
+        serde_json::from_str(raw).map_err(|err| ControlBlockError::Invalid(err.to_string()))?;
+    if control.version == 0 || control.version > CONTROL_SCHEMA_VERSION {
+        return Err(ControlBlockError::Invalid(format!(
+            "unsupported version {} (expected 1..={CONTROL_SCHEMA_VERSION})",
+            control.version
+        )));
+    }
+    if control.task_id.trim().is_empty() {
+        return Err(ControlBlockError::Invalid(
+            "task_id must not be empty".to_string(),
+        ));
+    }
+    Ok(control)
+}
+
+/// Parses the response's last complete `<CONTROL_JSON>` block, so an earlier
+/// quoted or abandoned block never bleeds into it, falling back to a bare
+/// JSON line for v1 responses.
+fn extract_control_block(text: &str) -> Result<ControlBlock, ControlBlockError> {
+    const START: &str = "<CONTROL_JSON>";
+    const END: &str = "</CONTROL_JSON>";
+
+    if let Some(e) = text.rfind(END)
+        && let Some(s) = text[..e].rfind(START)
+    {
+        return parse_control_block(text[s + START.len()..e].trim());
+    }
+
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('{')
+            && trimmed.ends_with('}')
+            && let Ok(control) = parse_control_block(trimmed)
+        {
+            return Ok(control);
+        }
+    }
+
+    Err(ControlBlockError::Missing)
+}
+
+const STRICT_CONTROL_SUFFIX: &str = "\n\n## Control block (required)\n\nYour recent replies ended without a valid CONTROL_JSON block. However this turn goes, finish your reply with exactly one <CONTROL_JSON>...</CONTROL_JSON> block in the format above and write nothing after it.\n";
+
+/// Counts a turn that ended without a usable control block and, every
+/// `control_miss_threshold` misses in a row, applies the task's next
+/// `[recovery] control_strategies` entry.
+fn note_control_miss(cfg: &Config, state: &mut RunState, idx: usize, journal: &Path) -> Result<()> {
+    let threshold = cfg.recovery.control_miss_threshold.max(1);
+    let task = &mut state.tasks[idx];
+    task.control_misses = task.control_misses.saturating_add(1);
+    if task.control_misses < threshold {
+        return Ok(());
+    }
+    let Some(&strategy) = cfg
+        .recovery
+        .control_strategies
+        .get(task.control_strategies.len())
+    else {
+        return Ok(());
+    };
+    task.control_misses = 0;
+    task.control_strategies.push(strategy);
+    let task_id = task.id.clone();
+    let outcome = match strategy {
+        ControlStrategy::StrictSuffix => {
+            "later prompts end with a strict control block instruction".to_string()
+        }
+        ControlStrategy::DowngradeThinking => {
+            let current = task.thinking_override.clone().or_else(|| {
+                cfg.backend
+                    .at(state.backend_index)
+                    .thinking()
+                    .map(str::to_string)
+            });
+            match current.as_deref().and_then(lower_thinking) {
+                Some(lower) => {
+                    state.tasks[idx].thinking_override = Some(lower.to_string());
+                    format!("thinking lowered to {lower}")
+                }
+                None => "thinking cannot be lowered further".to_string(),
+            }
+        }
+        ControlStrategy::FallbackBackend => match fail_over_backend(&cfg.backend, state) {
+            Some((from, to)) => format!("run failed over from the {from} backend to {to}"),
+            None => "no fallback backend is left".to_string(),
+        },
+    };
+    append_journal(
+        journal,
+        "control block recovery",
+        &format!(
+            "Task {task_id} ended {threshold} turn(s) in a row without a usable control block; applied {}: {outcome}.",
+            strategy.as_str()
+        ),
+    )
+}
+
+fn control_fix_prompt(task_id: &str, reason: &str) -> String {
+    format!(
+        "Your previous response for task {task_id} ended with an invalid CONTROL_JSON block: {reason}\n\
+         Do not redo any work. Reply with only a corrected block (version {CONTROL_SCHEMA_VERSION}):\n\
+         <CONTROL_JSON>\n\
+         {{\"version\":{CONTROL_SCHEMA_VERSION},\"task_id\":\"{task_id}\",\"status\":\"in_progress|completed|blocked\",\"needs_user_input\":false,\"summary\":\"...\",\"next_action\":\"...\",\"artifacts\":[],\"blockers\":[],\"requests\":[]}}\n\
+         </CONTROL_JSON>"
+    )
+}
+
+fn run_backend_command_streaming<F>(
+    mut cmd: Command,
+    prompt: &str,
+    backend_name: &str,
+    mut on_stdout_line: F,
+) -> Result<()>
+where
+    F: FnMut(&str) -> Result<()>,
+{
+    cmd.stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    let mut child = cmd
+        .spawn()
+        .with_context(|| format!("failed to spawn {backend_name} backend executable"))?;
+
+    {
+        let mut stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| anyhow!("failed to open {backend_name} stdin"))?;
+        if !prompt.is_empty() {
+            stdin
+                .write_all(prompt.as_bytes())
+                .with_context(|| format!("failed to write prompt to {backend_name}"))?;
+            if !prompt.ends_with('\n') {
+                stdin
+                    .write_all(b"\n")
+                    .with_context(|| format!("failed to finalize prompt for {backend_name}"))?;
+            }
+        }
+    }
+
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| anyhow!("failed to open {backend_name} stdout"))?;
+    let stderr = child
+        .stderr
+        .take()
+        .ok_or_else(|| anyhow!("failed to open {backend_name} stderr"))?;
+
+    let stderr_handle = thread::spawn(move || {
+        let mut stderr_text = String::new();
+        let mut reader = BufReader::new(stderr);
+        let _ = reader.read_to_string(&mut stderr_text);
+        stderr_text
+    });
+
+    let mut stdout_reader = BufReader::new(stdout);
+    let mut line_buf = String::new();
+    loop {
+        line_buf.clear();
+        let n = stdout_reader
+            .read_line(&mut line_buf)
+            .with_context(|| format!("failed reading {backend_name} stdout"))?;
+        if n == 0 {
+            break;
+        }
+        let line_trim = line_buf.trim();
+        if line_trim.is_empty() {
+            continue;
+        }
+        if let Err(err) = on_stdout_line(line_trim) {
+            // The turn was abandoned (e.g. its task lease moved to another
+            // node); do not leave the backend working on it.
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(err);
+        }
+    }
+
+    let status = child
+        .wait()
+        .with_context(|| format!("failed waiting for {backend_name} process"))?;
+    let stderr_text = stderr_handle.join().unwrap_or_default();
+
+    if !status.success() {
+        return Err(anyhow!(
+            "{backend_name} turn failed with status {}\nstderr:\n{}",
+            status,
+            stderr_text
+        ));
+    }
+
+    Ok(())
+}
+
+fn parse_assistant_text_from_content(content: &Value) -> Option<String> {
+    let blocks = content.as_array()?;
+    let mut text = String::new();
+    for block in blocks {
+        if block.get("type").and_then(|v| v.as_str()) == Some("text")
+            && let Some(t) = block.get("text").and_then(|v| v.as_str())
+        {
+            text.push_str(t);
+        }
+    }
+    if text.is_empty() { None } else { Some(text) }
+}
+
+struct CodexBackend(CodexBackendConfig);
+struct ClaudeBackend(ClaudeBackendConfig);
+struct DroidBackend(DroidBackendConfig);
+struct PiBackend(PiBackendConfig);
+struct MockBackend(MockBackendConfig);
+
+/// Backend factory keyed by the config `kind`.
+fn create_backend(config: &BackendConfig) -> Box<dyn Backend> {
+    match config {
+        BackendConfig::Codex(c) => Box::new(CodexBackend(c.clone())),
+        BackendConfig::Claude(c) => Box::new(ClaudeBackend(c.clone())),
+        BackendConfig::Droid(c) => Box::new(DroidBackend(c.clone())),
+        BackendConfig::Pi(c) => Box::new(PiBackend(c.clone())),
+        BackendConfig::Mock(c) => Box::new(MockBackend(c.clone())),
+        BackendConfig::Replay(c) => Box::new(ReplayBackend {
+            dir: c.dir.clone(),
+            next_turn: RefCell::new(BTreeMap::new()),
+        }),
+    }
+}
+
+/// One recorded turn: `<record_dir>/<task_id>-<n>.json`, `n` counting from 1 per task.
+#[derive(Debug, Serialize, Deserialize)]
+struct RecordedTurn {
+    task_id: String,
+    backend: String,
+    prompt: String,
+    thread_id: Option<String>,
+    events: Vec<String>,
+    final_response: String,
+    cost_usd: Option<f64>,
+}
+
+fn recorded_turn_path(dir: &Path, task_id: &str, n: u32) -> PathBuf {
+    dir.join(format!("{task_id}-{n}.json"))
+}
+
+/// Wraps a live backend and writes each successful turn to `dir`.
+struct RecordingBackend {
+    inner: Box<dyn Backend>,
+    dir: PathBuf,
+}
+
+impl Backend for RecordingBackend {
+    fn kind(&self) -> &'static str {
+        self.inner.kind()
+    }
+
+    fn run_turn(
+        &self,
+        ctx: TurnContext<'_>,
+        on_activity: &mut dyn FnMut() -> Result<()>,
+    ) -> Result<TurnResult> {
+        let events = RefCell::new(Vec::new());
+        let task_id = ctx.task_id.to_string();
+        let prompt = ctx.prompt.to_string();
+        let result = self.inner.run_turn(
+            TurnContext {
+                event_sink: Some(&events),
+                ..ctx
+            },
+            on_activity,
+        )?;
+
+        ensure_dir(&self.dir)?;
+        let mut n = 1;
+        while recorded_turn_path(&self.dir, &task_id, n).exists() {
+            n += 1;
+        }
+        let record = RecordedTurn {
+            task_id: task_id.clone(),
+            backend: self.inner.kind().to_string(),
+            prompt,
+            thread_id: result.thread_id.clone(),
+            events: events.into_inner(),
+            final_response: result.final_response.clone(),
+            cost_usd: result.cost_usd,
+        };
+        let path = recorded_turn_path(&self.dir, &task_id, n);
+        fs::write(&path, serde_json::to_string_pretty(&record)?)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        Ok(result)
+    }
+}
+
+/// Serves recorded turns back in order, re-emitting their events.
+struct ReplayBackend {
+    dir: PathBuf,
+    next_turn: RefCell<BTreeMap<String, u32>>,
+}
+
+impl Backend for ReplayBackend {
+    fn kind(&self) -> &'static str {
+        "replay"
+    }
+
+    fn run_turn(
+        &self,
+        ctx: TurnContext<'_>,
+        on_activity: &mut dyn FnMut() -> Result<()>,
+    ) -> Result<TurnResult> {
+        let n = {
+            let mut next_turn = self.next_turn.borrow_mut();
+            let n = next_turn.entry(ctx.task_id.to_string()).or_insert(1);
+            *n += 1;
+            *n - 1
+        };
+        let path = recorded_turn_path(&self.dir, ctx.task_id, n);
+        let text = fs::read_to_string(&path).with_context(|| {
+            format!(
+                "no recorded turn {n} for task '{}' ({})",
+                ctx.task_id,
+                path.display()
+            )
+        })?;
+        let record: RecordedTurn = serde_json::from_str(&text)
+            .with_context(|| format!("failed to parse {}", path.display()))?;
+        for line in &record.events {
+            ctx.append_event(line)?;
+            on_activity()?;
+        }
+        on_activity()?;
+        Ok(TurnResult {
+            thread_id: record.thread_id,
+            final_response: record.final_response,
+            cost_usd: record.cost_usd,
+        })
+    }
+}
+
+impl Backend for CodexBackend {
+    fn kind(&self) -> &'static str {
+        "codex"
+    }
+
+    fn run_turn(
+        &self,
+        ctx: TurnContext<'_>,
+        on_activity: &mut dyn FnMut() -> Result<()>,
+    ) -> Result<TurnResult> {
+        run_turn_codex(&self.0, &ctx, on_activity)
+    }
+}
+
+impl Backend for ClaudeBackend {
+    fn kind(&self) -> &'static str {
+        "claude"
+    }
+
+    fn run_turn(
+        &self,
+        ctx: TurnContext<'_>,
+        on_activity: &mut dyn FnMut() -> Result<()>,
+    ) -> Result<TurnResult> {
+        run_turn_claude(&self.0, &ctx, on_activity)
+    }
+}
+
+impl Backend for DroidBackend {
+    fn kind(&self) -> &'static str {
+        "droid"
+    }
+
+    fn run_turn(
+        &self,
+        ctx: TurnContext<'_>,
+        on_activity: &mut dyn FnMut() -> Result<()>,
+    ) -> Result<TurnResult> {
+        run_turn_droid(&self.0, &ctx, on_activity)
+    }
+}
+
+impl Backend for PiBackend {
+    fn kind(&self) -> &'static str {
+        "pi"
+    }
+
+    fn run_turn(
+        &self,
+        ctx: TurnContext<'_>,
+        on_activity: &mut dyn FnMut() -> Result<()>,
+    ) -> Result<TurnResult> {
+        run_turn_pi(&self.0, &ctx, on_activity)
+    }
+}
+
+impl Backend for MockBackend {
+    fn kind(&self) -> &'static str {
+        "mock"
+    }
+
+    fn run_turn(
+        &self,
+        ctx: TurnContext<'_>,
+        on_activity: &mut dyn FnMut() -> Result<()>,
+    ) -> Result<TurnResult> {
+        run_turn_mock(&self.0, &ctx, on_activity)
+    }
+}
+
+fn run_turn_codex(
+    backend: &CodexBackendConfig,
+    ctx: &TurnContext<'_>,
+    on_activity: &mut dyn FnMut() -> Result<()>,
+) -> Result<TurnResult> {
+    let workspace = &ctx.workspace;
+    let mut cmd = Command::new(&backend.binary);
+    cmd.current_dir(workspace);
+    ctx.apply_env(&mut cmd);
+    cmd.arg("exec")
+        .arg("--experimental-json")
+        .arg("--model")
+        .arg(&backend.model)
+        .arg("--sandbox")
+        .arg(match ctx.sandbox {
+            Some(profile) => profile.codex_sandbox_mode(),
+            None => backend.sandbox_mode.as_str(),
+        })
+        .arg("--config")
+        .arg(format!(
+            "model_reasoning_effort=\"{}\"",
+            ctx.thinking.unwrap_or(&backend.thinking)
+        ))
+        .arg("--config")
+        .arg(format!("approval_policy=\"{}\"", backend.approval_policy))
+        .arg("--cd")
+        .arg(workspace);
+    if let Some(profile) = &backend.profile {
+        cmd.arg("--profile").arg(profile);
+    }
+    for value in codex_config_overrides(backend)? {
+        cmd.arg("--config").arg(value);
+    }
+
+    for extra in &backend.extra_args {
+        cmd.arg(extra);
+    }
+
+    if let Some(thread_id) = ctx.thread_id {
+        cmd.arg("resume").arg(thread_id);
+    }
+
+    let mut parsed_thread_id: Option<String> = None;
+    let mut final_response = String::new();
+
+    run_backend_command_streaming(cmd, ctx.prompt, "codex", |line_trim| {
+        ctx.append_event(line_trim)?;
+        if let Ok(value) = serde_json::from_str::<Value>(line_trim) {
+            if value.get("type").and_then(|v| v.as_str()) == Some("thread.started")
+                && let Some(id) = value.get("thread_id").and_then(|v| v.as_str())
+            {
+                parsed_thread_id = Some(id.to_string());
+            }
+
+            if value.get("type").and_then(|v| v.as_str()) == Some("item.completed")
+                && let Some(item) = value.get("item")
+                && item.get("type").and_then(|v| v.as_str()) == Some("agent_message")
+                && let Some(text) = item.get("text").and_then(|v| v.as_str())
+            {
+                final_response = text.to_string();
+            }
+        }
+        on_activity()?;
+        Ok(())
+    })?;
+
+    if final_response.is_empty() {
+        final_response = "(no agent message captured)".to_string();
+    }
+
+    Ok(TurnResult {
+        thread_id: parsed_thread_id,
+        final_response,
+        cost_usd: None,
+    })
+}
+
+fn run_turn_claude(
+    backend: &ClaudeBackendConfig,
+    ctx: &TurnContext<'_>,
+    on_activity: &mut dyn FnMut() -> Result<()>,
+) -> Result<TurnResult> {
+    let budget = match ctx.thinking {
+        Some(level) => claude_level_budget(level),
+        None => backend.thinking_budget(),
+    }
+    .ok_or_else(|| {
+        anyhow!(
+            "claude backend thinking '{}' has no token budget",
+            ctx.thinking.unwrap_or(&backend.thinking)
+        )
+    })?;
+
+    let workspace = &ctx.workspace;
+    let mut cmd = Command::new(&backend.binary);
+    cmd.current_dir(workspace);
+    ctx.apply_env(&mut cmd);
+    cmd.arg("-p")
+        .arg("--verbose")
+        .arg("--output-format")
+        .arg("stream-json")
+        .arg("--input-format")
+        .arg("text")
+        .arg("--model")
+        .arg(&backend.model)
+        .arg("--thinking-budget-tokens")
+        .arg(budget.to_string())
+        .args(ctx.sandbox.unwrap_or(SandboxProfile::Full).claude_args())
+        .arg("--add-dir")
+        .arg(workspace);
+
+    for extra in &backend.extra_args {
+        cmd.arg(extra);
+    }
+
+    if let Some(session_id) = ctx.thread_id {
+        cmd.arg("--resume").arg(session_id);
+    }
+
+    let mut parsed_thread_id: Option<String> = None;
+    let mut final_response = String::new();
+    let mut cost_usd: Option<f64> = None;
+
+    run_backend_command_streaming(cmd, ctx.prompt, "claude", |line_trim| {
+        ctx.append_event(line_trim)?;
+        if let Ok(value) = serde_json::from_str::<Value>(line_trim) {
+            if let Some(id) = value.get("session_id").and_then(|v| v.as_str()) {
+                parsed_thread_id = Some(id.to_string());
+            }
+
+            match value.get("type").and_then(|v| v.as_str()) {
+                Some("assistant") => {
+                    if let Some(msg) = value.get("message")
+                        && let Some(content) = msg.get("content")
+                        && let Some(text) = parse_assistant_text_from_content(content)
+                    {
+                        final_response = text;
+                    }
+                }
+                Some("result") => {
+                    if let Some(text) = value.get("result").and_then(|v| v.as_str()) {
+                        final_response = text.to_string();
+                    }
+                    if let Some(cost) = value.get("total_cost_usd").and_then(|v| v.as_f64()) {
+                        cost_usd = Some(cost);
+                    }
+                }
+                _ => {}
+            }
+        }
+        on_activity()?;
+        Ok(())
+    })?;
+
+    if final_response.is_empty() {
+        final_response = "(no agent message captured)".to_string();
+    }
+
+    Ok(TurnResult {
+        thread_id: parsed_thread_id,
+        final_response,
+        cost_usd,
+    })
+}
+
+fn run_turn_droid(
+    backend: &DroidBackendConfig,
+    ctx: &TurnContext<'_>,
+    on_activity: &mut dyn FnMut() -> Result<()>,
+) -> Result<TurnResult> {
+    let effort = match ctx.thinking.unwrap_or(&backend.thinking) {
+        "xhigh" => "max",
+        other => other,
+    };
+
+    let workspace = &ctx.workspace;
+    let mut cmd = Command::new(&backend.binary);
+    cmd.current_dir(workspace);
+    ctx.apply_env(&mut cmd);
+    cmd.arg("exec")
+        .arg("--output-format")
+        .arg("stream-json")
+        .arg("--input-format")
+        .arg("text")
+        .arg("--model")
+        .arg(&backend.model)
+        .arg("--reasoning-effort")
+        .arg(effort)
+        .arg("--cwd")
+        .arg(workspace);
+    match ctx.sandbox {
+        None => {
+            cmd.arg("--auto").arg(&backend.auto);
+        }
+        Some(profile) => {
+            if let Some(level) = profile.droid_auto() {
+                cmd.arg("--auto").arg(level);
+            }
+        }
+    }
+
+    for extra in &backend.extra_args {
+        cmd.arg(extra);
+    }
+
+    if let Some(session_id) = ctx.thread_id {
+        cmd.arg("--session-id").arg(session_id);
+    }
+
+    let mut parsed_thread_id: Option<String> = None;
+    let mut final_response = String::new();
+
+    run_backend_command_streaming(cmd, ctx.prompt, "droid", |line_trim| {
+        ctx.append_event(line_trim)?;
+        if let Ok(value) = serde_json::from_str::<Value>(line_trim) {
+            if let Some(id) = value.get("session_id").and_then(|v| v.as_str()) {
+                parsed_thread_id = Some(id.to_string());
+            }
+
+            match value.get("type").and_then(|v| v.as_str()) {
+                Some("message") => {
+                    if value.get("role").and_then(|v| v.as_str()) == Some("assistant")
+                        && let Some(text) = value.get("text").and_then(|v| v.as_str())
+                    {
+                        final_response = text.to_string();
+                    }
+                }
+                Some("completion") => {
+                    if let Some(text) = value.get("finalText").and_then(|v| v.as_str()) {
+                        final_response = text.to_string();
+                    }
+                }
+                Some("result") => {
+                    if let Some(text) = value.get("result").and_then(|v| v.as_str()) {
+                        final_response = text.to_string();
+                    }
+                }
+                _ => {}
+            }
+        }
+        on_activity()?;
+        Ok(())
+    })?;
+
+    if final_response.is_empty() {
+        final_response = "(no agent message captured)".to_string();
+    }
+
+    Ok(TurnResult {
+        thread_id: parsed_thread_id,
+        final_response,
+        cost_usd: None,
+    })
+}
+
+fn run_turn_pi(
+    backend: &PiBackendConfig,
+    ctx: &TurnContext<'_>,
+    on_activity: &mut dyn FnMut() -> Result<()>,
+) -> Result<TurnResult> {
+    let workspace = &ctx.workspace;
+    let mut cmd = Command::new(&backend.binary);
+    cmd.current_dir(workspace);
+    ctx.apply_env(&mut cmd);
+    cmd.arg("--print")
+        .arg("--mode")
+        .arg("json")
+        .arg("--model")
+        .arg(&backend.model)
+        .arg("--thinking")
+        .arg(ctx.thinking.unwrap_or(&backend.thinking))
+        .arg("--session-dir")
+        .arg(ctx.state_dir.join("pi-sessions"))
+        .arg("--no-extensions")
+        .arg("--no-skills")
+        .arg("--no-prompt-templates")
+        .arg("--no-themes")
+        .arg(ctx.prompt);
+
+    if let Some(session_id) = ctx.thread_id {
+        cmd.arg("--session").arg(session_id);
+    }
+
+    if let Some(provider) = &backend.provider {
+        cmd.arg("--provider").arg(provider);
+    }
+
+    for extra in &backend.extra_args {
+        cmd.arg(extra);
+    }
+
+    let mut parsed_thread_id: Option<String> = None;
+    let mut final_response = String::new();
+
+    run_backend_command_streaming(cmd, "", "pi", |line_trim| {
+        ctx.append_event(line_trim)?;
+        if let Ok(value) = serde_json::from_str::<Value>(line_trim) {
+            if value.get("type").and_then(|v| v.as_str()) == Some("session")
+                && let Some(id) = value.get("id").and_then(|v| v.as_str())
+            {
+                parsed_thread_id = Some(id.to_string());
+            }
+
+            if value.get("type").and_then(|v| v.as_str()) == Some("message_end")
+                && let Some(msg) = value.get("message")
+                && msg.get("role").and_then(|v| v.as_str()) == Some("assistant")
+                && let Some(content) = msg.get("content")
+                && let Some(text) = parse_assistant_text_from_content(content)
+            {
+                final_response = text;
+            }
+        }
+        on_activity()?;
+        Ok(())
+    })?;
+
+    if final_response.is_empty() {
+        final_response = "(no agent message captured)".to_string();
+    }
+
+    Ok(TurnResult {
+        thread_id: parsed_thread_id.or_else(|| ctx.thread_id.map(str::to_string)),
+        final_response,
+        cost_usd: None,
+    })
+}
+
+fn run_turn_mock(
+    backend: &MockBackendConfig,
+    ctx: &TurnContext<'_>,
+    on_activity: &mut dyn FnMut() -> Result<()>,
+) -> Result<TurnResult> {
+    let coord = ctx.coord_dir;
+    ensure_dir(coord)?;
+    ensure_dir(&coord.join("heartbeats"))?;
+
+    let turns_path = coord.join("mock.turns");
+    let prev_turns = fs::read_to_string(&turns_path)
+        .ok()
+        .and_then(|s| s.trim().parse::<u32>().ok())
+        .unwrap_or(0);
+    let turns = prev_turns.saturating_add(1);
+    fs::write(&turns_path, turns.to_string())?;
+    if let Some(result) = run_scripted_mock_step(backend, ctx, turns, on_activity)? {
+        return Ok(result);
+    }
+    let heartbeat = serde_json::json!({
+        "epoch": now_epoch(),
+        "phase": "implementing",
+        "message": format!("mock turn {turns}"),
+    });
+    fs::write(
+        coord.join("heartbeats").join("implementer.json"),
+        heartbeat.to_string(),
+    )?;
+    on_activity()?;
+
+    let done = turns >= backend.steps_per_task.max(1);
+    let state_text = if done { "done\n" } else { "active\n" };
+    fs::write(coord.join("state.md"), state_text)?;
+
+    let status = if done { "completed" } else { "in_progress" };
+    Ok(mock_turn_result(
+        ctx.task_id,
+        turns,
+        Some(serde_json::json!({"status": status})),
+    ))
+}
+
+/// Plays the scenario step for this turn, if the task has one left.
+fn run_scripted_mock_step(
+    backend: &MockBackendConfig,
+    ctx: &TurnContext<'_>,
+    turns: u32,
+    on_activity: &mut dyn FnMut() -> Result<()>,
+) -> Result<Option<TurnResult>> {
+    let Some(path) = &backend.scenario else {
+        return Ok(None);
+    };
+    let mut scenario = load_mock_scenario(path)?;
+    let Some(step) = scenario
+        .remove(ctx.task_id)
+        .and_then(|steps| steps.into_iter().nth(turns as usize - 1))
+    else {
+        return Ok(None);
+    };
+    let coord = ctx.coord_dir;
+    let summary = step
+        .message
+        .clone()
+        .unwrap_or_else(|| format!("mock {:?}", step.action).to_lowercase());
+    if !matches!(step.action, MockAction::Stall | MockAction::Fail) {
+        fs::write(
+            coord.join("heartbeats").join("implementer.json"),
+            serde_json::json!({
+                "epoch": now_epoch(),
+                "phase": "implementing",
+                "message": format!("mock turn {turns}"),
+            })
+            .to_string(),
+        )?;
+        on_activity()?;
+    }
+    let control = match step.action {
+        MockAction::Progress => {
+            fs::write(coord.join("state.md"), "active\n")?;
+            Some(serde_json::json!({"status": "in_progress", "summary": summary}))
+        }
+        MockAction::Complete => {
+            fs::write(coord.join("state.md"), "done\n")?;
+            Some(serde_json::json!({"status": "completed", "summary": summary}))
+        }
+        MockAction::Blocked => Some(serde_json::json!({
+            "status": "blocked",
+            "summary": summary,
+            "blockers": [summary],
+        })),
+        MockAction::Escalate => Some(serde_json::json!({
+            "status": "in_progress",
+            "needs_user_input": true,
+            "summary": summary,
+            "next_action": "ESCALATE",
+            "requests": [{"kind": "question", "detail": summary}],
+        })),
+        MockAction::Fail => return Err(anyhow!("mock turn {turns} failed: {summary}")),
+        MockAction::Stall => {
+            clock_sleep(Duration::from_secs(step.sleep_secs));
+            Some(serde_json::json!({"status": "in_progress", "summary": summary}))
+        }
+        MockAction::NoControl => None,
+        MockAction::InvalidControl => Some(serde_json::json!({"status": "sideways"})),
+    };
+    Ok(Some(mock_turn_result(ctx.task_id, turns, control)))
+}
+
+/// Mock response text, with a control block built from `fields` over the defaults.
+fn mock_turn_result(task_id: &str, turns: u32, fields: Option<Value>) -> TurnResult {
+    let mut final_response = format!("Mock backend processed task {task_id} turn {turns}.");
+    if let Some(Value::Object(fields)) = fields {
+        let mut control = serde_json::json!({
+            "version": CONTROL_SCHEMA_VERSION,
+            "task_id": task_id,
+            "status": "in_progress",
+            "needs_user_input": false,
+            "summary": "mock progress",
+            "next_action": "continue",
+            "artifacts": [],
+            "blockers": [],
+            "requests": [],
+        });
+        if let Value::Object(map) = &mut control {
+            map.extend(fields);
+        }
+        final_response.push_str(&format!("\n<CONTROL_JSON>\n{control}\n</CONTROL_JSON>"));
+    }
+    TurnResult {
+        thread_id: None,
+        final_response,
+        cost_usd: None,
+    }
+}
+
+/// Moves the run to the next backend in the failover chain. Backend threads
+/// cannot be resumed across harnesses, so open tasks start fresh threads with a
+/// note to rebuild context from the coord dir. Returns `(from, to)` kinds.
+fn fail_over_backend(
+    section: &BackendSection,
+    state: &mut RunState,
+) -> Option<(&'static str, &'static str)> {
+    if state.backend_index >= section.fallbacks.len() {
+        return None;
+    }
+    let from = section.at(state.backend_index).kind();
+    state.backend_index += 1;
+    let to = section.at(state.backend_index).kind();
+    for task in state.tasks.iter_mut().filter(|t| !t.status.is_terminal()) {
+        if task.thread_id.take().is_some() {
+            task.pending_note = Some(format!(
+                "The run failed over from the {from} backend to {to}; the previous conversation thread is gone. Rebuild context from the todo file and coord dir before continuing."
+            ));
+        }
+    }
+    Some((from, to))
+}
+
+fn turn_context<'a>(
+    cfg: &'a Config,
+    task: &'a TaskRuntime,
+    prompt: &'a str,
+    redactor: &'a Redactor,
+) -> TurnContext<'a> {
+    TurnContext {
+        task_id: &task.id,
+        prompt,
+        thread_id: task.thread_id.as_deref(),
+        workspace: task_workspace(cfg, task),
+        coord_dir: Path::new(&task.coord_dir),
+        state_dir: &cfg.state_dir,
+        events_log: events_log_path(&cfg.state_dir),
+        redactor,
+        event_sink: None,
+        progress: None,
+        env_allowlist: cfg.security.env_allowlist.as_deref(),
+        task_env: cfg.tasks.iter().find(|t| t.id == task.id).map(|t| &t.env),
+        sandbox: cfg.sandbox.profile,
+        thinking: task.thinking_override.as_deref(),
+    }
+}
+
+/// Appends the turn to the combined turns log and archives it untruncated as
+/// `turns/<name>.md`, with the parsed control block in `turns/<name>.control.json`.
+/// Appends a turn to `turns.log` and archives it under `turns/`, both passed
+/// through `[logging]` redaction first.
+fn log_turn(
+    state_dir: &Path,
+    cycle: u64,
+    name: &str,
+    prompt: &str,
+    response: &str,
+    redactor: &Redactor,
+) -> Result<()> {
+    let prompt = &redactor.apply(prompt);
+    let response = &redactor.apply(response);
+    let turns_log = turns_log_path(state_dir);
+    let mut buf = String::new();
+    buf.push_str(&format!("\n===== TURN {} @ {} =====\n", cycle, now_iso()));
+    buf.push_str("--- PROMPT ---\n");
+    buf.push_str(prompt);
+    if !prompt.ends_with('\n') {
+        buf.push('\n');
+    }
+    buf.push_str("--- RESPONSE ---\n");
+    buf.push_str(response);
+    if !response.ends_with('\n') {
+        buf.push('\n');
+    }
+    append_text(&turns_log, &buf)?;
+    archive_turn(state_dir, cycle, name, prompt, response)
+}
+
+fn archive_turn(
+    state_dir: &Path,
+    cycle: u64,
+    name: &str,
+    prompt: &str,
+    response: &str,
+) -> Result<()> {
+    let dir = turns_archive_dir(state_dir);
+    ensure_dir(&dir)?;
+    let text = format!(
+        "# Turn {cycle}: {name}\n\nRecorded at {}\n\n## Prompt\n\n{prompt}\n\n## Response\n\n{response}\n",
+        now_iso()
+    );
+    let path = dir.join(format!("{name}.md"));
+    fs::write(&path, text).with_context(|| format!("failed to write {}", path.display()))?;
+
+    let control = match extract_control_block(response) {
+        Ok(control) => serde_json::to_value(control)?,
+        Err(err) => serde_json::json!({ "error": err.to_string() }),
+    };
+    let sidecar = dir.join(format!("{name}.control.json"));
+    fs::write(&sidecar, serde_json::to_string_pretty(&control)?)
+        .with_context(|| format!("failed to write {}", sidecar.display()))
+}
+
+/// The task's `workspace` override from config, else the run workspace.
+fn configured_task_workspace(cfg: &Config, task_id: &str) -> PathBuf {
+    cfg.tasks
+        .iter()
+        .find(|t| t.id == task_id)
+        .and_then(|t| t.workspace.as_ref())
+        .map(|w| cfg.workspace.join(w))
+        .unwrap_or_else(|| cfg.workspace.clone())
+}
+
+/// Where the task's turns run: its worktree when it has one, else its
+/// configured workspace.
+fn task_workspace(cfg: &Config, task: &TaskRuntime) -> PathBuf {
+    task.workspace
+        .as_ref()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| configured_task_workspace(cfg, &task.id))
+}
+
+fn task_branch_name(run_id: &str, task_id: &str) -> String {
+    format!("crank/{run_id}/{task_id}")
+}
+
+/// Names the state dir of the run whose worktrees share its directory, so
+/// `worktree gc` can tell whether that run is still live.
+const WORKTREE_RUN_MARKER: &str = ".crank-run";
+
+/// Directory holding every run's task worktrees for `repo`: `[git]
+/// worktree_dir`, else `crank-worktrees` in the repo's git dir. That is
+/// crank-owned, writable wherever the repo is, and never shows up in the
+/// checkout's `git status`.
+fn task_worktrees_root(cfg: &Config, repo: &Path) -> Result<PathBuf> {
+    if let Some(dir) = &cfg.git.worktree_dir {
+        return Ok(cfg.workspace.join(dir));
+    }
+    let common = git_output(
+        repo,
+        &["rev-parse", "--path-format=absolute", "--git-common-dir"],
+    )?;
+    Ok(PathBuf::from(common.trim()).join("crank-worktrees"))
+}
+
+/// Creates (or reuses, on resume) the worktree and branch for a task with
+/// `branch = true`, and points the task's turns at it.
+fn prepare_task_worktree(cfg: &Config, run_id: &str, task: &mut TaskRuntime) -> Result<()> {
+    let wants_branch = cfg
+        .tasks
+        .iter()
+        .find(|t| t.id == task.id)
+        .map(|t| t.branch)
+        .unwrap_or(false);
+    if !wants_branch || task.workspace.is_some() {
+        return Ok(());
+    }
+
+    let branch = task_branch_name(run_id, &task.id);
+    let repo = configured_task_workspace(cfg, &task.id);
+    let run_dir = task_worktrees_root(cfg, &repo)?.join(run_id);
+    let path = run_dir.join(&task.id);
+    if !path.exists() {
+        ensure_dir(&run_dir)?;
+        let state_dir = fs::canonicalize(&cfg.state_dir).unwrap_or_else(|_| cfg.state_dir.clone());
+        fs::write(
+            run_dir.join(WORKTREE_RUN_MARKER),
+            format!("{}\n", state_dir.display()),
+        )?;
+        let path_arg = path.display().to_string();
+        let branch_exists = git_output(
+            &repo,
+            &[
+                "rev-parse",
+                "--verify",
+                "--quiet",
+                &format!("refs/heads/{branch}"),
+            ],
+        )
+        .is_ok();
+        if branch_exists {
+            git_output(&repo, &["worktree", "add", &path_arg, &branch])?;
+        } else {
+            git_output(
+                &repo,
+                &["worktree", "add", "-b", &branch, &path_arg, "HEAD"],
+            )?;
+        }
+    }
+    let path = fs::canonicalize(&path).unwrap_or(path);
+    task.workspace = Some(path.display().to_string());
+    task.branch = Some(branch);
+    Ok(())
+}
+
+/// The main checkout a linked worktree belongs to.
+fn worktree_main_repo(worktree: &Path) -> Option<PathBuf> {
+    let common = git_output(
+        worktree,
+        &["rev-parse", "--path-format=absolute", "--git-common-dir"],
+    )
+    .ok()?;
+    Path::new(common.trim()).parent().map(Path::to_path_buf)
+}
+
+fn git_branch_merged(repo: &Path, branch: &str, base: &str) -> bool {
+    git_output(repo, &["merge-base", "--is-ancestor", branch, base]).is_ok()
+}
+
+/// Removes a task worktree; git refuses one with uncommitted or untracked
+/// files. A worktree already deleted by hand is pruned from git's records.
+fn remove_task_worktree(repo: &Path, worktree: &Path) -> Result<()> {
+    if worktree.exists() {
+        git_output(
+            repo,
+            &["worktree", "remove", &worktree.display().to_string()],
+        )?;
+    } else {
+        git_output(repo, &["worktree", "prune"])?;
+    }
+    Ok(())
+}
+
+/// With `[git] remove_merged_worktrees`, drops the worktree and branch of each
+/// completed task whose branch is merged into its repo's `HEAD`. The branch stays
+/// recorded in state for the run summary; reopening the task cuts a new one.
+/// Tasks whose worktree could not be removed go into `kept` and are journaled
+/// once rather than retried every cycle.
+fn remove_merged_task_worktrees(
+    cfg: &Config,
+    state: &mut RunState,
+    journal: &Path,
+    kept: &mut std::collections::BTreeSet<String>,
+) -> Result<()> {
+    if !cfg.git.remove_merged_worktrees {
+        return Ok(());
+    }
+    let mut removed = false;
+    for task in &mut state.tasks {
+        if task.status != TaskStatus::Completed || kept.contains(&task.id) {
+            continue;
+        }
+        let (Some(branch), Some(worktree)) = (task.branch.clone(), task.workspace.clone()) else {
+            continue;
+        };
+        let repo = configured_task_workspace(cfg, &task.id);
+        if !git_branch_merged(&repo, &branch, "HEAD") {
+            continue;
+        }
+        if let Err(err) = remove_task_worktree(&repo, Path::new(&worktree)) {
+            append_journal(
+                journal,
+                "task worktree kept",
+                &format!("Task {}: could not remove {worktree}: {err:#}", task.id),
+            )?;
+            kept.insert(task.id.clone());
+            continue;
+        }
+        task.workspace = None;
+        removed = true;
+        let outcome = match git_output(&repo, &["branch", "-d", &branch]) {
+            Ok(_) => format!("branch {branch} is merged; removed it and {worktree}."),
+            Err(err) => format!("removed {worktree}; kept branch {branch}: {err:#}"),
+        };
+        append_journal(
+            journal,
+            "task worktree removed",
+            &format!("Task {}: {outcome}", task.id),
+        )?;
+    }
+    if removed {
+        save_state(state, &cfg.state_dir)?;
+    }
+    Ok(())
+}
+
+fn parse_age_secs(raw: &str) -> Result<u64, String> {
+    let trimmed = raw.trim();
+    let split = trimmed
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(trimmed.len());
+    let (digits, unit) = trimmed.split_at(split);
+    let n: u64 = digits
+        .parse()
+        .map_err(|_| format!("invalid age '{raw}' (expected e.g. 14d)"))?;
+    let scale = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" | "" => 86_400,
+        "w" => 7 * 86_400,
+        _ => {
+            return Err(format!(
+                "invalid age unit '{unit}' in '{raw}' (use s, m, h, d, or w)"
+            ));
+        }
+    };
+    Ok(n * scale)
+}
+
+/// `crank worktree gc`: walks the `crank/*` branches in a repo and removes
+/// the ones matching the filters, with their worktrees. Branches checked out
+/// in the main worktree or in the worktree of a live run are skipped, and so
+/// are unmerged ones unless `--force`.
+fn gc_task_worktrees(args: &WorktreeGcArgs) -> Result<Vec<String>> {
+    let repo = &args.workspace;
+    // branch -> (worktree path, is the main worktree)
+    let mut worktrees = BTreeMap::new();
+    let mut main_worktree = true;
+    let mut current: Option<PathBuf> = None;
+    for line in git_output(repo, &["worktree", "list", "--porcelain"])?.lines() {
+        if let Some(path) = line.strip_prefix("worktree ") {
+            current = Some(PathBuf::from(path));
+        } else if let Some(branch) = line.strip_prefix("branch refs/heads/")
+            && let Some(path) = &current
+        {
+            worktrees.insert(branch.to_string(), (path.clone(), main_worktree));
+        } else if line.is_empty() {
+            main_worktree = false;
+        }
+    }
+
+    let now = clock_now().timestamp();
+    let refs = git_output(
+        repo,
+        &[
+            "for-each-ref",
+            "--format=%(refname:short) %(committerdate:unix)",
+            "refs/heads/crank/",
+        ],
+    )?;
+    let mut report = Vec::new();
+    for line in refs.lines() {
+        let Some((branch, epoch)) = line.rsplit_once(' ') else {
+            continue;
+        };
+        let worktree = worktrees.get(branch);
+        let skip = if worktree.is_some_and(|(_, main)| *main) {
+            Some("checked out in the main worktree".to_string())
+        } else if worktree.is_some_and(|(path, _)| worktree_run_is_live(path)) {
+            Some("its run is still live".to_string())
+        } else if let Some(secs) = args.older_than
+            && now - epoch.parse::<i64>().unwrap_or(now) < secs as i64
+        {
+            Some("newer than --older-than".to_string())
+        } else if !args.force && !git_branch_merged(repo, branch, &args.base) {
+            Some(format!(
+                "not merged into {} (--force deletes it)",
+                args.base
+            ))
+        } else {
+            None
+        };
+        if let Some(reason) = skip {
+            report.push(format!("kept {branch}: {reason}"));
+            continue;
+        }
+        let path = worktree.map(|(path, _)| path.as_path());
+        let target = match path {
+            Some(path) => format!("{branch} ({})", path.display()),
+            None => branch.to_string(),
+        };
+        if args.dry_run {
+            report.push(format!("would remove {target}"));
+            continue;
+        }
+        if let Some(path) = path
+            && let Err(err) = remove_task_worktree(repo, path)
+        {
+            report.push(format!("kept {branch}: {err:#}"));
+            continue;
+        }
+        // Merged into --base (checked above) or --force: safe to force past
+        // git's own check, which only knows about HEAD.
+        match git_output(repo, &["branch", "-D", branch]) {
+            Ok(_) => report.push(format!("removed {target}")),
+            Err(err) => report.push(format!(
+                "removed the worktree of {target}; kept the branch: {err:#}"
+            )),
+        }
+    }
+    Ok(report)
+}
+
+/// A task worktree's run is live while its lock names a running process. The
+/// run's state dir is named by the marker beside the worktree; worktrees from
+/// older runs sit at `<state_dir>/worktrees/<task>` instead.
+fn worktree_run_is_live(worktree: &Path) -> bool {
+    let Some(run_dir) = worktree.parent() else {
+        return false;
+    };
+    let state_dir = match fs::read_to_string(run_dir.join(WORKTREE_RUN_MARKER)) {
+        Ok(text) => PathBuf::from(text.trim()),
+        Err(_) => match run_dir.parent() {
+            Some(state_dir) => state_dir.to_path_buf(),
+            None => return false,
+        },
+    };
+    lock_pid(&state_dir.join("run.lock")).is_some_and(process_is_alive)
+}
+
+fn git_output(workspace: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(workspace)
+        .args(args)
+        .output()
+        .with_context(|| format!("failed to run git {}", args.join(" ")))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git {} failed with {}: {}",
+            args.join(" "),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn summarize_porcelain(porcelain: &str) -> (String, usize) {
+    let mut counts = std::collections::BTreeMap::<String, usize>::new();
+    let mut total = 0usize;
+    for line in porcelain.lines().filter(|l| l.len() > 3) {
+        *counts.entry(line[..2].trim().to_string()).or_default() += 1;
+        total += 1;
+    }
+    let summary = if counts.is_empty() {
+        "clean".to_string()
+    } else {
+        counts
+            .iter()
+            .map(|(code, n)| format!("{code}:{n}"))
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    (summary, total)
+}
+
+/// Files and dirs the run itself reads or owns: the state dir, a configured
+/// worktree dir, config files, prompt templates, todo files, and explicit
+/// coord dirs. Uncommitted copies
+/// of these are never the operator's stray work.
+fn run_owned_paths(cfg: &Config) -> Vec<PathBuf> {
+    let mut paths = vec![cfg.state_dir.clone()];
+    paths.extend(
+        cfg.git
+            .worktree_dir
+            .iter()
+            .map(|dir| cfg.workspace.join(dir)),
+    );
+    paths.extend(cfg.config_files.iter().cloned());
+    paths.extend(cfg.prompt_template.iter().cloned());
+    for task in &cfg.tasks {
+        paths.push(task.todo_file.clone());
+        paths.extend(task.prompt_template.iter().cloned());
+        paths.extend(task.coord_dir.iter().cloned());
+    }
+    paths
+}
+
+/// Pathspec covering the workspace minus `excluded` paths inside it, so
+/// crank's own files never count as (or get stashed with) uncommitted work.
+fn workspace_pathspec(workspace: &Path, excluded: &[PathBuf]) -> Vec<String> {
+    let mut spec = vec![".".to_string()];
+    let Ok(workspace) = fs::canonicalize(workspace) else {
+        return spec;
+    };
+    for path in excluded {
+        if let Ok(path) = fs::canonicalize(path)
+            && let Ok(rel) = path.strip_prefix(&workspace)
+            && !rel.as_os_str().is_empty()
+        {
+            spec.push(format!(":(exclude){}", rel.display()));
+        }
+    }
+    spec
+}
+
+/// The run workspace followed by each distinct per-task `workspace`.
+fn run_workspaces(cfg: &Config) -> Vec<PathBuf> {
+    let mut workspaces = vec![cfg.workspace.clone()];
+    let mut seen: Vec<PathBuf> = fs::canonicalize(&cfg.workspace).into_iter().collect();
+    for task in cfg.tasks.iter().filter(|t| t.workspace.is_some()) {
+        let workspace = configured_task_workspace(cfg, &task.id);
+        let canonical = fs::canonicalize(&workspace).unwrap_or_else(|_| workspace.clone());
+        if !seen.contains(&canonical) {
+            seen.push(canonical);
+            workspaces.push(workspace);
+        }
+    }
+    workspaces
+}
+
+const DIRTY_FILES_LISTED: usize = 20;
+
+/// Checks the run workspace and every per-task workspace for uncommitted
+/// changes before a fresh run's first turn, so agents never start on top of
+/// half-finished manual work. Dirt is stashed with `stash_on_start`, refused
+/// with `require_clean_start`, and otherwise journaled as a warning; files the
+/// run reads itself (see [`run_owned_paths`]) are left alone. A workspace git
+/// cannot read only matters when a clean start is required.
+fn guard_dirty_workspace(cfg: &Config, run_id: &str, journal: &Path) -> Result<()> {
+    let owned = run_owned_paths(cfg);
+    for workspace in run_workspaces(cfg) {
+        guard_dirty_checkout(cfg, &workspace, &owned, run_id, journal)?;
+    }
+    Ok(())
+}
+
+fn guard_dirty_checkout(
+    cfg: &Config,
+    workspace: &Path,
+    owned: &[PathBuf],
+    run_id: &str,
+    journal: &Path,
+) -> Result<()> {
+    let spec = workspace_pathspec(workspace, owned);
+    let git_with_spec = |args: &[&str]| {
+        let mut full: Vec<&str> = args.to_vec();
+        full.push("--");
+        full.extend(spec.iter().map(String::as_str));
+        git_output(workspace, &full)
+    };
+    let porcelain = match git_with_spec(&["status", "--porcelain"]) {
+        Ok(porcelain) => porcelain,
+        Err(err) if cfg.git.require_clean_start => {
+            return Err(err.context("git.require_clean_start: cannot check the workspace"));
+        }
+        Err(_) => return Ok(()),
+    };
+    if porcelain.trim().is_empty() {
+        return Ok(());
+    }
+    let (summary, changed) = summarize_porcelain(&porcelain);
+    let mut listing: Vec<&str> = porcelain.lines().take(DIRTY_FILES_LISTED).collect();
+    if changed > DIRTY_FILES_LISTED {
+        listing.push("...");
+    }
+    let detail = format!(
+        "{} has {changed} uncommitted change(s) ({summary}):\n{}",
+        workspace.display(),
+        listing.join("\n")
+    );
+
+    if cfg.git.stash_on_start {
+        let message = format!("crank: before run {run_id}");
+        git_with_spec(&["stash", "push", "--include-untracked", "-m", &message])?;
+        let stash = git_output(workspace, &["rev-parse", "stash@{0}"])?;
+        append_journal(
+            journal,
+            "workspace stashed",
+            &format!(
+                "{detail}\nStashed as {} (\"{message}\"); restore with `git stash apply {}`.",
+                stash.trim(),
+                stash.trim()
+            ),
+        )?;
+        return Ok(());
+    }
+    if cfg.git.require_clean_start {
+        append_journal(journal, "dirty workspace refused", &detail)?;
+        return Err(anyhow!(
+            "git.require_clean_start: {detail}\nCommit or stash the changes, or set git.stash_on_start"
+        ));
+    }
+    eprintln!("WARNING: starting over a dirty workspace. {detail}");
+    append_journal(journal, "WARNING: dirty workspace at start", &detail)
+}
+
+fn capture_git_snapshot(workspace: &Path, cycle: u64) -> Result<GitSnapshot> {
+    let head = git_output(workspace, &["rev-parse", "HEAD"])?;
+    let porcelain = git_output(workspace, &["status", "--porcelain"])?;
+    let diffstat = git_output(workspace, &["diff", "--shortstat", "HEAD"])?;
+    let (status_summary, changed_files) = summarize_porcelain(&porcelain);
+    Ok(GitSnapshot {
+        cycle,
+        taken_at: now_iso(),
+        head: head.trim().to_string(),
+        status_summary,
+        changed_files,
+        diffstat: diffstat.trim().to_string(),
+    })
+}
+
+/// Reason the whole run must stop because of `[limits]`, if any.
+fn run_limit_reason(limits: &LimitsConfig, state: &RunState) -> Option<String> {
+    if let Some(max) = limits.max_turns_per_run
+        && state.cycle >= max
+    {
+        return Some(format!("reached max_turns_per_run ({max})"));
+    }
+    if let Some(max) = limits.max_cost_usd
+        && state.cost_usd >= max
+    {
+        return Some(format!(
+            "reached max_cost_usd (${:.2} of ${max:.2})",
+            state.cost_usd
+        ));
+    }
+    None
+}
+
+const SYNC_BACKOFF_INITIAL_SECS: u64 = 5;
+const SYNC_BACKOFF_MAX_SECS: u64 = 300;
+/// Connect/read timeout handed to rsync and the aws CLI.
+const SYNC_NETWORK_TIMEOUT_SECS: u64 = 60;
+/// Wall-clock limit on one sync command, on top of the tools' own network timeouts.
+const SYNC_COMMAND_TIMEOUT_SECS: u64 = 120;
+/// How long the governor waits on the final sync when it stops.
+const SYNC_FINISH_TIMEOUT_SECS: u64 = 2 * SYNC_COMMAND_TIMEOUT_SECS;
+
+fn sync_binaries(cfg: &SyncConfig) -> Vec<(&'static str, &'static str)> {
+    let mut binaries = Vec::new();
+    if cfg.rsync_target.is_some() {
+        binaries.push(("rsync", "sync.rsync_target"));
+    }
+    if cfg.s3_uri.is_some() {
+        binaries.push(("aws", "sync.s3_uri"));
+    }
+    binaries
+}
+
+/// One command per destination (per file for S3) mirroring `files`.
+fn sync_commands(cfg: &SyncConfig, files: &[PathBuf]) -> Vec<Command> {
+    let mut commands = Vec::new();
+    if let Some(target) = &cfg.rsync_target {
+        let mut cmd = Command::new("rsync");
+        cmd.arg("-a")
+            .arg(format!("--timeout={SYNC_NETWORK_TIMEOUT_SECS}"))
+            .args(files)
+            .arg(format!("{}/", target.trim_end_matches('/')));
+        commands.push(cmd);
+    }
+    if let Some(uri) = &cfg.s3_uri {
+        for file in files {
+            let name = file
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let mut cmd = Command::new("aws");
+            cmd.args(["s3", "cp", "--only-show-errors"])
+                .arg(format!("--cli-connect-timeout={SYNC_NETWORK_TIMEOUT_SECS}"))
+                .arg(format!("--cli-read-timeout={SYNC_NETWORK_TIMEOUT_SECS}"))
+                .arg(file)
+                .arg(format!("{}/{name}", uri.trim_end_matches('/')));
+            commands.push(cmd);
+        }
+    }
+    commands
+}
+
+/// Mirrors whichever of the run's state files exist to every configured destination.
+fn sync_state_files(cfg: &SyncConfig, state_dir: &Path) -> Result<()> {
+    let files: Vec<PathBuf> = [
+        state_path(state_dir),
+        journal_path(state_dir),
+        run_summary_path(state_dir),
+    ]
+    .into_iter()
+    .filter(|path| path.is_file())
+    .collect();
+    if files.is_empty() {
+        return Ok(());
+    }
+    for mut cmd in sync_commands(cfg, &files) {
+        let program = cmd.get_program().to_string_lossy().into_owned();
+        let timeout = Duration::from_secs(SYNC_COMMAND_TIMEOUT_SECS);
+        let (status, _, stderr) = output_with_timeout(&mut cmd, timeout, &mut || {})
+            .with_context(|| format!("failed to run {program}"))?;
+        match status {
+            Some(status) if status.success() => {}
+            Some(status) => {
+                return Err(anyhow!(
+                    "{program} exited with {status}: {}",
+                    String::from_utf8_lossy(&stderr).trim()
+                ));
+            }
+            None => {
+                return Err(anyhow!(
+                    "{program} killed after {SYNC_COMMAND_TIMEOUT_SECS}s"
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn sync_backoff_secs(failures: u32) -> u64 {
+    let shift = failures.saturating_sub(1).min(10);
+    (SYNC_BACKOFF_INITIAL_SECS << shift).min(SYNC_BACKOFF_MAX_SECS)
+}
+
+/// Background `[sync]` worker. `request` never blocks the governor; requests
+/// made while a sync runs coalesce into one follow-up sync.
+struct StateSync {
+    requests: mpsc::Sender<()>,
+    /// Closed by the worker as it exits.
+    stopped: mpsc::Receiver<()>,
+    journal: PathBuf,
+}
+
+impl StateSync {
+    fn start(cfg: &SyncConfig, state_dir: &Path) -> Option<Self> {
+        if sync_binaries(cfg).is_empty() {
+            return None;
+        }
+        let (requests, pending) = mpsc::channel();
+        let (stop_signal, stopped) = mpsc::channel::<()>();
+        let cfg = cfg.clone();
+        let journal = journal_path(state_dir);
+        let state_dir = state_dir.to_path_buf();
+        thread::spawn(move || {
+            let _stop_signal = stop_signal;
+            run_state_sync(&cfg, &state_dir, &pending);
+        });
+        Some(Self {
+            requests,
+            stopped,
+            journal,
+        })
+    }
+
+    fn request(&self) {
+        let _ = self.requests.send(());
+    }
+
+    /// Syncs once more and waits for it, skipping any pending backoff, for at
+    /// most `SYNC_FINISH_TIMEOUT_SECS`.
+    fn finish(self) {
+        let Self {
+            requests,
+            stopped,
+            journal,
+        } = self;
+        let _ = requests.send(());
+        drop(requests);
+        let wait = Duration::from_secs(SYNC_FINISH_TIMEOUT_SECS);
+        if let Err(mpsc::RecvTimeoutError::Timeout) = stopped.recv_timeout(wait) {
+            let _ = append_journal(
+                &journal,
+                "state sync abandoned",
+                &format!("The final sync was still running after {SYNC_FINISH_TIMEOUT_SECS}s."),
+            );
+        }
+    }
+}
+
+/// Syncs on every request, retrying failures with backoff until they succeed
+/// or the governor finishes. Each failure and the eventual recovery are journaled.
+fn run_state_sync(cfg: &SyncConfig, state_dir: &Path, pending: &mpsc::Receiver<()>) {
+    let journal = journal_path(state_dir);
+    let mut failures = 0u32;
+    while pending.recv().is_ok() {
+        loop {
+            while pending.try_recv().is_ok() {}
+            let err = match sync_state_files(cfg, state_dir) {
+                Ok(()) => {
+                    if failures > 0 {
+                        let _ = append_journal(
+                            &journal,
+                            "state sync recovered",
+                            &format!("Mirrored state files after {failures} failed attempt(s)."),
+                        );
+                        failures = 0;
+                    }
+                    break;
+                }
+                Err(err) => err,
+            };
+            failures += 1;
+            let delay = sync_backoff_secs(failures);
+            let _ = append_journal(
+                &journal,
+                "state sync failed",
+                &format!("Attempt {failures}: {err:#}\nRetrying in {delay}s."),
+            );
+            let deadline = Instant::now() + Duration::from_secs(delay);
+            loop {
+                match pending.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                    Ok(()) => {}
+                    Err(mpsc::RecvTimeoutError::Timeout) => break,
+                    Err(mpsc::RecvTimeoutError::Disconnected) => {
+                        if let Err(err) = sync_state_files(cfg, state_dir) {
+                            let _ = append_journal(
+                                &journal,
+                                "state sync failed",
+                                &format!("Final attempt after the run stopped: {err:#}"),
+                            );
+                        }
+                        return;
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn compute_backoff_secs(recovery: &RecoveryConfig, failures: u32) -> u64 {
+    let shift = failures.saturating_sub(1).min(10);
+    let mult = 1u64 << shift;
+    let raw = recovery.backoff_initial_secs.saturating_mul(mult);
+    raw.clamp(1, recovery.backoff_max_secs.max(1))
+}
+
+/// Upper bounds (seconds) of the turn duration histogram buckets.
+const TURN_DURATION_BUCKETS: [f64; 10] = [
+    5.0, 15.0, 30.0, 60.0, 120.0, 300.0, 600.0, 1200.0, 1800.0, 3600.0,
+];
+
+/// Governor gauges and counters exposed in the Prometheus text format.
+#[derive(Debug, Default)]
+struct GovernorMetrics {
+    run_id: String,
+    cycle: u64,
+    tasks_by_status: std::collections::BTreeMap<&'static str, usize>,
+    consecutive_failures: u32,
+    /// Last progress of the running task; stall seconds are computed at scrape time.
+    running_last_progress_epoch: Option<i64>,
+    turn_bucket_counts: [u64; TURN_DURATION_BUCKETS.len()],
+    turn_count: u64,
+    turn_seconds_sum: f64,
+    turn_failures: u64,
+}
+
+impl GovernorMetrics {
+    fn observe_state(&mut self, state: &RunState, consecutive_failures: u32) {
+        self.run_id = state.run_id.clone();
+        self.cycle = state.cycle;
+        self.consecutive_failures = consecutive_failures;
+        self.tasks_by_status = [
+            TaskStatus::Pending,
+            TaskStatus::Running,
+            TaskStatus::Completed,
+            TaskStatus::BlockedBestEffort,
+            TaskStatus::Skipped,
+            TaskStatus::Cancelled,
+        ]
+        .iter()
+        .map(|status| {
+            let count = state.tasks.iter().filter(|t| &t.status == status).count();
+            (status.as_str(), count)
+        })
+        .collect();
+        self.running_last_progress_epoch = state
+            .tasks
+            .iter()
+            .find(|t| t.status == TaskStatus::Running)
+            .and_then(|t| t.last_progress_epoch);
+    }
+
+    fn observe_turn(&mut self, secs: f64, ok: bool) {
+        for (bound, count) in TURN_DURATION_BUCKETS
+            .iter()
+            .zip(self.turn_bucket_counts.iter_mut())
+        {
+            if secs <= *bound {
+                *count += 1;
+            }
+        }
+        self.turn_count += 1;
+        self.turn_seconds_sum += secs;
+        if !ok {
+            self.turn_failures += 1;
+        }
+    }
+
+    fn render(&self, now: i64) -> String {
+        let mut out = String::new();
+        out.push_str("# HELP crank_run_info Run being governed.\n# TYPE crank_run_info gauge\n");
+        out.push_str(&format!("crank_run_info{{run_id=\"{}\"}} 1\n", self.run_id));
+        out.push_str("# HELP crank_cycle Current governor cycle.\n# TYPE crank_cycle gauge\n");
+        out.push_str(&format!("crank_cycle {}\n", self.cycle));
+        out.push_str("# HELP crank_tasks Tasks by status.\n# TYPE crank_tasks gauge\n");
+        for (status, count) in &self.tasks_by_status {
+            out.push_str(&format!("crank_tasks{{status=\"{status}\"}} {count}\n"));
+        }
+        out.push_str(
+            "# HELP crank_consecutive_failures Consecutive failed turns.\n# TYPE crank_consecutive_failures gauge\n",
+        );
+        out.push_str(&format!(
+            "crank_consecutive_failures {}\n",
+            self.consecutive_failures
+        ));
+        out.push_str(
+            "# HELP crank_stall_seconds Seconds since the running task last made progress.\n# TYPE crank_stall_seconds gauge\n",
+        );
+        let stall = self
+            .running_last_progress_epoch
+            .map_or(0, |last| now.saturating_sub(last).max(0));
+        out.push_str(&format!("crank_stall_seconds {stall}\n"));
+        out.push_str(
+            "# HELP crank_turn_failures_total Turns that returned an error.\n# TYPE crank_turn_failures_total counter\n",
+        );
+        out.push_str(&format!(
+            "crank_turn_failures_total {}\n",
+            self.turn_failures
+        ));
+        out.push_str(
+            "# HELP crank_turn_duration_seconds Backend turn duration.\n# TYPE crank_turn_duration_seconds histogram\n",
+        );
+        for (bound, count) in TURN_DURATION_BUCKETS.iter().zip(self.turn_bucket_counts) {
+            out.push_str(&format!(
+                "crank_turn_duration_seconds_bucket{{le=\"{bound}\"}} {count}\n"
+            ));
+        }
+        out.push_str(&format!(
+            "crank_turn_duration_seconds_bucket{{le=\"+Inf\"}} {}\n",
+            self.turn_count
+        ));
+        out.push_str(&format!(
+            "crank_turn_duration_seconds_sum {}\n",
+            self.turn_seconds_sum
+        ));
+        out.push_str(&format!(
+            "crank_turn_duration_seconds_count {}\n",
+            self.turn_count
+        ));
+        out
+    }
+}
+
+/// How long a metrics client may take to send its request or read the reply.
+const METRICS_IO_TIMEOUT: Duration = Duration::from_secs(5);
+/// Threads accepting metrics connections.
+const METRICS_WORKERS: usize = 4;
+/// Longest request line read from a metrics client; the rest is ignored.
+const METRICS_MAX_REQUEST_LINE: u64 = 8192;
+
+/// Serves `GET /metrics` from a small fixed pool of threads sharing the
+/// listener, so a stalled client holds up at most one worker (and for at most
+/// [`METRICS_IO_TIMEOUT`] per read). Returns the bound address.
+fn start_metrics_server(
+    addr: SocketAddr,
+    metrics: Arc<Mutex<GovernorMetrics>>,
+) -> Result<SocketAddr> {
+    let listener =
+        TcpListener::bind(addr).with_context(|| format!("failed to bind metrics on {addr}"))?;
+    let local = listener.local_addr()?;
+    for _ in 0..METRICS_WORKERS {
+        let listener = listener.try_clone()?;
+        let metrics = Arc::clone(&metrics);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                serve_metrics_request(stream, &metrics);
+            }
+        });
+    }
+    Ok(local)
+}
+
+fn serve_metrics_request(mut stream: TcpStream, metrics: &Mutex<GovernorMetrics>) {
+    if stream.set_read_timeout(Some(METRICS_IO_TIMEOUT)).is_err()
+        || stream.set_write_timeout(Some(METRICS_IO_TIMEOUT)).is_err()
+    {
+        return;
+    }
+    let mut request_line = String::new();
+    if BufReader::new((&stream).take(METRICS_MAX_REQUEST_LINE))
+        .read_line(&mut request_line)
+        .is_err()
+    {
+        return;
+    }
+    let path = request_line.split_whitespace().nth(1).unwrap_or("");
+    let (status, body) = if path == "/metrics" {
+        let body = metrics
+            .lock()
+            .map(|m| m.render(now_epoch()))
+            .unwrap_or_default();
+        ("200 OK", body)
+    } else {
+        ("404 Not Found", "not found\n".to_string())
+    };
+    let _ = write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+}
+
+const PREFLIGHT_PROBE_PROMPT: &str =
+    "This is a crank preflight check. Reply with exactly CRANK_PREFLIGHT_OK and do nothing else.";
+
+fn probe_binary_version(binary: &str, what: &str) -> Option<String> {
+    if find_on_path(binary).is_none() {
+        return Some(format!(
+            "{what} binary '{binary}' not found on PATH; install it or point `binary` at it"
+        ));
+    }
+    match Command::new(binary).arg("--version").output() {
+        Ok(output) if output.status.success() => None,
+        Ok(output) => Some(format!(
+            "{what} `{binary} --version` failed with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )),
+        Err(err) => Some(format!("{what} `{binary} --version` could not run: {err}")),
+    }
+}
+
+/// Checks the files the run reads, every backend in the failover chain, and
+/// every role harness, returning actionable problems. Role harnesses are
+/// skipped for mock-only runs.
+fn preflight_problems(cfg: &Config, redactor: &Redactor) -> Vec<String> {
+    let mut problems = config_file_problems(cfg);
+    for backend in cfg.backend.chain() {
+        let Some(binary) = backend.binary() else {
+            continue;
+        };
+        let what = format!("{} backend", backend.kind());
+        if let Some(problem) = probe_binary_version(binary, &what) {
+            problems.push(problem);
+            continue;
+        }
+        if cfg.preflight.probe_turn {
+            let mut task = TaskRuntime {
+                id: "preflight".to_string(),
+                coord_dir: cfg.state_dir.join("preflight").display().to_string(),
+                ..task_runtime_from_config(&cfg.state_dir, &TaskConfig::default())
+            };
+            task.status = TaskStatus::Running;
+            let mut on_activity = || Ok(());
+            let ctx = turn_context(cfg, &task, PREFLIGHT_PROBE_PROMPT, redactor);
+            if let Err(err) = create_backend(backend).run_turn(ctx, &mut on_activity) {
+                problems.push(format!(
+                    "{what} probe turn failed (is `{binary}` logged in?): {err:#}"
+                ));
+            }
+        }
+    }
+
+    if cfg.backend.chain().any(|b| b.binary().is_some()) {
+        for (name, role) in [
+            ("implementer", &cfg.roles.implementer),
+            ("reviewer_1", &cfg.roles.reviewer_1),
+            ("reviewer_2", &cfg.roles.reviewer_2),
+        ] {
+            if let Some(problem) =
+                probe_binary_version(&role.harness, &format!("role {name} harness"))
+            {
+                problems.push(problem);
+            }
+        }
+    }
+    for (binary, what) in sync_binaries(&cfg.sync) {
+        if find_on_path(binary).is_none() {
+            problems.push(format!("{what} needs `{binary}` on PATH"));
+        }
+    }
+    problems.dedup();
+    problems
+}
+
+const MAX_DIGEST_INPUT_CHARS: usize = 60_000;
+
+/// Folds journal entries written since the last digest into DIGEST.md once
+/// `[digest] every_cycles` cycles have passed. A failed digest is journaled and
+/// retried at the next interval; the run carries on either way.
+fn maybe_digest_journal(
+    cfg: &Config,
+    state: &mut RunState,
+    backend: &dyn Backend,
+    redactor: &Redactor,
+) -> Result<()> {
+    let Some(every) = cfg.digest.every_cycles.filter(|n| *n > 0) else {
+        return Ok(());
+    };
+    if state.cycle < state.digest_cycle.saturating_add(every) {
+        return Ok(());
+    }
+    state.digest_cycle = state.cycle;
+    let journal = journal_path(&cfg.state_dir);
+    let text = fs::read(&journal).unwrap_or_default();
+    let offset = (state.digest_journal_offset as usize).min(text.len());
+    let fresh = String::from_utf8_lossy(&text[offset..]);
+    if fresh.trim().is_empty() {
+        return Ok(());
+    }
+    let skip = fresh.chars().count().saturating_sub(MAX_DIGEST_INPUT_CHARS);
+    let fresh: String = fresh.chars().skip(skip).collect();
+    let previous = fs::read_to_string(digest_path(&cfg.state_dir)).unwrap_or_default();
+    let prompt = format!(
+        "Summarize this unattended run's journal into a short markdown digest (at most ~40 bullet lines) that later turns can rely on instead of the full journal. Keep decisions, blockers, completed work, and open problems; drop routine noise. Reply with the digest only, no control block.\n\nPrevious digest:\n{}\n\nNew journal entries:\n{}",
+        if previous.trim().is_empty() {
+            "(none)"
+        } else {
+            previous.trim()
+        },
+        fresh
+    );
+    let task = TaskRuntime {
+        id: "digest".to_string(),
+        coord_dir: cfg.state_dir.join("digest").display().to_string(),
+        ..task_runtime_from_config(&cfg.state_dir, &TaskConfig::default())
+    };
+    // Digest turns can run long; keep the governor heartbeat fresh meanwhile.
+    let mut on_activity = || write_governor_heartbeat(&cfg.state_dir);
+    let result = backend.run_turn(
+        turn_context(cfg, &task, &prompt, redactor),
+        &mut on_activity,
+    );
+    let digest = match result {
+        Ok(turn) => turn
+            .final_response
+            .split("<CONTROL_JSON>")
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_string(),
+        Err(err) => {
+            return append_journal(
+                &journal,
+                "journal digest failed",
+                &format!("Keeping the previous digest: {err:#}"),
+            );
+        }
+    };
+    if digest.is_empty() {
+        return append_journal(
+            &journal,
+            "journal digest failed",
+            "The backend returned an empty digest; keeping the previous one.",
+        );
+    }
+    let path = digest_path(&cfg.state_dir);
+    fs::write(&path, format!("{digest}\n"))
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    state.digest_journal_offset = text.len() as u64;
+    append_journal(
+        &journal,
+        "journal digested",
+        &format!(
+            "Folded {} journal bytes into {} at cycle {}.",
+            text.len() - offset,
+            path.display(),
+            state.cycle
+        ),
+    )
+}
+
+/// Knobs for [`run_governor`] that are not part of the config file.
+#[derive(Debug, Clone, Default)]
+pub struct GovernorOptions {
+    /// Serve Prometheus metrics on this port.
+    pub metrics_port: Option<u16>,
+    /// Address the metrics server binds; loopback when unset.
+    pub metrics_addr: Option<IpAddr>,
+    /// Check backend and harness binaries before the first turn.
+    pub preflight: bool,
+    /// Config file to re-read between cycles, applying safe changes.
+    pub watch_config: Option<PathBuf>,
+    /// Time source for the run; [`SystemClock`] when unset.
+    pub clock: Option<Arc<dyn Clock>>,
+    /// Multiplexer whose pane gets titled after the active task.
+    pub mux: Option<Mux>,
+    /// Builds the run's backend instead of the `[backend]` failover chain.
+    pub backend_factory: Option<BackendFactory>,
+}
+
+/// Embedder-supplied constructor for a custom [`Backend`]. The governor calls
+/// it once at startup; the backend then takes every turn, with no failover.
+#[derive(Clone)]
+pub struct BackendFactory(Arc<BuildBackend>);
+
+type BuildBackend = dyn Fn(&Config) -> Result<Box<dyn Backend>> + Send + Sync;
+
+impl BackendFactory {
+    pub fn new(
+        build: impl Fn(&Config) -> Result<Box<dyn Backend>> + Send + Sync + 'static,
+    ) -> Self {
+        Self(Arc::new(build))
+    }
+}
+
+impl std::fmt::Debug for BackendFactory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("BackendFactory(..)")
+    }
+}
+
+/// Terminal multiplexer crank can drive from inside one of its sessions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Mux {
+    Tmux,
+    Zellij,
+}
+
+impl Mux {
+    /// The multiplexer this process runs inside, from `$TMUX` / `$ZELLIJ`.
+    fn detect() -> Option<Self> {
+        if std::env::var_os("TMUX").is_some() {
+            Some(Self::Tmux)
+        } else if std::env::var_os("ZELLIJ").is_some() {
+            Some(Self::Zellij)
+        } else {
+            None
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Tmux => "tmux",
+            Self::Zellij => "zellij",
+        }
+    }
+
+    fn rename_pane_command(self, title: &str) -> Command {
+        let mut cmd = Command::new(self.as_str());
+        match self {
+            Self::Tmux => {
+                cmd.arg("select-pane");
+                // Title our own pane, not whichever one has focus.
+                if let Some(pane) = std::env::var_os("TMUX_PANE") {
+                    cmd.arg("-t").arg(pane);
+                }
+                cmd.args(["-T", title]);
+            }
+            Self::Zellij => {
+                cmd.args(["action", "rename-pane", title]);
+            }
+        }
+        cmd
+    }
+
+    fn split_command(self, shell_command: &str) -> Command {
+        let mut cmd = Command::new(self.as_str());
+        match self {
+            Self::Tmux => cmd.args(["split-window", "-h", shell_command]),
+            Self::Zellij => cmd.args([
+                "run",
+                "--direction",
+                "right",
+                "--",
+                "sh",
+                "-c",
+                shell_command,
+            ]),
+        };
+        cmd
+    }
+}
+
+/// Best effort: a pane that cannot be titled must not stop the run.
+fn set_pane_title(mux: Mux, title: &str) {
+    let _ = mux
+        .rename_pane_command(title)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+}
+
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// `tail -F` of the journal and events log, for `ctl attach`.
+fn attach_tail_command(state_dir: &Path) -> String {
+    format!(
+        "tail -n 40 -F {} {}",
+        shell_quote(&journal_path(state_dir).display().to_string()),
+        shell_quote(&events_log_path(state_dir).display().to_string())
+    )
+}
+
+fn ctl_attach(state_dir: &Path, mux: Option<Mux>) -> Result<()> {
+    if !state_path(state_dir).is_file() {
+        return Err(anyhow!("no run state at {}", state_dir.display()));
+    }
+    let mux = mux
+        .or_else(Mux::detect)
+        .ok_or_else(|| anyhow!("not inside tmux or zellij; pass --mux or start a session"))?;
+    let status = mux
+        .split_command(&attach_tail_command(state_dir))
+        .status()
+        .with_context(|| format!("failed to run {}", mux.as_str()))?;
+    if !status.success() {
+        return Err(anyhow!("{} split failed with {status}", mux.as_str()));
+    }
+    Ok(())
+}
+
+/// Activity callback for one task's turns, main and re-prompt alike: renews the
+/// task's lease, marks progress for the stall detector, and saves state at most
+/// every 5s. A lease that moved to another node stops the turn.
+struct TurnActivity<'a> {
+    cfg: &'a Config,
+    leases: Option<&'a LeaseBoard>,
+    journal: &'a Path,
+    task_id: &'a str,
+    idx: usize,
+    progress: &'a RefCell<LiveTurn>,
+    next_lease_renewal_epoch: Option<i64>,
+    last_save_epoch: i64,
+    lease_lost: Option<TaskLease>,
+}
+
+impl TurnActivity<'_> {
+    fn tick(&mut self, state: &mut RunState) -> Result<()> {
+        let now = now_epoch();
+        if let Some(board) = self.leases
+            && self.next_lease_renewal_epoch.is_some_and(|at| now >= at)
+        {
+            let (holder, next_at) = board.renew(self.task_id, self.journal)?;
+            self.next_lease_renewal_epoch = Some(next_at);
+            if let Some(holder) = holder {
+                let node = holder.node.clone();
+                self.lease_lost = Some(holder);
+                return Err(anyhow!("task {} lease moved to node {node}", self.task_id));
+            }
+        }
+        if let Some(task) = state.tasks.get_mut(self.idx) {
+            task.last_progress_epoch = Some(now);
+        }
+        state.last_turn_at = Some(now_iso());
+        state.live_turn = Some(self.progress.borrow().clone());
+        if now.saturating_sub(self.last_save_epoch) >= 5 {
+            write_governor_heartbeat(&self.cfg.state_dir)?;
+            save_state(state, &self.cfg.state_dir)?;
+            self.last_save_epoch = now;
+        }
+        Ok(())
+    }
+}
+
+/// Runs (or resumes) the run described by `cfg` until every task is terminal,
+/// a limit is hit, or no task can make progress. Holds the state dir lock.
+pub fn run_governor(mut cfg: Config, options: GovernorOptions) -> Result<()> {
+    let _clock = options.clock.clone().map(install_clock);
+    ensure_dir(&cfg.state_dir)?;
+    ensure_dir(&cfg.state_dir.join("logs"))?;
+    ensure_log_files(&cfg.state_dir)?;
+    ensure_dir(&cfg.state_dir.join("coord"))?;
+
+    let _lock = LockGuard::acquire(&cfg.state_dir)?;
+    let templates = PromptTemplates::load(&cfg)?;
+    let redactor = Redactor::new(&cfg.logging)?;
+    let backends = match &options.backend_factory {
+        Some(factory) => {
+            cfg.backend.fallbacks.clear();
+            let backend = (factory.0)(&cfg).context("backend factory failed")?;
+            vec![cfg.backend.recorded(backend)]
+        }
+        None => cfg.backend.build(),
+    };
+
+    let mut state = init_state(&cfg)?;
+    let journal = PathBuf::from(&state.journal_path);
+
+    if state.cycle == 0 {
+        append_journal(
+            &journal,
+            "run boot",
+            &format!(
+                "Starting run {} in {} with {} tasks.",
+                state.run_id,
+                cfg.workspace.display(),
+                state.tasks.len()
+            ),
+        )?;
+    } else {
+        append_journal(
+            &journal,
+            "run resume",
+            &format!("Resuming run {} at cycle {}.", state.run_id, state.cycle),
+        )?;
+    }
+
+    let added = merge_new_config_tasks(&mut state, &cfg);
+    if !added.is_empty() {
+        append_journal(
+            &journal,
+            "tasks appended",
+            &format!(
+                "Merged {} new task(s) from config into existing state: {}",
+                added.len(),
+                added.join(", ")
+            ),
+        )?;
+    }
+
+    if options.preflight {
+        // An injected backend is the embedder's to check; only the run's files are.
+        let problems = match options.backend_factory {
+            Some(_) => config_file_problems(&cfg),
+            None => preflight_problems(&cfg, &redactor),
+        };
+        if !problems.is_empty() {
+            append_journal(&journal, "preflight failed", &problems.join("\n"))?;
+            return Err(anyhow!(
+                "preflight failed:\n  - {}",
+                problems.join("\n  - ")
+            ));
+        }
+        append_journal(
+            &journal,
+            "preflight passed",
+            "Run files exist; backend and role harness binaries are installed and respond to --version.",
+        )?;
+    }
+
+    // A resumed run's uncommitted changes are its own work in progress.
+    if state.cycle == 0 {
+        guard_dirty_workspace(&cfg, &state.run_id, &journal)?;
+    }
+
+    let env = hook_env(&cfg, &state.run_id, None);
+    if let Some(report) = run_hooks(
+        &journal,
+        "pre_run",
+        &cfg.hooks.pre_run,
+        cfg.hooks.timeout(),
+        &cfg.workspace,
+        &env,
+    )? {
+        return Err(anyhow!("pre_run hook failed: {report}"));
+    }
+
+    let metrics = Arc::new(Mutex::new(GovernorMetrics::default()));
+    if let Some(port) = options.metrics_port {
+        let ip = options
+            .metrics_addr
+            .unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        let addr = start_metrics_server(SocketAddr::new(ip, port), Arc::clone(&metrics))?;
+        append_journal(
+            &journal,
+            "metrics server",
+            &format!("Serving Prometheus metrics on http://{addr}/metrics"),
+        )?;
+    }
+
+    let state_sync = StateSync::start(&cfg.sync, &cfg.state_dir);
+    if state_sync.is_some() {
+        let targets: Vec<&str> = [&cfg.sync.rsync_target, &cfg.sync.s3_uri]
+            .into_iter()
+            .filter_map(|t| t.as_deref())
+            .collect();
+        append_journal(
+            &journal,
+            "state sync",
+            &format!(
+                "Mirroring state.json, JOURNAL.md, and run-summary.json to {}",
+                targets.join(", ")
+            ),
+        )?;
+    }
+
+    let leases = cfg.cluster.as_ref().map(LeaseBoard::new).transpose()?;
+    if let Some(board) = &leases {
+        append_journal(
+            &journal,
+            "cluster",
+            &format!(
+                "Sharing tasks through leases in {} as node {}.",
+                board.dir.display(),
+                board.node
+            ),
+        )?;
+    }
+    let mut waiting_on_nodes = false;
+
+    let mux = match options.mux {
+        Some(mux) if Mux::detect() != Some(mux) => {
+            append_journal(
+                &journal,
+                "mux unavailable",
+                &format!(
+                    "--mux {} given outside a {} session; the pane title stays unchanged.",
+                    mux.as_str(),
+                    mux.as_str()
+                ),
+            )?;
+            None
+        }
+        other => other,
+    };
+    let mut pane_task: Option<String> = None;
+    let mut kept_worktrees = std::collections::BTreeSet::new();
+
+    let mut consecutive_failures = 0u32;
+    let mut schedule_open: Option<bool> = None;
+    let expected_reviewer_quorum = configured_reviewer_quorum(&cfg.roles);
+    let mut config_mtime = options
+        .watch_config
+        .as_deref()
+        .and_then(config_sources_mtime);
+    save_state(&mut state, &cfg.state_dir)?;
+
+    loop {
+        write_governor_heartbeat(&cfg.state_dir)?;
+        if let Some(sync) = &state_sync {
+            sync.request();
+        }
+        apply_queued_task_edits(&mut state, &cfg.state_dir, &journal)?;
+        if let Some(path) = &options.watch_config
+            && config_sources_mtime(path) != config_mtime
+        {
+            config_mtime = config_sources_mtime(path);
+            match load_config(path) {
+                Ok(fresh) => {
+                    let reload = reload_config(&mut cfg, fresh);
+                    let added = merge_new_config_tasks(&mut state, &cfg);
+                    if !reload.applied.is_empty() {
+                        append_journal(
+                            &journal,
+                            "config reloaded",
+                            &format!(
+                                "Applied from {}:\n- {}",
+                                path.display(),
+                                reload.applied.join("\n- ")
+                            ),
+                        )?;
+                    }
+                    if !reload.refused.is_empty() {
+                        append_journal(
+                            &journal,
+                            "config change refused",
+                            &format!(
+                                "Not safe to change mid-run (restart to apply):\n- {}",
+                                reload.refused.join("\n- ")
+                            ),
+                        )?;
+                    }
+                    if !added.is_empty() {
+                        save_state(&mut state, &cfg.state_dir)?;
+                    }
+                }
+                Err(err) => append_journal(
+                    &journal,
+                    "config reload failed",
+                    &format!("Keeping the current config: {err:#}"),
+                )?,
+            }
+        }
+        for path in enforce_log_caps(&cfg.state_dir, &cfg.logging)? {
+            append_journal(
+                &journal,
+                "log capped",
+                &format!("{} exceeded its [logging] size cap.", path.display()),
+            )?;
+        }
+        let unverified =
+            sync_completion_and_progress(&mut state, cfg.policy.require_decision_quorum);
+        verify_task_completions(&cfg, &mut state, &journal, &unverified)?;
+        remove_merged_task_worktrees(&cfg, &mut state, &journal, &mut kept_worktrees)?;
+        let reopened = reopen_answered_escalations(&mut state, &cfg.state_dir);
+        if !reopened.is_empty() {
+            append_journal(
+                &journal,
+                "escalation answered",
+                &format!(
+                    "Reopened blocked task(s) with operator answers: {}",
+                    reopened.join(", ")
+                ),
+            )?;
+        }
+        for (task_id, reason) in skip_conditional_tasks(&cfg, &mut state) {
+            append_journal(
+                &journal,
+                "task skipped",
+                &format!("Task {task_id} skipped: {reason}."),
+            )?;
+        }
+        run_post_task_hooks(&cfg, &mut state, &journal)?;
+        let leased_elsewhere = match &leases {
+            Some(board) => board.sync(&mut state, &journal)?,
+            None => Default::default(),
+        };
+        if let Ok(mut m) = metrics.lock() {
+            m.observe_state(&state, consecutive_failures);
+        }
+
+        if all_terminal(&state) {
+            state.status = RunStatus::Completed;
+            save_state(&mut state, &cfg.state_dir)?;
+            write_run_summary(&state, &cfg)?;
+            append_journal(
+                &journal,
+                "run completed",
+                "All tasks reached terminal status.",
+            )?;
+            break;
+        }
+
+        if let Some(reason) = run_limit_reason(&cfg.limits, &state) {
+            state.status = RunStatus::LimitReached;
+            state.stop_reason = Some(reason.clone());
+            save_state(&mut state, &cfg.state_dir)?;
+            write_run_summary(&state, &cfg)?;
+            append_journal(&journal, "run limit reached", &reason)?;
+            break;
+        }
+
+        let wait = schedule_wait_secs(&cfg.schedule, clock_now().with_timezone(&chrono::Local));
+        if wait > 0 {
+            if schedule_open != Some(false) {
+                schedule_open = Some(false);
+                append_journal(
+                    &journal,
+                    "schedule window closed",
+                    &format!("Outside the configured schedule; next turn allowed in {wait}s."),
+                )?;
+                save_state(&mut state, &cfg.state_dir)?;
+            }
+            // Time spent outside the window must not count toward stall detection.
+            let now = now_epoch();
+            for task in &mut state.tasks {
+                if task.status == TaskStatus::Running {
+                    task.last_progress_epoch = Some(now);
+                }
+            }
+            governor_sleep(
+                &cfg.state_dir,
+                Duration::from_secs(wait.min(cfg.poll_interval_secs.max(1))),
+            );
+            continue;
+        }
+        if schedule_open == Some(false) {
+            append_journal(
+                &journal,
+                "schedule window opened",
+                "Inside the configured schedule; resuming turns.",
+            )?;
+        }
+        schedule_open = Some(true);
+
+        let mut active_idx = state
+            .tasks
+            .iter()
+            .position(|t| t.status == TaskStatus::Running);
+
+        if active_idx.is_none() {
+            if let Some(next) =
+                choose_next_pending_task(&state, cfg.policy.scheduling, &leased_elsewhere)
+            {
+                let task_id = state.tasks[next].id.clone();
+                if let Some(board) = &leases
+                    && let Some(holder) = board.claim(&task_id)?
+                {
+                    append_journal(
+                        &journal,
+                        "task lease taken",
+                        &format!(
+                            "Task {task_id} is {} on node {}; choosing another task.",
+                            holder.status.as_str(),
+                            holder.node
+                        ),
+                    )?;
+                    continue;
+                }
+                waiting_on_nodes = false;
+                mark_task_started(&mut state.tasks[next])?;
+                if let Err(err) = prepare_task_worktree(&cfg, &state.run_id, &mut state.tasks[next])
+                {
+                    let reason = format!("failed to prepare task worktree: {err:#}");
+                    append_journal(&journal, "task blocked worktree", &reason)?;
+                    mark_task_blocked(&mut state.tasks[next], &reason);
+                    save_state(&mut state, &cfg.state_dir)?;
+                    continue;
+                }
+                let env = hook_env(&cfg, &state.run_id, Some(&state.tasks[next]));
+                let workspace = task_workspace(&cfg, &state.tasks[next]);
+                if let Some(report) = run_hooks(
+                    &journal,
+                    "pre_task",
+                    &cfg.hooks.pre_task,
+                    cfg.hooks.timeout(),
+                    &workspace,
+                    &env,
+                )? {
+                    mark_task_blocked(
+                        &mut state.tasks[next],
+                        &format!("pre_task hook failed: {report}"),
+                    );
+                    save_state(&mut state, &cfg.state_dir)?;
+                    continue;
+                }
+                if let Some(branch) = &state.tasks[next].branch {
+                    append_journal(
+                        &journal,
+                        "task branch",
+                        &format!(
+                            "Task {} runs on branch {} in {}",
+                            task_id,
+                            branch,
+                            task_workspace(&cfg, &state.tasks[next]).display()
+                        ),
+                    )?;
+                }
+                append_journal(
+                    &journal,
+                    "task started",
+                    &format!(
+                        "Task {} started with coord dir {}",
+                        task_id, state.tasks[next].coord_dir
+                    ),
+                )?;
+                active_idx = Some(next);
+            } else if !leased_elsewhere.is_empty() {
+                if !waiting_on_nodes {
+                    waiting_on_nodes = true;
+                    append_journal(
+                        &journal,
+                        "waiting on other nodes",
+                        &format!(
+                            "No task is ready here; other nodes hold {}.",
+                            leased_elsewhere
+                                .iter()
+                                .map(String::as_str)
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        ),
+                    )?;
+                    save_state(&mut state, &cfg.state_dir)?;
+                }
+                governor_sleep(
+                    &cfg.state_dir,
+                    Duration::from_secs(cfg.poll_interval_secs.max(1)),
+                );
+                continue;
+            } else {
+                state.status = RunStatus::FailedTerminal;
+                save_state(&mut state, &cfg.state_dir)?;
+                write_run_summary(&state, &cfg)?;
+                append_journal(
+                    &journal,
+                    "deadlock",
+                    "No runnable pending task found; dependency graph may be invalid.",
+                )?;
+                break;
+            }
+        }
+
+        let idx = active_idx.expect("active index must be set");
+        if let Some(mux) = mux
+            && pane_task.as_deref() != Some(state.tasks[idx].id.as_str())
+        {
+            pane_task = Some(state.tasks[idx].id.clone());
+            set_pane_title(mux, &format!("crank: {}", state.tasks[idx].id));
+        }
+        if let Some(max) = cfg.limits.max_turns_per_task
+            && state.tasks[idx].turns >= max
+        {
+            let reason = format!("reached max_turns_per_task ({max})");
+            append_journal(
+                &journal,
+                "task blocked turn limit",
+                &format!("Task {} {}.", state.tasks[idx].id, reason),
+            )?;
+            mark_task_blocked(&mut state.tasks[idx], &reason);
+            save_state(&mut state, &cfg.state_dir)?;
+            continue;
+        }
+        if let Some(actual) = coord_reviewer_count(Path::new(&state.tasks[idx].coord_dir))
+            && actual != expected_reviewer_quorum
+        {
+            let reason = format!(
+                "reviewer quorum mismatch: expected {} from configured team roles, but coord meta.env has REVIEWER_COUNT={}",
+                expected_reviewer_quorum, actual
+            );
+            append_journal(&journal, "task blocked reviewer quorum", &reason)?;
+            let task = &mut state.tasks[idx];
+            mark_task_blocked(task, &reason);
+            save_state(&mut state, &cfg.state_dir)?;
+            governor_sleep(
+                &cfg.state_dir,
+                Duration::from_secs(cfg.poll_interval_secs.max(1)),
+            );
+            continue;
+        }
+
+        let now = now_epoch();
+        let mut recovery_note: Option<String> = None;
+        {
+            let task = &mut state.tasks[idx];
+            if task.last_progress_epoch.is_none() {
+                task.last_progress_epoch = Some(now);
+            }
+
+            let stalled_roles: Vec<String> = stalled_role_heartbeats(task, &cfg.timeouts, now)
+                .iter()
+                .map(|hb| hb.role.clone())
+                .collect();
+            if let Some(last) = task.last_progress_epoch {
+                let age = now.saturating_sub(last);
+                let stall = if age > cfg.timeouts.stall_secs as i64 {
+                    Some(format!(
+                        "no progress for {}s (threshold {}s)",
+                        age, cfg.timeouts.stall_secs
+                    ))
+                } else if !stalled_roles.is_empty() {
+                    let described: Vec<String> = task
+                        .heartbeats
+                        .iter()
+                        .filter(|hb| stalled_roles.contains(&hb.role))
+                        .map(|hb| {
+                            format!(
+                                "{} heartbeat silent for {}s in phase '{}'",
+                                hb.role,
+                                now.saturating_sub(hb.epoch),
+                                hb.phase
+                            )
+                        })
+                        .collect();
+                    Some(described.join("; "))
+                } else {
+                    None
+                };
+                if let Some(stall) = stall {
+                    for hb in &mut task.heartbeats {
+                        if stalled_roles.contains(&hb.role) {
+                            hb.stall_reported = true;
+                        }
+                    }
+                    if task.recovery_attempts >= cfg.recovery.max_recovery_attempts_per_task {
+                        let reason = format!("exceeded recovery attempts: {stall}");
+                        mark_task_blocked(task, &reason);
+                        append_journal(
+                            &journal,
+                            "task blocked best-effort",
+                            &format!(
+                                "Task {} exceeded recovery attempts ({}). Marked blocked_best_effort.",
+                                task.id, stall
+                            ),
+                        )?;
+                        save_state(&mut state, &cfg.state_dir)?;
+                        governor_sleep(
+                            &cfg.state_dir,
+                            Duration::from_secs(cfg.poll_interval_secs.max(1)),
+                        );
+                        continue;
+                    }
+
+                    task.recovery_attempts = task.recovery_attempts.saturating_add(1);
+                    recovery_note = Some(format!(
+                        "Stall detected: {}. Recovery attempt {} of {}.",
+                        stall, task.recovery_attempts, cfg.recovery.max_recovery_attempts_per_task
+                    ));
+                }
+            }
+
+            if let Some(note) = task.pending_note.take() {
+                recovery_note = Some(match recovery_note {
+                    Some(stall) => format!("{stall}\n\n{note}"),
+                    None => note,
+                });
+            }
+        }
+
+        let digest_backend = backends
+            .get(state.backend_index)
+            .unwrap_or(&backends[0])
+            .as_ref();
+        maybe_digest_journal(&cfg, &mut state, digest_backend, &redactor)?;
+
+        let task_snapshot = state.tasks[idx].clone();
+        let answers = pending_escalation_answers(&cfg.state_dir, &task_snapshot.id);
+        let instructions = pending_operator_instructions(&cfg.state_dir, &task_snapshot.id);
+        let operator_notes: Vec<String> = answers
+            .iter()
+            .map(|a| format!("- answer to escalation {}: {}", a.n, a.text))
+            .chain(
+                instructions
+                    .iter()
+                    .map(|i| format!("- instruction: {}", i.text)),
+            )
+            .collect();
+        let prompt_started = clock_now();
+        // A template that cannot render (unset env var, missing include) blocks
+        // the task; queued answers and instructions stay queued for a retry.
+        let mut prompt = match build_prompt(
+            &cfg,
+            &templates,
+            &state,
+            &task_snapshot,
+            recovery_note.as_deref(),
+            &operator_notes,
+        ) {
+            Ok(built) => {
+                for (title, body) in &built.journal {
+                    append_journal(&journal, title, body)?;
+                }
+                built.text
+            }
+            Err(err) => {
+                let reason = format!("failed to build turn prompt: {err:#}");
+                append_journal(
+                    &journal,
+                    "task blocked prompt",
+                    &format!("Task {}: {reason}", task_snapshot.id),
+                )?;
+                mark_task_blocked(&mut state.tasks[idx], &reason);
+                save_state(&mut state, &cfg.state_dir)?;
+                continue;
+            }
+        };
+        if task_snapshot
+            .control_strategies
+            .contains(&ControlStrategy::StrictSuffix)
+        {
+            prompt.push_str(STRICT_CONTROL_SUFFIX);
+        }
+        let prompt_build_ms = elapsed_ms(prompt_started);
+
+        state.cycle = state.cycle.saturating_add(1);
+        state.tasks[idx].turns = state.tasks[idx].turns.saturating_add(1);
+        state.last_turn_at = Some(now_iso());
+        let progress = RefCell::new(LiveTurn {
+            task_id: task_snapshot.id.clone(),
+            started_at: now_iso(),
+            ..LiveTurn::default()
+        });
+        state.live_turn = Some(progress.borrow().clone());
+        save_state(&mut state, &cfg.state_dir)?;
+
+        let backend = backends
+            .get(state.backend_index)
+            .unwrap_or(&backends[0])
+            .as_ref();
+        let mut activity = TurnActivity {
+            cfg: &cfg,
+            leases: leases.as_ref(),
+            journal: &journal,
+            task_id: &task_snapshot.id,
+            idx,
+            progress: &progress,
+            next_lease_renewal_epoch: leases
+                .as_ref()
+                .map(|board| now_epoch() + board.lease_secs as i64 / 2),
+            last_save_epoch: 0,
+            lease_lost: None,
+        };
+
+        let turn_started = clock_now();
+        let turn = backend.run_turn(
+            TurnContext {
+                progress: Some(&progress),
+                ..turn_context(&cfg, &task_snapshot, &prompt, &redactor)
+            },
+            &mut || activity.tick(&mut state),
+        );
+        state.live_turn = None;
+        if let Some(holder) = activity.lease_lost.take() {
+            state.tasks[idx].status = TaskStatus::Pending;
+            append_journal(
+                &journal,
+                "task lease lost",
+                &format!(
+                    "Task {} is now leased by node {}; stopped its turn here.",
+                    task_snapshot.id, holder.node
+                ),
+            )?;
+            save_state(&mut state, &cfg.state_dir)?;
+            continue;
+        }
+        // A degenerate response is logged and paid for, then handled like a failed turn.
+        let turn = match turn {
+            Ok(result) => match degenerate_response_reason(&cfg.recovery, &result.final_response) {
+                Some(reason) => {
+                    if let Some(cost) = result.cost_usd {
+                        state.cost_usd += cost;
+                    }
+                    // The backend session and any control block still stand.
+                    if let Some(id) = &result.thread_id {
+                        state.tasks[idx].thread_id = Some(id.clone());
+                    }
+                    if let Ok(control) = extract_control_block(&result.final_response) {
+                        append_journal(
+                            &journal,
+                            "degenerate turn control",
+                            &format!(
+                                "task={} status={}\nsummary={}\nnext_action={}",
+                                task_snapshot.id,
+                                control.status.as_str(),
+                                control.summary,
+                                control.next_action
+                            ),
+                        )?;
+                    }
+                    log_turn(
+                        &cfg.state_dir,
+                        state.cycle,
+                        &format!("{}-{}", state.cycle, task_snapshot.id),
+                        &prompt,
+                        &result.final_response,
+                        &redactor,
+                    )?;
+                    Err(anyhow!("degenerate response: {reason}"))
+                }
+                None => Ok(result),
+            },
+            Err(err) => Err(err),
+        };
+        if let Ok(mut m) = metrics.lock() {
+            m.observe_turn(clock_elapsed(turn_started).as_secs_f64(), turn.is_ok());
+        }
+        let mut timing = CycleTiming {
+            cycle: state.cycle,
+            task_id: task_snapshot.id.clone(),
+            ok: turn.is_ok(),
+            prompt_build_ms,
+            backend_ms: elapsed_ms(turn_started),
+            parse_ms: 0,
+            sleep_ms: 0,
+        };
+        let pause = match turn {
+            Ok(turn_result) => {
+                consecutive_failures = 0;
+                if let Some(cost) = turn_result.cost_usd {
+                    state.cost_usd += cost;
+                }
+                if !answers.is_empty() {
+                    mark_escalation_answers_delivered(&cfg.state_dir, &task_snapshot.id, &answers)?;
+                    append_journal(
+                        &journal,
+                        "escalation answer delivered",
+                        &format!(
+                            "Task {} received operator answer(s) for escalation(s) {}.",
+                            task_snapshot.id,
+                            answers
+                                .iter()
+                                .map(|a| a.n.to_string())
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        ),
+                    )?;
+                }
+                if !instructions.is_empty() {
+                    mark_operator_instructions_delivered(&instructions)?;
+                    append_journal(
+                        &journal,
+                        "operator instruction delivered",
+                        &format!(
+                            "Task {} received {} operator instruction(s):\n{}",
+                            task_snapshot.id,
+                            instructions.len(),
+                            instructions
+                                .iter()
+                                .map(|i| format!("- {}", i.text))
+                                .collect::<Vec<_>>()
+                                .join("\n")
+                        ),
+                    )?;
+                }
+                if let Some(id) = turn_result.thread_id {
+                    state.tasks[idx].thread_id = Some(id);
+                }
+                state.last_turn_at = Some(now_iso());
+                log_turn(
+                    &cfg.state_dir,
+                    state.cycle,
+                    &format!("{}-{}", state.cycle, task_snapshot.id),
+                    &prompt,
+                    &turn_result.final_response,
+                    &redactor,
+                )?;
+
+                if cfg.git.snapshot_per_turn {
+                    match capture_git_snapshot(
+                        &task_workspace(&cfg, &state.tasks[idx]),
+                        state.cycle,
+                    ) {
+                        Ok(snapshot) => {
+                            let mut event = serde_json::to_value(&snapshot)?;
+                            event["type"] = Value::from("crank.git_snapshot");
+                            event["task_id"] = Value::from(task_snapshot.id.clone());
+                            append_event_line(
+                                &events_log_path(&cfg.state_dir),
+                                &event.to_string(),
+                                &redactor,
+                            )?;
+                            state.tasks[idx].last_git_snapshot = Some(snapshot);
+                        }
+                        Err(err) => {
+                            append_journal(
+                                &journal,
+                                "git snapshot failed",
+                                &format!("Task {}: {:#}", task_snapshot.id, err),
+                            )?;
+                        }
+                    }
+                }
+
+                let parse_started = clock_now();
+                let mut control_result = extract_control_block(&turn_result.final_response);
+                timing.parse_ms = elapsed_ms(parse_started);
+                if let Err(ControlBlockError::Invalid(reason)) = &control_result {
+                    append_journal(
+                        &journal,
+                        "control block schema violation",
+                        &format!("Task {}: {}", task_snapshot.id, reason),
+                    )?;
+                    if cfg.policy.reprompt_invalid_control
+                        && let Some(limit) = cfg
+                            .limits
+                            .max_turns_per_task
+                            .filter(|max| state.tasks[idx].turns >= *max)
+                            .map(|max| format!("reached max_turns_per_task ({max})"))
+                            .or_else(|| run_limit_reason(&cfg.limits, &state))
+                    {
+                        append_journal(
+                            &journal,
+                            "control block re-prompt skipped",
+                            &format!("Task {}: {limit}.", task_snapshot.id),
+                        )?;
+                    } else if cfg.policy.reprompt_invalid_control {
+                        let fix_prompt = control_fix_prompt(&task_snapshot.id, reason);
+                        let mut fix_task = task_snapshot.clone();
+                        fix_task.thread_id = state.tasks[idx].thread_id.clone();
+                        // The re-prompt is a turn of its own for the turn limits.
+                        state.cycle = state.cycle.saturating_add(1);
+                        state.tasks[idx].turns = state.tasks[idx].turns.saturating_add(1);
+                        state.live_turn = Some(progress.borrow().clone());
+                        save_state(&mut state, &cfg.state_dir)?;
+                        let fix_started = clock_now();
+                        let fix_turn = backend.run_turn(
+                            TurnContext {
+                                progress: Some(&progress),
+                                ..turn_context(&cfg, &fix_task, &fix_prompt, &redactor)
+                            },
+                            &mut || activity.tick(&mut state),
+                        );
+                        state.live_turn = None;
+                        timing.backend_ms += elapsed_ms(fix_started);
+                        if let Some(holder) = activity.lease_lost.take() {
+                            state.tasks[idx].status = TaskStatus::Pending;
+                            append_journal(
+                                &journal,
+                                "task lease lost",
+                                &format!(
+                                    "Task {} is now leased by node {}; stopped its re-prompt here.",
+                                    task_snapshot.id, holder.node
+                                ),
+                            )?;
+                            save_state(&mut state, &cfg.state_dir)?;
+                            continue;
+                        }
+                        match fix_turn {
+                            Ok(fix) => {
+                                if let Some(cost) = fix.cost_usd {
+                                    state.cost_usd += cost;
+                                }
+                                if let Some(id) = fix.thread_id {
+                                    state.tasks[idx].thread_id = Some(id);
+                                }
+                                log_turn(
+                                    &cfg.state_dir,
+                                    state.cycle,
+                                    &format!("{}-{}-reprompt", state.cycle, task_snapshot.id),
+                                    &fix_prompt,
+                                    &fix.final_response,
+                                    &redactor,
+                                )?;
+                                let parse_started = clock_now();
+                                control_result = extract_control_block(&fix.final_response);
+                                timing.parse_ms += elapsed_ms(parse_started);
+                                append_journal(
+                                    &journal,
+                                    "control block re-prompt",
+                                    &format!(
+                                        "Task {} re-prompted once for a valid control block: {}",
+                                        task_snapshot.id,
+                                        match &control_result {
+                                            Ok(_) => "fixed".to_string(),
+                                            Err(err) => err.to_string(),
+                                        }
+                                    ),
+                                )?;
+                            }
+                            Err(err) => {
+                                append_journal(
+                                    &journal,
+                                    "control block re-prompt failed",
+                                    &format!("Task {}: {}", task_snapshot.id, err),
+                                )?;
+                            }
+                        }
+                    }
+                }
+
+                let mut escalated_block_reason: Option<String> = None;
+                let control_ok = control_result.is_ok();
+                match control_result {
+                    Ok(control) => {
+                        append_journal(
+                            &journal,
+                            "turn control",
+                            &format!(
+                                "task={} control_task={} version={} status={} needs_user_input={}\nsummary={}\nnext_action={}\nartifacts=[{}]\nblockers=[{}]\nrequests=[{}]",
+                                task_snapshot.id,
+                                control.task_id,
+                                control.version,
+                                control.status.as_str(),
+                                control.needs_user_input,
+                                control.summary,
+                                control.next_action,
+                                control.artifacts.join(", "),
+                                control.blockers.join("; "),
+                                control
+                                    .requests
+                                    .iter()
+                                    .map(|r| format!("{}: {}", r.kind, r.detail))
+                                    .collect::<Vec<_>>()
+                                    .join("; ")
+                            ),
+                        )?;
+
+                        if cfg.unattended && control.needs_user_input {
+                            append_journal(
+                                &journal,
+                                "unattended override",
+                                "Orchestrator indicated user input was needed. Governor will continue with best-effort without user interaction.",
+                            )?;
+                        }
+
+                        let handling = {
+                            let task = &mut state.tasks[idx];
+                            decide_unattended_escalate(
+                                cfg.unattended,
+                                cfg.policy.unattended_escalate,
+                                task,
+                                Some(control.status.as_str()),
+                                Some(&control.next_action),
+                            )
+                        };
+                        if handling != EscalateHandling::Ignore {
+                            let question = write_escalation_question(
+                                &cfg.state_dir,
+                                &state.tasks[idx],
+                                state.cycle,
+                                &control,
+                            )?;
+                            append_journal(
+                                &journal,
+                                "escalation question",
+                                &format!(
+                                    "Task {} escalated; question written to {}. Answer with `crank ctl answer`.",
+                                    task_snapshot.id,
+                                    question.display()
+                                ),
+                            )?;
+                        }
+                        match handling {
+                            EscalateHandling::Ignore => {}
+                            EscalateHandling::Retry => {
+                                append_journal(
+                                    &journal,
+                                    "unattended escalate retry",
+                                    &format!(
+                                        "Task {} requested ESCALATE. Applying best_effort_once retry path (attempt {}).",
+                                        task_snapshot.id,
+                                        state.tasks[idx].unattended_escalate_retries
+                                    ),
+                                )?;
+                            }
+                            EscalateHandling::Block => {
+                                escalated_block_reason = Some(format!(
+                                    "orchestrator requested ESCALATE in unattended mode (policy={})",
+                                    cfg.policy.unattended_escalate.as_str()
+                                ));
+                            }
+                        }
+                    }
+                    Err(ControlBlockError::Missing) => {
+                        append_journal(
+                            &journal,
+                            "missing control block",
+                            "No CONTROL_JSON block found in orchestrator response. Continuing.",
+                        )?;
+                    }
+                    Err(ControlBlockError::Invalid(reason)) => {
+                        append_journal(
+                            &journal,
+                            "invalid control block",
+                            &format!(
+                                "Ignoring CONTROL_JSON for task {} after schema violation: {}. Continuing.",
+                                task_snapshot.id, reason
+                            ),
+                        )?;
+                    }
+                }
+
+                if control_ok {
+                    state.tasks[idx].control_misses = 0;
+                } else {
+                    note_control_miss(&cfg, &mut state, idx, &journal)?;
+                }
+
+                let unverified =
+                    sync_completion_and_progress(&mut state, cfg.policy.require_decision_quorum);
+                verify_task_completions(&cfg, &mut state, &journal, &unverified)?;
+                if let Some(reason) = escalated_block_reason {
+                    let task = &mut state.tasks[idx];
+                    if task.status != TaskStatus::Completed {
+                        mark_task_blocked(task, &reason);
+                        append_journal(&journal, "task blocked escalate policy", &reason)?;
+                    }
+                }
+                save_state(&mut state, &cfg.state_dir)?;
+                Duration::from_secs(cfg.poll_interval_secs.max(1))
+            }
+            Err(err) => {
+                consecutive_failures = consecutive_failures.saturating_add(1);
+                append_journal(
+                    &journal,
+                    "turn failure",
+                    &format!(
+                        "Task {} turn failed (consecutive failures={}): {}",
+                        task_snapshot.id, consecutive_failures, err
+                    ),
+                )?;
+
+                let failover = if consecutive_failures >= cfg.backend.failover_after {
+                    fail_over_backend(&cfg.backend, &mut state)
+                } else {
+                    None
+                };
+                if let Some((from, to)) = failover {
+                    append_journal(
+                        &journal,
+                        "backend failover",
+                        &format!(
+                            "Switched from the {from} backend to {to} after {consecutive_failures} consecutive turn failures."
+                        ),
+                    )?;
+                    consecutive_failures = 0;
+                } else if consecutive_failures >= cfg.recovery.max_failures_before_block {
+                    let task = &mut state.tasks[idx];
+                    let reason = format!("hit {} consecutive turn failures", consecutive_failures);
+                    mark_task_blocked(task, &reason);
+                    append_journal(
+                        &journal,
+                        "task blocked after repeated failures",
+                        &format!(
+                            "Task {} hit {} consecutive turn failures and was marked blocked_best_effort.",
+                            task.id, consecutive_failures
+                        ),
+                    )?;
+                    consecutive_failures = 0;
+                }
+
+                save_state(&mut state, &cfg.state_dir)?;
+                let backoff = compute_backoff_secs(&cfg.recovery, consecutive_failures.max(1));
+                Duration::from_secs(backoff)
+            }
+        };
+        if let Some(sync) = &state_sync {
+            sync.request();
+        }
+        let sleep_started = clock_now();
+        governor_sleep(&cfg.state_dir, pause);
+        timing.sleep_ms = elapsed_ms(sleep_started);
+        append_cycle_timing(&cfg.state_dir, &timing)?;
+    }
+
+    if let Some(board) = &leases {
+        board.settle(&state)?;
+    }
+    if let Some(mux) = mux {
+        set_pane_title(mux, &format!("crank: {}", state.status.as_str()));
+    }
+
+    let mut env = hook_env(&cfg, &state.run_id, None);
+    env.push(("CRANK_RUN_STATUS", state.status.as_str().to_string()));
+    let post_run = run_hooks(
+        &journal,
+        "post_run",
+        &cfg.hooks.post_run,
+        cfg.hooks.timeout(),
+        &cfg.workspace,
+        &env,
+    );
+    // post_run hooks often merge the task branches.
+    let cleanup = remove_merged_task_worktrees(&cfg, &mut state, &journal, &mut kept_worktrees);
+    if let Some(sync) = state_sync {
+        sync.finish();
+    }
+    post_run?;
+    cleanup?;
+
+    Ok(())
+}
+
+fn toml_string(value: &str) -> String {
+    format!("{value:?}")
+}
+
+fn toml_array(values: &[String]) -> String {
+    let quoted: Vec<String> = values.iter().map(|v| toml_string(v)).collect();
+    format!("[{}]", quoted.join(", "))
+}
+
+fn render_role_block(name: &str, role: &RoleConfig) -> String {
+    format!(
+        r#"[roles.{name}]
+harness = {harness}
+model = {model}
+thinking = {thinking}
+launch_args = {launch_args}
+"#,
+        harness = toml_string(&role.harness),
+        model = toml_string(&role.model),
+        thinking = toml_string(&role.thinking),
+        launch_args = toml_array(&role.launch_args),
+    )
+}
+
+fn write_default_config(output: &Path, roles: &RolesConfig) -> Result<()> {
+    let content = format!(
+        r#"run_id = "pika-call-plans"
+workspace = "/Users/justin/code/pika"
+state_dir = "/Users/justin/code/crank/runs/pika-call-plans"
+unattended = true
+poll_interval_secs = 30
+
+[timeouts]
+stall_secs = 900
+
+[recovery]
+max_recovery_attempts_per_task = 4
+max_failures_before_block = 6
+backoff_initial_secs = 5
+backoff_max_secs = 120
+
+[policy]
+unattended_escalate = "best_effort_once"
+
+[backend]
+kind = "codex"
+binary = "codex"
+model = "gpt-5.3-codex"
+thinking = "xhigh"
+approval_policy = "never"
+sandbox_mode = "danger-full-access"
+extra_args = []
+
+{implementer_role}
+{reviewer_1_role}
+{reviewer_2_role}
+
+[[tasks]]
+id = "call-audio"
+todo_file = "/Users/justin/code/pika/todos/call-audio-plan.md"
+depends_on = []
+
+[[tasks]]
+id = "call-transport"
+todo_file = "/Users/justin/code/pika/todos/call-transport-plan.md"
+depends_on = ["call-audio"]
+
+[[tasks]]
+id = "call-video"
+todo_file = "/Users/justin/code/pika/todos/call-video-plan.md"
+depends_on = ["call-audio", "call-transport"]
+
+[[tasks]]
+id = "call-native-audio"
+todo_file = "/Users/justin/code/pika/todos/call-native-audio-plan.md"
+depends_on = ["call-audio", "call-transport", "call-video"]
+"#,
+        implementer_role = render_role_block("implementer", &roles.implementer),
+        reviewer_1_role = render_role_block("reviewer_1", &roles.reviewer_1),
+        reviewer_2_role = render_role_block("reviewer_2", &roles.reviewer_2),
+    );
+
+    write_config_file(output, &content)
+}
+
+fn write_config_file(output: &Path, content: &str) -> Result<()> {
+    if let Some(parent) = output.parent() {
+        ensure_dir(parent)?;
+    }
+    fs::write(output, content).with_context(|| format!("failed to write {}", output.display()))?;
+    Ok(())
+}
+
+/// Answers collected by `crank init --interactive`.
+#[derive(Debug)]
+struct InitAnswers {
+    run_id: String,
+    workspace: PathBuf,
+    state_dir: PathBuf,
+    backend: String,
+    model: String,
+    thinking: String,
+    team: Option<String>,
+    /// `(id, todo_file, depends_on)` in declaration order.
+    tasks: Vec<(String, PathBuf, Vec<String>)>,
+}
+
+const INIT_BACKEND_KINDS: [&str; 5] = ["codex", "claude", "droid", "pi", "mock"];
+
+/// Asks one question, returning the trimmed answer or `default` when blank.
+/// Errors at end of input so a closed stdin cannot loop forever.
+fn prompt_line(
+    input: &mut dyn BufRead,
+    out: &mut dyn Write,
+    question: &str,
+    default: &str,
+) -> Result<String> {
+    if default.is_empty() {
+        write!(out, "{question}: ")?;
+    } else {
+        write!(out, "{question} [{default}]: ")?;
+    }
+    out.flush()?;
+    let mut line = String::new();
+    if input.read_line(&mut line)? == 0 {
+        return Err(anyhow!("input ended while asking: {question}"));
+    }
+    let answer = line.trim();
+    Ok(if answer.is_empty() { default } else { answer }.to_string())
+}
+
+fn prompt_init_answers(
+    input: &mut dyn BufRead,
+    out: &mut dyn Write,
+    ask_team: bool,
+) -> Result<InitAnswers> {
+    let cwd = std::env::current_dir().context("failed to get current dir")?;
+    let workspace = PathBuf::from(prompt_line(
+        input,
+        out,
+        "Workspace (repo the tasks work in)",
+        &cwd.display().to_string(),
+    )?);
+    let default_run_id = workspace
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "crank-run".to_string());
+    let run_id = prompt_line(input, out, "Run id", &default_run_id)?;
+    let state_dir = PathBuf::from(prompt_line(
+        input,
+        out,
+        "State dir",
+        &cwd.join("runs").join(&run_id).display().to_string(),
+    )?);
+
+    let backend = loop {
+        let kind = prompt_line(
+            input,
+            out,
+            &format!("Backend kind ({})", INIT_BACKEND_KINDS.join("|")),
+            "codex",
+        )?;
+        if INIT_BACKEND_KINDS.contains(&kind.as_str()) {
+            break kind;
+        }
+        writeln!(out, "unknown backend kind '{kind}'")?;
+    };
+    let (model, thinking) = match backend.as_str() {
+        "mock" => (String::new(), String::new()),
+        kind => {
+            let (model, thinking) = match kind {
+                "codex" => ("gpt-5.3-codex", "xhigh"),
+                "claude" => ("claude-opus-4-6", "high"),
+                _ => ("", "high"),
+            };
+            let model = loop {
+                let model = prompt_line(input, out, "Backend model", model)?;
+                if !model.is_empty() {
+                    break model;
+                }
+            };
+            (
+                model,
+                prompt_line(input, out, "Backend thinking", thinking)?,
+            )
+        }
+    };
+
+    let team = if ask_team {
+        Some(prompt_line(
+            input,
+            out,
+            "Team (blank for the default roles)",
+            "",
+        )?)
+        .filter(|t| !t.is_empty())
+    } else {
+        None
+    };
+
+    let mut tasks: Vec<(String, PathBuf, Vec<String>)> = Vec::new();
+    loop {
+        let question = if tasks.is_empty() {
+            "Task id"
+        } else {
+            "Task id (blank to finish)"
+        };
+        let id = prompt_line(input, out, question, "")?;
+        if id.is_empty() {
+            if tasks.is_empty() {
+                continue;
+            }
+            break;
+        }
+        if tasks.iter().any(|(existing, _, _)| *existing == id) {
+            writeln!(out, "task '{id}' already exists")?;
+            continue;
+        }
+        let todo_file = PathBuf::from(prompt_line(
+            input,
+            out,
+            &format!("Todo file for {id}"),
+            &workspace
+                .join("todos")
+                .join(format!("{id}.md"))
+                .display()
+                .to_string(),
+        )?);
+        let previous = tasks
+            .last()
+            .map(|(prev, _, _)| prev.clone())
+            .unwrap_or_default();
+        let depends_on = prompt_line(
+            input,
+            out,
+            &format!("{id} depends on (comma-separated, '-' for none)"),
+            if previous.is_empty() { "-" } else { &previous },
+        )?;
+        let depends_on = depends_on
+            .split(',')
+            .map(str::trim)
+            .filter(|d| !d.is_empty() && *d != "-")
+            .map(str::to_string)
+            .collect();
+        tasks.push((id, todo_file, depends_on));
+    }
+
+    Ok(InitAnswers {
+        run_id,
+        workspace,
+        state_dir,
+        backend,
+        model,
+        thinking,
+        team,
+        tasks,
+    })
+}
+
+fn render_init_config(answers: &InitAnswers, roles: &RolesConfig) -> String {
+    let mut backend = format!("[backend]\nkind = {}\n", toml_string(&answers.backend));
+    if answers.backend != "mock" {
+        backend.push_str(&format!(
+            "model = {}\nthinking = {}\n",
+            toml_string(&answers.model),
+            toml_string(&answers.thinking)
+        ));
+    }
+    let mut content = format!(
+        r#"run_id = {run_id}
+workspace = {workspace}
+state_dir = {state_dir}
+unattended = true
+poll_interval_secs = 30
+
+[timeouts]
+stall_secs = 900
+
+[recovery]
+max_recovery_attempts_per_task = 4
+max_failures_before_block = 6
+backoff_initial_secs = 5
+backoff_max_secs = 120
+
+[policy]
+unattended_escalate = "best_effort_once"
+
+{backend}
+{implementer_role}
+{reviewer_1_role}
+{reviewer_2_role}"#,
+        run_id = toml_string(&answers.run_id),
+        workspace = toml_string(&answers.workspace.display().to_string()),
+        state_dir = toml_string(&answers.state_dir.display().to_string()),
+        implementer_role = render_role_block("implementer", &roles.implementer),
+        reviewer_1_role = render_role_block("reviewer_1", &roles.reviewer_1),
+        reviewer_2_role = render_role_block("reviewer_2", &roles.reviewer_2),
+    );
+    for (id, todo_file, depends_on) in &answers.tasks {
+        content.push_str(&format!(
+            "\n[[tasks]]\nid = {}\ntodo_file = {}\ndepends_on = {}\n",
+            toml_string(id),
+            toml_string(&todo_file.display().to_string()),
+            toml_array(depends_on)
+        ));
+    }
+    content
+}
+
+/// Reads `state.json` from a run's state directory, or `state.json.bak` if the
+/// primary is torn.
+/// Runs `args.config` with every backend replaced by the scripted mock and time
+/// simulated, so backoff, stall, and escalation policy play out in seconds. The
+/// run writes the usual journal, logs, and summary into a fresh state dir.
+/// Strips everything that reaches outside the simulation state dir: hooks,
+/// verify commands, git guards and task branches, state sync, cluster leases,
+/// and explicit coord dirs (which move under the state dir). Returns what was
+/// turned off.
+fn isolate_simulation(cfg: &mut Config) -> Vec<&'static str> {
+    let mut disabled = Vec::new();
+    let hooks = &cfg.hooks;
+    if !(hooks.pre_run.is_empty()
+        && hooks.post_run.is_empty()
+        && hooks.pre_task.is_empty()
+        && hooks.post_task.is_empty())
+    {
+        disabled.push("hooks");
+    }
+    cfg.hooks = HooksConfig::default();
+    let git = &cfg.git;
+    if git.snapshot_per_turn
+        || git.require_clean_start
+        || git.stash_on_start
+        || git.remove_merged_worktrees
+    {
+        disabled.push("[git]");
+    }
+    cfg.git = GitConfig::default();
+    if cfg.sync.s3_uri.is_some() || cfg.sync.rsync_target.is_some() {
+        disabled.push("[sync]");
+    }
+    cfg.sync = SyncConfig::default();
+    if cfg.cluster.take().is_some() {
+        disabled.push("[cluster]");
+    }
+    let mut task_settings = [
+        (false, "verify commands"),
+        (false, "task branches"),
+        (false, "coord_dir overrides"),
+    ];
+    for task in &mut cfg.tasks {
+        task_settings[0].0 |= !std::mem::take(&mut task.verify).is_empty();
+        task_settings[1].0 |= std::mem::take(&mut task.branch);
+        task_settings[2].0 |= task.coord_dir.take().is_some();
+    }
+    disabled.extend(
+        task_settings
+            .iter()
+            .filter(|(set, _)| *set)
+            .map(|(_, name)| *name),
+    );
+    disabled
+}
+
+fn simulate(args: &SimulateArgs) -> Result<()> {
+    let mut cfg = load_config(&args.config)?;
+    load_mock_scenario(&args.scenario)?;
+    let steps_per_task = match &cfg.backend.primary {
+        BackendConfig::Mock(mock) => mock.steps_per_task,
+        _ => default_mock_steps_per_task(),
+    };
+    cfg.backend = BackendSection::from(BackendConfig::Mock(MockBackendConfig {
+        steps_per_task,
+        scenario: Some(args.scenario.clone()),
+    }));
+    cfg.state_dir = match &args.state_dir {
+        Some(dir) => dir.clone(),
+        None => {
+            let name = cfg
+                .state_dir
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "crank".to_string());
+            cfg.state_dir.with_file_name(format!("{name}-simulation"))
+        }
+    };
+    if state_path(&cfg.state_dir).exists() {
+        return Err(anyhow!(
+            "simulation state dir {} already holds a run; remove it or pass --state-dir",
+            cfg.state_dir.display()
+        ));
+    }
+    let disabled = isolate_simulation(&mut cfg);
+    if !disabled.is_empty() {
+        println!("simulation skips: {}", disabled.join(", "));
+    }
+
+    let start = Utc::now();
+    let clock = Arc::new(SimulatedClock::starting_at(start));
+    let wall = Instant::now();
+    run_governor(
+        cfg.clone(),
+        GovernorOptions {
+            clock: Some(clock.clone()),
+            ..Default::default()
+        },
+    )?;
+    let state = read_state(&cfg.state_dir)?;
+    println!(
+        "simulated {}s of run time in {:.1}s: status {}, {} cycles",
+        (clock.now() - start).num_seconds(),
+        wall.elapsed().as_secs_f64(),
+        state.status.as_str(),
+        state.cycle
+    );
+    println!("summary: {}", run_summary_path(&cfg.state_dir).display());
+    println!("journal: {}", journal_path(&cfg.state_dir).display());
+    Ok(())
+}
+
+pub fn read_state(state_dir: &Path) -> Result<RunState> {
+    load_state_with_backup(state_dir).map(|(state, _)| state)
+}
+
+fn ctl_snapshot(state_dir: &Path) -> Result<()> {
+    let state = read_state(state_dir)?;
+    println!("{}", serde_json::to_string_pretty(&state)?);
+    Ok(())
+}
+
+fn ctl_can_exit(state_dir: &Path) -> Result<bool> {
+    let state = read_state(state_dir)?;
+    Ok(can_exit(&state))
+}
+
+fn ctl_note(state_dir: &Path, message: &str) -> Result<()> {
+    append_journal(&journal_path(state_dir), "operator note", message)
+}
+
+fn ctl_instruct(state_dir: &Path, task_id: &str, message: &str) -> Result<()> {
+    let state = read_state(state_dir)?;
+    let Some(task) = state.tasks.iter().find(|t| t.id == task_id) else {
+        return Err(anyhow!("unknown task '{task_id}'"));
+    };
+    if message.trim().is_empty() {
+        return Err(anyhow!("instruction message must not be empty"));
+    }
+    let dir = instructions_dir(state_dir, task_id);
+    ensure_dir(&dir)?;
+    let path = dir.join(format!("{}.md", clock_now().format("%Y%m%dT%H%M%S%.6f")));
+    fs::write(&path, format!("{}\n", message.trim()))
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    append_journal(
+        &journal_path(state_dir),
+        "operator instruction queued",
+        &format!("Task {task_id}: {}", message.trim()),
+    )?;
+    if task.status.is_terminal() {
+        println!(
+            "queued\t{} (task is {}; delivered if it runs again)",
+            path.display(),
+            task.status.as_str()
+        );
+    } else {
+        println!("queued\t{}", path.display());
+    }
+    Ok(())
+}
+
+fn ctl_kick(state_dir: &Path) -> Result<()> {
+    read_state(state_dir)?;
+    let path = kick_path(state_dir);
+    fs::write(&path, format!("{}\n", now_epoch()))
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    append_journal(
+        &journal_path(state_dir),
+        "operator kick",
+        "Requested an immediate governor cycle.",
+    )?;
+    let running = lock_pid(&state_dir.join("run.lock")).is_some_and(process_is_alive);
+    if running {
+        println!("kicked");
+    } else {
+        println!("kicked (no governor is running; the next `crank run` picks it up)");
+    }
+    Ok(())
+}
+
+struct JournalEntry {
+    at: String,
+    title: String,
+    body: String,
+}
+
+/// Splits JOURNAL.md into the `## <timestamp>` / `**<title>**` entries written by
+/// `append_journal`.
+fn parse_journal(text: &str) -> Vec<JournalEntry> {
+    let mut entries: Vec<JournalEntry> = Vec::new();
+    for line in text.lines() {
+        if let Some(at) = line.strip_prefix("## ") {
+            entries.push(JournalEntry {
+                at: at.trim().to_string(),
+                title: String::new(),
+                body: String::new(),
+            });
+        } else if let Some(entry) = entries.last_mut() {
+            if entry.title.is_empty()
+                && let Some(title) = line.strip_prefix("**").and_then(|l| l.strip_suffix("**"))
+            {
+                entry.title = title.to_string();
+            } else {
+                if !entry.body.is_empty() {
+                    entry.body.push('\n');
+                }
+                entry.body.push_str(line);
+            }
+        }
+    }
+    entries
+}
+
+fn iso_span_secs(start: &str, end: &str) -> Option<i64> {
+    let start = chrono::DateTime::parse_from_rfc3339(start).ok()?;
+    let end = chrono::DateTime::parse_from_rfc3339(end).ok()?;
+    Some((end - start).num_seconds().max(0))
+}
+
+fn format_span(secs: i64) -> String {
+    let (h, m, s) = (secs / 3600, secs % 3600 / 60, secs % 60);
+    if h > 0 {
+        format!("{h}h {m:02}m {s:02}s")
+    } else if m > 0 {
+        format!("{m}m {s:02}s")
+    } else {
+        format!("{s}s")
+    }
+}
+
+fn render_report(
+    state: &RunState,
+    summary: Option<&Value>,
+    journal: &[JournalEntry],
+    turns_logged: usize,
+) -> String {
+    let finished_at = summary
+        .and_then(|v| v.get("finished_at"))
+        .and_then(Value::as_str)
+        .unwrap_or(&state.updated_at);
+    let mut out = format!("# Run report: {}\n\n", state.run_id);
+    out.push_str(&format!("- Status: `{}`\n", state.status.as_str()));
+    if let Some(reason) = &state.stop_reason {
+        out.push_str(&format!("- Stop reason: {reason}\n"));
+    }
+    out.push_str(&format!("- Workspace: `{}`\n", state.workspace));
+    out.push_str(&format!("- Started: {}\n", state.started_at));
+    out.push_str(&format!("- Last update: {finished_at}\n"));
+    if let Some(secs) = iso_span_secs(&state.started_at, finished_at) {
+        out.push_str(&format!("- Duration: {}\n", format_span(secs)));
+    }
+    out.push_str(&format!("- Cycles: {}\n", state.cycle));
+    out.push_str(&format!("- Turns logged: {turns_logged}\n"));
+    if state.cost_usd > 0.0 {
+        out.push_str(&format!("- Cost: ${:.2}\n", state.cost_usd));
+    }
+    out.push_str(&format!("- Unattended: {}\n", state.unattended));
+
+    out.push_str("\n## Tasks\n\n| Task | Status | Turns | Started | Completed | Duration |\n");
+    out.push_str("|---|---|---|---|---|---|\n");
+    for task in &state.tasks {
+        let duration = match (&task.started_at, &task.completed_at) {
+            (Some(start), Some(end)) => iso_span_secs(start, end).map(format_span),
+            _ => None,
+        };
+        out.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {} |\n",
+            task.id,
+            task.status.as_str(),
+            task.turns,
+            task.started_at.as_deref().unwrap_or("-"),
+            task.completed_at.as_deref().unwrap_or("-"),
+            duration.as_deref().unwrap_or("-"),
+        ));
+    }
+
+    for (heading, status) in [
+        ("Blockers", TaskStatus::BlockedBestEffort),
+        ("Cancelled", TaskStatus::Cancelled),
+    ] {
+        let tasks: Vec<_> = state.tasks.iter().filter(|t| t.status == status).collect();
+        if tasks.is_empty() {
+            continue;
+        }
+        out.push_str(&format!("\n## {heading}\n\n"));
+        for task in tasks {
+            out.push_str(&format!(
+                "- **{}**: {}\n",
+                task.id,
+                task.blocked_reason.as_deref().unwrap_or("-")
+            ));
+        }
+    }
+
+    // Per-turn control entries are summarized by the task table above.
+    let timeline: Vec<_> = journal
+        .iter()
+        .filter(|e| e.title != "turn control")
+        .collect();
+    if !timeline.is_empty() {
+        out.push_str("\n## Timeline\n\n");
+        for entry in timeline {
+            let first = entry
+                .body
+                .lines()
+                .find(|l| !l.trim().is_empty())
+                .unwrap_or("");
+            let first: String = first.chars().take(160).collect();
+            out.push_str(&format!(
+                "- {} **{}** {}\n",
+                entry.at,
+                entry.title,
+                first.trim()
+            ));
+        }
+    }
+    out
+}
+
+fn ctl_report(state_dir: &Path, out: Option<&Path>) -> Result<()> {
+    let state = read_state(state_dir)?;
+    let summary = fs::read(run_summary_path(state_dir))
+        .ok()
+        .and_then(|bytes| serde_json::from_slice::<Value>(&bytes).ok());
+    let journal = fs::read_to_string(journal_path(state_dir)).unwrap_or_default();
+    let turns_logged = fs::read_to_string(turns_log_path(state_dir))
+        .unwrap_or_default()
+        .matches("\n===== TURN ")
+        .count();
+    let report = render_report(
+        &state,
+        summary.as_ref(),
+        &parse_journal(&journal),
+        turns_logged,
+    );
+    match out {
+        Some(path) => {
+            fs::write(path, report).with_context(|| format!("failed to write {}", path.display()))
+        }
+        None => {
+            print!("{report}");
+            Ok(())
+        }
+    }
+}
+
+/// Per-task outcome shared by the `ctl summary` formats.
+#[derive(Serialize)]
+struct TaskSummaryRow {
+    id: String,
+    status: &'static str,
+    turns: u32,
+    started_at: Option<String>,
+    completed_at: Option<String>,
+    duration_secs: Option<i64>,
+    reason: Option<String>,
+}
+
+fn task_summary_rows(state: &RunState) -> Vec<TaskSummaryRow> {
+    state
+        .tasks
+        .iter()
+        .map(|task| TaskSummaryRow {
+            id: task.id.clone(),
+            status: task.status.as_str(),
+            turns: task.turns,
+            started_at: task.started_at.clone(),
+            completed_at: task.completed_at.clone(),
+            duration_secs: match (&task.started_at, &task.completed_at) {
+                (Some(start), Some(end)) => iso_span_secs(start, end),
+                _ => None,
+            },
+            reason: task.blocked_reason.clone(),
+        })
+        .collect()
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// Renders `run-summary.json` (when the run has written one) plus per-task
+/// timings from `state` in the requested format.
+fn render_summary(
+    state: &RunState,
+    summary: Option<&Value>,
+    format: SummaryFormat,
+) -> Result<String> {
+    let rows = task_summary_rows(state);
+    match format {
+        SummaryFormat::Json => {
+            let mut out = summary.cloned().unwrap_or_else(|| {
+                serde_json::json!({
+                    "run_id": state.run_id,
+                    "status": state.status.as_str(),
+                    "cycle": state.cycle,
+                    "started_at": state.started_at,
+                })
+            });
+            out["tasks"] = serde_json::to_value(&rows)?;
+            Ok(format!("{}\n", serde_json::to_string_pretty(&out)?))
+        }
+        SummaryFormat::Junit => {
+            let count =
+                |statuses: &[&str]| rows.iter().filter(|r| statuses.contains(&r.status)).count();
+            let total_secs: i64 = rows.iter().filter_map(|r| r.duration_secs).sum();
+            let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+            out.push_str(&format!(
+                "<testsuite name=\"crank.{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{}\">\n",
+                xml_escape(&state.run_id),
+                rows.len(),
+                count(&["blocked_best_effort"]),
+                count(&["pending", "running"]),
+                count(&["skipped", "cancelled"]),
+                total_secs
+            ));
+            for row in &rows {
+                out.push_str(&format!(
+                    "  <testcase classname=\"crank.{}\" name=\"{}\" time=\"{}\"",
+                    xml_escape(&state.run_id),
+                    xml_escape(&row.id),
+                    row.duration_secs.unwrap_or(0)
+                ));
+                let reason = xml_escape(row.reason.as_deref().unwrap_or(row.status));
+                match row.status {
+                    "completed" => out.push_str("/>\n"),
+                    "blocked_best_effort" => out.push_str(&format!(
+                        ">\n    <failure message=\"{reason}\"/>\n  </testcase>\n"
+                    )),
+                    "skipped" | "cancelled" => out.push_str(&format!(
+                        ">\n    <skipped message=\"{}: {reason}\"/>\n  </testcase>\n",
+                        row.status
+                    )),
+                    status => out.push_str(&format!(
+                        ">\n    <error message=\"task did not finish ({status})\"/>\n  </testcase>\n"
+                    )),
+                }
+            }
+            out.push_str("</testsuite>\n");
+            Ok(out)
+        }
+        SummaryFormat::Html => {
+            let mut out = format!(
+                "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>crank run {id}</title></head>\n<body>\n<h1>crank run {id}</h1>\n<p>Status: {status}, cycles: {cycle}</p>\n<table>\n<tr><th>Task</th><th>Status</th><th>Turns</th><th>Started</th><th>Completed</th><th>Duration</th><th>Reason</th></tr>\n",
+                id = xml_escape(&state.run_id),
+                status = state.status.as_str(),
+                cycle = state.cycle
+            );
+            for row in &rows {
+                out.push_str(&format!(
+                    "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                    xml_escape(&row.id),
+                    row.status,
+                    row.turns,
+                    xml_escape(row.started_at.as_deref().unwrap_or("-")),
+                    xml_escape(row.completed_at.as_deref().unwrap_or("-")),
+                    row.duration_secs.map(format_span).unwrap_or_else(|| "-".to_string()),
+                    xml_escape(row.reason.as_deref().unwrap_or(""))
+                ));
+            }
+            out.push_str("</table>\n</body>\n</html>\n");
+            Ok(out)
+        }
+        SummaryFormat::Csv => {
+            let mut out =
+                String::from("id,status,turns,started_at,completed_at,duration_secs,reason\n");
+            for row in &rows {
+                out.push_str(&format!(
+                    "{},{},{},{},{},{},{}\n",
+                    csv_field(&row.id),
+                    row.status,
+                    row.turns,
+                    row.started_at.as_deref().unwrap_or(""),
+                    row.completed_at.as_deref().unwrap_or(""),
+                    row.duration_secs.map(|d| d.to_string()).unwrap_or_default(),
+                    csv_field(row.reason.as_deref().unwrap_or(""))
+                ));
+            }
+            Ok(out)
+        }
+    }
+}
+
+fn ctl_summary(state_dir: &Path, format: SummaryFormat, out: Option<&Path>) -> Result<()> {
+    let state = read_state(state_dir)?;
+    let summary = fs::read(run_summary_path(state_dir))
+        .ok()
+        .and_then(|bytes| serde_json::from_slice::<Value>(&bytes).ok());
+    let rendered = render_summary(&state, summary.as_ref(), format)?;
+    match out {
+        Some(path) => {
+            fs::write(path, rendered).with_context(|| format!("failed to write {}", path.display()))
+        }
+        None => {
+            print!("{rendered}");
+            Ok(())
+        }
+    }
+}
+
+/// Inclusive cycle bounds for `ctl grep`; open ends are unbounded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct CycleRange {
+    start: Option<u64>,
+    end: Option<u64>,
+}
+
+impl CycleRange {
+    fn contains(self, cycle: u64) -> bool {
+        self.start.is_none_or(|s| cycle >= s) && self.end.is_none_or(|e| cycle <= e)
+    }
+}
+
+fn parse_cycle_range(raw: &str) -> Result<CycleRange, String> {
+    let bound = |s: &str| -> Result<Option<u64>, String> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Ok(None);
+        }
+        s.parse()
+            .map(Some)
+            .map_err(|_| format!("invalid cycle '{s}' in range '{raw}'"))
+    };
+    match raw.split_once("..") {
+        Some((start, end)) => Ok(CycleRange {
+            start: bound(start)?,
+            end: bound(end)?,
+        }),
+        None => {
+            let cycle = bound(raw)?;
+            Ok(CycleRange {
+                start: cycle,
+                end: cycle,
+            })
+        }
+    }
+}
+
+/// A `ctl grep` hit. `offset` is the byte offset of the match within `source`.
+#[derive(Debug, Serialize, PartialEq)]
+struct TurnMatch {
+    cycle: u64,
+    /// Unknown for turns only found in the combined turns log.
+    task: Option<String>,
+    section: &'static str,
+    source: String,
+    offset: u64,
+    snippet: String,
+}
+
+const GREP_SNIPPET_CHARS: usize = 160;
+
+/// Up to `GREP_SNIPPET_CHARS` of `line` centred on the match at `start..end`.
+fn grep_snippet(line: &str, start: usize, end: usize) -> String {
+    let budget = GREP_SNIPPET_CHARS.saturating_sub(line[start..end].chars().count()) / 2;
+    let before: String = {
+        let chars: Vec<char> = line[..start].chars().collect();
+        chars[chars.len().saturating_sub(budget)..].iter().collect()
+    };
+    let after: String = line[end..].chars().take(budget).collect();
+    let mut snippet = String::new();
+    if before.len() < start {
+        snippet.push('…');
+    }
+    snippet.push_str(&before);
+    snippet.push_str(&line[start..end]);
+    snippet.push_str(&after);
+    if after.len() < line.len() - end {
+        snippet.push('…');
+    }
+    snippet.trim().to_string()
+}
+
+/// Scans `path` line by line, tracking the section via `section_of` (which
+/// returns a new section for header lines), and records every match.
+fn grep_file(
+    path: &Path,
+    regex: &regex::Regex,
+    mut section_of: impl FnMut(&str) -> Option<(Option<u64>, &'static str)>,
+    mut emit: impl FnMut(Option<u64>, &'static str, u64, String),
+) -> Result<()> {
+    let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let mut reader = BufReader::new(file);
+    let mut offset = 0u64;
+    let mut cycle = None;
+    let mut section = "header";
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        let read = reader.read_until(b'\n', &mut buf)?;
+        if read == 0 {
+            return Ok(());
+        }
+        let line = String::from_utf8_lossy(&buf);
+        let line = line.trim_end_matches(['\n', '\r']);
+        if let Some((next_cycle, next_section)) = section_of(line) {
+            cycle = next_cycle.or(cycle);
+            section = next_section;
+        } else {
+            for m in regex.find_iter(line) {
+                emit(
+                    cycle,
+                    section,
+                    offset + m.start() as u64,
+                    grep_snippet(line, m.start(), m.end()),
+                );
+            }
+        }
+        offset += read as u64;
+    }
+}
+
+/// Searches `turns/*.md` archives, then the combined turns log for cycles with
+/// no archive (older runs, or archives removed to save space).
+fn grep_turns(
+    state_dir: &Path,
+    regex: &regex::Regex,
+    task: Option<&str>,
+    range: CycleRange,
+) -> Result<Vec<TurnMatch>> {
+    let mut archives: Vec<(u64, String, PathBuf)> = fs::read_dir(turns_archive_dir(state_dir))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let name = path.file_name()?.to_str()?.strip_suffix(".md")?.to_string();
+            let (cycle, rest) = name.split_once('-')?;
+            let task = rest.strip_suffix("-reprompt").unwrap_or(rest).to_string();
+            Some((cycle.parse().ok()?, task, path))
+        })
+        .collect();
+    archives.sort();
+    let archived: std::collections::BTreeSet<u64> = archives.iter().map(|(c, _, _)| *c).collect();
+
+    let mut matches = Vec::new();
+    for (cycle, archive_task, path) in &archives {
+        if !range.contains(*cycle) || task.is_some_and(|t| t != archive_task) {
+            continue;
+        }
+        let source = path.display().to_string();
+        grep_file(
+            path,
+            regex,
+            |line| match line {
+                "## Prompt" => Some((None, "prompt")),
+                "## Response" => Some((None, "response")),
+                _ => None,
+            },
+            |_, section, offset, snippet| {
+                matches.push(TurnMatch {
+                    cycle: *cycle,
+                    task: Some(archive_task.clone()),
+                    section,
+                    source: source.clone(),
+                    offset,
+                    snippet,
+                })
+            },
+        )?;
+    }
+
+    let log = turns_log_path(state_dir);
+    if task.is_none() && log.exists() {
+        let source = log.display().to_string();
+        grep_file(
+            &log,
+            regex,
+            |line| {
+                if let Some(rest) = line.strip_prefix("===== TURN ") {
+                    let cycle = rest.split_whitespace().next()?.parse().ok()?;
+                    Some((Some(cycle), "header"))
+                } else {
+                    match line {
+                        "--- PROMPT ---" => Some((None, "prompt")),
+                        "--- RESPONSE ---" => Some((None, "response")),
+                        _ => None,
+                    }
+                }
+            },
+            |cycle, section, offset, snippet| {
+                if let Some(cycle) = cycle
+                    && range.contains(cycle)
+                    && !archived.contains(&cycle)
+                {
+                    matches.push(TurnMatch {
+                        cycle,
+                        task: None,
+                        section,
+                        source: source.clone(),
+                        offset,
+                        snippet,
+                    });
+                }
+            },
+        )?;
+        matches.sort_by_key(|m| m.cycle);
+    }
+    Ok(matches)
+}
+
+fn ctl_grep(
+    state_dir: &Path,
+    pattern: &str,
+    task: Option<&str>,
+    range: CycleRange,
+    json: bool,
+) -> Result<()> {
+    let regex =
+        regex::Regex::new(pattern).with_context(|| format!("invalid pattern '{pattern}'"))?;
+    for m in grep_turns(state_dir, &regex, task, range)? {
+        if json {
+            println!("{}", serde_json::to_string(&m)?);
+        } else {
+            println!(
+                "cycle {}\t{}\t{}\t{}:{}\t{}",
+                m.cycle,
+                m.task.as_deref().unwrap_or("-"),
+                m.section,
+                m.source,
+                m.offset,
+                m.snippet
+            );
+        }
+    }
+    Ok(())
+}
+
+/// One `ctl status` row. `error` is set (and the rest left empty) when the
+/// state dir holds a `state.json` that could not be read.
+#[derive(Debug, Serialize, PartialEq)]
+struct RunOverview {
+    state_dir: String,
+    run_id: Option<String>,
+    status: Option<&'static str>,
+    cycle: Option<u64>,
+    blocked: Option<usize>,
+    /// Seconds since the newer of `updated_at` and the governor heartbeat.
+    last_update_age_secs: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    live_turn: Option<LiveTurn>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+fn run_overview(state_dir: &Path, now: i64) -> RunOverview {
+    let mut row = RunOverview {
+        state_dir: state_dir.display().to_string(),
+        run_id: None,
+        status: None,
+        cycle: None,
+        blocked: None,
+        last_update_age_secs: None,
+        live_turn: None,
+        error: None,
+    };
+    match read_state(state_dir) {
+        Ok(state) => {
+            let heartbeat = read_governor_heartbeat(state_dir);
+            row.last_update_age_secs = last_governor_activity_epoch(&state, heartbeat)
+                .map(|last| now.saturating_sub(last).max(0));
+            row.blocked = Some(
+                state
+                    .tasks
+                    .iter()
+                    .filter(|t| t.status == TaskStatus::BlockedBestEffort)
+                    .count(),
+            );
+            row.status = Some(state.status.as_str());
+            row.cycle = Some(state.cycle);
+            row.live_turn = state.live_turn;
+            row.run_id = Some(state.run_id);
+        }
+        Err(err) => row.error = Some(format!("{err:#}")),
+    }
+    row
+}
+
+/// Every state dir (a directory holding `state.json`) under `runs_dir`, sorted.
+/// Does not descend into a state dir once found, so worktrees and archives
+/// inside a run are never walked.
+fn find_state_dirs(runs_dir: &Path) -> Result<Vec<PathBuf>> {
+    fn walk(dir: &Path, found: &mut Vec<PathBuf>) -> Result<()> {
+        if state_path(dir).is_file() {
+            found.push(dir.to_path_buf());
+            return Ok(());
+        }
+        let entries =
+            fs::read_dir(dir).with_context(|| format!("failed to read {}", dir.display()))?;
+        for entry in entries {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                walk(&entry.path(), found)?;
+            }
+        }
+        Ok(())
+    }
+
+    if !runs_dir.is_dir() {
+        return Err(anyhow!("runs dir {} does not exist", runs_dir.display()));
+    }
+    let mut found = Vec::new();
+    walk(runs_dir, &mut found)?;
+    found.sort();
+    Ok(found)
+}
+
+fn render_run_overview(row: &RunOverview) -> String {
+    if let Some(err) = &row.error {
+        return format!("{}\terror: {}", row.state_dir, err);
+    }
+    let mut line = format!(
+        "{}\t{}\t{}\tcycle {}\tblocked {}\tupdated {}",
+        row.state_dir,
+        row.run_id.as_deref().unwrap_or("-"),
+        row.status.unwrap_or("-"),
+        row.cycle.unwrap_or(0),
+        row.blocked.unwrap_or(0),
+        row.last_update_age_secs
+            .map_or_else(|| "unknown".to_string(), |age| format!("{age}s ago"))
+    );
+    if let Some(turn) = &row.live_turn {
+        line.push_str(&format!(
+            "\tturn {} since {}: {} events, {} bytes, last {}",
+            turn.task_id,
+            turn.started_at,
+            turn.events,
+            turn.bytes,
+            turn.last_event_type.as_deref().unwrap_or("-")
+        ));
+    }
+    line
+}
+
+fn ctl_status(state_dir: Option<&Path>, runs_dir: Option<&Path>, json: bool) -> Result<()> {
+    let dirs = match (state_dir, runs_dir) {
+        (Some(dir), _) => vec![dir.to_path_buf()],
+        (None, Some(runs_dir)) => find_state_dirs(runs_dir)?,
+        (None, None) => return Err(anyhow!("pass --state-dir or --all")),
+    };
+    let now = now_epoch();
+    let rows: Vec<RunOverview> = dirs.iter().map(|dir| run_overview(dir, now)).collect();
+    if json {
+        println!("{}", serde_json::to_string_pretty(&rows)?);
+    } else if rows.is_empty() {
+        println!(
+            "no runs under {}",
+            runs_dir.unwrap_or(Path::new(".")).display()
+        );
+    } else {
+        for row in &rows {
+            println!("{}", render_run_overview(row));
+        }
+    }
+    Ok(())
+}
+
+/// One line per artifact: task, producing cycle, size, sha256, and name.
+fn render_artifacts(state: &RunState, task_id: Option<&str>) -> Result<String> {
+    if let Some(id) = task_id
+        && !state.tasks.iter().any(|t| t.id == id)
+    {
+        return Err(anyhow!("unknown task '{id}'"));
+    }
+    let mut out = String::new();
+    for task in state
+        .tasks
+        .iter()
+        .filter(|t| task_id.is_none_or(|id| t.id == id))
+    {
+        for artifact in &task.artifacts {
+            out.push_str(&format!(
+                "{}\tcycle {}\t{} bytes\t{}\t{}\n",
+                task.id, artifact.cycle, artifact.size, artifact.sha256, artifact.name
+            ));
+        }
+    }
+    Ok(out)
+}
+
+fn ctl_artifacts(state_dir: &Path, task_id: Option<&str>) -> Result<()> {
+    let state = read_state(state_dir)?;
+    let listing = render_artifacts(&state, task_id)?;
+    if listing.is_empty() {
+        println!("no artifacts indexed");
+    } else {
+        print!("{listing}");
+    }
+    Ok(())
+}
+
+/// Operator change to a single task, applied by `ctl edit-task` directly when no
+/// governor holds the run lock, or queued under `state_dir/edits` for the
+/// running governor to apply between turns.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TaskEdit {
+    task_id: String,
+    #[serde(default)]
+    status: Option<TaskStatus>,
+    #[serde(default)]
+    depends_on: Option<Vec<String>>,
+    /// An empty string clears the completion file.
+    #[serde(default)]
+    completion_file: Option<String>,
+    /// Recorded as the task's reason when the edit cancels it.
+    #[serde(default)]
+    reason: Option<String>,
+    /// Set by `ctl add-task`: `task_id` is a new task, not an existing one.
+    #[serde(default)]
+    add: Option<NewTask>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NewTask {
+    todo_file: String,
+    #[serde(default)]
+    priority: i32,
+}
+
+impl TaskEdit {
+    fn journal_title(&self) -> &'static str {
+        if self.add.is_some() {
+            "task added"
+        } else {
+            "task edited"
+        }
+    }
+}
+
+fn task_edits_dir(state_dir: &Path) -> PathBuf {
+    state_dir.join("edits")
+}
+
+/// Applies an edit to a copy of the state and only commits it when the result
+/// is valid. Returns a description of the change.
+fn apply_task_edit(state: &mut RunState, edit: &TaskEdit) -> Result<String> {
+    if let Some(new) = &edit.add {
+        return apply_task_addition(state, edit, new);
+    }
+    let idx = state
+        .tasks
+        .iter()
+        .position(|t| t.id == edit.task_id)
+        .ok_or_else(|| anyhow!("unknown task id '{}'", edit.task_id))?;
+    let mut next = state.clone();
+    let mut changes = Vec::new();
+    let task = &mut next.tasks[idx];
+
+    if let Some(deps) = &edit.depends_on {
+        let deps: Vec<String> = deps.iter().filter(|d| !d.is_empty()).cloned().collect();
+        changes.push(format!(
+            "depends_on [{}] -> [{}]",
+            task.depends_on.join(", "),
+            deps.join(", ")
+        ));
+        task.depends_on = deps;
+    }
+    if let Some(file) = &edit.completion_file {
+        let file = (!file.is_empty()).then(|| file.clone());
+        changes.push(format!(
+            "completion_file {} -> {}",
+            task.completion_file.as_deref().unwrap_or("none"),
+            file.as_deref().unwrap_or("none")
+        ));
+        task.completion_file = file;
+    }
+    if let Some(status) = &edit.status {
+        changes.push(format!(
+            "status {} -> {}",
+            task.status.as_str(),
+            status.as_str()
+        ));
+        match status {
+            TaskStatus::Running => {
+                return Err(anyhow!(
+                    "task '{}' cannot be set to running; only the governor starts tasks (use pending)",
+                    edit.task_id
+                ));
+            }
+            TaskStatus::Pending => {
+                task.status = status.clone();
+                task.completed_at = None;
+                task.blocked_reason = None;
+                task.last_progress_epoch = Some(now_epoch());
+            }
+            TaskStatus::BlockedBestEffort => mark_task_blocked(task, "blocked by operator edit"),
+            TaskStatus::Completed | TaskStatus::Skipped => {
+                task.status = status.clone();
+                task.completed_at = Some(now_iso());
+                task.blocked_reason = None;
+            }
+            TaskStatus::Cancelled => {
+                task.status = TaskStatus::Cancelled;
+                task.completed_at = Some(now_iso());
+                task.blocked_reason = Some(
+                    edit.reason
+                        .clone()
+                        .unwrap_or_else(|| "cancelled by operator".to_string()),
+                );
+            }
+        }
+    }
+    if changes.is_empty() {
+        return Err(anyhow!("no changes requested for task '{}'", edit.task_id));
+    }
+
+    validate_edited_state(&next, &edit.task_id)?;
+    if !next.tasks.iter().all(|t| t.status.is_terminal()) {
+        next.status = RunStatus::Running;
+    }
+    *state = next;
+    Ok(format!("task {}: {}", edit.task_id, changes.join("; ")))
+}
+
+/// Appends the pending task described by an add edit, reopening a finished run.
+fn apply_task_addition(state: &mut RunState, edit: &TaskEdit, new: &NewTask) -> Result<String> {
+    if edit.task_id.trim().is_empty() {
+        return Err(anyhow!("task id must not be empty"));
+    }
+    if state.tasks.iter().any(|t| t.id == edit.task_id) {
+        return Err(anyhow!("task id '{}' already exists", edit.task_id));
+    }
+    let depends_on: Vec<String> = edit
+        .depends_on
+        .iter()
+        .flatten()
+        .filter(|d| !d.is_empty())
+        .cloned()
+        .collect();
+    let task = TaskConfig {
+        id: edit.task_id.clone(),
+        todo_file: PathBuf::from(&new.todo_file),
+        depends_on: depends_on.clone(),
+        completion_file: edit
+            .completion_file
+            .as_ref()
+            .filter(|f| !f.is_empty())
+            .map(PathBuf::from),
+        priority: new.priority,
+        ..Default::default()
+    };
+    let mut next = state.clone();
+    next.tasks
+        .push(task_runtime_from_config(Path::new(&state.state_dir), &task));
+    validate_edited_state(&next, &edit.task_id)?;
+    next.status = RunStatus::Running;
+    *state = next;
+    Ok(format!(
+        "task {}: todo_file {}, depends_on [{}]",
+        edit.task_id,
+        new.todo_file,
+        depends_on.join(", ")
+    ))
+}
+
+fn validate_edited_state(next: &RunState, task_id: &str) -> Result<()> {
+    let graph: Vec<TaskConfig> = next
+        .tasks
+        .iter()
+        .map(|t| TaskConfig {
+            id: t.id.clone(),
+            depends_on: t.depends_on.clone(),
+            ..Default::default()
+        })
+        .collect();
+    let mut problems = config_graph_problems(&graph);
+    if next
+        .tasks
+        .iter()
+        .filter(|t| t.status == TaskStatus::Running)
+        .count()
+        > 1
+    {
+        problems.push("only one task may be running at a time".to_string());
+    }
+    if !problems.is_empty() {
+        return Err(anyhow!(
+            "edit to task '{task_id}' rejected: {}",
+            problems.join("; ")
+        ));
+    }
+    Ok(())
+}
+
+/// Applies edits queued by `ctl edit-task` in file-name order, journaling each.
+/// Rejected edits are kept as `<name>.rejected`.
+fn apply_queued_task_edits(state: &mut RunState, state_dir: &Path, journal: &Path) -> Result<()> {
+    let dir = task_edits_dir(state_dir);
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return Ok(());
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+    for path in paths {
+        let outcome = fs::read(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|bytes| Ok(serde_json::from_slice::<TaskEdit>(&bytes)?))
+            .and_then(|edit| Ok((edit.journal_title(), apply_task_edit(state, &edit)?)));
+        match outcome {
+            Ok((title, change)) => {
+                append_journal(journal, title, &change)?;
+                fs::remove_file(&path)
+                    .with_context(|| format!("failed to remove {}", path.display()))?;
+            }
+            Err(err) => {
+                append_journal(journal, "task edit rejected", &format!("{err:#}"))?;
+                fs::rename(&path, path.with_extension("json.rejected"))
+                    .with_context(|| format!("failed to set aside {}", path.display()))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn ctl_edit_task(state_dir: &Path, edit: TaskEdit) -> Result<()> {
+    match LockGuard::acquire(state_dir) {
+        Ok(_lock) => {
+            let mut state = read_state(state_dir)?;
+            let change = apply_task_edit(&mut state, &edit)?;
+            save_state(&mut state, state_dir)?;
+            append_journal(&journal_path(state_dir), edit.journal_title(), &change)?;
+            println!("ok\t{change}");
+        }
+        Err(err) => {
+            // A live governor owns state.json; hand the edit to it. Any other
+            // lock failure (permissions, a half-written lock) is the caller's.
+            let live = lock_pid(&state_dir.join("run.lock")).is_some_and(process_is_alive);
+            if !live {
+                return Err(err);
+            }
+            let mut state = read_state(state_dir)?;
+            apply_task_edit(&mut state, &edit)?;
+            let dir = task_edits_dir(state_dir);
+            ensure_dir(&dir)?;
+            let path = dir.join(format!(
+                "{}-{}.json",
+                clock_now().format("%Y%m%dT%H%M%S%.6f"),
+                edit.task_id
+            ));
+            write_json_atomic(&path, &edit)?;
+            println!(
+                "queued\t{} (the running governor applies it before its next turn)",
+                path.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+fn ctl_answer(state_dir: &Path, task_id: &str, message: &str, question: Option<u32>) -> Result<()> {
+    let dir = escalations_dir(state_dir, task_id);
+    let numbers = escalation_numbers(&dir);
+    let answered = |n: u32| {
+        escalation_answer_path(&dir, n).exists() || escalation_delivered_path(&dir, n).exists()
+    };
+    let n = match question {
+        // The first answer stands: the agent may already have acted on it.
+        Some(n) if numbers.contains(&n) && answered(n) => {
+            return Err(anyhow!(
+                "escalation {n} for task '{task_id}' is already answered; send follow-ups with `crank ctl instruct`"
+            ));
+        }
+        Some(n) if numbers.contains(&n) => n,
+        Some(n) => return Err(anyhow!("task '{task_id}' has no escalation {n}")),
+        None => numbers
+            .iter()
+            .rev()
+            .copied()
+            .find(|n| !answered(*n))
+            .ok_or_else(|| anyhow!("task '{task_id}' has no unanswered escalation"))?,
+    };
+    let path = escalation_answer_path(&dir, n);
+    fs::write(&path, format!("{}\n", message.trim()))
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    append_journal(
+        &journal_path(state_dir),
+        "operator answer",
+        &format!("Answered escalation {n} for task {task_id}: {message}"),
+    )?;
+    println!("queued answer {}", path.display());
+    Ok(())
+}
+
+fn read_governor_heartbeat(state_dir: &Path) -> Option<i64> {
+    let text = fs::read_to_string(governor_heartbeat_path(state_dir)).ok()?;
+    text.trim().parse::<i64>().ok()
+}
+
+/// Latest sign of life from the governor: the newer of state.json `updated_at`
+/// and the governor heartbeat file.
+fn last_governor_activity_epoch(state: &RunState, heartbeat: Option<i64>) -> Option<i64> {
+    let updated = chrono::DateTime::parse_from_rfc3339(&state.updated_at)
+        .ok()
+        .map(|ts| ts.timestamp());
+    match (updated, heartbeat) {
+        (Some(a), Some(b)) => Some(a.max(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+struct WatchdogArgs<'a> {
+    state_dir: &'a Path,
+    stall_secs: u64,
+    exec: &'a str,
+    poll_secs: u64,
+    once: bool,
+}
+
+fn ctl_watchdog(args: WatchdogArgs<'_>) -> Result<()> {
+    let journal = journal_path(args.state_dir);
+    // Activity epoch the command last fired for; re-armed once the governor moves past it.
+    let mut fired_for: Option<i64> = None;
+
+    loop {
+        match read_state(args.state_dir) {
+            Ok(state) if state.status != RunStatus::Running => {
+                println!(
+                    "run {} is {:?}; watchdog exiting",
+                    state.run_id, state.status
+                );
+                return Ok(());
+            }
+            Ok(state) => {
+                let heartbeat = read_governor_heartbeat(args.state_dir);
+                let last = last_governor_activity_epoch(&state, heartbeat).unwrap_or(0);
+                let age = now_epoch().saturating_sub(last);
+                if age > args.stall_secs as i64 && fired_for != Some(last) {
+                    fired_for = Some(last);
+                    let status = Command::new("sh")
+                        .arg("-c")
+                        .arg(args.exec)
+                        .env("CRANK_RUN_ID", &state.run_id)
+                        .env("CRANK_STATE_DIR", args.state_dir)
+                        .env("CRANK_STALE_SECS", age.to_string())
+                        .status()
+                        .with_context(|| {
+                            format!("failed to spawn watchdog command: {}", args.exec)
+                        });
+                    let outcome = match &status {
+                        Ok(status) => status.to_string(),
+                        Err(err) => format!("{err:#}"),
+                    };
+                    println!("watchdog fired after {age}s stale: {outcome}");
+                    append_journal(
+                        &journal,
+                        "watchdog fired",
+                        &format!(
+                            "No governor activity for {}s (threshold {}s). Ran `{}`: {}",
+                            age, args.stall_secs, args.exec, outcome
+                        ),
+                    )?;
+                    if args.once {
+                        let status = status?;
+                        if !status.success() {
+                            return Err(anyhow!(
+                                "watchdog command `{}` failed: {status}",
+                                args.exec
+                            ));
+                        }
+                        return Ok(());
+                    }
+                }
+            }
+            Err(err) => eprintln!("watchdog: {err:#}"),
+        }
+        clock_sleep(Duration::from_secs(args.poll_secs.max(1)));
+    }
+}
+
+fn resolve_team_roles(
+    team: Option<&str>,
+    team_file: Option<&Path>,
+    teams_dir: &Path,
+) -> Result<Option<RolesConfig>> {
+    if team.is_some() && team_file.is_some() {
+        return Err(anyhow!("use either --team or --team-file, not both"));
+    }
+
+    if let Some(path) = team_file {
+        let loaded = load_team_from_file(path)?;
+        return Ok(Some(loaded.roles));
+    }
+
+    if let Some(name) = team {
+        let loaded = load_team(teams_dir, name)?;
+        return Ok(Some(loaded.roles));
+    }
+
+    Ok(None)
+}
+
+/// Parses `std::env::args` and runs the matching `crank` subcommand.
+pub fn run_cli() -> Result<()> {
+    let cli = Cli::parse();
+
+    match cli.command {
+        Commands::Run(args) => {
+            let mut cfg = load_config(&args.config)?;
+            if let Some(team_roles) = resolve_team_roles(
+                args.team.as_deref(),
+                args.team_file.as_deref(),
+                &args.teams_dir,
+            )? {
+                cfg.roles = team_roles;
+            }
+            validate_roles(&cfg.roles).with_context(|| {
+                format!(
+                    "invalid roles for run config {} (codex requires '{}' and claude requires '{}')",
+                    args.config.display(),
+                    REQUIRED_CODEX_ARG,
+                    REQUIRED_CLAUDE_ARG
+                )
+            })?;
+            run_governor(
+                cfg,
+                GovernorOptions {
+                    metrics_port: args.metrics_port,
+                    metrics_addr: Some(args.metrics_addr),
+                    preflight: !args.skip_preflight,
+                    watch_config: args.watch_config.then(|| args.config.clone()),
+                    clock: None,
+                    mux: args.mux,
+                    backend_factory: None,
+                },
+            )
+        }
+        Commands::Simulate(args) => simulate(&args),
+        Commands::Init(args) => {
+            let answers = if args.interactive {
+                let ask_team = args.team.is_none() && args.team_file.is_none();
+                let stdin = std::io::stdin();
+                let mut stdout = std::io::stdout();
+                Some(prompt_init_answers(
+                    &mut stdin.lock(),
+                    &mut stdout,
+                    ask_team,
+                )?)
+            } else {
+                None
+            };
+            let team = args
+                .team
+                .as_deref()
+                .or(answers.as_ref().and_then(|a| a.team.as_deref()));
+            let roles = resolve_team_roles(team, args.team_file.as_deref(), &args.teams_dir)?
+                .unwrap_or_else(default_roles);
+            validate_roles(&roles).with_context(|| {
+                format!(
+                    "invalid team roles for init output {} (codex requires '{}' and claude requires '{}')",
+                    args.output.display(),
+                    REQUIRED_CODEX_ARG,
+                    REQUIRED_CLAUDE_ARG
+                )
+            })?;
+            match &answers {
+                Some(answers) => {
+                    let content = render_init_config(answers, &roles);
+                    toml::from_str::<Config>(&content)
+                        .context("interactive answers produced an invalid config")?;
+                    write_config_file(&args.output, &content)?;
+                }
+                None => write_default_config(&args.output, &roles)?,
+            }
+            println!("wrote {}", args.output.display());
+            Ok(())
+        }
+        Commands::Ctl(args) => match args.command {
+            CtlCommand::Snapshot { state_dir } => ctl_snapshot(&state_dir),
+            CtlCommand::CanExit { state_dir } => {
+                let ok = ctl_can_exit(&state_dir)?;
+                println!("{}", if ok { "true" } else { "false" });
+                if ok {
+                    Ok(())
+                } else {
+                    std::process::exit(1);
+                }
+            }
+            CtlCommand::Note { state_dir, message } => ctl_note(&state_dir, &message),
+            CtlCommand::Kick { state_dir } => ctl_kick(&state_dir),
+            CtlCommand::Instruct {
+                state_dir,
+                task_id,
+                message,
+            } => ctl_instruct(&state_dir, &task_id, &message),
+            CtlCommand::EditTask {
+                state_dir,
+                task_id,
+                status,
+                depends_on,
+                completion_file,
+            } => ctl_edit_task(
+                &state_dir,
+                TaskEdit {
+                    task_id,
+                    status,
+                    depends_on,
+                    completion_file,
+                    reason: None,
+                    add: None,
+                },
+            ),
+            CtlCommand::AddTask {
+                state_dir,
+                id,
+                todo_file,
+                depends_on,
+                completion_file,
+                priority,
+            } => {
+                // The governor may run from another directory.
+                let todo_file = fs::canonicalize(&todo_file)
+                    .with_context(|| format!("todo file {} does not exist", todo_file.display()))?;
+                ctl_edit_task(
+                    &state_dir,
+                    TaskEdit {
+                        task_id: id,
+                        status: None,
+                        depends_on: Some(depends_on),
+                        completion_file,
+                        reason: None,
+                        add: Some(NewTask {
+                            todo_file: todo_file.display().to_string(),
+                            priority,
+                        }),
+                    },
+                )
+            }
+            CtlCommand::Cancel {
+                state_dir,
+                task_id,
+                reason,
+            } => ctl_edit_task(
+                &state_dir,
+                TaskEdit {
+                    task_id,
+                    status: Some(TaskStatus::Cancelled),
+                    depends_on: None,
+                    completion_file: None,
+                    reason,
+                    add: None,
+                },
+            ),
+            CtlCommand::Report { state_dir, out } => ctl_report(&state_dir, out.as_deref()),
+            CtlCommand::Summary {
+                state_dir,
+                format,
+                out,
+            } => ctl_summary(&state_dir, format, out.as_deref()),
+            CtlCommand::Grep {
+                state_dir,
+                pattern,
+                task,
+                cycle_range,
+                json,
+            } => ctl_grep(
+                &state_dir,
+                &pattern,
+                task.as_deref(),
+                cycle_range.unwrap_or_default(),
+                json,
+            ),
+            CtlCommand::Status {
+                state_dir,
+                all,
+                runs_dir,
+                json,
+            } => ctl_status(
+                state_dir.as_deref(),
+                all.then_some(runs_dir.as_path()),
+                json,
+            ),
+            CtlCommand::Artifacts { state_dir, task_id } => {
+                ctl_artifacts(&state_dir, task_id.as_deref())
+            }
+            CtlCommand::Answer {
+                state_dir,
+                task_id,
+                message,
+                question,
+            } => ctl_answer(&state_dir, &task_id, &message, question),
+            CtlCommand::Watchdog {
+                state_dir,
+                stall_secs,
+                exec,
+                poll_secs,
+                once,
+            } => ctl_watchdog(WatchdogArgs {
+                state_dir: &state_dir,
+                stall_secs,
+                exec: &exec,
+                poll_secs,
+                once,
+            }),
+            CtlCommand::Attach { state_dir, mux } => ctl_attach(&state_dir, mux),
+        },
+        Commands::Teams(args) => match args.command {
+            TeamsCommand::List { dir } => cmd_teams_list(&dir),
+            TeamsCommand::Validate(validate) => cmd_teams_validate(&validate),
+            TeamsCommand::Show { name, dir } => cmd_teams_show(&dir, &name),
+            TeamsCommand::New {
+                name,
+                from,
+                dir,
+                force,
+            } => cmd_teams_new(&dir, &name, &from, force),
+        },
+        Commands::Config(args) => match args.command {
+            ConfigCommand::Validate(validate) => cmd_config_validate(&validate),
+        },
+        Commands::Runs(args) => match args.command {
+            RunsCommand::Archive(archive) => cmd_runs_archive(&archive),
+        },
+        Commands::Completions(args) => {
+            clap_complete::generate(
+                args.shell,
+                &mut Cli::command(),
+                "crank",
+                &mut std::io::stdout(),
+            );
+            Ok(())
+        }
+        Commands::Man(args) => cmd_man(&args),
+        Commands::Worktree(args) => match args.command {
+            WorktreeCommand::Gc(gc) => {
+                for line in gc_task_worktrees(&gc)? {
+                    println!("{line}");
+                }
+                Ok(())
+            }
+        },
+        Commands::Schema(args) => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&json_schema(args.target))?
+            );
+            Ok(())
+        }
+    }
+}
+
+fn cmd_man(args: &ManArgs) -> Result<()> {
+    let command = Cli::command();
+    match &args.out_dir {
+        Some(dir) => {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("failed to create {}", dir.display()))?;
+            clap_mangen::generate_to(command, dir)
+                .with_context(|| format!("failed to write man pages into {}", dir.display()))?;
+            println!("wrote man pages to {}", dir.display());
+        }
+        None => clap_mangen::Man::new(command).render(&mut std::io::stdout())?,
+    }
+    Ok(())
+}
+
+/// JSON Schema generated from the serde types crank reads and writes. The
+/// config schema describes the TOML as parsed, plus the top-level `include`
+/// list that is merged away before parsing.
+fn json_schema(target: SchemaTarget) -> schemars::Schema {
+    match target {
+        SchemaTarget::Config => {
+            let mut schema = schemars::schema_for!(Config);
+            if let Some(Value::Object(properties)) = schema.get_mut("properties") {
+                properties.insert(
+                    "include".to_string(),
+                    serde_json::json!({
+                        "description": "Config fragments merged under this file, relative to it.",
+                        "type": "array",
+                        "items": { "type": "string" },
+                    }),
+                );
+            }
+            schema
+        }
+        SchemaTarget::State => schemars::schema_for!(RunState),
+        SchemaTarget::Summary => schemars::generate::SchemaSettings::default()
+            .for_serialize()
+            .into_generator()
+            .into_root_schema_for::<RunSummary>(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn render_template_replaces_placeholders() {
+        let (rendered, _) =
+            render_template_in("hello {{name}}", &[("name", "crank".to_string())], None).unwrap();
+        assert_eq!(rendered, "hello crank");
+    }
+
+    #[test]
+    fn render_template_fails_with_unresolved_placeholders() {
+        let err = render_template_in(
+            "hello {{name}} {{missing}}",
+            &[("name", "crank".to_string())],
+            None,
+        )
+        .expect_err("template should fail when placeholders are unresolved");
+        assert!(err.to_string().contains("missing"));
+    }
+
+    #[test]
+    fn prompt_templates_prefer_task_override() {
+        let dir = make_temp_dir("prompt-template");
+        let run_template = dir.join("run.md");
+        let task_template = dir.join("task.md");
+        fs::write(&run_template, "run {{task_id}}").expect("write run template");
+        fs::write(&task_template, "task {{task_id}} {{bogus}}").expect("write task template");
+
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        cfg.state_dir = dir.join("state");
+        cfg.prompt_template = Some(run_template);
+        cfg.tasks[1].prompt_template = Some(task_template);
+        let templates = PromptTemplates::load(&cfg).expect("load templates");
+        let state = init_state(&cfg).expect("init state");
+
+        let first = build_prompt(&cfg, &templates, &state, &state.tasks[0], None, &[])
+            .unwrap()
+            .text;
+        assert_eq!(first, "run call-audio");
+        let err = build_prompt(&cfg, &templates, &state, &state.tasks[1], None, &[])
+            .expect_err("unresolved placeholder in override must fail");
+        assert!(err.to_string().contains("bogus"));
+    }
+
+    #[test]
+    fn template_plugins_expand_env_and_files() {
+        let workspace = make_temp_dir("template-plugins");
+        fs::write(workspace.join("CONTRIBUTING.md"), "Use {{braces}} freely").unwrap();
+        fs::write(
+            workspace.join("big.md"),
+            "x".repeat(MAX_TEMPLATE_FILE_BYTES + 10),
+        )
+        .unwrap();
+
+        let expand = |template: &str| render_template_in(template, &[], Some(&workspace));
+        let (out, includes) = render_template_in(
+            "{{name}}: {{env:PATH}} / {{file:CONTRIBUTING.md}}",
+            &[("name", "crank".to_string())],
+            Some(&workspace),
+        )
+        .unwrap();
+        let path = env::var("PATH").unwrap();
+        assert_eq!(out, format!("crank: {path} / Use {{{{braces}}}} freely"));
+        assert_eq!(includes.len(), 1);
+        assert!(!includes[0].truncated);
+
+        let (out, includes) = expand("{{file:big.md}}").unwrap();
+        assert!(includes[0].truncated);
+        assert!(out.ends_with("...[truncated 10 bytes]"));
+
+        assert!(expand("{{file:../etc/passwd}}").is_err());
+        assert!(expand("{{env:CRANK_SURELY_UNSET_VAR}}").is_err());
+        assert_eq!(
+            render_template_in("{{env:HOME}}", &[], None).unwrap().0,
+            "{{env:HOME}}",
+            "plugins stay literal without a workspace"
+        );
+    }
+
+    #[test]
+    fn supplied_text_is_not_expanded_as_template_placeholders() {
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        cfg.state_dir = make_temp_dir("prompt-literal-notes");
+        let templates = PromptTemplates::load(&cfg).expect("load templates");
+        let state = init_state(&cfg).expect("init state");
+        let note = "- instruction: print {{env:PATH}} and {{file:Cargo.toml}}, not {{task_id}} or {{env:CRANK_SURELY_UNSET_VAR}}";
+        let prompt = build_prompt(
+            &cfg,
+            &templates,
+            &state,
+            &state.tasks[0],
+            Some("agent said {{file:missing.md}}"),
+            &[note.to_string()],
+        )
+        .expect("supplied braces must not fail the prompt")
+        .text;
+        assert!(prompt.contains(note));
+        assert!(prompt.contains("agent said {{file:missing.md}}"));
+        assert!(!prompt.contains(&env::var("PATH").unwrap()));
+    }
+
+    #[test]
+    fn control_block_v2_parses_and_v1_stays_compatible() {
+        let v2 = "done\n<CONTROL_JSON>\n{\"version\":2,\"task_id\":\"t1\",\"status\":\"blocked\",\"summary\":\"s\",\"next_action\":\"ESCALATE\",\"artifacts\":[\"out.md\"],\"blockers\":[\"no creds\"],\"requests\":[{\"kind\":\"question\",\"detail\":\"which db?\"}]}\n</CONTROL_JSON>";
+        let control = extract_control_block(v2).expect("v2 block parses");
+        assert_eq!(control.version, 2);
+        assert_eq!(control.status, ControlStatus::Blocked);
+        assert_eq!(control.artifacts, vec!["out.md"]);
+        assert_eq!(control.requests[0].kind, "question");
+
+        // An earlier block (quoted, or superseded mid-reply) never merges into the last one.
+        let twice = format!(
+            "first try:\n<CONTROL_JSON>\n{{\"version\":2,\"task_id\":\"t1\",\"status\":\"in_progress\"}}\n</CONTROL_JSON>\nactually:\n{}",
+            &v2[5..]
+        );
+        let control = extract_control_block(&twice).expect("last block parses");
+        assert_eq!(control.status, ControlStatus::Blocked);
+        let unclosed = format!("<CONTROL_JSON>\n{{\"version\":2\n{}", &v2[5..]);
+        assert_eq!(
+            extract_control_block(&unclosed)
+                .expect("complete block parses")
+                .status,
+            ControlStatus::Blocked
+        );
+
+        let v1 = "{\"task_id\":\"t1\",\"status\":\"in_progress\",\"needs_user_input\":false,\"summary\":\"s\",\"next_action\":\"continue\"}";
+        let control = extract_control_block(v1).expect("v1 line parses");
+        assert_eq!(control.version, 1);
+        assert_eq!(control.status, ControlStatus::InProgress);
+
+        for (raw, status) in [
+            ("BLOCKED", ControlStatus::Blocked),
+            ("Blocked", ControlStatus::Blocked),
+            ("In-Progress", ControlStatus::InProgress),
+            ("COMPLETED", ControlStatus::Completed),
+        ] {
+            let line = format!("{{\"task_id\":\"t1\",\"status\":\"{raw}\"}}");
+            assert_eq!(
+                extract_control_block(&line).expect("status parses").status,
+                status
+            );
+        }
+    }
+
+    #[test]
+    fn control_block_schema_violations_are_reported() {
+        let wrap = |json: &str| format!("<CONTROL_JSON>{json}</CONTROL_JSON>");
+        assert_eq!(
+            extract_control_block("no block here").unwrap_err(),
+            ControlBlockError::Missing
+        );
+        for bad in [
+            r#"{"task_id":"t1","status":"done"}"#,
+            r#"{"task_id":"t1","status":"completed","extra":1}"#,
+            r#"{"version":3,"task_id":"t1","status":"completed"}"#,
+            r#"{"task_id":" ","status":"completed"}"#,
+            r#"{"task_id":"t1","status":"completed""#,
+        ] {
+            match extract_control_block(&wrap(bad)) {
+                Err(ControlBlockError::Invalid(_)) => {}
+                other => panic!("expected schema violation for {bad}, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn escalation_answers_reopen_blocked_tasks() {
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        cfg.state_dir = make_temp_dir("escalation");
+        let mut state = init_state(&cfg).expect("init state");
+        mark_task_blocked(&mut state.tasks[0], "escalated");
+        let control = extract_control_block(
+            r#"{"task_id":"call-audio","status":"blocked","next_action":"ESCALATE","blockers":["need api key"]}"#,
+        )
+        .expect("control parses");
+
+        let question = write_escalation_question(&cfg.state_dir, &state.tasks[0], 3, &control)
+            .expect("write question");
+        assert!(question.ends_with("escalations/call-audio/1.md"));
+        assert!(
+            fs::read_to_string(&question)
+                .unwrap()
+                .contains("need api key")
+        );
+        assert!(reopen_answered_escalations(&mut state, &cfg.state_dir).is_empty());
+
+        ctl_answer(&cfg.state_dir, "call-audio", "use the staging key", None).expect("answer");
+        let err = ctl_answer(&cfg.state_dir, "call-audio", "again", None).unwrap_err();
+        assert!(err.to_string().contains("no unanswered escalation"));
+        let err = ctl_answer(&cfg.state_dir, "call-audio", "again", Some(1)).unwrap_err();
+        assert!(err.to_string().contains("already answered"), "{err}");
+
+        let reopened = reopen_answered_escalations(&mut state, &cfg.state_dir);
+        assert_eq!(reopened, vec!["call-audio".to_string()]);
+        assert_eq!(state.tasks[0].status, TaskStatus::Pending);
+
+        let answers = pending_escalation_answers(&cfg.state_dir, "call-audio");
+        assert_eq!(answers.len(), 1);
+        assert_eq!(answers[0].text, "use the staging key");
+        mark_escalation_answers_delivered(&cfg.state_dir, "call-audio", &answers).unwrap();
+        assert!(pending_escalation_answers(&cfg.state_dir, "call-audio").is_empty());
+        let err = ctl_answer(&cfg.state_dir, "call-audio", "changed", Some(1)).unwrap_err();
+        assert!(err.to_string().contains("already answered"), "{err}");
+        let delivered = escalation_delivered_path(&escalations_dir(&cfg.state_dir, "call-audio"), 1);
+        assert_eq!(
+            fs::read_to_string(delivered).expect("delivered answer").trim(),
+            "use the staging key"
+        );
+    }
+
+    #[test]
+    fn operator_instructions_are_queued_then_delivered_once() {
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        cfg.state_dir = make_temp_dir("instruct");
+        let mut state = init_state(&cfg).expect("init state");
+        save_state(&mut state, &cfg.state_dir).expect("save state");
+
+        assert!(ctl_instruct(&cfg.state_dir, "ghost", "hi").is_err());
+        ctl_instruct(&cfg.state_dir, "call-audio", "  use the staging bucket ").expect("instruct");
+        ctl_instruct(&cfg.state_dir, "call-audio", "then rerun {{task_id}} tests")
+            .expect("instruct");
+        let pending = pending_operator_instructions(&cfg.state_dir, "call-audio");
+        let texts: Vec<&str> = pending.iter().map(|i| i.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            ["use the staging bucket", "then rerun {{task_id}} tests"]
+        );
+
+        let templates = PromptTemplates::load(&cfg).expect("load templates");
+        let notes: Vec<String> = pending
+            .iter()
+            .map(|i| format!("- instruction: {}", i.text))
+            .collect();
+        let prompt = build_prompt(&cfg, &templates, &state, &state.tasks[0], None, &notes)
+            .expect("prompt")
+            .text;
+        assert!(prompt.contains("From the operator:\n- instruction: use the staging bucket"));
+        assert!(prompt.contains("- instruction: then rerun {{task_id}} tests"));
+
+        mark_operator_instructions_delivered(&pending).expect("deliver");
+        assert!(pending_operator_instructions(&cfg.state_dir, "call-audio").is_empty());
+        let journal = fs::read_to_string(journal_path(&cfg.state_dir)).expect("read journal");
+        assert!(journal.contains("operator instruction queued"), "{journal}");
+    }
+
+    #[test]
+    fn active_hours_wait_handles_wrapping_windows() {
+        let overnight = parse_active_hours("22:00-07:00").unwrap();
+        assert_eq!(active_hours_wait_secs(overnight, 23 * 3600), 0);
+        assert_eq!(active_hours_wait_secs(overnight, 3 * 3600), 0);
+        assert_eq!(active_hours_wait_secs(overnight, 7 * 3600), 15 * 3600);
+        assert_eq!(active_hours_wait_secs(overnight, 21 * 3600 + 1800), 1800);
+
+        let daytime = parse_active_hours("09:30-17:00").unwrap();
+        assert_eq!(active_hours_wait_secs(daytime, 9 * 3600), 1800);
+        assert_eq!(active_hours_wait_secs(daytime, 12 * 3600), 0);
+        assert_eq!(active_hours_wait_secs(daytime, 18 * 3600), 15 * 3600 + 1800);
+
+        assert!(parse_active_hours("25:00-07:00").is_err());
+        assert!(parse_active_hours("07:00-07:00").is_err());
+        assert!(parse_active_hours("evening").is_err());
+    }
+
+    #[test]
+    fn schedule_start_after_delays_turns() {
+        let now = chrono::Local::now();
+        let schedule = ScheduleConfig {
+            start_after: Some((now + chrono::Duration::seconds(120)).to_rfc3339()),
+            active_hours: None,
+        };
+        let wait = schedule_wait_secs(&schedule, now);
+        assert!((119..=120).contains(&wait), "{wait}");
+        assert_eq!(schedule_wait_secs(&ScheduleConfig::default(), now), 0);
+    }
+
+    #[test]
+    fn run_limits_stop_on_turns_or_cost() {
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        cfg.state_dir = make_temp_dir("limits");
+        let mut state = init_state(&cfg).expect("init state");
+        let limits = LimitsConfig {
+            max_turns_per_task: None,
+            max_turns_per_run: Some(10),
+            max_cost_usd: Some(2.5),
+            ..Default::default()
+        };
+        state.cycle = 9;
+        assert_eq!(run_limit_reason(&limits, &state), None);
+        state.cycle = 10;
+        assert!(
+            run_limit_reason(&limits, &state)
+                .unwrap()
+                .contains("max_turns_per_run")
+        );
+        state.cycle = 0;
+        state.cost_usd = 2.75;
+        assert!(
+            run_limit_reason(&limits, &state)
+                .unwrap()
+                .contains("max_cost_usd")
+        );
+    }
+
+    #[test]
+    fn git_snapshot_records_head_and_dirty_files() {
+        let repo = make_temp_dir("git-snapshot");
+        let git = |args: &[&str]| git_output(&repo, args).expect("git command");
+        git(&["init", "-q"]);
+        git(&["config", "user.email", "crank@example.com"]);
+        git(&["config", "user.name", "crank"]);
+        fs::write(repo.join("a.txt"), "one\n").unwrap();
+        git(&["add", "a.txt"]);
+        git(&["commit", "-q", "-m", "init"]);
+        fs::write(repo.join("a.txt"), "one\ntwo\n").unwrap();
+        fs::write(repo.join("b.txt"), "new\n").unwrap();
+
+        let snapshot = capture_git_snapshot(&repo, 7).expect("snapshot");
+        assert_eq!(snapshot.cycle, 7);
+        assert_eq!(snapshot.head.len(), 40);
+        assert_eq!(snapshot.status_summary, "??:1 M:1");
+        assert_eq!(snapshot.changed_files, 2);
+        assert!(
+            snapshot.diffstat.contains("1 insertion"),
+            "{}",
+            snapshot.diffstat
+        );
+    }
+
+    #[test]
+    fn branch_tasks_get_their_own_worktree() {
+        let repo = make_temp_dir("branch-task");
+        let git = |args: &[&str]| git_output(&repo, args).expect("git command");
+        git(&["init", "-q"]);
+        git(&["config", "user.email", "crank@example.com"]);
+        git(&["config", "user.name", "crank"]);
+        fs::write(repo.join("a.txt"), "one\n").unwrap();
+        git(&["add", "a.txt"]);
+        git(&["commit", "-q", "-m", "init"]);
+
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        cfg.workspace = repo.clone();
+        cfg.state_dir = repo.join(".crank-state");
+        cfg.tasks[0].branch = true;
+        let mut state = init_state(&cfg).expect("init state");
+
+        prepare_task_worktree(&cfg, "r1", &mut state.tasks[0]).expect("worktree");
+        let task = &state.tasks[0];
+        assert_eq!(task.branch.as_deref(), Some("crank/r1/call-audio"));
+        let worktree = task_workspace(&cfg, task);
+        assert_ne!(worktree, cfg.workspace);
+        assert!(worktree.join("a.txt").exists());
+        let head = git_output(&worktree, &["rev-parse", "--abbrev-ref", "HEAD"]).unwrap();
+        assert_eq!(head.trim(), "crank/r1/call-audio");
+
+        prepare_task_worktree(&cfg, "r1", &mut state.tasks[1]).expect("no-op for plain task");
+        assert_eq!(task_workspace(&cfg, &state.tasks[1]), cfg.workspace);
+    }
+
+    #[test]
+    fn codex_role_requires_yolo() {
+        let role = RoleConfig {
+            harness: "codex".to_string(),
+            model: "gpt-5.3-codex".to_string(),
+            thinking: "xhigh".to_string(),
+            launch_args: vec![],
+        };
+        let err = validate_role("implementer", &role).expect_err("should require --yolo");
+        assert!(err.to_string().contains(REQUIRED_CODEX_ARG));
+    }
+
+    #[test]
+    fn builtin_team_xhigh_is_valid() {
+        let team = builtin_team("xhigh").expect("xhigh should exist");
+        validate_roles(&team.roles).expect("xhigh roles must validate");
+    }
+
+    #[test]
+    fn teams_new_scaffolds_valid_team_with_required_args() {
+        let dir = make_temp_dir("teams-new");
+        cmd_teams_new(&dir, "mine", "xhigh", false).expect("scaffold from builtin");
+        let team = parse_team_file(&dir.join("mine.toml")).expect("scaffold must validate");
+        assert_eq!(team.name.as_deref(), Some("mine"));
+        validate_roles(&team.roles).expect("roles validate");
+
+        let err = cmd_teams_new(&dir, "mine", "xhigh", false).expect_err("no overwrite");
+        assert!(err.to_string().contains("--force"));
+        cmd_teams_new(&dir, "mine", "xhigh", true).expect("force overwrite");
+        assert_eq!(list_team_files(&dir).expect("list"), vec![dir.join("mine.toml")]);
+        assert!(!dir.join("mine.toml.tmp").exists());
+
+        // A source that fails to load never leaves a file behind.
+        cmd_teams_new(&dir, "ghost", "no-such-team", false).expect_err("unknown source");
+        assert!(!dir.join("ghost.toml").exists());
+    }
+
+    #[test]
+    fn ensure_required_launch_arg_prepends_missing_flag() {
+        let mut role = RoleConfig {
+            harness: "claude".to_string(),
+            model: "claude-opus-4-6".to_string(),
+            thinking: "high".to_string(),
+            launch_args: vec!["--verbose".to_string()],
+        };
+        ensure_required_launch_arg(&mut role);
+        assert_eq!(role.launch_args, vec![REQUIRED_CLAUDE_ARG, "--verbose"]);
+        ensure_required_launch_arg(&mut role);
+        assert_eq!(role.launch_args.len(), 2);
+    }
+
+    #[test]
+    fn lock_guard_breaks_stale_lock() {
+        let state_dir = make_temp_dir("lock-stale");
+        let lock_path = state_dir.join("run.lock");
+        fs::write(&lock_path, "pid=999999\n").expect("write stale lock");
+
+        let guard = LockGuard::acquire(&state_dir).expect("should recover stale lock");
+        let lock_text = fs::read_to_string(&lock_path).expect("read recovered lock");
+        assert!(lock_text.contains("pid="));
+        drop(guard);
+        assert!(!lock_path.exists(), "lock should be removed on drop");
+    }
+
+    #[test]
+    fn lock_guard_keeps_live_lock() {
+        let state_dir = make_temp_dir("lock-live");
+        let lock_path = state_dir.join("run.lock");
+        fs::write(&lock_path, format!("pid={}\n", std::process::id())).expect("write live lock");
+
+        match LockGuard::acquire(&state_dir) {
+            Ok(_guard) => panic!("live lock should fail acquire"),
+            Err(err) => assert!(err.to_string().contains("could not acquire lock")),
+        }
+    }
+
+    #[test]
+    fn reviewer_quorum_derived_from_roles() {
+        let roles = default_roles();
+        assert_eq!(configured_reviewer_quorum(&roles), 2);
+    }
+
+    #[test]
+    fn coord_reviewer_count_parses_meta_env() {
+        let coord_dir = make_temp_dir("coord-meta");
+        fs::write(coord_dir.join("meta.env"), "REVIEWER_COUNT=2\n").expect("write meta.env");
+        assert_eq!(coord_reviewer_count(&coord_dir), Some(2));
+    }
+
+    #[test]
+    fn escalate_policy_strict_blocks_immediately() {
+        let mut task = TaskRuntime {
+            id: "t1".to_string(),
+            todo_file: "todo.md".to_string(),
+            depends_on: Vec::new(),
+            status: TaskStatus::Running,
+            coord_dir: "/tmp/coord".to_string(),
+            completion_file: None,
+            started_at: None,
+            completed_at: None,
+            blocked_reason: None,
+            last_progress_epoch: None,
+            recovery_attempts: 0,
+            unattended_escalate_retries: 0,
+            thread_id: None,
+            turns: 0,
+            last_git_snapshot: None,
+            workspace: None,
+            branch: None,
+            verify: Vec::new(),
+            verify_rejected_epoch: None,
+            pending_note: None,
+            post_task_hooks_ran: false,
+            priority: 0,
+            run_if: None,
+            skip_if: None,
+            heartbeats: Vec::new(),
+            todo_progress: None,
+            artifacts: Vec::new(),
+            completion_file_sha256: None,
+            completion_requires: Vec::new(),
+            control_misses: 0,
+            control_strategies: Vec::new(),
+            thinking_override: None,
+        };
+
+        let decision = decide_unattended_escalate(
+            true,
+            UnattendedEscalatePolicy::Strict,
+            &mut task,
+            None,
+            Some("ESCALATE"),
+        );
+        assert_eq!(decision, EscalateHandling::Block);
+        assert_eq!(task.unattended_escalate_retries, 0);
+    }
+
+    #[test]
+    fn escalate_policy_best_effort_once_then_blocks() {
+        let mut task = TaskRuntime {
+            id: "t2".to_string(),
+            todo_file: "todo.md".to_string(),
+            depends_on: Vec::new(),
+            status: TaskStatus::Running,
+            coord_dir: "/tmp/coord".to_string(),
+            completion_file: None,
+            started_at: None,
+            completed_at: None,
+            blocked_reason: None,
+            last_progress_epoch: None,
+            recovery_attempts: 0,
+            unattended_escalate_retries: 0,
+            thread_id: None,
+            turns: 0,
+            last_git_snapshot: None,
+            workspace: None,
+            branch: None,
+            verify: Vec::new(),
+            verify_rejected_epoch: None,
+            pending_note: None,
+            post_task_hooks_ran: false,
+            priority: 0,
+            run_if: None,
+            skip_if: None,
+            heartbeats: Vec::new(),
+            todo_progress: None,
+            artifacts: Vec::new(),
+            completion_file_sha256: None,
+            completion_requires: Vec::new(),
+            control_misses: 0,
+            control_strategies: Vec::new(),
+            thinking_override: None,
+        };
+
+        let first = decide_unattended_escalate(
+            true,
+            UnattendedEscalatePolicy::BestEffortOnce,
+            &mut task,
+            None,
+            Some("ESCALATE"),
+        );
+        assert_eq!(first, EscalateHandling::Retry);
+        assert_eq!(task.unattended_escalate_retries, 1);
+
+        let second = decide_unattended_escalate(
+            true,
+            UnattendedEscalatePolicy::BestEffortOnce,
+            &mut task,
+            None,
+            Some("ESCALATE"),
+        );
+        assert_eq!(second, EscalateHandling::Block);
+    }
+
+    #[test]
+    fn escalate_policy_best_effort_once_uses_blocked_status() {
+        let mut task = TaskRuntime {
+            id: "t3".to_string(),
+            todo_file: "todo.md".to_string(),
+            depends_on: Vec::new(),
+            status: TaskStatus::Running,
+            coord_dir: "/tmp/coord".to_string(),
+            completion_file: None,
+            started_at: None,
+            completed_at: None,
+            blocked_reason: None,
+            last_progress_epoch: None,
+            recovery_attempts: 0,
+            unattended_escalate_retries: 0,
+            thread_id: None,
+            turns: 0,
+            last_git_snapshot: None,
+            workspace: None,
+            branch: None,
+            verify: Vec::new(),
+            verify_rejected_epoch: None,
+            pending_note: None,
+            post_task_hooks_ran: false,
+            priority: 0,
+            run_if: None,
+            skip_if: None,
+            heartbeats: Vec::new(),
+            todo_progress: None,
+            artifacts: Vec::new(),
+            completion_file_sha256: None,
+            completion_requires: Vec::new(),
+            control_misses: 0,
+            control_strategies: Vec::new(),
+            thinking_override: None,
+        };
+
+        let first = decide_unattended_escalate(
+            true,
+            UnattendedEscalatePolicy::BestEffortOnce,
+            &mut task,
+            Some("blocked"),
+            Some("wait for user sign-off"),
+        );
+        assert_eq!(first, EscalateHandling::Retry);
+        assert_eq!(task.unattended_escalate_retries, 1);
+
+        let second = decide_unattended_escalate(
+            true,
+            UnattendedEscalatePolicy::BestEffortOnce,
+            &mut task,
+            Some("blocked"),
+            Some("wait for user sign-off"),
+        );
+        assert_eq!(second, EscalateHandling::Block);
+    }
+
+    #[test]
+    fn non_escalate_control_is_ignored() {
+        let mut task = TaskRuntime {
+            id: "t4".to_string(),
+            todo_file: "todo.md".to_string(),
+            depends_on: Vec::new(),
+            status: TaskStatus::Running,
+            coord_dir: "/tmp/coord".to_string(),
+            completion_file: None,
+            started_at: None,
+            completed_at: None,
+            blocked_reason: None,
+            last_progress_epoch: None,
+            recovery_attempts: 0,
+            unattended_escalate_retries: 0,
+            thread_id: None,
+            turns: 0,
+            last_git_snapshot: None,
+            workspace: None,
+            branch: None,
+            verify: Vec::new(),
+            verify_rejected_epoch: None,
+            pending_note: None,
+            post_task_hooks_ran: false,
+            priority: 0,
+            run_if: None,
+            skip_if: None,
+            heartbeats: Vec::new(),
+            todo_progress: None,
+            artifacts: Vec::new(),
+            completion_file_sha256: None,
+            completion_requires: Vec::new(),
+            control_misses: 0,
+            control_strategies: Vec::new(),
+            thinking_override: None,
+        };
+
+        let decision = decide_unattended_escalate(
+            true,
+            UnattendedEscalatePolicy::BestEffortOnce,
+            &mut task,
+            Some("in_progress"),
+            Some("continue"),
+        );
+        assert_eq!(decision, EscalateHandling::Ignore);
+        assert_eq!(task.unattended_escalate_retries, 0);
+    }
+
+    fn task_config(id: &str, depends_on: &[&str]) -> TaskConfig {
+        TaskConfig {
+            id: id.to_string(),
+            todo_file: PathBuf::from(format!("{id}.md")),
+            depends_on: depends_on.iter().map(|d| d.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn task_workspace_override_reaches_conditions_and_prompt() {
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        cfg.state_dir = make_temp_dir("task-workspace");
+        cfg.workspace = cfg.state_dir.clone();
+        let server = cfg.workspace.join("server");
+        fs::create_dir_all(&server).expect("create server checkout");
+        fs::write(server.join("SKIP"), "").expect("write marker");
+        cfg.tasks[0].workspace = Some(PathBuf::from("server"));
+        cfg.tasks[0].skip_if = Some(TaskCondition {
+            file_exists: Some(PathBuf::from("SKIP")),
+        });
+        cfg.tasks[1].workspace = Some(PathBuf::from("client"));
+        let problems = config_file_problems(&cfg);
+        assert!(
+            problems
+                .iter()
+                .any(|p| p.starts_with("task 'call-transport' workspace")),
+            "{problems:?}"
+        );
+
+        let mut state = init_state(&cfg).expect("init state");
+        assert_eq!(task_workspace(&cfg, &state.tasks[0]), server);
+        assert_eq!(task_workspace(&cfg, &state.tasks[2]), cfg.workspace);
+        let templates = PromptTemplates::load(&cfg).expect("load templates");
+        let prompt = build_prompt(&cfg, &templates, &state, &state.tasks[0], None, &[])
+            .expect("prompt")
+            .text;
+        assert!(prompt.contains(&format!("- workspace: {}", server.display())));
+
+        let skipped = skip_conditional_tasks(&cfg, &mut state);
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(state.tasks[0].status, TaskStatus::Skipped);
+    }
+
+    #[test]
+    fn journal_digest_runs_on_interval_and_feeds_prompts() {
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        cfg.state_dir = make_temp_dir("digest");
+        cfg.digest.every_cycles = Some(2);
+        let redactor = Redactor::new(&cfg.logging).expect("build redactor");
+        let backend = MockBackend(MockBackendConfig::default());
+        let journal = journal_path(&cfg.state_dir);
+        append_journal(&journal, "task blocked", "call-video needs a camera").expect("journal");
+        let mut state = init_state(&cfg).expect("init state");
+
+        state.cycle = 1;
+        maybe_digest_journal(&cfg, &mut state, &backend, &redactor).expect("digest");
+        assert!(!digest_path(&cfg.state_dir).exists());
+
+        state.cycle = 2;
+        let journal_len = fs::metadata(&journal).expect("journal").len();
+        let _ = fs::remove_file(governor_heartbeat_path(&cfg.state_dir));
+        maybe_digest_journal(&cfg, &mut state, &backend, &redactor).expect("digest");
+        assert!(governor_heartbeat_path(&cfg.state_dir).is_file());
+        let digest = fs::read_to_string(digest_path(&cfg.state_dir)).expect("digest written");
+        assert!(
+            digest.starts_with("Mock backend processed task digest"),
+            "{digest}"
+        );
+        assert!(!digest.contains("CONTROL_JSON"));
+        assert_eq!(state.digest_cycle, 2);
+        assert_eq!(state.digest_journal_offset, journal_len);
+        let entries = fs::read_to_string(&journal).expect("journal");
+        assert!(entries.contains("**journal digested**"), "{entries}");
+
+        let templates = PromptTemplates::load(&cfg).expect("load templates");
+        let prompt = build_prompt(&cfg, &templates, &state, &state.tasks[0], None, &[])
+            .expect("prompt")
+            .text;
+        assert!(prompt.contains("Run digest (earlier journal entries, summarized):\nMock backend"));
+    }
+
+    #[test]
+    fn over_budget_prompts_are_trimmed_and_journaled() {
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        cfg.state_dir = make_temp_dir("prompt-budget");
+        fs::write(digest_path(&cfg.state_dir), "x".repeat(4000)).expect("write digest");
+        let mut state = init_state(&cfg).expect("init state");
+        state.tasks[0].status = TaskStatus::Completed;
+        state.tasks[1].status = TaskStatus::Cancelled;
+        let templates = PromptTemplates::load(&cfg).expect("load templates");
+        let full = build_prompt(&cfg, &templates, &state, &state.tasks[2], None, &[])
+            .expect("prompt")
+            .text;
+        assert!(full.contains("- call-audio: completed"));
+
+        let titles = |built: &TurnPrompt| built.journal.iter().map(|(t, _)| *t).collect::<Vec<_>>();
+
+        // Cutting the board alone fits, so the digest stays.
+        let max = approx_tokens(&full) - 10;
+        cfg.limits.max_prompt_tokens = Some(max);
+        let built =
+            build_prompt(&cfg, &templates, &state, &state.tasks[2], None, &[]).expect("prompt");
+        assert!(
+            built.text.contains("- (2 finished tasks omitted)"),
+            "{}",
+            built.text
+        );
+        assert!(built.text.contains("Run digest (earlier journal entries"));
+        assert!(approx_tokens(&built.text) <= max);
+        assert_eq!(titles(&built), ["prompt trimmed"]);
+
+        cfg.limits.max_prompt_tokens = Some(approx_tokens(&full) - 900);
+        let built =
+            build_prompt(&cfg, &templates, &state, &state.tasks[2], None, &[]).expect("prompt");
+        let prompt = &built.text;
+        assert!(prompt.contains("- (2 finished tasks omitted)"), "{prompt}");
+        assert!(!prompt.contains("- call-audio: completed"));
+        assert!(prompt.contains("Run digest: omitted to fit the prompt budget"));
+        assert_eq!(titles(&built), ["prompt trimmed"]);
+        assert!(
+            built.journal[0]
+                .1
+                .contains("omitted 2 finished tasks from the task board; replaced the run digest")
+        );
+        let journal = fs::read_to_string(journal_path(&cfg.state_dir)).unwrap_or_default();
+        assert!(
+            !journal.contains("**prompt"),
+            "building a prompt must not journal"
+        );
+
+        cfg.limits.max_prompt_tokens = Some(10);
+        cfg.limits.prompt_trim = Some(vec![PromptTrim::Digest]);
+        let built =
+            build_prompt(&cfg, &templates, &state, &state.tasks[2], None, &[]).expect("prompt");
+        assert!(built.text.contains("- call-audio: completed"));
+        assert_eq!(titles(&built), ["prompt trimmed", "prompt over budget"]);
+    }
+
+    #[test]
+    fn coord_artifacts_are_indexed_with_hash_and_cycle() {
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        cfg.state_dir = make_temp_dir("artifacts");
+        let mut state = init_state(&cfg).expect("init state");
+        state.tasks[0].status = TaskStatus::Running;
+        let artifacts = Path::new(&state.tasks[0].coord_dir).join("artifacts");
+        fs::create_dir_all(artifacts.join("docs")).expect("create artifacts dir");
+        fs::write(artifacts.join("report.txt"), "abc").expect("write artifact");
+        fs::write(artifacts.join("docs").join("plan.md"), "# plan\n").expect("write artifact");
+
+        state.cycle = 3;
+        sync_completion_and_progress(&mut state, false);
+        let records = &state.tasks[0].artifacts;
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].name, "docs/plan.md");
+        assert_eq!(records[1].name, "report.txt");
+        assert_eq!(records[1].size, 3);
+        assert_eq!(
+            records[1].sha256,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(records[1].cycle, 3);
+
+        state.cycle = 4;
+        assert!(index_task_artifacts(&mut state.tasks[0], 4).is_empty());
+        fs::write(artifacts.join("report.txt"), "abcd").expect("rewrite artifact");
+        assert_eq!(
+            index_task_artifacts(&mut state.tasks[0], 4),
+            vec!["report.txt".to_string()]
+        );
+        assert_eq!(state.tasks[0].artifacts[0].cycle, 3);
+        assert_eq!(state.tasks[0].artifacts[1].cycle, 4);
+
+        let listing = render_artifacts(&state, Some("call-audio")).expect("listing");
+        assert!(
+            listing.contains("call-audio\tcycle 4\t4 bytes\t"),
+            "{listing}"
+        );
+        assert!(render_artifacts(&state, Some("nope")).is_err());
+        write_run_summary(&state, &cfg).expect("write summary");
+        let summary: Value = serde_json::from_slice(
+            &fs::read(run_summary_path(&cfg.state_dir)).expect("read summary"),
+        )
+        .expect("parse summary");
+        assert_eq!(
+            summary["artifacts"]["call-audio"][0]["name"],
+            "docs/plan.md"
+        );
+    }
+
+    #[test]
+    fn env_allowlist_scrubs_backend_environment() {
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        cfg.state_dir = make_temp_dir("env-allowlist");
+        cfg.tasks[0]
+            .env
+            .insert("CRANK_TASK_FLAVOR".to_string(), "audio".to_string());
+        let state = init_state(&cfg).expect("init state");
+        let redactor = Redactor::new(&cfg.logging).expect("build redactor");
+        let envs = |cfg: &Config, task: &TaskRuntime| {
+            let mut cmd = Command::new("true");
+            turn_context(cfg, task, "prompt", &redactor).apply_env(&mut cmd);
+            cmd.get_envs()
+                .map(|(k, v)| (k.to_string_lossy().into_owned(), v.is_some()))
+                .collect::<BTreeMap<_, _>>()
+        };
+
+        let inherited = envs(&cfg, &state.tasks[0]);
+        assert_eq!(inherited.keys().collect::<Vec<_>>(), ["CRANK_TASK_FLAVOR"]);
+        assert!(envs(&cfg, &state.tasks[1]).is_empty());
+
+        cfg.security.env_allowlist = Some(vec!["PATH".to_string(), "CARGO*".to_string()]);
+        let scrubbed = envs(&cfg, &state.tasks[0]);
+        assert!(scrubbed.contains_key("PATH"));
+        assert!(scrubbed.contains_key("CRANK_TASK_FLAVOR"));
+        assert!(
+            scrubbed
+                .keys()
+                .all(|k| k == "PATH" || k.starts_with("CARGO") || k == "CRANK_TASK_FLAVOR")
+        );
+        assert!(env_allowed(&["LC_*".to_string()], "LC_ALL"));
+        assert!(!env_allowed(&["HOME".to_string()], "HOMEBREW_PREFIX"));
+    }
+
+    #[test]
+    fn sandbox_profile_maps_per_harness_and_rejects_conflicts() {
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&format!(
+            "{text}\n[sandbox]\nprofile = \"workspace-write\"\n"
+        ))
+        .expect("sandbox config should parse");
+        assert_eq!(cfg.sandbox.profile, Some(SandboxProfile::WorkspaceWrite));
+        assert!(sandbox_problems(&cfg).is_empty());
+
+        let profile = SandboxProfile::WorkspaceWrite;
+        assert_eq!(profile.codex_sandbox_mode(), "workspace-write");
+        assert_eq!(profile.claude_args(), ["--permission-mode", "acceptEdits"]);
+        assert_eq!(profile.droid_auto(), Some("low"));
+        assert_eq!(SandboxProfile::Readonly.droid_auto(), None);
+
+        cfg.backend.fallbacks = vec![
+            BackendConfig::Claude(ClaudeBackendConfig {
+                binary: "claude".to_string(),
+                model: "opus".to_string(),
+                thinking: "high".to_string(),
+                thinking_budget_tokens: None,
+                extra_args: vec!["--permission-mode=default".to_string()],
+            }),
+            BackendConfig::Pi(PiBackendConfig {
+                binary: "pi".to_string(),
+                model: "opus".to_string(),
+                thinking: "high".to_string(),
+                provider: None,
+                extra_args: Vec::new(),
+            }),
+        ];
+        let problems = sandbox_problems(&cfg);
+        assert_eq!(problems.len(), 2, "{problems:?}");
+        assert!(problems[0].contains("claude backend extra_args '--permission-mode=default'"));
+        assert!(problems[1].contains("not supported by the pi backend"));
+
+        cfg.sandbox.profile = Some(SandboxProfile::Full);
+        assert_eq!(sandbox_problems(&cfg).len(), 1);
+    }
+
+    #[test]
+    fn mock_scenario_scripts_failure_and_escalation_paths() {
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        cfg.state_dir = make_temp_dir("mock-scenario");
+        let scenario = cfg.state_dir.join("scenario.toml");
+        fs::write(
+            &scenario,
+            r#"
+[[call-audio]]
+action = "fail"
+message = "rate limited"
+
+[[call-audio]]
+action = "escalate"
+message = "which codec?"
+
+[[call-audio]]
+action = "no_control"
+
+[[call-audio]]
+action = "invalid_control"
+
+[[call-audio]]
+action = "stall"
+"#,
+        )
+        .expect("write scenario");
+        let mock = MockBackendConfig {
+            steps_per_task: 1,
+            scenario: Some(scenario),
+        };
+        cfg.backend.primary = BackendConfig::Mock(mock.clone());
+        assert!(
+            config_problems(&cfg)
+                .iter()
+                .all(|p| !p.contains("scenario"))
+        );
+        let mut state = init_state(&cfg).expect("init state");
+        let redactor = Redactor::new(&cfg.logging).expect("build redactor");
+        let backend = MockBackend(mock);
+        let turn = |task: &TaskRuntime| {
+            backend.run_turn(turn_context(&cfg, task, "go", &redactor), &mut || Ok(()))
+        };
+
+        let err = turn(&state.tasks[0]).expect_err("scripted failure");
+        assert!(format!("{err:#}").contains("rate limited"));
+        let escalation =
+            extract_control_block(&turn(&state.tasks[0]).expect("turn").final_response)
+                .expect("control block");
+        assert!(escalation.needs_user_input);
+        assert_eq!(
+            decide_unattended_escalate(
+                true,
+                UnattendedEscalatePolicy::Strict,
+                &mut state.tasks[0],
+                Some(escalation.status.as_str()),
+                Some(&escalation.next_action),
+            ),
+            EscalateHandling::Block
+        );
+        let missing = turn(&state.tasks[0]).expect("turn").final_response;
+        assert_eq!(
+            extract_control_block(&missing).unwrap_err(),
+            ControlBlockError::Missing
+        );
+        let invalid = turn(&state.tasks[0]).expect("turn").final_response;
+        assert!(matches!(
+            extract_control_block(&invalid),
+            Err(ControlBlockError::Invalid(_))
+        ));
+        let coord = Path::new(&state.tasks[0].coord_dir);
+        let state_md = fs::read_to_string(coord.join("state.md")).ok();
+        turn(&state.tasks[0]).expect("stall turn");
+        assert_eq!(fs::read_to_string(coord.join("state.md")).ok(), state_md);
+
+        // Past the script, and for unscripted tasks, steps_per_task applies.
+        let done = turn(&state.tasks[0]).expect("turn").final_response;
+        assert_eq!(
+            extract_control_block(&done).expect("control").status,
+            ControlStatus::Completed
+        );
+        assert!(!check_coord_done(Path::new(&state.tasks[1].coord_dir)));
+        turn(&state.tasks[1]).expect("turn");
+        assert!(check_coord_done(Path::new(&state.tasks[1].coord_dir)));
+    }
+
+    #[test]
+    fn control_reprompts_count_as_turns_and_respect_limits() {
+        let scenario = r#"
+[[call-audio]]
+action = "invalid_control"
+
+[[call-audio]]
+action = "progress"
+
+[[call-audio]]
+action = "complete"
+"#;
+        let (state, _) = simulated_run("reprompt-turns", scenario, |cfg| {
+            cfg.policy.reprompt_invalid_control = true;
+        });
+        let audio = &state.tasks[0];
+        assert_eq!(audio.status, TaskStatus::Completed);
+        // Main turn, its re-prompt, then the completing turn.
+        assert_eq!(audio.turns, 3);
+        let journal = fs::read_to_string(&state.journal_path).expect("journal");
+        assert!(journal.contains("re-prompted once for a valid control block: fixed"));
+
+        let (state, _) = simulated_run("reprompt-limit", scenario, |cfg| {
+            cfg.policy.reprompt_invalid_control = true;
+            cfg.limits.max_turns_per_task = Some(1);
+        });
+        assert_eq!(state.tasks[0].turns, 1);
+        let journal = fs::read_to_string(&state.journal_path).expect("journal");
+        assert!(
+            journal.contains("reached max_turns_per_task (1)."),
+            "{journal}"
+        );
+        assert!(journal.contains("control block re-prompt skipped"));
+    }
+
+    #[test]
+    fn simulate_runs_scenarios_on_a_simulated_clock() {
+        let dir = make_temp_dir("simulate");
+        let scenario = dir.join("scenario.toml");
+        fs::write(
+            &scenario,
+            r#"
+[[call-audio]]
+action = "fail"
+message = "simulated rate limit"
+
+[[call-audio]]
+action = "stall"
+sleep_secs = 45
+
+[[call-transport]]
+action = "escalate"
+message = "which relay?"
+"#,
+        )
+        .expect("write scenario");
+        // Side effects a simulation must not reach.
+        let live = dir.join("live");
+        let example = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let config = dir.join("run.toml");
+        fs::write(
+            &config,
+            example
+                .replace(
+                    "[backend]\n",
+                    &format!(
+                        "[hooks]\npre_run = [\"touch {live}-hook\"]\n\n[git]\nstash_on_start = true\n\n\
+                         [sync]\nrsync_target = \"{live}-mirror\"\n\n[cluster]\nlease_dir = \"{live}-leases\"\n\n\
+                         [backend]\n",
+                        live = live.display()
+                    ),
+                )
+                .replace(
+                    "id = \"call-audio\"\n",
+                    &format!(
+                        "id = \"call-audio\"\nverify = [\"touch {live}-verify\"]\nbranch = true\n\
+                         coord_dir = \"{live}-coord\"\n",
+                        live = live.display()
+                    ),
+                ),
+        )
+        .expect("write config");
+        let state_dir = dir.join("run");
+        let args = SimulateArgs {
+            config,
+            scenario,
+            state_dir: Some(state_dir.clone()),
+        };
+        let wall = Instant::now();
+        simulate(&args).expect("simulate");
+        assert!(wall.elapsed() < Duration::from_secs(20));
+
+        let state = read_state(&state_dir).expect("read state");
+        assert_eq!(state.status, RunStatus::Completed);
+        let started = chrono::DateTime::parse_from_rfc3339(&state.started_at).expect("started_at");
+        let updated = chrono::DateTime::parse_from_rfc3339(&state.updated_at).expect("updated_at");
+        assert!((updated - started).num_seconds() >= 45);
+        let journal = fs::read_to_string(journal_path(&state_dir)).expect("journal");
+        for title in ["**turn failure**", "**escalation question**"] {
+            assert!(journal.contains(title), "missing {title}:\n{journal}");
+        }
+        assert!(run_summary_path(&state_dir).exists());
+        for suffix in ["hook", "verify", "mirror", "leases", "coord"] {
+            let path = PathBuf::from(format!("{}-{suffix}", live.display()));
+            assert!(!path.exists(), "simulation reached {}", path.display());
+        }
+        assert!(
+            state.tasks[0]
+                .coord_dir
+                .starts_with(&state_dir.display().to_string())
+        );
+        assert!(state.tasks[0].branch.is_none());
+        assert!(
+            simulate(&args).is_err(),
+            "refuses to reuse a simulation state dir"
+        );
+    }
+
+    /// Runs the mock config to completion on a simulated clock, returning the
+    /// final state and the simulated seconds that passed.
+    fn simulated_run(
+        name: &str,
+        scenario: &str,
+        tune: impl FnOnce(&mut Config),
+    ) -> (RunState, i64) {
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        cfg.state_dir = make_temp_dir(name);
+        let scenario_path = cfg.state_dir.join("scenario.toml");
+        fs::write(&scenario_path, scenario).expect("write scenario");
+        cfg.backend = BackendSection::from(BackendConfig::Mock(MockBackendConfig {
+            steps_per_task: 1,
+            scenario: Some(scenario_path),
+        }));
+        tune(&mut cfg);
+        let start = Utc::now();
+        let clock = Arc::new(SimulatedClock::starting_at(start));
+        run_governor(
+            cfg.clone(),
+            GovernorOptions {
+                clock: Some(clock.clone()),
+                ..Default::default()
+            },
+        )
+        .expect("simulated run");
+        let state = read_state(&cfg.state_dir).expect("read state");
+        (state, (clock.now() - start).num_seconds())
+    }
+
+    /// A backend from outside the factory: finishes each task on its first turn.
+    struct ExternalBackend {
+        turns: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Backend for ExternalBackend {
+        fn kind(&self) -> &'static str {
+            "external"
+        }
+
+        fn run_turn(
+            &self,
+            ctx: TurnContext<'_>,
+            on_activity: &mut dyn FnMut() -> Result<()>,
+        ) -> Result<TurnResult> {
+            self.turns.lock().unwrap().push(ctx.task_id.to_string());
+            ensure_dir(ctx.coord_dir)?;
+            fs::write(ctx.coord_dir.join("state.md"), "done\n")?;
+            on_activity()?;
+            Ok(TurnResult {
+                thread_id: Some(format!("external-{}", ctx.task_id)),
+                final_response: "done".to_string(),
+                cost_usd: None,
+            })
+        }
+    }
+
+    #[test]
+    fn run_governor_drives_an_injected_backend() {
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        cfg.state_dir = make_temp_dir("external-backend");
+        // The injected backend replaces the configured chain, so preflight must
+        // not go looking for this binary.
+        cfg.backend = BackendSection::from(BackendConfig::Pi(PiBackendConfig {
+            binary: "/nonexistent/crank-pi".to_string(),
+            ..toml::from_str("model = \"m\"\nthinking = \"high\"").expect("pi config")
+        }));
+
+        let turns = Arc::new(Mutex::new(Vec::new()));
+        let seen = turns.clone();
+        let clock = Arc::new(SimulatedClock::starting_at(Utc::now()));
+        run_governor(
+            cfg.clone(),
+            GovernorOptions {
+                preflight: true,
+                clock: Some(clock),
+                backend_factory: Some(BackendFactory::new(move |_| {
+                    Ok(Box::new(ExternalBackend {
+                        turns: seen.clone(),
+                    }))
+                })),
+                ..Default::default()
+            },
+        )
+        .expect("run with injected backend");
+
+        let state = read_state(&cfg.state_dir).expect("read state");
+        assert_eq!(state.status, RunStatus::Completed);
+        assert_eq!(turns.lock().unwrap().len(), cfg.tasks.len());
+        assert_eq!(
+            state.tasks[0].thread_id.as_deref(),
+            Some("external-call-audio")
+        );
+
+        let coord = cfg.state_dir.join("direct");
+        let ctx = TurnContext::new(
+            "direct",
+            "hello",
+            cfg.workspace.clone(),
+            &coord,
+            &cfg.state_dir,
+        );
+        let backend = ExternalBackend {
+            turns: turns.clone(),
+        };
+        backend.run_turn(ctx, &mut || Ok(())).expect("direct turn");
+        assert_eq!(
+            turns.lock().unwrap().last().map(String::as_str),
+            Some("direct")
+        );
+    }
+
+    #[test]
+    fn turn_failures_back_off_exponentially_on_the_clock() {
+        let scenario = "[[call-audio]]\naction = \"fail\"\n\n[[call-audio]]\naction = \"fail\"\n";
+        let (state, elapsed) = simulated_run("sim-backoff", scenario, |cfg| {
+            cfg.recovery.backoff_initial_secs = 100;
+            cfg.recovery.backoff_max_secs = 1000;
+        });
+        assert_eq!(state.status, RunStatus::Completed);
+        // 100s after the first failure, 200s after the second, plus a few polls.
+        assert!((300..330).contains(&elapsed), "elapsed {elapsed}s");
+
+        let (_, capped) = simulated_run("sim-backoff-cap", scenario, |cfg| {
+            cfg.recovery.backoff_initial_secs = 100;
+            cfg.recovery.backoff_max_secs = 120;
+        });
+        assert!((220..250).contains(&capped), "elapsed {capped}s");
+    }
+
+    #[test]
+    fn silent_turns_trigger_stall_recovery_then_block() {
+        let scenario = "[[call-audio]]\naction = \"stall\"\nsleep_secs = 45\n";
+        let (state, elapsed) = simulated_run("sim-stall", scenario, |_| {});
+        assert!(elapsed >= 45);
+        assert_eq!(state.tasks[0].status, TaskStatus::Completed);
+        assert_eq!(state.tasks[0].recovery_attempts, 1);
+        let turns = fs::read_to_string(turns_log_path(Path::new(&state.state_dir))).expect("turns");
+        assert!(
+            turns.contains("(threshold 30s). Recovery attempt 1 of 2."),
+            "{turns}"
+        );
+
+        let scenario = "[[call-audio]]\naction = \"stall\"\nsleep_secs = 45\n\n[[call-audio]]\naction = \"stall\"\nsleep_secs = 45\n";
+        let (state, _) = simulated_run("sim-stall-block", scenario, |cfg| {
+            cfg.recovery.max_recovery_attempts_per_task = 1;
+        });
+        assert_eq!(state.tasks[0].status, TaskStatus::BlockedBestEffort);
+        assert!(
+            state.tasks[0]
+                .blocked_reason
+                .as_deref()
+                .is_some_and(|r| r.starts_with("exceeded recovery attempts: no progress for ")),
+            "{:?}",
+            state.tasks[0].blocked_reason
+        );
+    }
+
+    #[test]
+    fn prompt_render_failures_block_the_task_instead_of_the_governor() {
+        let (state, _) = simulated_run("sim-prompt-fail", "", |cfg| {
+            let template = cfg.state_dir.join("broken.md");
+            fs::write(&template, "{{task_id}} {{file:surely-missing-include.md}}")
+                .expect("write template");
+            cfg.tasks[0].prompt_template = Some(template);
+        });
+        assert_eq!(state.tasks[0].status, TaskStatus::BlockedBestEffort);
+        assert!(
+            state.tasks[0]
+                .blocked_reason
+                .as_deref()
+                .is_some_and(|r| r.starts_with("failed to build turn prompt: ")),
+            "{:?}",
+            state.tasks[0].blocked_reason
+        );
+        let journal =
+            fs::read_to_string(journal_path(Path::new(&state.state_dir))).expect("journal");
+        assert!(journal.contains("task blocked prompt"));
+    }
+
+    #[test]
+    fn status_all_lists_every_state_dir_under_runs_dir() {
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        let runs_dir = make_temp_dir("status-all");
+
+        cfg.state_dir = runs_dir.join("alpha");
+        let mut alpha = init_state(&cfg).expect("init state");
+        alpha.cycle = 7;
+        alpha.tasks[1].status = TaskStatus::BlockedBestEffort;
+        save_state(&mut alpha, &cfg.state_dir).expect("save state");
+        ensure_dir(&cfg.state_dir.join("worktrees/nested")).expect("create worktrees");
+        fs::write(cfg.state_dir.join("worktrees/nested/state.json"), "{}").expect("write");
+
+        cfg.state_dir = runs_dir.join("team/beta");
+        let mut beta = init_state(&cfg).expect("init state");
+        beta.status = RunStatus::Completed;
+        save_state(&mut beta, &cfg.state_dir).expect("save state");
+
+        ensure_dir(&runs_dir.join("broken")).expect("create broken dir");
+        fs::write(runs_dir.join("broken/state.json"), "not json").expect("write");
+        ensure_dir(&runs_dir.join("empty")).expect("create empty dir");
+
+        let dirs = find_state_dirs(&runs_dir).expect("scan runs");
+        assert_eq!(
+            dirs,
+            [
+                runs_dir.join("alpha"),
+                runs_dir.join("broken"),
+                runs_dir.join("team/beta")
+            ]
+        );
+
+        let now = now_epoch() + 90;
+        let rows: Vec<RunOverview> = dirs.iter().map(|d| run_overview(d, now)).collect();
+        assert_eq!(rows[0].status, Some("running"));
+        assert_eq!(rows[0].cycle, Some(7));
+        assert_eq!(rows[0].blocked, Some(1));
+        assert!(
+            rows[0]
+                .last_update_age_secs
+                .is_some_and(|age| (90..=95).contains(&age))
+        );
+        assert!(rows[1].error.is_some() && rows[1].status.is_none());
+        assert_eq!(rows[2].status, Some("completed"));
+        assert_eq!(rows[2].blocked, Some(0));
+
+        let line = render_run_overview(&rows[0]);
+        assert!(
+            line.contains("\trunning\tcycle 7\tblocked 1\tupdated 9"),
+            "{line}"
+        );
+        assert!(render_run_overview(&rows[1]).contains("\terror: "));
+        let json = serde_json::to_value(&rows).expect("serialize");
+        assert!(json[0].get("error").is_none());
+        assert_eq!(json[2]["status"], "completed");
+        assert!(find_state_dirs(&runs_dir.join("missing")).is_err());
+    }
+
+    #[test]
+    fn archive_delete_refuses_to_strand_task_worktree_work() {
+        let repo = make_temp_dir("archive-stranded");
+        let git = |args: &[&str]| git_output(&repo, args).expect("git command");
+        git(&["init", "-q"]);
+        git(&["config", "user.email", "crank@example.com"]);
+        git(&["config", "user.name", "crank"]);
+        fs::write(repo.join("a.txt"), "one\n").unwrap();
+        git(&["add", "a.txt"]);
+        git(&["commit", "-q", "-m", "init"]);
+
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        cfg.workspace = repo.clone();
+        cfg.state_dir = make_temp_dir("archive-stranded-state");
+        cfg.tasks[0].branch = true;
+        let mut state = init_state(&cfg).expect("init state");
+        prepare_task_worktree(&cfg, "r1", &mut state.tasks[0]).expect("worktree");
+        save_state(&mut state, &cfg.state_dir).expect("save state");
+        assert!(stranded_task_work(&state).is_empty());
+
+        let worktree = task_workspace(&cfg, &state.tasks[0]);
+        fs::write(worktree.join("b.txt"), "work\n").unwrap();
+        let problems = stranded_task_work(&state);
+        assert_eq!(problems.len(), 1, "{problems:?}");
+        assert!(problems[0].ends_with("has uncommitted changes"));
+
+        git_output(&worktree, &["add", "b.txt"]).expect("add");
+        git_output(&worktree, &["commit", "-q", "-m", "work"]).expect("commit");
+        let problems = stranded_task_work(&state);
+        assert_eq!(
+            problems,
+            ["task call-audio: branch crank/r1/call-audio is not merged into any other branch"]
+        );
+        let err = cmd_runs_archive(&RunsArchiveArgs {
+            state_dir: cfg.state_dir.clone(),
+            output: None,
+            delete_original: true,
+        })
+        .expect_err("unmerged work");
+        assert!(format!("{err:#}").contains("refusing --delete-original"));
+        assert!(state_path(&cfg.state_dir).exists());
+
+        git(&["merge", "-q", "crank/r1/call-audio"]);
+        assert!(stranded_task_work(&state).is_empty());
+
+        // Deleting the run removes its worktrees, which live outside the state dir.
+        state.status = RunStatus::Completed;
+        save_state(&mut state, &cfg.state_dir).expect("save state");
+        write_run_summary(&state, &cfg).expect("write summary");
+        cmd_runs_archive(&RunsArchiveArgs {
+            state_dir: cfg.state_dir.clone(),
+            output: Some(make_temp_dir("archive-stranded-out").join("run.tar.zst")),
+            delete_original: true,
+        })
+        .expect("archive and delete");
+        assert!(!cfg.state_dir.exists());
+        assert!(!worktree.exists());
+        assert!(!git(&["worktree", "list"]).contains("call-audio"));
+    }
+
+    #[test]
+    fn runs_archive_packs_finished_run_without_scratch() {
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        let root = make_temp_dir("runs-archive");
+        cfg.state_dir = root.join("finished");
+        let mut state = init_state(&cfg).expect("init state");
+        save_state(&mut state, &cfg.state_dir).expect("save state");
+        let output = root.join("finished.tar.zst");
+        assert_eq!(
+            default_archive_path(&cfg.state_dir).expect("default path"),
+            output
+        );
+
+        write_run_summary(&state, &cfg).expect("write summary");
+        let err = archive_run(&cfg.state_dir, &output).expect_err("running run");
+        assert!(format!("{err:#}").contains("still running"), "{err:#}");
+
+        state.status = RunStatus::Completed;
+        state.cycle = 4;
+        save_state(&mut state, &cfg.state_dir).expect("save state");
+        let err = archive_run(&cfg.state_dir, &output).expect_err("stale summary");
+        let err = format!("{err:#}");
+        assert!(
+            err.contains("status is \"running\" but state.json says \"completed\"")
+                && err.contains("cycle is 0"),
+            "{err}"
+        );
+
+        write_run_summary(&state, &cfg).expect("write summary");
+        fs::write(
+            cfg.state_dir.join("logs/orchestrator.events.jsonl"),
+            "{}\n".repeat(500),
+        )
+        .expect("write events");
+        fs::write(cfg.state_dir.join("governor.heartbeat"), "1\n").expect("write heartbeat");
+        fs::write(cfg.state_dir.join("state.json.tmp"), "{").expect("write tmp");
+        let heartbeats = Path::new(&state.tasks[0].coord_dir).join("heartbeats");
+        ensure_dir(&heartbeats).expect("create heartbeats");
+        fs::write(heartbeats.join("dev.json"), "{}").expect("write role heartbeat");
+        fs::write(
+            Path::new(&state.tasks[0].coord_dir).join("state.md"),
+            "done",
+        )
+        .expect("write coord state");
+
+        let stats = archive_run(&cfg.state_dir, &output).expect("archive");
+        assert_eq!(stats.skipped, 3);
+        assert_eq!(
+            archive_contents(&output).expect("read back").files,
+            stats.files
+        );
+        assert!(!root.join("finished.tar.zst.partial").exists());
+
+        let mut archive = tar::Archive::new(
+            zstd::Decoder::new(File::open(&output).expect("open archive")).expect("decoder"),
+        );
+        let names: Vec<String> = archive
+            .entries()
+            .expect("entries")
+            .map(|e| {
+                e.expect("entry")
+                    .path()
+                    .expect("path")
+                    .display()
+                    .to_string()
+            })
+            .collect();
+        for kept in [
+            "finished/state.json",
+            "finished/run-summary.json",
+            "finished/logs/orchestrator.events.jsonl",
+            "finished/coord/call-audio/state.md",
+        ] {
+            assert!(names.iter().any(|n| n == kept), "missing {kept}: {names:?}");
+        }
+        assert!(
+            !names
+                .iter()
+                .any(|n| n.contains("heartbeat") || n.ends_with(".tmp")),
+            "{names:?}"
+        );
+
+        let err = archive_run(&cfg.state_dir, &output).expect_err("existing output");
+        assert!(format!("{err:#}").contains("already exists"), "{err:#}");
+        let err = archive_run(&cfg.state_dir, &cfg.state_dir.join("self.tar.zst"))
+            .expect_err("output inside state dir");
+        assert!(
+            format!("{err:#}").contains("outside the state dir"),
+            "{err:#}"
+        );
+    }
+
+    #[test]
+    fn claude_thinking_levels_map_to_token_budgets() {
+        let claude = |thinking: &str, budget: Option<u32>| ClaudeBackendConfig {
+            binary: "claude".to_string(),
+            model: "opus".to_string(),
+            thinking: thinking.to_string(),
+            thinking_budget_tokens: budget,
+            extra_args: Vec::new(),
+        };
+        assert_eq!(claude("high", None).thinking_budget(), Some(32_000));
+        assert_eq!(claude("xhigh", None).thinking_budget(), Some(64_000));
+        assert_eq!(
+            claude("xhigh", Some(20_000)).thinking_budget(),
+            Some(20_000)
+        );
+        assert_eq!(claude("max", None).thinking_budget(), None);
+
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        assert!(claude_thinking_problems(&cfg).is_empty());
+        cfg.backend.fallbacks = vec![
+            BackendConfig::Claude(claude("max", None)),
+            BackendConfig::Claude(claude("max", Some(48_000))),
+            BackendConfig::Claude(claude("high", Some(0))),
+        ];
+        let problems = claude_thinking_problems(&cfg);
+        assert_eq!(problems.len(), 2, "{problems:?}");
+        assert!(problems[0].contains("thinking 'max' has no token budget"));
+        assert!(problems[1].contains("must be positive"));
+    }
+
+    #[test]
+    fn codex_config_file_and_overrides_become_config_flags() {
+        let dir = make_temp_dir("codex-config");
+        let file = dir.join("codex.toml");
+        fs::write(
+            &file,
+            "model_provider = \"org\"\n\n[model_providers.org]\nbase_url = \"https://llm.example\"\n\n[profiles.ci]\napproval_policy = \"never\"\n",
+        )
+        .expect("write codex config");
+        let backend: CodexBackendConfig = toml::from_str(&format!(
+            r#"
+model = "gpt-5.3-codex"
+thinking = "xhigh"
+profile = "ci"
+config_file = "{}"
+config_overrides = {{ model_provider = "local", "sandbox_workspace_write.network_access" = true }}
+"#,
+            file.display()
+        ))
+        .expect("codex backend should parse");
+        assert_eq!(backend.profile.as_deref(), Some("ci"));
+        assert_eq!(
+            codex_config_overrides(&backend).expect("overrides"),
+            [
+                "model_provider=\"org\"",
+                "model_providers.org.base_url=\"https://llm.example\"",
+                "profiles.ci.approval_policy=\"never\"",
+                "model_provider=\"local\"",
+                "sandbox_workspace_write.network_access=true",
+            ]
+        );
+
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        let mut missing = backend.clone();
+        missing.config_file = Some(dir.join("absent.toml"));
+        missing.profile = Some(" ".to_string());
+        cfg.backend.fallbacks = vec![BackendConfig::Codex(backend), BackendConfig::Codex(missing)];
+        let problems = config_problems(&cfg);
+        assert!(
+            problems
+                .iter()
+                .any(|p| p == "codex backend profile must not be empty"),
+            "{problems:?}"
+        );
+        assert!(
+            problems.iter().any(
+                |p| p.contains("failed to read codex config_file") && p.contains("absent.toml")
+            ),
+            "{problems:?}"
+        );
+    }
+
+    #[test]
+    fn live_turn_tracks_streamed_events_and_clears_after_the_turn() {
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        cfg.state_dir = make_temp_dir("live-turn");
+        let mut state = init_state(&cfg).expect("init state");
+        let redactor = Redactor::new(&cfg.logging).expect("redactor");
+        let progress = RefCell::new(LiveTurn {
+            task_id: "call-audio".to_string(),
+            started_at: now_iso(),
+            ..LiveTurn::default()
+        });
+        let ctx = TurnContext {
+            progress: Some(&progress),
+            ..turn_context(&cfg, &state.tasks[0], "prompt", &redactor)
+        };
+        ctx.append_event(r#"{"type":"thread.started","thread_id":"t1"}"#)
+            .expect("append event");
+        ctx.append_event("plain stderr-ish line")
+            .expect("append event");
+        ctx.append_event(r#"{"type":"item.completed"}"#)
+            .expect("append event");
+        let live = progress.borrow().clone();
+        assert_eq!(live.events, 3);
+        assert_eq!(live.bytes, 42 + 21 + 25);
+        assert_eq!(live.last_event_type.as_deref(), Some("item.completed"));
+        assert!(live.last_event_at.is_some());
+
+        state.live_turn = Some(live);
+        save_state(&mut state, &cfg.state_dir).expect("save state");
+        let snapshot: Value =
+            serde_json::from_str(&fs::read_to_string(state_path(&cfg.state_dir)).expect("read"))
+                .expect("state json");
+        assert_eq!(snapshot["live_turn"]["events"], 3);
+        let line = render_run_overview(&run_overview(&cfg.state_dir, now_epoch()));
+        assert!(
+            line.contains("\tturn call-audio since ")
+                && line.ends_with(": 3 events, 88 bytes, last item.completed"),
+            "{line}"
+        );
+        // A resumed governor drops the dead turn.
+        assert!(init_state(&cfg).expect("resume").live_turn.is_none());
+
+        let (state, _) = simulated_run("live-turn-sim", "", |_| {});
+        assert!(state.live_turn.is_none());
+    }
+
+    #[test]
+    fn cycle_timings_are_logged_and_summarized() {
+        let scenario = "[[call-audio]]\naction = \"fail\"\n\n[[call-audio]]\naction = \"stall\"\nsleep_secs = 7\n";
+        let (state, _) = simulated_run("sim-timings", scenario, |cfg| {
+            cfg.recovery.backoff_initial_secs = 100;
+            cfg.recovery.backoff_max_secs = 100;
+        });
+        let state_dir = Path::new(&state.state_dir);
+        let timings: Vec<CycleTiming> = fs::read_to_string(cycle_metrics_path(state_dir))
+            .expect("read metrics")
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("timing line"))
+            .collect();
+        let turns: u32 = state.tasks.iter().map(|t| t.turns).sum();
+        assert_eq!(timings.len(), turns as usize);
+        assert_eq!(
+            (
+                timings[0].task_id.as_str(),
+                timings[0].ok,
+                timings[0].sleep_ms
+            ),
+            ("call-audio", false, 100_000)
+        );
+        assert_eq!((timings[1].ok, timings[1].backend_ms), (true, 7_000));
+        assert!(timings[1..].iter().all(|t| t.sleep_ms == 1_000));
+        assert!(timings.windows(2).all(|w| w[0].cycle < w[1].cycle));
+
+        let summary: Value = serde_json::from_str(
+            &fs::read_to_string(run_summary_path(state_dir)).expect("read summary"),
+        )
+        .expect("summary json");
+        let sleep = &summary["cycle_timings"]["sleep"];
+        assert_eq!(sleep["count"], timings.len());
+        assert_eq!(sleep["max_ms"], 100_000);
+        assert_eq!(sleep["p50_ms"], 1_000);
+        assert_eq!(summary["cycle_timings"]["backend"]["max_ms"], 7_000);
+
+        let stats = TimingStats::from_samples((1..=100).rev().collect()).expect("stats");
+        assert_eq!(
+            (stats.p50_ms, stats.p90_ms, stats.p99_ms, stats.max_ms),
+            (50, 90, 99, 100)
+        );
+        assert_eq!(stats.mean_ms, 50.5);
+        assert!(TimingStats::from_samples(Vec::new()).is_none());
+    }
+
+    #[test]
+    fn dirty_workspace_is_warned_refused_or_stashed_at_start() {
+        let repo = make_temp_dir("dirty-start");
+        let git = |args: &[&str]| git_output(&repo, args).expect("git command");
+        git(&["init", "-q"]);
+        git(&["config", "user.email", "crank@example.com"]);
+        git(&["config", "user.name", "crank"]);
+        fs::write(repo.join("a.txt"), "one\n").unwrap();
+        git(&["add", "a.txt"]);
+        git(&["commit", "-q", "-m", "init"]);
+
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        cfg.workspace = repo.clone();
+        cfg.state_dir = repo.join(".crank-state");
+        let state = init_state(&cfg).expect("init state");
+        let journal = PathBuf::from(&state.journal_path);
+
+        // The untracked state dir alone does not make the workspace dirty.
+        cfg.git.require_clean_start = true;
+        guard_dirty_workspace(&cfg, "r1", &journal).expect("clean workspace");
+
+        fs::write(repo.join("a.txt"), "manual edit\n").unwrap();
+        fs::write(repo.join("notes.txt"), "draft\n").unwrap();
+        let err = guard_dirty_workspace(&cfg, "r1", &journal).expect_err("dirty workspace");
+        let err = format!("{err:#}");
+        assert!(
+            err.contains("2 uncommitted change(s)") && err.contains("notes.txt"),
+            "{err}"
+        );
+
+        cfg.git.require_clean_start = false;
+        guard_dirty_workspace(&cfg, "r1", &journal).expect("warn only");
+        let text = fs::read_to_string(&journal).expect("read journal");
+        assert!(text.contains("dirty workspace refused"));
+        assert!(text.contains("WARNING: dirty workspace at start"));
+
+        // Untracked run inputs stay put; a per-task checkout is guarded too.
+        fs::write(repo.join("crank.toml"), "# run config\n").unwrap();
+        fs::write(repo.join("audio-plan.md"), "- [ ] step\n").unwrap();
+        cfg.config_files = vec![repo.join("crank.toml")];
+        cfg.tasks[0].todo_file = repo.join("audio-plan.md");
+        let server = make_temp_dir("dirty-start-server");
+        let server_git = |args: &[&str]| git_output(&server, args).expect("git command");
+        server_git(&["init", "-q"]);
+        server_git(&["config", "user.email", "crank@example.com"]);
+        server_git(&["config", "user.name", "crank"]);
+        fs::write(server.join("b.txt"), "one\n").unwrap();
+        server_git(&["add", "b.txt"]);
+        server_git(&["commit", "-q", "-m", "init"]);
+        fs::write(server.join("b.txt"), "manual edit\n").unwrap();
+        cfg.tasks[1].workspace = Some(server.clone());
+
+        cfg.git.stash_on_start = true;
+        guard_dirty_workspace(&cfg, "r1", &journal).expect("stash");
+        assert_eq!(fs::read_to_string(repo.join("a.txt")).unwrap(), "one\n");
+        assert!(!repo.join("notes.txt").exists());
+        assert!(repo.join("crank.toml").is_file() && repo.join("audio-plan.md").is_file());
+        assert!(journal.exists(), "stash must leave the state dir alone");
+        assert!(git(&["stash", "list"]).contains("crank: before run r1"));
+        assert_eq!(fs::read_to_string(server.join("b.txt")).unwrap(), "one\n");
+        assert!(server_git(&["stash", "list"]).contains("crank: before run r1"));
+        let text = fs::read_to_string(&journal).expect("read journal");
+        assert!(text.contains("workspace stashed") && text.contains("git stash apply "));
+        assert!(text.contains(&format!("{} has 1 uncommitted", server.display())));
+    }
+
+    #[test]
+    fn completion_waits_for_required_artifacts_and_checksum() {
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&format!(
+            "{text}\ncompletion_requires = [\"tests_passed.marker\", {{ path = \"report.md\", matches = \"(?m)^result: pass$\" }}]\n"
+        ))
+        .expect("config with completion_requires should parse");
+        // The appended keys land on the last [[tasks]] table; test that task.
+        let last = cfg.tasks.len() - 1;
+        cfg.tasks.swap(0, last);
+        cfg.state_dir = make_temp_dir("completion-requires");
+        cfg.workspace = cfg.state_dir.clone();
+        let done = cfg.state_dir.join("done.md");
+        cfg.tasks[0].completion_file = Some(done.clone());
+        cfg.tasks[0].completion_file_sha256 =
+            Some("3b62c9d2bbb4a6c1e6f0a3bd1b6d2b1cf1c5e1a0c5f5d4c3b2a1f0e9d8c7b6a5".to_string());
+        let mut state = init_state(&cfg).expect("init state");
+        state.tasks[0].status = TaskStatus::Running;
+        let coord = PathBuf::from(&state.tasks[0].coord_dir);
+        fs::create_dir_all(&coord).expect("create coord dir");
+        let journal = journal_path(&cfg.state_dir);
+        let prompt_line = |state: &RunState| {
+            let templates = PromptTemplates::load(&cfg).expect("templates");
+            build_prompt(&cfg, &templates, state, &state.tasks[0], None, &[])
+                .expect("prompt")
+                .text
+        };
+        assert!(prompt_line(&state).contains("report.md (content matching /(?m)^result: pass$/)"));
+
+        fs::write(&done, "done\n").expect("write completion file");
+        let unverified =
+            sync_completion_and_progress(&mut state, cfg.policy.require_decision_quorum);
+        assert_eq!(unverified, vec![0]);
+        verify_task_completions(&cfg, &mut state, &journal, &unverified).expect("verify");
+        assert_eq!(state.tasks[0].status, TaskStatus::Running);
+        let note = state.tasks[0].pending_note.clone().expect("recovery note");
+        assert!(note.contains("expected 3b62c9d2"), "{note}");
+        assert!(
+            note.contains("tests_passed.marker does not exist"),
+            "{note}"
+        );
+        assert!(note.contains("report.md does not exist"), "{note}");
+        assert!(
+            sync_completion_and_progress(&mut state, cfg.policy.require_decision_quorum).is_empty()
+        );
+
+        let actual = sha256_file(&done).expect("hash");
+        state.tasks[0].completion_file_sha256 = Some(actual.to_uppercase());
+        fs::write(coord.join("tests_passed.marker"), "").expect("write marker");
+        fs::write(coord.join("report.md"), "result: fail\n").expect("write report");
+        state.tasks[0].verify_rejected_epoch = Some(0);
+        let unverified =
+            sync_completion_and_progress(&mut state, cfg.policy.require_decision_quorum);
+        verify_task_completions(&cfg, &mut state, &journal, &unverified).expect("verify");
+        let note = state.tasks[0].pending_note.clone().expect("recovery note");
+        assert!(
+            note.contains("does not match /(?m)^result: pass$/"),
+            "{note}"
+        );
+        assert!(
+            !note.contains("sha256") && !note.contains("marker"),
+            "{note}"
+        );
+
+        fs::write(coord.join("report.md"), "summary\nresult: pass\n").expect("write report");
+        state.tasks[0].verify_rejected_epoch = Some(0);
+        let unverified =
+            sync_completion_and_progress(&mut state, cfg.policy.require_decision_quorum);
+        verify_task_completions(&cfg, &mut state, &journal, &unverified).expect("verify");
+        assert_eq!(state.tasks[0].status, TaskStatus::Completed);
+
+        cfg.tasks[0].completion_file = None;
+        cfg.tasks[0].completion_file_sha256 = Some("abc".to_string());
+        cfg.tasks[0].completion_requires = vec![CompletionRequirement::Matches {
+            path: PathBuf::from("x"),
+            matches: "(".to_string(),
+        }];
+        let problems = config_problems(&cfg);
+        for expected in [
+            "completion_file_sha256 without completion_file",
+            "must be 64 hex digits",
+            "completion_requires pattern '(' is invalid",
+        ] {
+            assert!(
+                problems.iter().any(|p| p.contains(expected)),
+                "{problems:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn config_includes_merge_in_order_under_the_including_file() {
+        let dir = make_temp_dir("config-include");
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let roles_start = text.find("[roles.implementer]").expect("roles section");
+        let tasks_start = text.find("[[tasks]]").expect("tasks section");
+        ensure_dir(&dir.join("common")).expect("create common dir");
+        fs::write(
+            dir.join("common/recovery.toml"),
+            "[recovery]\nmax_recovery_attempts_per_task = 5\n\n[limits]\nmax_turns_per_run = 40\n\n[digest]\nevery_cycles = 3\n",
+        )
+        .expect("write recovery fragment");
+        fs::write(
+            dir.join("common/roles.toml"),
+            format!(
+                "include = [\"../base.toml\"]\n\n{}",
+                &text[roles_start..tasks_start]
+            ),
+        )
+        .expect("write roles fragment");
+        fs::write(
+            dir.join("base.toml"),
+            "poll_interval_secs = 7\n\n[digest]\nevery_cycles = 6\n",
+        )
+        .expect("write base fragment");
+        let main = dir.join("run.toml");
+        fs::write(
+            &main,
+            format!(
+                "include = [\"common/recovery.toml\", \"common/roles.toml\"]\n{}{}",
+                &text[..roles_start],
+                &text[tasks_start..]
+            ),
+        )
+        .expect("write main config");
+
+        let cfg = load_config(&main).expect("config with includes");
+        assert_eq!(cfg.roles.reviewer_2.harness, "claude");
+        // The including file beats its includes, key by key.
+        assert_eq!(cfg.poll_interval_secs, 1);
+        assert_eq!(cfg.recovery.max_recovery_attempts_per_task, 2);
+        assert_eq!(cfg.limits.max_turns_per_run, Some(40));
+        // Later includes beat earlier ones, nested includes included.
+        assert_eq!(cfg.digest.every_cycles, Some(6));
+
+        let files = read_config_source(&main).expect("sources").files;
+        assert_eq!(files.len(), 4);
+        assert_eq!(
+            config_sources_mtime(&main),
+            files.iter().filter_map(|f| mtime_epoch(f)).max()
+        );
+
+        fs::write(dir.join("base.toml"), "include = [\"run.toml\"]\n").expect("write cycle");
+        let err = format!("{:#}", load_config(&main).expect_err("include cycle"));
+        assert!(err.contains("config include cycle"), "{err}");
+        fs::write(dir.join("base.toml"), "include = \"x.toml\"\n").expect("write bad include");
+        let err = format!("{:#}", load_config(&main).expect_err("bad include"));
+        assert!(err.contains("include must be an array of paths"), "{err}");
+    }
+
+    #[test]
+    fn state_sync_mirrors_state_files_and_journals_failures() {
+        let dir = make_temp_dir("state-sync");
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        cfg.state_dir = dir.join("state");
+        let target = dir.join("mirror");
+        ensure_dir(&target).expect("create mirror dir");
+        cfg.sync.rsync_target = Some(target.display().to_string());
+        cfg.sync.s3_uri = Some("s3://bucket/runs/".to_string());
+        let mut state = init_state(&cfg).expect("init state");
+        save_state(&mut state, &cfg.state_dir).expect("save state");
+
+        let files = [state_path(&cfg.state_dir)];
+        let argv: Vec<Vec<String>> = sync_commands(&cfg.sync, &files)
+            .iter()
+            .map(|cmd| {
+                std::iter::once(cmd.get_program())
+                    .chain(cmd.get_args())
+                    .map(|a| a.to_string_lossy().into_owned())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(argv[0][0], "rsync");
+        assert_eq!(argv[0].last(), Some(&format!("{}/", target.display())));
+        assert_eq!(
+            argv[1][argv[1].len() - 1],
+            "s3://bucket/runs/state.json".to_string()
+        );
+        assert!(argv[1].contains(&"--cli-read-timeout=60".to_string()));
+        assert_eq!(sync_backoff_secs(1), SYNC_BACKOFF_INITIAL_SECS);
+        assert_eq!(sync_backoff_secs(30), SYNC_BACKOFF_MAX_SECS);
+
+        cfg.sync.s3_uri = None;
+        let sync = StateSync::start(&cfg.sync, &cfg.state_dir).expect("sync configured");
+        sync.request();
+        sync.finish();
+        let journal = fs::read_to_string(journal_path(&cfg.state_dir)).expect("journal");
+        if find_on_path("rsync").is_some() {
+            assert!(target.join("state.json").is_file());
+            assert!(target.join("JOURNAL.md").is_file());
+            assert!(!journal.contains("state sync failed"), "{journal}");
+        } else {
+            assert!(
+                journal.contains("**state sync failed**\nAttempt 1:"),
+                "{journal}"
+            );
+            assert!(
+                journal.contains("Final attempt after the run stopped"),
+                "{journal}"
+            );
+        }
+    }
+
+    #[test]
+    fn task_leases_partition_tasks_between_nodes() {
+        let dir = make_temp_dir("task-leases");
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        cfg.state_dir = dir.join("state-a");
+        let cluster = |node: &str| ClusterConfig {
+            lease_dir: dir.join("leases"),
+            node_id: Some(node.to_string()),
+            lease_secs: 600,
+        };
+        let a = LeaseBoard::new(&cluster("a")).expect("board a");
+        let b = LeaseBoard::new(&cluster("b")).expect("board b");
+        let journal = dir.join("JOURNAL.md");
+
+        assert_eq!(a.claim("call-audio").expect("claim"), None);
+        assert_eq!(a.claim("call-audio").expect("renew"), None);
+        let held = b.claim("call-audio").expect("claim held");
+        assert_eq!(held.map(|l| l.node), Some("a".to_string()));
+
+        // b's view: call-audio is busy elsewhere and every other task waits on it.
+        let mut state = init_state(&cfg).expect("init state");
+        let elsewhere = b.sync(&mut state, &journal).expect("sync");
+        assert!(elsewhere.contains("call-audio"));
+        assert_eq!(
+            choose_next_pending_task(&state, SchedulingPolicy::Fifo, &elsewhere),
+            None
+        );
+
+        // An expired lease can be taken over; its old holder then gives the task up.
+        let mut stale = a.read("call-audio").expect("read").expect("lease");
+        stale.expires_epoch = now_epoch() - 1;
+        a.write(&stale, true).expect("expire lease");
+        assert_eq!(b.claim("call-audio").expect("take over"), None);
+        let (holder, _) = a.renew("call-audio", &journal).expect("renew");
+        assert_eq!(holder.map(|l| l.node), Some("b".to_string()));
+        // An unreadable lease is journaled and retried soon, not fatal to the turn.
+        fs::write(a.path("call-video"), "{not json").expect("corrupt lease");
+        let (holder, next_at) = a.renew("call-video", &journal).expect("renew");
+        assert!(holder.is_none());
+        assert!(next_at <= now_epoch() + LEASE_RETRY_SECS);
+        fs::remove_file(a.path("call-video")).expect("remove corrupt lease");
+        let mut state_a = init_state(&cfg).expect("init state");
+        state_a.tasks[0].status = TaskStatus::Running;
+        let elsewhere = a.sync(&mut state_a, &journal).expect("sync a");
+        assert_eq!(state_a.tasks[0].status, TaskStatus::Pending);
+        assert!(elsewhere.contains("call-audio"));
+
+        // Outcomes published by one node are adopted by the others.
+        state.tasks[0].status = TaskStatus::Completed;
+        b.sync(&mut state, &journal).expect("publish");
+        a.sync(&mut state_a, &journal).expect("adopt");
+        assert_eq!(state_a.tasks[0].status, TaskStatus::Completed);
+        assert!(state_a.tasks[0].post_task_hooks_ran);
+        let text = fs::read_to_string(&journal).expect("journal");
+        assert!(text.contains("task lease lost"), "{text}");
+        assert!(text.contains("lease renewal failed"), "{text}");
+        assert!(text.contains("Task call-audio ended as completed on node b."));
+    }
+
+    #[test]
+    fn abandoned_streaming_turns_kill_the_backend() {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg("echo started; sleep 30; echo never");
+        let started = Instant::now();
+        let err = run_backend_command_streaming(cmd, "", "sh", |_| Err(anyhow!("lease moved")))
+            .expect_err("callback error ends the turn");
+        assert!(err.to_string().contains("lease moved"));
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn a_second_node_adopts_tasks_finished_by_the_first() {
+        let leases = make_temp_dir("sim-cluster-leases");
+        let join = |node: &str| {
+            let lease_dir = leases.clone();
+            let node = node.to_string();
+            move |cfg: &mut Config| {
+                cfg.cluster = Some(ClusterConfig {
+                    lease_dir,
+                    node_id: Some(node),
+                    lease_secs: 3600,
+                });
+            }
+        };
+        let (first, _) = simulated_run("sim-cluster-a", "", join("a"));
+        assert_eq!(first.status, RunStatus::Completed);
+        assert!(first.tasks.iter().all(|t| t.turns > 0));
+
+        let (second, _) = simulated_run("sim-cluster-b", "", join("b"));
+        assert_eq!(second.status, RunStatus::Completed);
+        assert!(second.tasks.iter().all(|t| t.turns == 0));
+        assert!(
+            second
+                .tasks
+                .iter()
+                .all(|t| t.status == TaskStatus::Completed)
+        );
+    }
+
+    #[test]
+    fn mux_commands_title_the_pane_and_split_a_log_tail() {
+        let argv = |cmd: Command| -> Vec<String> {
+            std::iter::once(cmd.get_program())
+                .chain(cmd.get_args())
+                .map(|a| a.to_string_lossy().into_owned())
+                .collect()
+        };
+        assert_eq!(
+            argv(Mux::Zellij.rename_pane_command("crank: call-audio")),
+            ["zellij", "action", "rename-pane", "crank: call-audio"]
+        );
+        let tmux_title = argv(Mux::Tmux.rename_pane_command("crank: call-audio"));
+        assert_eq!(tmux_title[..2], ["tmux", "select-pane"]);
+        assert_eq!(
+            tmux_title[tmux_title.len() - 2..],
+            ["-T", "crank: call-audio"]
+        );
+
+        let state_dir = Path::new("/runs/it's late");
+        let tail = attach_tail_command(state_dir);
+        assert_eq!(
+            tail,
+            "tail -n 40 -F '/runs/it'\\''s late/JOURNAL.md' '/runs/it'\\''s late/logs/orchestrator.events.jsonl'"
+        );
+        assert_eq!(
+            argv(Mux::Tmux.split_command(&tail)),
+            ["tmux", "split-window", "-h", tail.as_str()]
+        );
+        assert_eq!(
+            argv(Mux::Zellij.split_command("true"))[..4],
+            ["zellij", "run", "--direction", "right"]
+        );
+
+        let err = ctl_attach(&make_temp_dir("mux-attach"), Some(Mux::Tmux)).expect_err("no run");
+        assert!(err.to_string().contains("no run state"), "{err}");
+    }
+
+    #[test]
+    fn missing_control_blocks_escalate_through_recovery_strategies() {
+        let scenario = "[[call-audio]]\naction = \"no_control\"\n".repeat(6);
+        let (state, _) = simulated_run("sim-control-strategies", &scenario, |cfg| {
+            cfg.recovery.control_miss_threshold = 2;
+            cfg.recovery.control_strategies = vec![
+                ControlStrategy::StrictSuffix,
+                ControlStrategy::DowngradeThinking,
+                ControlStrategy::FallbackBackend,
+            ];
+            cfg.backend.fallbacks = vec![BackendConfig::Mock(MockBackendConfig {
+                steps_per_task: 1,
+                scenario: None,
+            })];
+        });
+        assert_eq!(state.status, RunStatus::Completed);
+        assert_eq!(state.backend_index, 1);
+        let audio = &state.tasks[0];
+        assert_eq!(audio.turns, 7);
+        assert_eq!(audio.control_misses, 0);
+        assert_eq!(audio.control_strategies.len(), 3);
+
+        let state_dir = Path::new(&state.state_dir);
+        let turn = |name: &str| fs::read_to_string(state_dir.join("turns").join(name)).unwrap();
+        assert!(!turn("2-call-audio.md").contains("## Control block (required)"));
+        assert!(turn("3-call-audio.md").contains("## Control block (required)"));
+        let journal = fs::read_to_string(journal_path(state_dir)).expect("journal");
+        for outcome in [
+            "applied strict_suffix: later prompts end with a strict control block instruction.",
+            "applied downgrade_thinking: thinking cannot be lowered further.",
+            "applied fallback_backend: run failed over from the mock backend to mock.",
+        ] {
+            assert!(journal.contains(outcome), "{outcome}\n{journal}");
+        }
+    }
+
+    #[test]
+    fn downgrade_thinking_lowers_the_turn_thinking_level() {
+        assert_eq!(lower_thinking("xhigh"), Some("high"));
+        assert_eq!(lower_thinking("low"), None);
+        assert_eq!(lower_thinking("turbo"), None);
+        assert_eq!(claude_level_budget("medium"), Some(10_000));
+
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        cfg.state_dir = make_temp_dir("control-downgrade");
+        cfg.backend = BackendSection::from(BackendConfig::Claude(ClaudeBackendConfig {
+            binary: "claude".to_string(),
+            model: "opus".to_string(),
+            thinking: "high".to_string(),
+            thinking_budget_tokens: None,
+            extra_args: Vec::new(),
+        }));
+        cfg.recovery.control_miss_threshold = 1;
+        cfg.recovery.control_strategies = vec![ControlStrategy::DowngradeThinking];
+        let mut state = init_state(&cfg).expect("init state");
+        let journal = journal_path(&cfg.state_dir);
+        note_control_miss(&cfg, &mut state, 0, &journal).expect("first miss");
+        assert_eq!(state.tasks[0].thinking_override.as_deref(), Some("medium"));
+        // Strategies run out; further misses change nothing.
+        note_control_miss(&cfg, &mut state, 0, &journal).expect("second miss");
+        assert_eq!(state.tasks[0].thinking_override.as_deref(), Some("medium"));
+        let redactor = Redactor::new(&cfg.logging).expect("redactor");
+        let ctx = turn_context(&cfg, &state.tasks[0], "prompt", &redactor);
+        assert_eq!(ctx.thinking, Some("medium"));
+    }
+
+    #[test]
+    fn degenerate_responses_count_as_failed_turns() {
+        let mut recovery = RecoveryConfig {
+            degenerate_patterns: vec!["(?i)i cannot continue".to_string()],
+            min_response_chars: Some(20),
+            ..Default::default()
+        };
+        compile_degenerate_patterns(&mut recovery).expect("compile patterns");
+        assert_eq!(
+            degenerate_response_reason(&recovery, "  sorry  \n"),
+            Some("response is 5 chars, below min_response_chars (20)".to_string())
+        );
+        assert!(
+            degenerate_response_reason(
+                &recovery,
+                "Apologies, but I cannot continue with this task."
+            )
+            .is_some_and(|r| r.contains("degenerate pattern"))
+        );
+        assert_eq!(
+            degenerate_response_reason(
+                &recovery,
+                "Implemented the codec switch and ran the tests."
+            ),
+            None
+        );
+
+        let scenario = "[[call-audio]]\naction = \"progress\"\nmessage = \"I apologize, I cannot continue.\"\n"
+            .repeat(2);
+        let (state, elapsed) = simulated_run("sim-degenerate", &scenario, |cfg| {
+            cfg.recovery.degenerate_patterns = vec!["(?i)i cannot continue".to_string()];
+            compile_degenerate_patterns(&mut cfg.recovery).expect("compile patterns");
+            cfg.recovery.backoff_initial_secs = 100;
+            cfg.recovery.backoff_max_secs = 1000;
+        });
+        assert_eq!(state.status, RunStatus::Completed);
+        assert_eq!(state.tasks[0].turns, 3);
+        // Backed off 100s and then 200s, as for any failed turn.
+        assert!((300..330).contains(&elapsed), "elapsed {elapsed}s");
+        let state_dir = Path::new(&state.state_dir);
+        let journal = fs::read_to_string(journal_path(state_dir)).expect("journal");
+        assert!(
+            journal.contains("degenerate response: response matches degenerate pattern"),
+            "{journal}"
+        );
+        assert!(journal.contains("degenerate turn control"), "{journal}");
+        assert!(state_dir.join("turns/1-call-audio.md").is_file());
+
+        let mut bad = RecoveryConfig {
+            degenerate_patterns: vec!["(unclosed".to_string()],
+            ..Default::default()
+        };
+        let err = compile_degenerate_patterns(&mut bad).expect_err("invalid pattern");
+        assert!(err.to_string().contains("'(unclosed' is invalid"), "{err}");
+    }
+
+    #[test]
+    fn schema_export_describes_config_state_and_summary() {
+        let config = json_schema(SchemaTarget::Config);
+        let properties = config.get("properties").expect("config properties");
+        for key in ["workspace", "tasks", "backend", "include"] {
+            assert!(properties.get(key).is_some(), "missing {key}");
+        }
+        let state = serde_json::to_string(&json_schema(SchemaTarget::State)).expect("state");
+        assert!(state.contains("\"tasks\""));
+        let summary = json_schema(SchemaTarget::Summary);
+        assert!(
+            summary
+                .get("properties")
+                .and_then(|p| p.get("run_id"))
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn completions_and_man_pages_cover_nested_subcommands() {
+        let mut script = Vec::new();
+        clap_complete::generate(
+            clap_complete::Shell::Bash,
+            &mut Cli::command(),
+            "crank",
+            &mut script,
+        );
+        let script = String::from_utf8(script).expect("utf8 completions");
+        assert!(script.contains("add-task"));
+        assert!(script.contains("archive"));
+
+        let dir = make_temp_dir("man-pages");
+        cmd_man(&ManArgs {
+            out_dir: Some(dir.clone()),
+        })
+        .expect("write man pages");
+        assert!(dir.join("crank.1").exists());
+        assert!(dir.join("crank-ctl-edit-task.1").exists());
+        assert!(dir.join("crank-runs-archive.1").exists());
+    }
+
+    #[test]
+    fn merged_task_worktrees_are_removed_and_gc_finds_stale_branches() {
+        let repo = make_temp_dir("worktree-gc");
+        let git = |args: &[&str]| git_output(&repo, args).expect("git command");
+        git(&["init", "-q"]);
+        git(&["config", "user.email", "crank@example.com"]);
+        git(&["config", "user.name", "crank"]);
+        fs::write(repo.join("a.txt"), "one\n").unwrap();
+        git(&["add", "a.txt"]);
+        git(&["commit", "-q", "-m", "init"]);
+
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        cfg.workspace = repo.clone();
+        cfg.state_dir = repo.join(".crank-state");
+        cfg.tasks[0].branch = true;
+        cfg.tasks[1].branch = true;
+        cfg.git.remove_merged_worktrees = true;
+        let mut state = init_state(&cfg).expect("init state");
+        let journal = journal_path(&cfg.state_dir);
+        for idx in 0..2 {
+            prepare_task_worktree(&cfg, "r1", &mut state.tasks[idx]).expect("worktree");
+            let worktree = task_workspace(&cfg, &state.tasks[idx]);
+            fs::write(worktree.join(format!("{idx}.txt")), "work\n").unwrap();
+            git_output(&worktree, &["add", "."]).expect("add");
+            git_output(&worktree, &["commit", "-q", "-m", "work"]).expect("commit");
+            state.tasks[idx].status = TaskStatus::Completed;
+        }
+        git(&["merge", "-q", "crank/r1/call-audio"]);
+        let merged_worktree = task_workspace(&cfg, &state.tasks[0]);
+        // Worktrees default to crank's own dir in the repo's git dir: never
+        // outside the repo, never in the checkout's status.
+        let root = fs::canonicalize(&repo)
+            .expect("canonical repo")
+            .join(".git")
+            .join("crank-worktrees");
+        assert_eq!(merged_worktree, root.join("r1").join("call-audio"));
+        let status = git(&["status", "--porcelain", "--untracked-files=all"]);
+        assert!(!status.contains("crank-worktrees"), "{status}");
+        // Beside the repo is opt-in through worktree_dir.
+        let mut sibling = cfg.clone();
+        sibling.git.worktree_dir = Some(PathBuf::from("../repo.crank-worktrees"));
+        assert_eq!(
+            task_worktrees_root(&sibling, &repo).expect("root"),
+            repo.join("../repo.crank-worktrees")
+        );
+
+        let mut kept = std::collections::BTreeSet::new();
+        remove_merged_task_worktrees(&cfg, &mut state, &journal, &mut kept).expect("cleanup");
+        assert!(!merged_worktree.exists());
+        assert!(state.tasks[0].workspace.is_none());
+        assert_eq!(
+            state.tasks[0].branch.as_deref(),
+            Some("crank/r1/call-audio")
+        );
+        assert!(state.tasks[1].workspace.is_some());
+        assert!(kept.is_empty());
+        let branches = git(&["branch", "--list", "crank/*"]);
+        assert!(!branches.contains("crank/r1/call-audio"));
+        let text = fs::read_to_string(&journal).expect("journal");
+        assert!(text.contains("task worktree removed"));
+
+        git(&["branch", "crank/old/leftover"]);
+        let mut args = WorktreeGcArgs {
+            workspace: repo.clone(),
+            base: "HEAD".to_string(),
+            force: true,
+            older_than: None,
+            dry_run: true,
+        };
+        // The marker beside the worktrees leads gc to the run's lock.
+        let lock = cfg.state_dir.join("run.lock");
+        fs::write(&lock, format!("pid={}\n", std::process::id())).expect("live lock");
+        let report = gc_task_worktrees(&args).expect("gc");
+        assert!(
+            report.contains(&"kept crank/r1/call-transport: its run is still live".to_string()),
+            "{report:?}"
+        );
+        fs::remove_file(&lock).expect("remove lock");
+        args.force = false;
+        let unmerged = "kept crank/r1/call-transport: not merged into HEAD (--force deletes it)";
+        let report = gc_task_worktrees(&args).expect("gc");
+        assert!(report.contains(&"would remove crank/old/leftover".to_string()));
+        assert!(report.contains(&unmerged.to_string()), "{report:?}");
+
+        args.dry_run = false;
+        args.older_than = Some(parse_age_secs("2w").expect("age"));
+        let report = gc_task_worktrees(&args).expect("gc");
+        assert!(
+            report
+                .iter()
+                .all(|line| line.ends_with("newer than --older-than"))
+        );
+
+        args.older_than = None;
+        let report = gc_task_worktrees(&args).expect("gc");
+        assert_eq!(
+            report,
+            [
+                "removed crank/old/leftover".to_string(),
+                unmerged.to_string()
+            ]
+        );
+        assert!(task_workspace(&cfg, &state.tasks[1]).exists());
+
+        args.force = true;
+        let report = gc_task_worktrees(&args).expect("gc");
+        assert_eq!(report.len(), 1, "{report:?}");
+        assert!(report[0].starts_with("removed crank/r1/call-transport ("));
+        assert!(git(&["branch", "--list", "crank/*"]).trim().is_empty());
+        assert!(!task_workspace(&cfg, &state.tasks[1]).exists());
+        assert!(parse_age_secs("3x").is_err());
+    }
+
+    #[test]
+    fn interactive_init_renders_a_loadable_config() {
+        let script =
+            "/tmp/client\n\n/tmp/runs/client\nbogus\nclaude\n\n\n\napi\n\n-\nui\ntodos/ui.md\n\n\n";
+        let mut input = std::io::Cursor::new(script);
+        let mut out = Vec::new();
+        let answers = prompt_init_answers(&mut input, &mut out, true).expect("answers");
+        let transcript = String::from_utf8(out).expect("utf8");
+        assert!(
+            transcript.contains("unknown backend kind 'bogus'"),
+            "{transcript}"
+        );
+        assert_eq!(answers.run_id, "client");
+        assert_eq!(answers.model, "claude-opus-4-6");
+        assert_eq!(answers.team, None);
+        assert_eq!(answers.tasks.len(), 2);
+        assert_eq!(
+            answers.tasks[0].1,
+            PathBuf::from("/tmp/client/todos/api.md")
+        );
+        assert_eq!(answers.tasks[1].2, vec!["api".to_string()]);
+
+        let cfg: Config = toml::from_str(&render_init_config(&answers, &default_roles()))
+            .expect("rendered config should parse");
+        assert_eq!(cfg.state_dir, PathBuf::from("/tmp/runs/client"));
+        assert_eq!(cfg.backend.primary.kind(), "claude");
+        assert_eq!(cfg.tasks[1].depends_on, vec!["api".to_string()]);
+        assert!(config_graph_problems(&cfg.tasks).is_empty());
+
+        let mut truncated = std::io::Cursor::new("/tmp/client\n");
+        assert!(prompt_init_answers(&mut truncated, &mut Vec::new(), false).is_err());
+    }
+
+    #[test]
+    fn kick_file_cuts_governor_sleep_short() {
+        let dir = make_temp_dir("kick");
+        fs::write(kick_path(&dir), "1\n").expect("write kick");
+        let started = Instant::now();
+        governor_sleep(&dir, Duration::from_secs(30));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(!kick_path(&dir).exists());
+
+        let started = Instant::now();
+        governor_sleep(&dir, Duration::from_millis(300));
+        assert!(started.elapsed() >= Duration::from_millis(300));
+    }
+
+    #[test]
+    fn torn_state_recovers_from_backup() {
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        cfg.state_dir = make_temp_dir("state-backup");
+        let mut state = init_state(&cfg).expect("init state");
+        state.cycle = 1;
+        save_state(&mut state, &cfg.state_dir).expect("save state");
+        assert!(!state_backup_path(&cfg.state_dir).exists());
+        state.cycle = 2;
+        save_state(&mut state, &cfg.state_dir).expect("save state");
+
+        fs::write(state_path(&cfg.state_dir), "{\"run_id\": \"mock").expect("tear state");
+        // A torn primary never replaces the good backup.
+        backup_state(&cfg.state_dir).expect("backup");
+        assert_eq!(read_state(&cfg.state_dir).expect("read state").cycle, 1);
+
+        let recovered = init_state(&cfg).expect("recover state");
+        assert_eq!(recovered.cycle, 1);
+        let journal = fs::read_to_string(journal_path(&cfg.state_dir)).expect("read journal");
+        assert!(journal.contains("state recovered from backup"), "{journal}");
+        let primary = fs::read(state_path(&cfg.state_dir)).expect("read primary");
+        assert!(serde_json::from_slice::<RunState>(&primary).is_ok());
+    }
+
+    #[test]
+    fn config_graph_problems_reports_cycles_and_unknown_deps() {
+        let tasks = vec![
+            task_config("a", &["c"]),
+            task_config("b", &["a", "ghost"]),
+            task_config("c", &["b"]),
+        ];
+        let problems = config_graph_problems(&tasks);
+        assert!(
+            problems
+                .iter()
+                .any(|p| p.contains("unknown task id 'ghost'"))
+        );
+        assert!(
+            problems
+                .iter()
+                .any(|p| p == "dependency cycle: a -> c -> b -> a")
+        );
+
+        let acyclic = vec![task_config("a", &[]), task_config("b", &["a"])];
+        assert!(config_graph_problems(&acyclic).is_empty());
+    }
+
+    #[test]
+    fn load_config_rejects_structural_problems_only() {
+        let dir = make_temp_dir("config-problems");
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let text = text.replacen(
+            "[timeouts]",
+            "[cluster]\nlease_dir = \"/tmp/leases\"\nlease_secs = 10\n\n[timeouts]",
+            1,
+        );
+        let path = dir.join("short-lease.toml");
+        fs::write(&path, text).expect("write config");
+
+        let msg = load_config(&path).expect_err("short lease").to_string();
+        assert!(msg.contains("cluster.lease_secs (10) must exceed"), "{msg}");
+
+        // Environment problems wait for preflight or `config validate`:
+        // `simulate` swaps the backend and `--team` replaces the roles.
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let text = text
+            .replace(
+                "kind = \"mock\"\nsteps_per_task = 2",
+                "kind = \"codex\"\nbinary = \"/nonexistent/crank-codex\"\nmodel = \"gpt-5.3-codex\"\nthinking = \"high\"",
+            )
+            .replace("launch_args = [\"--yolo\"]", "launch_args = []")
+            .replace("call-video-plan.md", "missing-plan.md");
+        let path = dir.join("environment.toml");
+        fs::write(&path, text).expect("write config");
+        let cfg = load_config(&path).expect("structurally valid config loads");
+        assert!(validate_roles(&cfg.roles).is_err());
+        assert!(
+            config_binary_problems(&cfg)
+                .iter()
+                .any(|p| p.contains("'/nonexistent/crank-codex' not found on PATH"))
+        );
+        let files = config_file_problems(&cfg);
+        assert_eq!(files.len(), 1, "{files:?}");
+        assert!(
+            files[0].contains("missing-plan.md does not exist"),
+            "{files:?}"
+        );
+        let redactor = Redactor::new(&cfg.logging).expect("build redactor");
+        assert!(
+            preflight_problems(&cfg, &redactor)
+                .iter()
+                .any(|p| p.contains("missing-plan.md does not exist"))
+        );
+    }
+
+    #[test]
+    fn load_config_rejects_dependency_cycles() {
+        let dir = make_temp_dir("config-cycle");
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let cyclic = text.replacen("depends_on = []", "depends_on = [\"call-video\"]", 1);
+        let path = dir.join("cyclic.toml");
+        fs::write(&path, cyclic).expect("write cyclic config");
+
+        let err = load_config(&path).expect_err("cyclic config should be rejected");
+        let msg = err.to_string();
+        assert!(msg.contains("dependency cycle"), "{msg}");
+        assert!(
+            msg.contains("call-audio") && msg.contains("call-video"),
+            "{msg}"
+        );
+    }
+
+    #[test]
+    fn merge_new_config_tasks_appends_and_reopens_run() {
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        cfg.state_dir = make_temp_dir("merge-tasks");
+        let mut state = init_state(&cfg).expect("init state");
+        for task in &mut state.tasks {
+            task.status = TaskStatus::Completed;
+        }
+        state.status = RunStatus::Completed;
+
+        assert!(merge_new_config_tasks(&mut state, &cfg).is_empty());
+        assert_eq!(state.status, RunStatus::Completed);
+
+        cfg.tasks.push(TaskConfig {
+            id: "call-followup".to_string(),
+            todo_file: PathBuf::from("followup.md"),
+            depends_on: vec!["call-video".to_string()],
+            ..Default::default()
+        });
+        let added = merge_new_config_tasks(&mut state, &cfg);
+        assert_eq!(added, vec!["call-followup".to_string()]);
+        assert_eq!(state.status, RunStatus::Running);
+        assert_eq!(state.tasks.len(), 5);
+        assert_eq!(state.tasks[0].status, TaskStatus::Completed);
+        assert_eq!(state.tasks[4].status, TaskStatus::Pending);
+    }
+
+    #[test]
+    fn reload_config_applies_safe_changes_and_refuses_the_rest() {
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        let mut fresh = cfg.clone();
+
+        assert!(reload_config(&mut cfg, fresh.clone()).applied.is_empty());
+
+        fresh.poll_interval_secs = cfg.poll_interval_secs + 7;
+        fresh.timeouts.stall_secs = 600;
+        fresh.run_id = Some("renamed".to_string());
+        fresh.tasks[0].todo_file = PathBuf::from("moved.md");
+        fresh.tasks.pop();
+        fresh.tasks.push(TaskConfig {
+            id: "call-followup".to_string(),
+            todo_file: PathBuf::from("followup.md"),
+            ..Default::default()
+        });
+        let original_run_id = cfg.run_id.clone();
+        let reload = reload_config(&mut cfg, fresh.clone());
+
+        assert_eq!(cfg.poll_interval_secs, fresh.poll_interval_secs);
+        assert_eq!(cfg.timeouts.stall_secs, 600);
+        assert_eq!(cfg.run_id, original_run_id);
+        assert_eq!(cfg.tasks.len(), 5);
+        assert_eq!(
+            cfg.tasks[0].todo_file,
+            PathBuf::from("examples/mock-project/todos/call-audio-plan.md")
+        );
+        assert!(
+            reload
+                .applied
+                .iter()
+                .any(|c| c == "added task 'call-followup'")
+        );
+        assert!(reload.applied.iter().any(|c| c.starts_with("timeouts:")));
+        assert!(reload.refused.iter().any(|c| c == "run_id changed"));
+        assert!(
+            reload
+                .refused
+                .iter()
+                .any(|c| c.contains("definition changed"))
+        );
+        assert!(reload.refused.iter().any(|c| c.ends_with("removed")));
+    }
+
+    #[test]
+    fn failed_verification_keeps_task_open_until_artifact_changes() {
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        cfg.state_dir = make_temp_dir("verify");
+        cfg.workspace = cfg.state_dir.clone();
+        cfg.tasks[0].verify = vec!["true".to_string(), "echo broken build; exit 3".to_string()];
+        let mut state = init_state(&cfg).expect("init state");
+        state.tasks[0].status = TaskStatus::Running;
+        let coord = PathBuf::from(&state.tasks[0].coord_dir);
+        fs::create_dir_all(&coord).expect("create coord dir");
+        fs::write(coord.join("state.md"), "done\n").expect("write state.md");
+        let journal = journal_path(&cfg.state_dir);
+
+        let unverified =
+            sync_completion_and_progress(&mut state, cfg.policy.require_decision_quorum);
+        assert_eq!(unverified, vec![0]);
+        verify_task_completions(&cfg, &mut state, &journal, &unverified).expect("verify");
+        let task = &state.tasks[0];
+        assert_eq!(task.status, TaskStatus::Running);
+        let note = task.pending_note.as_deref().expect("recovery note");
+        assert!(note.contains("echo broken build; exit 3"), "{note}");
+        assert!(note.contains("broken build"), "{note}");
+
+        // The same artifact is not re-verified.
+        assert!(
+            sync_completion_and_progress(&mut state, cfg.policy.require_decision_quorum).is_empty()
+        );
+
+        // A rewritten artifact is checked again.
+        cfg.tasks[0].verify = vec!["true".to_string()];
+        state.tasks[0].verify = cfg.tasks[0].verify.clone();
+        state.tasks[0].verify_rejected_epoch = Some(0);
+        let unverified =
+            sync_completion_and_progress(&mut state, cfg.policy.require_decision_quorum);
+        verify_task_completions(&cfg, &mut state, &journal, &unverified).expect("verify");
+        assert_eq!(state.tasks[0].status, TaskStatus::Completed);
+
+        // A hung verify command is killed at `verify_secs`, heartbeating meanwhile.
+        cfg.timeouts.verify_secs = Some(2);
+        state.tasks[0].status = TaskStatus::Running;
+        state.tasks[0].verify = vec!["sleep 30".to_string()];
+        let _ = fs::remove_file(governor_heartbeat_path(&cfg.state_dir));
+        let started = Instant::now();
+        verify_task_completions(&cfg, &mut state, &journal, &[0]).expect("verify");
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert_eq!(state.tasks[0].status, TaskStatus::Running);
+        let note = state.tasks[0]
+            .pending_note
+            .as_deref()
+            .expect("timeout note");
+        assert!(note.contains("`sleep 30` timed out after 2s"), "{note}");
+        assert!(governor_heartbeat_path(&cfg.state_dir).is_file());
+    }
+
+    #[test]
+    fn decision_quorum_gates_coord_done_completion() {
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        cfg.state_dir = make_temp_dir("decisions");
+        cfg.workspace = cfg.state_dir.clone();
+        cfg.policy.require_decision_quorum = true;
+        cfg.roles.reviewer_1.harness = "codex".to_string();
+        cfg.roles.reviewer_2.harness = "claude".to_string();
+        let mut state = init_state(&cfg).expect("init state");
+        state.tasks[0].status = TaskStatus::Running;
+        state.tasks[0].completion_file = None;
+        let coord = PathBuf::from(&state.tasks[0].coord_dir);
+        for sub in ["requests", "decisions"] {
+            fs::create_dir_all(coord.join(sub)).expect("create coord subdir");
+        }
+        fs::write(coord.join("requests").join("0001.md"), "step 1").expect("write request");
+        fs::write(
+            coord.join("decisions").join("0001-r1.json"),
+            r#"{"request": "0001", "reviewer": "reviewer-1", "verdict": "APPROVE"}"#,
+        )
+        .expect("write decision");
+        fs::write(
+            coord.join("decisions").join("0001-r2.md"),
+            "request: 0001\nreviewer: reviewer-2\nverdict: CHANGES_REQUESTED\n",
+        )
+        .expect("write decision");
+        fs::write(coord.join("state.md"), "done\n").expect("write state.md");
+        let journal = journal_path(&cfg.state_dir);
+
+        let unverified = sync_completion_and_progress(&mut state, true);
+        assert_eq!(unverified, vec![0]);
+        verify_task_completions(&cfg, &mut state, &journal, &unverified).expect("verify");
+        assert_eq!(state.tasks[0].status, TaskStatus::Running);
+        let note = state.tasks[0].pending_note.as_deref().expect("quorum note");
+        assert!(note.contains("has 1 of 2 required approval(s)"), "{note}");
+        assert!(note.contains("reviewer-2: ChangesRequested"), "{note}");
+        assert!(sync_completion_and_progress(&mut state, true).is_empty());
+
+        fs::write(
+            coord.join("decisions").join("0001-r2b.json"),
+            r#"{"request": "0001", "reviewer": "reviewer-2", "verdict": "approve"}"#,
+        )
+        .expect("write decision");
+        state.tasks[0].verify_rejected_epoch = Some(0);
+        let unverified = sync_completion_and_progress(&mut state, true);
+        verify_task_completions(&cfg, &mut state, &journal, &unverified).expect("verify");
+        assert_eq!(state.tasks[0].status, TaskStatus::Completed);
+    }
+
+    #[test]
+    fn post_task_hooks_run_once_per_terminal_transition() {
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        cfg.state_dir = make_temp_dir("hooks");
+        cfg.workspace = cfg.state_dir.clone();
+        cfg.hooks.post_task = vec![
+            "echo \"$CRANK_RUN_ID $CRANK_TASK_ID $CRANK_TASK_STATUS\" >> hooks.out".to_string(),
+        ];
+        let mut state = init_state(&cfg).expect("init state");
+        let journal = journal_path(&cfg.state_dir);
+        let out = cfg.state_dir.join("hooks.out");
+
+        state.tasks[0].status = TaskStatus::Completed;
+        run_post_task_hooks(&cfg, &mut state, &journal).expect("hooks");
+        run_post_task_hooks(&cfg, &mut state, &journal).expect("hooks");
+        assert_eq!(
+            fs::read_to_string(&out).expect("read hook output"),
+            "mock-call-plans call-audio completed\n"
+        );
+
+        state.tasks[0].status = TaskStatus::Running;
+        run_post_task_hooks(&cfg, &mut state, &journal).expect("hooks");
+        state.tasks[0].status = TaskStatus::BlockedBestEffort;
+        run_post_task_hooks(&cfg, &mut state, &journal).expect("hooks");
+        let lines = fs::read_to_string(&out).expect("read hook output");
+        assert_eq!(lines.lines().count(), 2);
+        assert!(
+            lines.ends_with("call-audio blocked_best_effort\n"),
+            "{lines}"
+        );
+
+        // A hung hook is killed, with its children, after `timeout_secs`.
+        cfg.hooks.timeout_secs = Some(1);
+        cfg.hooks.post_task = vec!["sleep 30 & wait".to_string()];
+        state.tasks[1].status = TaskStatus::Completed;
+        let started = Instant::now();
+        run_post_task_hooks(&cfg, &mut state, &journal).expect("hooks");
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert!(state.tasks[1].post_task_hooks_ran);
+        let entries = fs::read_to_string(&journal).expect("journal");
+        assert!(
+            entries.contains("`sleep 30 & wait` timed out after 1s"),
+            "{entries}"
+        );
+    }
+
+    #[test]
+    fn watchdog_once_fails_when_its_command_fails() {
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        cfg.state_dir = make_temp_dir("watchdog-once");
+        let mut state = init_state(&cfg).expect("init state");
+        state.status = RunStatus::Running;
+        state.updated_at = "2020-01-01T00:00:00Z".to_string();
+        fs::write(
+            state_path(&cfg.state_dir),
+            serde_json::to_string(&state).expect("serialize state"),
+        )
+        .expect("write stale state");
+        let watch = |exec: &str| {
+            ctl_watchdog(WatchdogArgs {
+                state_dir: &cfg.state_dir,
+                stall_secs: 60,
+                exec,
+                poll_secs: 1,
+                once: true,
+            })
+        };
+
+        watch("true").expect("successful command");
+        let err = watch("exit 3").expect_err("failed command");
+        assert!(err.to_string().contains("`exit 3` failed"), "{err}");
+    }
+
+    #[test]
+    fn grep_turns_reports_cycle_task_section_and_offset() {
+        let state_dir = make_temp_dir("turn-grep");
+        ensure_dir(&state_dir.join("logs")).expect("create logs dir");
+        log_turn(
+            &state_dir,
+            3,
+            "3-call-audio",
+            "edit src/audio.rs",
+            "touched src/audio.rs",
+            &Redactor::default(),
+        )
+        .expect("log turn");
+        log_turn(
+            &state_dir,
+            4,
+            "4-call-video",
+            "plan",
+            "opened src/video.rs",
+            &Redactor::default(),
+        )
+        .expect("log turn");
+        log_turn(
+            &state_dir,
+            5,
+            "5-call-audio-reprompt",
+            "fix src/audio.rs",
+            "ok",
+            &Redactor::default(),
+        )
+        .expect("log turn");
+        fs::remove_file(turns_archive_dir(&state_dir).join("4-call-video.md"))
+            .expect("drop archive");
+
+        let regex = regex::Regex::new(r"src/\w+\.rs").expect("regex");
+        let all = grep_turns(&state_dir, &regex, None, CycleRange::default()).expect("grep");
+        let found: Vec<(u64, Option<&str>, &str)> = all
+            .iter()
+            .map(|m| (m.cycle, m.task.as_deref(), m.section))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (3, Some("call-audio"), "prompt"),
+                (3, Some("call-audio"), "response"),
+                (4, None, "response"),
+                (5, Some("call-audio"), "prompt"),
+            ]
+        );
+        let text = fs::read(&all[2].source).expect("read source");
+        let offset = all[2].offset as usize;
+        assert_eq!(&text[offset..offset + 12], b"src/video.rs");
+        assert_eq!(all[2].snippet, "opened src/video.rs");
+
+        let audio = grep_turns(
+            &state_dir,
+            &regex,
+            Some("call-audio"),
+            parse_cycle_range("4..").expect("range"),
+        )
+        .expect("grep");
+        assert_eq!(audio.len(), 1);
+        assert_eq!(audio[0].cycle, 5);
+        assert_eq!(
+            parse_cycle_range("..7"),
+            Ok(CycleRange {
+                start: None,
+                end: Some(7)
+            })
+        );
+        assert!(parse_cycle_range("a..b").is_err());
+    }
+
+    #[test]
+    fn log_turn_archives_prompt_response_and_control_sidecar() {
+        let state_dir = make_temp_dir("turn-archive");
+        ensure_dir(&state_dir.join("logs")).expect("create logs dir");
+        let response = "worked\n<CONTROL_JSON>\n{\"version\":2,\"task_id\":\"t1\",\"status\":\"in_progress\",\"summary\":\"s\",\"next_action\":\"continue\"}\n</CONTROL_JSON>";
+        let redactor = Redactor::new(&LoggingConfig {
+            redact: vec!["sk-[a-z0-9]+".to_string()],
+            ..Default::default()
+        })
+        .expect("redactor");
+        log_turn(&state_dir, 7, "7-t1", "the prompt", response, &redactor).expect("log turn");
+        log_turn(
+            &state_dir,
+            7,
+            "7-t1-reprompt",
+            "use key sk-abc123",
+            "no block, sk-abc123 failed",
+            &redactor,
+        )
+        .expect("log turn");
+
+        let dir = turns_archive_dir(&state_dir);
+        let md = fs::read_to_string(dir.join("7-t1.md")).expect("read archive");
+        assert!(md.contains("the prompt") && md.contains(response), "{md}");
+        let control: Value = serde_json::from_str(
+            &fs::read_to_string(dir.join("7-t1.control.json")).expect("read sidecar"),
+        )
+        .expect("sidecar json");
+        assert_eq!(control["task_id"], "t1");
+        assert_eq!(control["status"], "in_progress");
+        let missing: Value = serde_json::from_str(
+            &fs::read_to_string(dir.join("7-t1-reprompt.control.json")).expect("read sidecar"),
+        )
+        .expect("sidecar json");
+        assert_eq!(missing["error"], "no CONTROL_JSON block found");
+        let reprompt = fs::read_to_string(dir.join("7-t1-reprompt.md")).expect("read archive");
+        let log = fs::read_to_string(turns_log_path(&state_dir)).expect("read turns log");
+        for text in [&reprompt, &log] {
+            assert!(!text.contains("sk-abc123"), "{text}");
+            assert!(text.contains("use key [REDACTED]"), "{text}");
+        }
+    }
+
+    #[test]
+    fn role_heartbeats_surface_and_stall_once_per_role() {
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        cfg.state_dir = make_temp_dir("heartbeats");
+        cfg.timeouts.stall_secs = 900;
+        cfg.timeouts
+            .role_stall_secs
+            .insert("reviewer-1".to_string(), 60);
+        let mut state = init_state(&cfg).expect("init state");
+        state.tasks[0].status = TaskStatus::Running;
+        let heartbeats = Path::new(&state.tasks[0].coord_dir).join("heartbeats");
+        fs::create_dir_all(&heartbeats).expect("create heartbeats dir");
+        let now = now_epoch();
+        for (role, age, phase) in [
+            ("implementer", 120, "implementing"),
+            ("reviewer-1", 120, "reviewing"),
+            ("reviewer-2", 5000, "idle"),
+        ] {
+            let body = serde_json::json!({"epoch": now - age, "phase": phase, "message": "step 2"});
+            fs::write(heartbeats.join(format!("{role}.json")), body.to_string())
+                .expect("write heartbeat");
+        }
+        fs::write(heartbeats.join("broken.json"), "{").expect("write broken heartbeat");
+
+        sync_completion_and_progress(&mut state, cfg.policy.require_decision_quorum);
+        let task = &mut state.tasks[0];
+        let roles: Vec<&str> = task.heartbeats.iter().map(|hb| hb.role.as_str()).collect();
+        assert_eq!(roles, ["implementer", "reviewer-1", "reviewer-2"]);
+        let stalled: Vec<&str> = stalled_role_heartbeats(task, &cfg.timeouts, now)
+            .iter()
+            .map(|hb| hb.role.as_str())
+            .collect();
+        assert_eq!(stalled, ["reviewer-1"]);
+
+        task.heartbeats[1].stall_reported = true;
+        refresh_role_heartbeats(task);
+        assert!(task.heartbeats[1].stall_reported);
+        assert!(stalled_role_heartbeats(task, &cfg.timeouts, now).is_empty());
+        let board = task_board(&state, 0);
+        assert!(board.contains("  - reviewer-1 heartbeat 12"), "{board}");
+        assert!(board.contains("s ago: reviewing (step 2)"), "{board}");
+    }
+
+    #[test]
+    fn todo_checkboxes_are_tallied_and_count_as_progress() {
+        assert_eq!(parse_todo_progress("# Plan\nno boxes here\n- item\n"), None);
+        assert_eq!(
+            parse_todo_progress("- [x] a\n  - [ ] b\n* [X] c\n+ [ ] d\n- [-] e\n"),
+            Some(TodoProgress {
+                checked: 2,
+                total: 4
+            })
+        );
+
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        cfg.state_dir = make_temp_dir("todo-progress");
+        let todo = cfg.state_dir.join("plan.md");
+        fs::write(&todo, "- [ ] one\n- [ ] two\n").expect("write plan");
+        cfg.tasks[0].todo_file = todo.clone();
+        let mut state = init_state(&cfg).expect("init state");
+        state.tasks[0].status = TaskStatus::Running;
+
+        sync_completion_and_progress(&mut state, false);
+        assert_eq!(state.tasks[0].last_progress_epoch, None);
+        assert_eq!(state.tasks[0].todo_progress.map(|t| t.checked), Some(0));
+
+        fs::write(&todo, "- [x] one\n- [ ] two\n").expect("write plan");
+        sync_completion_and_progress(&mut state, false);
+        assert!(state.tasks[0].last_progress_epoch.is_some());
+        let board = task_board(&state, 0);
+        assert!(
+            board.contains("call-audio: running (deps: []) todo 1/2 (50%)"),
+            "{board}"
+        );
+    }
+
+    #[test]
+    fn log_caps_rotate_generations_or_truncate_head() {
+        let dir = make_temp_dir("log-caps");
+        let log = dir.join("events.jsonl");
+        for round in 1..=3 {
+            fs::write(&log, format!("round {round}\n").repeat(10)).expect("write log");
+            assert!(enforce_log_cap(&log, 40, 2).expect("cap log"));
+        }
+        assert!(!enforce_log_cap(&log, 40, 2).expect("cap log"));
+        assert_eq!(fs::read_to_string(&log).expect("read log"), "");
+        let newest = fs::read_to_string(rotated_log_path(&log, 1)).expect("read .1");
+        assert!(newest.starts_with("round 3"), "{newest}");
+        let oldest = fs::read_to_string(rotated_log_path(&log, 2)).expect("read .2");
+        assert!(oldest.starts_with("round 2"), "{oldest}");
+        assert!(!rotated_log_path(&log, 3).exists());
+
+        let lines: String = (0..20).map(|i| format!("line {i:02}\n")).collect();
+        fs::write(&log, &lines).expect("write log");
+        assert!(enforce_log_cap(&log, 40, 0).expect("truncate log"));
+        assert_eq!(
+            fs::read_to_string(&log).expect("read log"),
+            "line 18\nline 19\n"
+        );
+    }
+
+    #[test]
+    fn append_event_line_redacts_patterns_and_env_values() {
+        let dir = make_temp_dir("redact");
+        let path = dir.join("events.jsonl");
+        let logging = LoggingConfig {
+            redact: vec!["sk-[A-Za-z0-9]{8,}".to_string()],
+            redact_env: vec!["PATH".to_string(), "CRANK_UNSET_VAR".to_string()],
+            ..Default::default()
+        };
+        let redactor = Redactor::new(&logging).expect("build redactor");
+        let secret_path = std::env::var("PATH").expect("PATH is set");
+        let line = serde_json::json!({
+            "type": "item.completed",
+            "aggregated_output": format!("key=sk-abcdef123456 path={secret_path}"),
+        })
+        .to_string();
+        append_event_line(&path, &line, &redactor).expect("append event");
+        append_event_line(&path, "raw sk-zyxwvuts99", &redactor).expect("append raw");
+
+        let text = fs::read_to_string(&path).expect("read events");
+        assert!(!text.contains("sk-abcdef123456") && !text.contains("sk-zyxwvuts99"));
+        assert!(!text.contains(&secret_path));
+        assert_eq!(text.matches(REDACTED).count(), 3, "{text}");
+
+        let bad = LoggingConfig {
+            redact: vec!["(".to_string()],
+            ..Default::default()
+        };
+        assert!(Redactor::new(&bad).is_err());
+    }
+
+    #[test]
+    fn report_renders_tasks_blockers_and_timeline() {
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        cfg.state_dir = make_temp_dir("report");
+        let mut state = init_state(&cfg).expect("init state");
+        state.tasks[0].started_at = Some("2026-01-01T00:00:00+00:00".to_string());
+        state.tasks[0].completed_at = Some("2026-01-01T01:02:03+00:00".to_string());
+        state.tasks[0].status = TaskStatus::Completed;
+        mark_task_blocked(&mut state.tasks[1], "needs credentials");
+
+        let journal = parse_journal(
+            "# Journal\n\n## 2026-01-01T00:00:00+00:00\n**run boot**\nStarting run.\n\n\
+             ## 2026-01-01T00:05:00+00:00\n**turn control**\ntask=call-audio\n",
+        );
+        assert_eq!(journal.len(), 2);
+        assert_eq!(journal[0].title, "run boot");
+
+        let report = render_report(&state, None, &journal, 3);
+        assert!(
+            report.contains("| call-audio | completed | 0 |"),
+            "{report}"
+        );
+        assert!(report.contains("| 1h 02m 03s |"), "{report}");
+        assert!(
+            report.contains("- **call-transport**: needs credentials"),
+            "{report}"
+        );
+        assert!(report.contains("**run boot** Starting run."), "{report}");
+        assert!(!report.contains("turn control"), "{report}");
+        assert!(report.contains("- Turns logged: 3"), "{report}");
+    }
+
+    #[test]
+    fn metrics_server_serves_prometheus_text() {
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        cfg.state_dir = make_temp_dir("metrics");
+        let mut state = init_state(&cfg).expect("init state");
+        state.cycle = 4;
+        state.tasks[0].status = TaskStatus::Running;
+        state.tasks[0].last_progress_epoch = Some(now_epoch() - 90);
+
+        let metrics = Arc::new(Mutex::new(GovernorMetrics::default()));
+        {
+            let mut m = metrics.lock().expect("lock metrics");
+            m.observe_state(&state, 2);
+            m.observe_turn(20.0, true);
+            m.observe_turn(4000.0, false);
+        }
+        let addr = start_metrics_server("127.0.0.1:0".parse().expect("addr"), Arc::clone(&metrics))
+            .expect("start server");
+
+        // Clients that connect and send nothing must not hold up other scrapes.
+        let _idle: Vec<TcpStream> = (1..METRICS_WORKERS)
+            .map(|_| TcpStream::connect(addr).expect("connect idle client"))
+            .collect();
+        let mut stream = TcpStream::connect(addr).expect("connect");
+        stream
+            .set_read_timeout(Some(Duration::from_secs(2)))
+            .expect("read timeout");
+        write!(stream, "GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n").expect("send");
+        let mut response = String::new();
+        stream.read_to_string(&mut response).expect("read response");
+
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+        assert!(response.contains("crank_cycle 4\n"), "{response}");
+        assert!(response.contains("crank_tasks{status=\"running\"} 1\n"));
+        assert!(response.contains("crank_tasks{status=\"pending\"} 3\n"));
+        assert!(response.contains("crank_consecutive_failures 2\n"));
+        assert!(response.contains("crank_turn_duration_seconds_bucket{le=\"15\"} 0\n"));
+        assert!(response.contains("crank_turn_duration_seconds_bucket{le=\"30\"} 1\n"));
+        assert!(response.contains("crank_turn_duration_seconds_bucket{le=\"+Inf\"} 2\n"));
+        assert!(response.contains("crank_turn_failures_total 1\n"));
+        let stall: i64 = response
+            .lines()
+            .find_map(|l| l.strip_prefix("crank_stall_seconds "))
+            .and_then(|v| v.parse().ok())
+            .expect("stall gauge");
+        assert!((90..100).contains(&stall), "{stall}");
+    }
+
+    #[test]
+    fn scheduling_policy_orders_ready_tasks() {
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        cfg.state_dir = make_temp_dir("scheduling");
+        cfg.tasks = vec![
+            task_config("root", &[]),
+            task_config("mid", &["root"]),
+            task_config("leaf", &["mid"]),
+            task_config("side", &[]),
+            task_config("urgent", &["root"]),
+        ];
+        cfg.tasks[4].priority = 5;
+        cfg.tasks[2].priority = 9;
+        let mut state = init_state(&cfg).expect("init state");
+        assert_eq!(
+            choose_next_pending_task(
+                &state,
+                SchedulingPolicy::Fifo,
+                &std::collections::BTreeSet::new()
+            ),
+            Some(0)
+        );
+
+        state.tasks[0].status = TaskStatus::Completed;
+        state.tasks[1].status = TaskStatus::Completed;
+        // Ready: leaf (depth 2, priority 9), side (depth 0), urgent (depth 1, priority 5).
+        assert_eq!(
+            choose_next_pending_task(
+                &state,
+                SchedulingPolicy::Fifo,
+                &std::collections::BTreeSet::new()
+            ),
+            Some(2)
+        );
+        assert_eq!(
+            choose_next_pending_task(
+                &state,
+                SchedulingPolicy::Priority,
+                &std::collections::BTreeSet::new()
+            ),
+            Some(2)
+        );
+        assert_eq!(
+            choose_next_pending_task(
+                &state,
+                SchedulingPolicy::ShortestDepsFirst,
+                &std::collections::BTreeSet::new()
+            ),
+            Some(3)
+        );
+        state.tasks[2].priority = 0;
+        assert_eq!(
+            choose_next_pending_task(
+                &state,
+                SchedulingPolicy::Priority,
+                &std::collections::BTreeSet::new()
+            ),
+            Some(4)
+        );
+        assert_eq!(dependency_depths(&state), vec![0, 1, 2, 0, 1]);
+
+        // Stacked diamonds have 2^layers paths; each depth is still computed once.
+        let layers = 40;
+        let mut names = vec![
+            ("l0a".to_string(), Vec::new()),
+            ("l0b".to_string(), Vec::new()),
+        ];
+        for layer in 1..=layers {
+            let below = [format!("l{}a", layer - 1), format!("l{}b", layer - 1)];
+            for side in ["a", "b"] {
+                names.push((format!("l{layer}{side}"), below.to_vec()));
+            }
+        }
+        cfg.tasks = names
+            .iter()
+            .map(|(id, deps)| task_config(id, &deps.iter().map(String::as_str).collect::<Vec<_>>()))
+            .collect();
+        let state = init_state(&cfg).expect("init diamond state");
+        assert_eq!(dependency_depths(&state).last(), Some(&layers));
+    }
+
+    #[test]
+    fn conditional_tasks_skip_when_ready() {
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        cfg.state_dir = make_temp_dir("conditional");
+        cfg.workspace = cfg.state_dir.clone();
+        fs::write(cfg.workspace.join("migrations.txt"), "1").expect("write marker");
+        let exists = |p: &str| TaskCondition {
+            file_exists: Some(PathBuf::from(p)),
+        };
+        cfg.tasks = vec![
+            task_config("build", &[]),
+            TaskConfig {
+                run_if: Some(exists("missing.txt")),
+                ..task_config("cleanup", &["build"])
+            },
+            TaskConfig {
+                skip_if: Some(exists("migrations.txt")),
+                ..task_config("migrate", &[])
+            },
+            TaskConfig {
+                run_if: Some(exists("migrations.txt")),
+                ..task_config("verify", &[])
+            },
+        ];
+        let mut state = init_state(&cfg).expect("init state");
+
+        let skipped = skip_conditional_tasks(&cfg, &mut state);
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].0, "migrate");
+        assert!(skipped[0].1.starts_with("skip_if met"), "{:?}", skipped[0]);
+        assert_eq!(state.tasks[1].status, TaskStatus::Pending);
+        assert_eq!(state.tasks[3].status, TaskStatus::Pending);
+
+        state.tasks[0].status = TaskStatus::Completed;
+        let skipped = skip_conditional_tasks(&cfg, &mut state);
+        assert_eq!(skipped[0].0, "cleanup");
+        assert_eq!(state.tasks[1].status, TaskStatus::Skipped);
+        assert!(state.tasks[1].status.is_terminal());
+    }
+
+    #[test]
+    fn backend_fallbacks_parse_and_fail_over_in_order() {
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let text = text.replace(
+            "steps_per_task = 2\n",
+            "steps_per_task = 2\nfailover_after = 3\nfallbacks = [\n  { kind = \"claude\", model = \"opus\", thinking = \"high\" },\n  { kind = \"mock\" },\n]\n",
+        );
+        let mut cfg: Config = toml::from_str(&text).expect("config with fallbacks should parse");
+        assert_eq!(cfg.backend.primary.kind(), "mock");
+        assert_eq!(cfg.backend.failover_after, 3);
+        let kinds: Vec<_> = cfg.backend.chain().map(BackendConfig::kind).collect();
+        assert_eq!(kinds, vec!["mock", "claude", "mock"]);
+
+        cfg.state_dir = make_temp_dir("failover");
+        let mut state = init_state(&cfg).expect("init state");
+        state.tasks[0].thread_id = Some("thread-1".to_string());
+        state.tasks[1].thread_id = Some("thread-2".to_string());
+        state.tasks[1].status = TaskStatus::Completed;
+
+        assert_eq!(
+            fail_over_backend(&cfg.backend, &mut state),
+            Some(("mock", "claude"))
+        );
+        assert_eq!(cfg.backend.at(state.backend_index).kind(), "claude");
+        assert_eq!(state.tasks[0].thread_id, None);
+        assert!(state.tasks[0].pending_note.is_some());
+        assert_eq!(state.tasks[1].thread_id.as_deref(), Some("thread-2"));
+        assert_eq!(
+            fail_over_backend(&cfg.backend, &mut state),
+            Some(("claude", "mock"))
+        );
+        assert_eq!(fail_over_backend(&cfg.backend, &mut state), None);
+
+        let bad = text.replace("{ kind = \"mock\" }", "{ kind = \"mock\", bogus = 1 }");
+        assert!(toml::from_str::<Config>(&bad).is_err());
+    }
+
+    #[test]
+    fn backend_factory_dispatches_on_kind() {
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        cfg.state_dir = make_temp_dir("backend-trait");
+        let state = init_state(&cfg).expect("init state");
+        let task = &state.tasks[0];
+        let backend = create_backend(&cfg.backend.primary);
+        assert_eq!(backend.kind(), "mock");
+
+        let redactor = Redactor::new(&cfg.logging).expect("build redactor");
+        let mut activity = 0;
+        let mut on_activity = || -> Result<()> {
+            activity += 1;
+            Ok(())
+        };
+        let result = backend
+            .run_turn(
+                turn_context(&cfg, task, "do the thing", &redactor),
+                &mut on_activity,
+            )
+            .expect("mock turn");
+        assert!(activity > 0);
+        assert!(result.final_response.contains(&task.id));
+    }
+
+    #[test]
+    fn recorded_turns_replay_in_order() {
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        cfg.state_dir = make_temp_dir("record-replay");
+        let record_dir = cfg.state_dir.join("recorded");
+        cfg.backend.record_dir = Some(record_dir.clone());
+        let state = init_state(&cfg).expect("init state");
+        let task = &state.tasks[0];
+        let redactor = Redactor::new(&cfg.logging).expect("build redactor");
+
+        let recorder = &cfg.backend.build()[0];
+        let mut recorded = Vec::new();
+        for prompt in ["first", "second"] {
+            let result = recorder
+                .run_turn(turn_context(&cfg, task, prompt, &redactor), &mut || Ok(()))
+                .expect("recorded turn");
+            recorded.push(result.final_response);
+        }
+        let first: RecordedTurn = serde_json::from_str(
+            &fs::read_to_string(recorded_turn_path(&record_dir, &task.id, 1)).expect("read"),
+        )
+        .expect("parse recorded turn");
+        assert_eq!(first.prompt, "first");
+        assert_eq!(first.backend, "mock");
+
+        let replay = create_backend(&BackendConfig::Replay(ReplayBackendConfig {
+            dir: record_dir,
+        }));
+        for expected in &recorded {
+            let result = replay
+                .run_turn(turn_context(&cfg, task, "ignored", &redactor), &mut || {
+                    Ok(())
+                })
+                .expect("replayed turn");
+            assert_eq!(&result.final_response, expected);
+        }
+        let err = replay
+            .run_turn(turn_context(&cfg, task, "ignored", &redactor), &mut || {
+                Ok(())
+            })
+            .expect_err("recordings exhausted");
+        assert!(format!("{err:#}").contains("no recorded turn 3"));
+    }
+
+    #[test]
+    fn preflight_reports_missing_binaries_and_skips_mock_roles() {
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        cfg.state_dir = make_temp_dir("preflight");
+        let redactor = Redactor::new(&cfg.logging).expect("build redactor");
+        // Mock-only runs never launch the role harnesses.
+        assert!(preflight_problems(&cfg, &redactor).is_empty());
+
+        cfg.backend.fallbacks = vec![BackendConfig::Claude(ClaudeBackendConfig {
+            binary: "/nonexistent/crank-claude".to_string(),
+            model: "opus".to_string(),
+            thinking: "high".to_string(),
+            thinking_budget_tokens: None,
+            extra_args: Vec::new(),
+        })];
+        for role in [
+            &mut cfg.roles.implementer,
+            &mut cfg.roles.reviewer_1,
+            &mut cfg.roles.reviewer_2,
+        ] {
+            role.harness = "cargo".to_string();
+        }
+        let problems = preflight_problems(&cfg, &redactor);
+        assert_eq!(problems.len(), 1, "{problems:?}");
+        assert!(
+            problems[0].contains("claude backend binary '/nonexistent/crank-claude' not found"),
+            "{problems:?}"
+        );
+    }
+
+    #[test]
+    fn task_edits_validate_and_apply_from_queue() {
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        cfg.state_dir = make_temp_dir("edit-task");
+        let mut state = init_state(&cfg).expect("init state");
+        let edit = |task_id: &str| TaskEdit {
+            task_id: task_id.to_string(),
+            status: None,
+            depends_on: None,
+            completion_file: None,
+            reason: None,
+            add: None,
+        };
+
+        let cyclic = TaskEdit {
+            depends_on: Some(vec!["call-video".to_string()]),
+            ..edit("call-audio")
+        };
+        let err = apply_task_edit(&mut state, &cyclic).expect_err("cycle must be rejected");
+        assert!(err.to_string().contains("dependency cycle"), "{err}");
+        assert!(state.tasks[0].depends_on.is_empty());
+        assert!(apply_task_edit(&mut state, &edit("call-audio")).is_err());
+
+        let change = apply_task_edit(
+            &mut state,
+            &TaskEdit {
+                status: Some(TaskStatus::Completed),
+                completion_file: Some("/tmp/done.md".to_string()),
+                ..edit("call-audio")
+            },
+        )
+        .expect("valid edit");
+        assert!(change.contains("status pending -> completed"), "{change}");
+        assert_eq!(
+            state.tasks[0].completion_file.as_deref(),
+            Some("/tmp/done.md")
+        );
+
+        let queue = task_edits_dir(&cfg.state_dir);
+        fs::create_dir_all(&queue).expect("create edits dir");
+        let queued = TaskEdit {
+            status: Some(TaskStatus::Skipped),
+            ..edit("call-transport")
+        };
+        write_json_atomic(&queue.join("1-call-transport.json"), &queued).expect("queue edit");
+        write_json_atomic(&queue.join("2-ghost.json"), &edit("ghost")).expect("queue edit");
+        let journal = journal_path(&cfg.state_dir);
+        apply_queued_task_edits(&mut state, &cfg.state_dir, &journal).expect("apply edits");
+        assert_eq!(state.tasks[1].status, TaskStatus::Skipped);
+        assert!(!queue.join("1-call-transport.json").exists());
+        assert!(queue.join("2-ghost.json.rejected").exists());
+        assert_eq!(
+            "blocked_best_effort".parse(),
+            Ok(TaskStatus::BlockedBestEffort)
+        );
+
+        let cancel = TaskEdit {
+            status: Some("cancelled".parse().expect("status parses")),
+            reason: Some("descoped".to_string()),
+            ..edit("call-video")
+        };
+        apply_task_edit(&mut state, &cancel).expect("cancel");
+        assert_eq!(state.tasks[2].status, TaskStatus::Cancelled);
+        assert!(state.tasks[2].status.is_terminal());
+        assert_eq!(state.tasks[2].blocked_reason.as_deref(), Some("descoped"));
+        write_run_summary(&state, &cfg).expect("write summary");
+        let summary: Value = serde_json::from_slice(
+            &fs::read(run_summary_path(&cfg.state_dir)).expect("read summary"),
+        )
+        .expect("parse summary");
+        assert_eq!(summary["tasks_cancelled"], 1);
+        assert_eq!(summary["tasks_blocked"], 0);
+        assert_eq!(summary["cancelled_tasks"][0]["reason"], "descoped");
+        let report = render_report(&state, None, &[], 0);
+        assert!(
+            report.contains("## Cancelled\n\n- **call-video**: descoped"),
+            "{report}"
+        );
+        assert!(!report.contains("## Blockers"), "{report}");
+
+        let running = TaskEdit {
+            status: Some(TaskStatus::Running),
+            ..edit("call-native-audio")
+        };
+        let err = apply_task_edit(&mut state, &running).expect_err("running is governor-only");
+        assert!(
+            err.to_string().contains("only the governor starts tasks"),
+            "{err}"
+        );
+
+        // Edits are queued only for a live governor; other lock failures surface.
+        save_state(&mut state, &cfg.state_dir).expect("save state");
+        let skip = || TaskEdit {
+            status: Some(TaskStatus::Skipped),
+            ..edit("call-native-audio")
+        };
+        let queued_edits = || {
+            fs::read_dir(&queue)
+                .expect("read edits dir")
+                .flatten()
+                .filter(|e| e.path().extension().is_some_and(|ext| ext == "json"))
+                .count()
+        };
+        let lock = cfg.state_dir.join("run.lock");
+        fs::create_dir(&lock).expect("unreadable lock");
+        ctl_edit_task(&cfg.state_dir, skip()).expect_err("lock error is returned");
+        assert_eq!(queued_edits(), 0);
+        fs::remove_dir(&lock).expect("remove lock dir");
+        fs::write(&lock, format!("pid={}\n", std::process::id())).expect("live lock");
+        ctl_edit_task(&cfg.state_dir, skip()).expect("queued for live governor");
+        assert_eq!(queued_edits(), 1);
+        fs::remove_file(&lock).expect("remove lock");
+    }
+
+    #[test]
+    fn added_tasks_join_the_graph_and_reopen_the_run() {
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        cfg.state_dir = make_temp_dir("add-task");
+        let mut state = init_state(&cfg).expect("init state");
+        for task in &mut state.tasks {
+            task.status = TaskStatus::Completed;
+        }
+        state.status = RunStatus::Completed;
+        let add = |id: &str, deps: &[&str]| TaskEdit {
+            task_id: id.to_string(),
+            status: None,
+            depends_on: Some(deps.iter().map(|d| d.to_string()).collect()),
+            completion_file: None,
+            reason: None,
+            add: Some(NewTask {
+                todo_file: "/plans/followup.md".to_string(),
+                priority: 2,
+            }),
+        };
+
+        let err = apply_task_edit(&mut state, &add("call-audio", &[])).expect_err("duplicate id");
+        assert!(err.to_string().contains("already exists"), "{err}");
+        let err = apply_task_edit(&mut state, &add("followup", &["ghost"])).expect_err("bad dep");
+        assert!(err.to_string().contains("unknown task id 'ghost'"), "{err}");
+        assert_eq!(state.tasks.len(), 4);
+
+        let change = apply_task_edit(&mut state, &add("followup", &["call-video", "call-audio"]))
+            .expect("add task");
+        assert_eq!(
+            change,
+            "task followup: todo_file /plans/followup.md, depends_on [call-video, call-audio]"
+        );
+        assert_eq!(state.status, RunStatus::Running);
+        let task = &state.tasks[4];
+        assert_eq!(task.status, TaskStatus::Pending);
+        assert_eq!(task.priority, 2);
+        assert_eq!(
+            Path::new(&task.coord_dir),
+            cfg.state_dir.join("coord/followup")
+        );
+
+        // Queued for a running governor, it is journaled as an addition.
+        let queue = task_edits_dir(&cfg.state_dir);
+        fs::create_dir_all(&queue).expect("create edits dir");
+        write_json_atomic(&queue.join("1-later.json"), &add("later", &["followup"]))
+            .expect("queue add");
+        let journal = journal_path(&cfg.state_dir);
+        apply_queued_task_edits(&mut state, &cfg.state_dir, &journal).expect("apply edits");
+        assert_eq!(state.tasks[5].depends_on, vec!["followup".to_string()]);
+        let text = fs::read_to_string(&journal).expect("journal");
+        assert!(text.contains("**task added**\ntask later:"), "{text}");
+    }
+
+    #[test]
+    fn summary_exports_render_task_outcomes() {
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        cfg.state_dir = make_temp_dir("summary-export");
+        let mut state = init_state(&cfg).expect("init state");
+        state.tasks[0].status = TaskStatus::Completed;
+        state.tasks[0].started_at = Some("2026-01-01T00:00:00Z".to_string());
+        state.tasks[0].completed_at = Some("2026-01-01T00:02:05Z".to_string());
+        mark_task_blocked(&mut state.tasks[1], "needs <api>, \"key\"");
+        state.tasks[2].status = TaskStatus::Cancelled;
+        state.tasks[2].blocked_reason = Some("descoped".to_string());
+
+        let junit = render_summary(&state, None, SummaryFormat::Junit).expect("junit");
+        assert!(
+            junit.contains(r#"tests="4" failures="1" errors="1" skipped="1" time="125""#),
+            "{junit}"
+        );
+        assert!(
+            junit.contains(r#"<failure message="needs &lt;api&gt;, &quot;key&quot;"/>"#),
+            "{junit}"
+        );
+        assert!(
+            junit.contains(r#"<skipped message="cancelled: descoped"/>"#),
+            "{junit}"
+        );
+
+        let csv = render_summary(&state, None, SummaryFormat::Csv).expect("csv");
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines[1],
+            "call-audio,completed,0,2026-01-01T00:00:00Z,2026-01-01T00:02:05Z,125,"
+        );
+        assert!(
+            lines[2].ends_with(r#","needs <api>, ""key""""#),
+            "{}",
+            lines[2]
+        );
+
+        let json = render_summary(&state, None, SummaryFormat::Json).expect("json");
+        let parsed: Value = serde_json::from_str(&json).expect("parse json");
+        assert_eq!(parsed["run_id"], state.run_id.as_str());
+        assert_eq!(parsed["tasks"][0]["duration_secs"], 125);
+
+        let html = render_summary(&state, None, SummaryFormat::Html).expect("html");
+        assert!(html.contains("<td>2m 05s</td>"), "{html}");
+    }
+
+    #[test]
+    fn legacy_thread_id_moves_to_running_task() {
+        let text = r#"{
+            "run_id": "legacy", "workspace": "/tmp", "state_dir": "/tmp/legacy",
+            "unattended": true, "status": "running",
+            "started_at": "2026-01-01T00:00:00+00:00", "updated_at": "2026-01-01T00:00:00+00:00",
+            "journal_path": "/tmp/legacy/JOURNAL.md", "thread_id": "thread-abc",
+            "cycle": 3, "last_turn_at": null,
+            "tasks": [
+                {"id": "a", "todo_file": "a.md", "depends_on": [], "status": "completed",
+                 "coord_dir": "/tmp/a", "completion_file": null,
+                 "started_at": "2026-01-01T00:00:00+00:00", "completed_at": null,
+                 "last_progress_epoch": null, "recovery_attempts": 0},
+                {"id": "b", "todo_file": "b.md", "depends_on": ["a"], "status": "running",
+                 "coord_dir": "/tmp/b", "completion_file": null,
+                 "started_at": "2026-01-01T01:00:00+00:00", "completed_at": null,
+                 "last_progress_epoch": null, "recovery_attempts": 0}
+            ]
+        }"#;
+        let mut state: RunState = serde_json::from_str(text).expect("legacy state parses");
+        migrate_legacy_thread_id(&mut state);
+        assert_eq!(state.thread_id, None);
+        assert_eq!(state.tasks[0].thread_id, None);
+        assert_eq!(state.tasks[1].thread_id.as_deref(), Some("thread-abc"));
+        // Finished tasks in old state already ran their post_task hooks.
+        assert!(state.tasks[0].post_task_hooks_ran);
+        let rendered = serde_json::to_value(&state).expect("serialize state");
+        assert!(rendered.get("thread_id").is_none());
+    }
+
+    #[test]
+    fn run_summary_keeps_legacy_thread_id_beside_thread_ids() {
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        let mut cfg: Config = toml::from_str(&text).expect("example config should parse");
+        cfg.state_dir = make_temp_dir("summary-thread-id");
+        let mut state = init_state(&cfg).expect("init state");
+        state.tasks[0].thread_id = Some("thread-audio".to_string());
+        state.tasks[0].last_progress_epoch = Some(100);
+        state.tasks[1].thread_id = Some("thread-transport".to_string());
+        state.tasks[1].last_progress_epoch = Some(200);
+        write_run_summary(&state, &cfg).expect("write summary");
+
+        let summary: Value = serde_json::from_str(
+            &fs::read_to_string(run_summary_path(&cfg.state_dir)).expect("read summary"),
+        )
+        .expect("parse summary");
+        assert_eq!(summary["thread_id"], "thread-transport");
+        assert_eq!(summary["thread_ids"]["call-audio"], "thread-audio");
+        assert_eq!(summary["thread_ids"]["call-transport"], "thread-transport");
+    }
+
+    #[test]
+    fn config_rejects_unknown_keys() {
+        let text = fs::read_to_string("examples/mock-run.toml").expect("read example config");
+        toml::from_str::<Config>(&text).expect("example config should parse");
+        let bad = text.replace(
+            "steps_per_task = 2",
+            "steps_per_task = 2\nstep_per_task = 3",
+        );
+        let err = toml::from_str::<Config>(&bad).expect_err("unknown key should fail");
+        assert!(err.to_string().contains("step_per_task"));
+    }
+
+    #[test]
+    fn watchdog_activity_prefers_newest_signal() {
+        let mut state = RunState {
+            run_id: "wd".to_string(),
+            workspace: "/tmp".to_string(),
+            state_dir: "/tmp/wd".to_string(),
+            unattended: true,
+            status: RunStatus::Running,
+            started_at: "2026-01-01T00:00:00+00:00".to_string(),
+            updated_at: "2026-01-01T00:00:00+00:00".to_string(),
+            journal_path: "/tmp/wd/JOURNAL.md".to_string(),
+            thread_id: None,
+            cycle: 1,
+            last_turn_at: None,
+            cost_usd: 0.0,
+            stop_reason: None,
+            backend_index: 0,
+            digest_cycle: 0,
+            digest_journal_offset: 0,
+            live_turn: None,
+            tasks: Vec::new(),
+        };
+        let updated = 1_767_225_600;
+        assert_eq!(last_governor_activity_epoch(&state, None), Some(updated));
+        assert_eq!(
+            last_governor_activity_epoch(&state, Some(updated + 60)),
+            Some(updated + 60)
+        );
+        assert_eq!(
+            last_governor_activity_epoch(&state, Some(updated - 60)),
+            Some(updated)
+        );
+        state.updated_at = "garbage".to_string();
+        assert_eq!(last_governor_activity_epoch(&state, Some(42)), Some(42));
+    }
+
+    fn make_temp_dir(prefix: &str) -> PathBuf {
+        let ts = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("clock must be after epoch")
+            .as_millis();
+        let pid = std::process::id();
+        let dir = env::temp_dir().join(format!("crank-{prefix}-{pid}-{ts}"));
+        fs::create_dir_all(&dir).expect("failed to create temp dir");
+        dir
+    }
+
+    fn local_smoke_run(backend: BackendConfig) -> Result<TurnResult> {
+        let state_dir = make_temp_dir("local-e2e");
+        let workspace = env::current_dir().context("failed to get current dir")?;
+        fs::create_dir_all(state_dir.join("logs")).context("failed to create logs dir")?;
+        fs::create_dir_all(state_dir.join("coord")).context("failed to create coord dir")?;
+
+        let cfg = Config {
+            run_id: Some("local-e2e".to_string()),
+            workspace: workspace.clone(),
+            state_dir: state_dir.clone(),
+            unattended: true,
+            poll_interval_secs: 1,
+            timeouts: TimeoutsConfig {
+                stall_secs: 900,
+                ..Default::default()
+            },
+            recovery: RecoveryConfig::default(),
+            policy: PolicyConfig::default(),
+            schedule: ScheduleConfig::default(),
+            limits: LimitsConfig::default(),
+            digest: DigestConfig::default(),
+            git: GitConfig::default(),
+            hooks: HooksConfig::default(),
+            logging: LoggingConfig::default(),
+            preflight: PreflightConfig::default(),
+            security: SecurityConfig::default(),
+            sandbox: SandboxConfig::default(),
+            sync: SyncConfig::default(),
+            cluster: None,
+            prompt_template: None,
+            backend: backend.into(),
+            roles: default_roles(),
+            tasks: Vec::new(),
+            config_files: Vec::new(),
+        };
+
+        let task = TaskRuntime {
+            id: "smoke".to_string(),
+            todo_file: "N/A".to_string(),
+            depends_on: Vec::new(),
+            status: TaskStatus::Running,
+            coord_dir: state_dir.join("coord").join("smoke").display().to_string(),
+            completion_file: None,
+            started_at: None,
+            completed_at: None,
+            blocked_reason: None,
+            last_progress_epoch: None,
+            recovery_attempts: 0,
+            unattended_escalate_retries: 0,
+            thread_id: None,
+            turns: 0,
+            last_git_snapshot: None,
+            workspace: None,
+            branch: None,
+            verify: Vec::new(),
+            verify_rejected_epoch: None,
+            pending_note: None,
+            post_task_hooks_ran: false,
+            priority: 0,
+            run_if: None,
+            skip_if: None,
+            heartbeats: Vec::new(),
+            todo_progress: None,
+            artifacts: Vec::new(),
+            completion_file_sha256: None,
+            completion_requires: Vec::new(),
+            control_misses: 0,
+            control_strategies: Vec::new(),
+            thinking_override: None,
+        };
+
+        let redactor = Redactor::new(&cfg.logging)?;
+        let prompt = "Respond with a one-line greeting and include the token CRANK_LOCAL_SMOKE.";
+        let mut on_activity = || -> Result<()> { Ok(()) };
+        create_backend(&cfg.backend.primary).run_turn(
+            turn_context(&cfg, &task, prompt, &redactor),
+            &mut on_activity,
+        )
+    }
+
+    #[test]
+    #[ignore = "local e2e; requires authenticated claude CLI"]
+    fn local_e2e_claude_backend_smoke() {
+        let result = local_smoke_run(BackendConfig::Claude(ClaudeBackendConfig {
+            binary: "claude".to_string(),
+            model: "claude-opus-4-6".to_string(),
+            thinking: "high".to_string(),
+            thinking_budget_tokens: None,
+            extra_args: Vec::new(),
+        }))
+        .expect("claude local smoke should succeed");
+        assert!(!result.final_response.trim().is_empty());
+    }
+
+    #[test]
+    #[ignore = "local e2e; requires authenticated droid CLI"]
+    fn local_e2e_droid_backend_smoke() {
+        let result = local_smoke_run(BackendConfig::Droid(DroidBackendConfig {
+            binary: "droid".to_string(),
+            model: "claude-opus-4-6".to_string(),
+            thinking: "high".to_string(),
+            auto: "high".to_string(),
+            extra_args: Vec::new(),
+        }))
+        .expect("droid local smoke should succeed");
+        assert!(!result.final_response.trim().is_empty());
+    }
+
+    #[test]
+    #[ignore = "local e2e; requires authenticated pi CLI"]
+    fn local_e2e_pi_backend_smoke() {
+        let result = local_smoke_run(BackendConfig::Pi(PiBackendConfig {
+            binary: "pi".to_string(),
+            model: "claude-opus-4-6".to_string(),
+            thinking: "high".to_string(),
+            provider: Some("anthropic".to_string()),
+            extra_args: Vec::new(),
+        }))
+        .expect("pi local smoke should succeed");
+        assert!(!result.final_response.trim().is_empty());
+    }
+}